@@ -1,19 +1,27 @@
 use std::{
+    collections::HashMap,
     sync::{Arc, RwLock},
     time::Duration,
 };
 
 use near_jsonrpc_client::JsonRpcClient;
-use near_primitives::{hash::CryptoHash, views::BlockView};
+use near_primitives::{
+    hash::CryptoHash,
+    types::{BlockHeight, BlockId, BlockReference},
+    views::BlockView,
+};
 use tokio::time;
 
-use crate::rpc::get_latest_block;
+use crate::rpc::{get_block, get_latest_block};
 
 pub struct BlockService {
     rpc_client: JsonRpcClient,
     refresh_interval: Duration,
     /// A block that's refreshed every `refresh_interval`.
     block: RwLock<BlockView>,
+    /// Heights of blocks looked up by `Self::get_height`. Blocks don't change, so entries are
+    /// never invalidated.
+    heights: RwLock<HashMap<CryptoHash, BlockHeight>>,
 }
 
 impl BlockService {
@@ -28,7 +36,12 @@ impl BlockService {
         // expiring transactions.
         let refresh_interval = Duration::from_secs(30);
         let block = get_latest_block(&rpc_client).await.expect("should be able to get a block");
-        Self { rpc_client, refresh_interval, block: RwLock::new(block) }
+        Self {
+            rpc_client,
+            refresh_interval,
+            block: RwLock::new(block),
+            heights: Default::default(),
+        }
     }
 
     /// # Panics
@@ -59,4 +72,17 @@ impl BlockService {
     pub fn get_block_hash(&self) -> CryptoHash {
         self.get_block().header.hash
     }
+
+    /// Returns the height of the block with the given hash. Used to measure latencies in blocks,
+    /// so heights are cached to query every block at most once.
+    pub async fn get_height(&self, block_hash: CryptoHash) -> anyhow::Result<BlockHeight> {
+        let cached = self.heights.read().unwrap().get(&block_hash).copied();
+        if let Some(height) = cached {
+            return Ok(height);
+        }
+        let block_ref = BlockReference::BlockId(BlockId::Hash(block_hash));
+        let height = get_block(&self.rpc_client, block_ref).await?.header.height;
+        self.heights.write().unwrap().insert(block_hash, height);
+        Ok(height)
+    }
 }
@@ -0,0 +1,531 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::account::{accounts_from_dir, Account};
+use crate::block_service::BlockService;
+use crate::latency::LatencyHistogram;
+use crate::rpc::{tx_execution_level, ResponseCheckSeverity, RpcCallResult, RpcResponseHandler};
+use clap::Args;
+use log::{info, warn};
+use near_jsonrpc_client::methods::send_tx::RpcSendTransactionRequest;
+use near_jsonrpc_client::methods::tx::RpcTransactionResponse;
+use near_jsonrpc_client::JsonRpcClient;
+use near_primitives::action::{Action, DeployContractAction, FunctionCallAction};
+use near_primitives::hash::CryptoHash;
+use near_primitives::transaction::{SignedTransaction, Transaction, TransactionV0};
+use near_primitives::types::{AccountId, Balance, BlockHeight, Gas};
+use near_primitives::views::{ExecutionStatusView, FinalExecutionStatus, TxExecutionStatus};
+use rand::distributions::{Distribution, Uniform, WeightedIndex};
+use rand::Rng;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio::time;
+
+/// Gas attached to every function call. Low enough to batch two calls in a transaction.
+const CALL_GAS: Gas = 100_000_000_000_000;
+/// Gas attached to the promise created by the `promise` workload.
+const PROMISE_GAS: Gas = 10_000_000_000_000;
+/// Deposit required to register an account with the fungible token contract.
+const FT_STORAGE_DEPOSIT: Balance = 1_250_000_000_000_000_000_000;
+
+#[derive(Args, Debug)]
+pub struct DeployContractsArgs {
+    #[arg(long)]
+    pub rpc_url: String,
+    #[arg(long)]
+    pub user_data_dir: PathBuf,
+    /// Wasm file deployed to every account in `user_data_dir`, except for `ft_account_id`. The
+    /// built-in workloads call methods of the test contract in
+    /// `runtime/near-test-contracts/test-contract-rs`.
+    #[arg(long)]
+    pub contract_path: PathBuf,
+    /// Fungible token contract deployed to `ft_account_id`, e.g.
+    /// `runtime/near-test-contracts/res/fungible_token.wasm`. Every other account is registered
+    /// with it and receives `ft_balance` tokens.
+    #[arg(long, requires = "ft_account_id")]
+    pub ft_contract_path: Option<PathBuf>,
+    /// One of the accounts in `user_data_dir`, which holds the fungible token contract.
+    #[arg(long)]
+    pub ft_account_id: Option<AccountId>,
+    #[arg(long, default_value_t = 1_000_000_000)]
+    pub ft_balance: u128,
+    /// Acts as upper bound on the number of concurrently open RPC requests.
+    #[arg(long)]
+    pub channel_buffer_size: usize,
+    /// After each tick (in microseconds) a transaction is sent. If the hardware cannot keep up with
+    /// that or if the NEAR node is congested, transactions are sent at a slower rate.
+    #[arg(long)]
+    pub interval_duration_micros: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct BenchmarkArgs {
+    #[arg(long)]
+    pub rpc_url: String,
+    #[arg(long)]
+    pub user_data_dir: PathBuf,
+    #[arg(long)]
+    pub num_transactions: u64,
+    /// Acts as upper bound on the number of concurrently open RPC requests. Transactions are
+    /// sent with `wait_until: EXECUTED_OPTIMISTIC` to measure their latency, so this also limits
+    /// the throughput to roughly `channel_buffer_size / latency`.
+    #[arg(long)]
+    pub channel_buffer_size: usize,
+    /// After each tick (in microseconds) a transaction is sent. If the hardware cannot keep up with
+    /// that or if the NEAR node is congested, transactions are sent at a slower rate.
+    #[arg(long)]
+    pub interval_duration_micros: u64,
+    /// Comma separated workloads with their relative weights, e.g. `storage=2,compute=1`.
+    /// Workloads are `storage`, `compute`, `promise`, `ft` and `custom`.
+    #[arg(long, default_value = "storage=1,compute=1,promise=1")]
+    pub mix: WorkloadMix,
+    /// Number of storage entries written by each `storage` call.
+    #[arg(long, default_value_t = 100)]
+    pub storage_writes: u64,
+    /// Number of loop iterations of each `compute` call.
+    #[arg(long, default_value_t = 1_000_000)]
+    pub compute_iterations: u64,
+    /// Account holding the fungible token contract, required for the `ft` workload.
+    #[arg(long)]
+    pub ft_account_id: Option<AccountId>,
+    /// Method called by the `custom` workload, to benchmark your own contract.
+    #[arg(long)]
+    pub custom_method: Option<String>,
+    /// Arguments passed to `custom_method`.
+    #[arg(long, default_value = "")]
+    pub custom_args: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Workload {
+    /// Writes `storage_writes` entries in the test contract.
+    Storage,
+    /// Runs a loop of `compute_iterations` in the test contract.
+    Compute,
+    /// Calls the test contract, which calls `noop` on the contract of another account.
+    Promise,
+    /// Transfers one fungible token to another account.
+    Ft,
+    /// Calls `custom_method` with `custom_args`.
+    Custom,
+}
+
+impl FromStr for Workload {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "storage" => Ok(Self::Storage),
+            "compute" => Ok(Self::Compute),
+            "promise" => Ok(Self::Promise),
+            "ft" => Ok(Self::Ft),
+            "custom" => Ok(Self::Custom),
+            _ => Err(format!("unknown workload {s}")),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WorkloadMix(Vec<(Workload, u32)>);
+
+impl FromStr for WorkloadMix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mix = vec![];
+        for entry in s.split(',') {
+            let (workload, weight) = entry.split_once('=').unwrap_or((entry, "1"));
+            let weight = weight.parse().map_err(|err| format!("invalid weight {weight}: {err}"))?;
+            mix.push((workload.trim().parse()?, weight));
+        }
+        if mix.iter().all(|(_, weight)| *weight == 0) {
+            return Err("at least one workload must have a positive weight".to_string());
+        }
+        Ok(Self(mix))
+    }
+}
+
+impl WorkloadMix {
+    fn contains(&self, workload: Workload) -> bool {
+        self.0.iter().any(|(w, weight)| *w == workload && *weight > 0)
+    }
+}
+
+/// Deploys the contracts called by [`benchmark`].
+pub async fn deploy(args: &DeployContractsArgs) -> anyhow::Result<()> {
+    let mut accounts = accounts_from_dir(&args.user_data_dir)?;
+    let code = std::fs::read(&args.contract_path)?;
+
+    let client = JsonRpcClient::connect(&args.rpc_url);
+    let block_service = Arc::new(BlockService::new(client.clone()).await);
+    block_service.clone().start().await;
+
+    // All accounts except for the contract account hold fungible tokens.
+    let num_ft_holders = u128::try_from(accounts.len().saturating_sub(1))?;
+    let mut transactions = vec![];
+    for account in accounts.iter_mut() {
+        let actions = if Some(&account.id) == args.ft_account_id.as_ref() {
+            let Some(ft_contract_path) = &args.ft_contract_path else {
+                continue;
+            };
+            let init_args = json!({
+                "owner_id": account.id,
+                "total_supply": (args.ft_balance * num_ft_holders).to_string(),
+            });
+            vec![
+                Action::DeployContract(DeployContractAction {
+                    code: std::fs::read(ft_contract_path)?,
+                }),
+                function_call("new_default_meta", init_args.to_string().into_bytes(), 0),
+            ]
+        } else {
+            vec![Action::DeployContract(DeployContractAction { code: code.clone() })]
+        };
+        let receiver_id = account.id.clone();
+        transactions.push(new_transaction(
+            account,
+            receiver_id,
+            actions,
+            block_service.get_block_hash(),
+        ));
+    }
+    info!("Deploying contracts to {} accounts", transactions.len());
+    send_and_check(&client, transactions, args.channel_buffer_size, args.interval_duration_micros)
+        .await;
+
+    if let (Some(ft_account_id), Some(_)) = (&args.ft_account_id, &args.ft_contract_path) {
+        let holders: Vec<AccountId> = accounts
+            .iter()
+            .map(|account| account.id.clone())
+            .filter(|id| id != ft_account_id)
+            .collect();
+        let ft_account = accounts
+            .iter_mut()
+            .find(|account| &account.id == ft_account_id)
+            .ok_or_else(|| anyhow::anyhow!("{ft_account_id} is not in the user data dir"))?;
+        let mut transactions = vec![];
+        for holder in holders {
+            // The transfer is the last action, so the transaction has the empty success value
+            // of `ft_transfer` instead of the storage balance returned by `storage_deposit`.
+            let actions = vec![
+                function_call(
+                    "storage_deposit",
+                    json!({ "account_id": holder }).to_string().into_bytes(),
+                    FT_STORAGE_DEPOSIT,
+                ),
+                ft_transfer_action(&holder, args.ft_balance),
+            ];
+            let receiver_id = ft_account.id.clone();
+            transactions.push(new_transaction(
+                ft_account,
+                receiver_id,
+                actions,
+                block_service.get_block_hash(),
+            ));
+        }
+        info!("Registering {} accounts with the fungible token contract", transactions.len());
+        send_and_check(
+            &client,
+            transactions,
+            args.channel_buffer_size,
+            args.interval_duration_micros,
+        )
+        .await;
+    }
+
+    for account in accounts.iter() {
+        account.write_to_dir(&args.user_data_dir)?;
+    }
+    Ok(())
+}
+
+/// Sends the transactions in order and panics unless all of them succeed.
+async fn send_and_check(
+    client: &JsonRpcClient,
+    transactions: Vec<SignedTransaction>,
+    channel_buffer_size: usize,
+    interval_duration_micros: u64,
+) {
+    let mut interval = time::interval(Duration::from_micros(interval_duration_micros));
+    let (channel_tx, channel_rx) = mpsc::channel(channel_buffer_size);
+
+    let wait_until = TxExecutionStatus::ExecutedOptimistic;
+    let wait_until_channel = wait_until.clone();
+    let num_expected_responses = u64::try_from(transactions.len()).unwrap();
+    let response_handler_task = tokio::task::spawn(async move {
+        let mut rpc_response_handler = RpcResponseHandler::new(
+            channel_rx,
+            wait_until_channel,
+            ResponseCheckSeverity::Assert,
+            num_expected_responses,
+        );
+        rpc_response_handler.handle_all_responses().await;
+    });
+
+    for transaction in transactions {
+        let request = RpcSendTransactionRequest {
+            signed_transaction: transaction,
+            wait_until: wait_until.clone(),
+        };
+        interval.tick().await;
+        let client = client.clone();
+        let permit = channel_tx.clone().reserve_owned().await.unwrap();
+        tokio::spawn(async move {
+            let res = client.call(request).await;
+            permit.send(res);
+        });
+    }
+
+    response_handler_task.await.expect("response handler tasks should succeed");
+}
+
+/// The outcome of a transaction sent by [`benchmark`].
+struct CallResult {
+    workload: Workload,
+    /// Time from sending the transaction until it was executed.
+    latency: Duration,
+    /// Blocks from the inclusion of the transaction until its last receipt was executed.
+    blocks: Option<BlockHeight>,
+    result: RpcCallResult,
+}
+
+pub async fn benchmark(args: &BenchmarkArgs) -> anyhow::Result<()> {
+    let mut accounts = accounts_from_dir(&args.user_data_dir)?;
+    let ft_account_id = args.ft_account_id.clone();
+    if let Some(ft_account_id) = &ft_account_id {
+        // The fungible token contract account doesn't have the test contract.
+        accounts.retain(|account| &account.id != ft_account_id);
+    } else if args.mix.contains(Workload::Ft) {
+        anyhow::bail!("the ft workload requires --ft-account-id");
+    }
+    if args.mix.contains(Workload::Custom) && args.custom_method.is_none() {
+        anyhow::bail!("the custom workload requires --custom-method");
+    }
+    assert!(accounts.len() >= 2);
+
+    let mut interval = time::interval(Duration::from_micros(args.interval_duration_micros));
+    let timer = Instant::now();
+
+    let between = Uniform::from(0..accounts.len());
+    let workloads = WeightedIndex::new(args.mix.0.iter().map(|(_, weight)| *weight))?;
+    let mut rng = rand::thread_rng();
+
+    let client = JsonRpcClient::connect(&args.rpc_url);
+    let block_service = Arc::new(BlockService::new(client.clone()).await);
+    block_service.clone().start().await;
+
+    // Before a request is made, a permit to send into the channel is awaited. Hence buffer size
+    // limits the number of outstanding requests. This helps to avoid congestion.
+    let (channel_tx, channel_rx) = mpsc::channel(args.channel_buffer_size);
+
+    let wait_until = TxExecutionStatus::ExecutedOptimistic;
+    let num_expected_responses = args.num_transactions;
+    let response_handler_task =
+        tokio::task::spawn(handle_responses(channel_rx, num_expected_responses));
+
+    for i in 0..args.num_transactions {
+        let workload = args.mix.0[workloads.sample(&mut rng)].0;
+        let idx_sender = usize::try_from(i % u64::try_from(accounts.len()).unwrap()).unwrap();
+        let idx_receiver = other_index(idx_sender, &between, &mut rng);
+        let receiver_id = accounts[idx_receiver].id.clone();
+
+        let (receiver_id, action) = match workload {
+            Workload::Storage => {
+                let range = [0u64.to_le_bytes(), args.storage_writes.to_le_bytes()].concat();
+                (receiver_id, function_call("insert_strings", range, 0))
+            }
+            Workload::Compute => {
+                let n = args.compute_iterations.to_le_bytes().to_vec();
+                (receiver_id, function_call("sum_n", n, 0))
+            }
+            Workload::Promise => {
+                let callee = &accounts[other_index(idx_receiver, &between, &mut rng)].id;
+                let promises = json!([{
+                    "create": {
+                        "account_id": callee,
+                        "method_name": "noop",
+                        "arguments": [],
+                        "amount": "0",
+                        "gas": PROMISE_GAS,
+                    },
+                    "id": 0,
+                }]);
+                (receiver_id, function_call("call_promise", promises.to_string().into_bytes(), 0))
+            }
+            Workload::Ft => (ft_account_id.clone().unwrap(), ft_transfer_action(&receiver_id, 1)),
+            Workload::Custom => {
+                let method_name = args.custom_method.as_deref().unwrap();
+                let custom_args = args.custom_args.clone().into_bytes();
+                (receiver_id, function_call(method_name, custom_args, 0))
+            }
+        };
+        let sender = &mut accounts[idx_sender];
+        let transaction =
+            new_transaction(sender, receiver_id, vec![action], block_service.get_block_hash());
+        let request = RpcSendTransactionRequest {
+            signed_transaction: transaction,
+            wait_until: wait_until.clone(),
+        };
+
+        interval.tick().await;
+        let client = client.clone();
+        let block_service = block_service.clone();
+        // Await permit before sending the request to make channel buffer size a limit for the
+        // number of outstanding requests.
+        let permit = channel_tx.clone().reserve_owned().await.unwrap();
+        tokio::spawn(async move {
+            let start = Instant::now();
+            let result = client.call(request).await;
+            let latency = start.elapsed();
+            let blocks = match &result {
+                Ok(response) => blocks_to_execute(&block_service, response).await,
+                Err(_) => None,
+            };
+            permit.send(CallResult { workload, latency, blocks, result });
+        });
+        if i > 0 && i % 10000 == 0 {
+            info!("num txs sent: {}", i);
+        }
+    }
+
+    info!("Sent {} txs in {:.2} seconds", args.num_transactions, timer.elapsed().as_secs_f64());
+
+    for account in accounts.iter() {
+        account.write_to_dir(&args.user_data_dir)?;
+    }
+
+    // Ensure all rpc responses are handled.
+    response_handler_task.await.expect("response handler tasks should succeed");
+
+    Ok(())
+}
+
+/// Returns a random index other than `idx`, relies on at least two indices.
+fn other_index(idx: usize, between: &Uniform<usize>, rng: &mut impl Rng) -> usize {
+    loop {
+        let other = between.sample(rng);
+        if other != idx {
+            return other;
+        }
+    }
+}
+
+/// Checks the responses and logs latency histograms per workload once all were received.
+async fn handle_responses(mut receiver: mpsc::Receiver<CallResult>, num_expected_responses: u64) {
+    #[derive(Default)]
+    struct Stats {
+        latency_millis: LatencyHistogram,
+        blocks: LatencyHistogram,
+        failures: u64,
+    }
+    let mut stats: HashMap<Workload, Stats> = HashMap::new();
+
+    let mut num_received = 0;
+    while num_received < num_expected_responses {
+        let Some(call) = receiver.recv().await else {
+            warn!(
+                "Expected {num_expected_responses} responses but channel closed after {num_received}"
+            );
+            break;
+        };
+        num_received += 1;
+
+        let stats = stats.entry(call.workload).or_default();
+        if let Err(msg) = check_response(&call.result) {
+            warn!("{:?} transaction failed: {msg}", call.workload);
+            stats.failures += 1;
+            continue;
+        }
+        stats.latency_millis.record(u64::try_from(call.latency.as_millis()).unwrap());
+        if let Some(blocks) = call.blocks {
+            stats.blocks.record(blocks);
+        }
+    }
+
+    for (workload, stats) in stats {
+        info!(
+            "{workload:?}: {} succeeded, {} failed\nlatency: {}\nblocks until executed: {}",
+            stats.latency_millis.num_samples(),
+            stats.failures,
+            stats.latency_millis.report("ms"),
+            stats.blocks.report("blocks"),
+        );
+    }
+}
+
+/// Unlike the transfers, contract calls may return any value, so only failures are checked.
+fn check_response(result: &RpcCallResult) -> Result<(), String> {
+    let response = result.as_ref().map_err(|err| format!("rpc call failed: {err}"))?;
+    if tx_execution_level(&response.final_execution_status)
+        < tx_execution_level(&TxExecutionStatus::ExecutedOptimistic)
+    {
+        return Err(format!("got final execution status {:?}", response.final_execution_status));
+    }
+    let outcome = response
+        .final_execution_outcome
+        .clone()
+        .ok_or_else(|| "response has no outcome".to_string())?
+        .into_outcome();
+    if !matches!(outcome.status, FinalExecutionStatus::SuccessValue(_)) {
+        return Err(format!("got outcome.status {:?}", outcome.status));
+    }
+    for receipt_outcome in outcome.receipts_outcome.iter() {
+        if let ExecutionStatusView::Failure(err) = &receipt_outcome.outcome.status {
+            return Err(format!("receipt failed: {err}"));
+        }
+    }
+    Ok(())
+}
+
+/// Number of blocks between the inclusion of the transaction and the execution of its last
+/// receipt, `None` if it can't be determined.
+async fn blocks_to_execute(
+    block_service: &BlockService,
+    response: &RpcTransactionResponse,
+) -> Option<BlockHeight> {
+    let outcome = response.final_execution_outcome.clone()?.into_outcome();
+    let included = block_service.get_height(outcome.transaction_outcome.block_hash).await.ok()?;
+    let mut executed = included;
+    for receipt_outcome in outcome.receipts_outcome.iter() {
+        let height = block_service.get_height(receipt_outcome.block_hash).await.ok()?;
+        executed = executed.max(height);
+    }
+    Some(executed - included)
+}
+
+/// Creates a transaction with the next nonce of `sender` and increments the nonce.
+fn new_transaction(
+    sender: &mut Account,
+    receiver_id: AccountId,
+    actions: Vec<Action>,
+    block_hash: CryptoHash,
+) -> SignedTransaction {
+    sender.nonce += 1;
+    let tx = Transaction::V0(TransactionV0 {
+        signer_id: sender.id.clone(),
+        public_key: sender.public_key.clone(),
+        nonce: sender.nonce,
+        receiver_id,
+        block_hash,
+        actions,
+    });
+    tx.sign(&sender.as_signer())
+}
+
+fn function_call(method_name: &str, args: Vec<u8>, deposit: Balance) -> Action {
+    Action::FunctionCall(Box::new(FunctionCallAction {
+        method_name: method_name.to_string(),
+        args,
+        gas: CALL_GAS,
+        deposit,
+    }))
+}
+
+/// `ft_transfer` requires a deposit of exactly one yoctoNEAR.
+fn ft_transfer_action(receiver_id: &AccountId, amount: u128) -> Action {
+    let args = json!({ "receiver_id": receiver_id, "amount": amount.to_string() });
+    function_call("ft_transfer", args.to_string().into_bytes(), 1)
+}
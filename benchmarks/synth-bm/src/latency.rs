@@ -0,0 +1,50 @@
+use std::fmt::Write;
+
+/// Collects latency samples of a workload and summarizes them as a histogram with power of two
+/// buckets.
+#[derive(Default, Debug)]
+pub struct LatencyHistogram {
+    samples: Vec<u64>,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, value: u64) {
+        self.samples.push(value);
+    }
+
+    pub fn num_samples(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Formats percentiles followed by one line per non-empty bucket, with values in `unit`.
+    pub fn report(&self, unit: &str) -> String {
+        if self.samples.is_empty() {
+            return "no samples".to_string();
+        }
+        let mut samples = self.samples.clone();
+        samples.sort_unstable();
+        let percentile = |p: usize| samples[(samples.len() - 1) * p / 100];
+
+        let mut out = format!(
+            "p50 {} {unit}, p90 {} {unit}, p99 {} {unit}, max {} {unit}",
+            percentile(50),
+            percentile(90),
+            percentile(99),
+            samples[samples.len() - 1],
+        );
+        // Bucket `i` holds values in `(2^(i-1), 2^i]`, bucket 0 holds zero and one.
+        let mut buckets = vec![0usize; 65];
+        for value in &samples {
+            let bucket = value.checked_next_power_of_two().map_or(64, |p| p.trailing_zeros());
+            buckets[bucket as usize] += 1;
+        }
+        let first = buckets.iter().position(|count| *count > 0).unwrap();
+        let last = buckets.iter().rposition(|count| *count > 0).unwrap();
+        for (bucket, count) in buckets.iter().enumerate().take(last + 1).skip(first) {
+            let upper = if bucket == 64 { u64::MAX } else { 1u64 << bucket };
+            let bar = "#".repeat((count * 50).div_ceil(samples.len()));
+            write!(out, "\n  <= {upper:>8} {unit} {count:>8} {bar}").unwrap();
+        }
+        out
+    }
+}
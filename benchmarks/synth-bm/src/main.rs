@@ -3,6 +3,8 @@ use clap::{Parser, Subcommand};
 mod account;
 use account::{create_sub_accounts, CreateSubAccountsArgs};
 mod block_service;
+mod contract_call;
+mod latency;
 mod native_transfer;
 mod rpc;
 
@@ -19,6 +21,10 @@ enum Commands {
     /// Creates sub accounts for the signer.
     CreateSubAccounts(CreateSubAccountsArgs),
     BenchmarkNativeTransfers(native_transfer::BenchmarkArgs),
+    /// Deploys the contracts called by `benchmark-contract-calls` to the user accounts.
+    DeployContracts(contract_call::DeployContractsArgs),
+    /// Sends a mix of function call workloads and reports their latencies.
+    BenchmarkContractCalls(contract_call::BenchmarkArgs),
 }
 
 #[tokio::main]
@@ -35,6 +41,12 @@ async fn main() -> anyhow::Result<()> {
         Commands::BenchmarkNativeTransfers(args) => {
             native_transfer::benchmark(args).await?;
         }
+        Commands::DeployContracts(args) => {
+            contract_call::deploy(args).await?;
+        }
+        Commands::BenchmarkContractCalls(args) => {
+            contract_call::benchmark(args).await?;
+        }
     }
     Ok(())
 }
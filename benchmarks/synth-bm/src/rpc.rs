@@ -123,7 +123,7 @@ impl RpcResponseHandler {
 }
 
 /// Maps `TxExecutionStatus` to integers s.t. higher numbers represent a higher finality.
-fn tx_execution_level(status: &TxExecutionStatus) -> u8 {
+pub fn tx_execution_level(status: &TxExecutionStatus) -> u8 {
     match status {
         TxExecutionStatus::None => 0,
         TxExecutionStatus::Included => 1,
@@ -6,6 +6,7 @@ use crate::block_processing_utils::{
 use crate::blocks_delay_tracker::BlocksDelayTracker;
 use crate::chain_update::ChainUpdate;
 use crate::crypto_hash_timer::CryptoHashTimer;
+use crate::garbage_collection::UntrackedShardsCleanup;
 use crate::lightclient::get_epoch_block_producers_view;
 use crate::migrations::check_if_block_is_first_with_chunk_of_version;
 use crate::missing_chunks::MissingChunksPool;
@@ -2048,6 +2049,7 @@ impl Chain {
         if self.epoch_manager.is_next_block_epoch_start(block.header().prev_hash())? {
             // Keep in memory only these tries that we care about this or next epoch.
             self.runtime_adapter.get_tries().retain_memtries(&shards_cares_this_or_next_epoch);
+            self.schedule_untracked_shards_cleanup(&block, &shards_cares_this_or_next_epoch)?;
         }
 
         if let Err(err) = self.garbage_collect_state_transition_data(&block) {
@@ -2169,6 +2171,45 @@ impl Chain {
         Ok(Some(new_flat_head))
     }
 
+    /// Schedules the removal of flat storage and trie nodes of the shards which
+    /// are not tracked in this or the next epoch anymore, e.g. after the
+    /// tracked shards were changed in the dynamic config. `block` is the first
+    /// block of the epoch, which may still be reverted by a fork, so the
+    /// cleanup is run by garbage collection once the last block of the
+    /// previous epoch is final, see `DBCol::UntrackedShardsCleanup`.
+    fn schedule_untracked_shards_cleanup(
+        &self,
+        block: &Block,
+        shards_cares_this_or_next_epoch: &[ShardUId],
+    ) -> Result<(), Error> {
+        let flat_storage_manager = self.runtime_adapter.get_flat_storage_manager();
+        let untracked_shards: Vec<ShardUId> = self
+            .epoch_manager
+            .get_shard_layout(block.header().epoch_id())?
+            .shard_uids()
+            .filter(|shard_uid| {
+                !shards_cares_this_or_next_epoch.contains(shard_uid)
+                    && flat_storage_manager.get_flat_storage_for_shard(*shard_uid).is_some()
+            })
+            .collect();
+        if untracked_shards.is_empty() {
+            return Ok(());
+        }
+        tracing::info!(target: "chain", ?untracked_shards, "Scheduled removal of flat storage and state of untracked shards");
+        let cleanup = UntrackedShardsCleanup {
+            untracked_shards,
+            tracked_shards: shards_cares_this_or_next_epoch.to_vec(),
+        };
+        let mut store_update = self.chain_store.store().store_update();
+        store_update.set_ser(
+            DBCol::UntrackedShardsCleanup,
+            block.header().prev_hash().as_ref(),
+            &cleanup,
+        )?;
+        store_update.commit()?;
+        Ok(())
+    }
+
     /// Update flat storage and memtrie for given `shard_id` and newly
     /// processed `block`.
     fn update_flat_storage_and_memtrie(
@@ -542,9 +542,14 @@ impl Doomslug {
         target_height: BlockHeight,
         signer: &Option<Arc<ValidatorSigner>>,
     ) -> Option<Approval> {
-        signer.as_ref().map(|signer| {
-            Approval::new(self.tip.block_hash, self.tip.height, target_height, &*signer)
-        })
+        let signer = signer.as_ref()?;
+        match Approval::new(self.tip.block_hash, self.tip.height, target_height, &*signer) {
+            Ok(approval) => Some(approval),
+            Err(err) => {
+                tracing::error!(target: "doomslug", %err, "failed to sign approval");
+                None
+            }
+        }
     }
 
     /// Determines whether a block has enough approvals to be produced.
@@ -960,7 +965,7 @@ mod tests {
         // "test1", 2 -> 2
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 2, &signers[0]),
+                &Approval::new(hash(&[1]), 1, 2, &signers[0]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -969,7 +974,7 @@ mod tests {
         // "test3", 4 -> 3
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 4, &signers[2]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[2]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -978,7 +983,7 @@ mod tests {
         // "test4", 4 -> 4
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 4, &signers[3]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[3]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -989,7 +994,7 @@ mod tests {
         // "test1", 4 -> same account, still 5
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 4, &signers[3]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[3]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -998,7 +1003,7 @@ mod tests {
         // "test2", 4 -> 5
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 4, &signers[1]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[1]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::ReadySince(clock.now()),
@@ -1007,7 +1012,7 @@ mod tests {
         // "test1", 4 -> 7
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 4, &signers[0]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[0]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::ReadySince(clock.now()),
@@ -1016,7 +1021,7 @@ mod tests {
         // "test4", 2 -> 3
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 2, &signers[3]),
+                &Approval::new(hash(&[1]), 1, 2, &signers[3]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -1027,7 +1032,7 @@ mod tests {
         // "test3", 2 -> 6
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[1]), 1, 2, &signers[2]),
+                &Approval::new(hash(&[1]), 1, 2, &signers[2]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::ReadySince(clock.now()),
@@ -1036,7 +1041,7 @@ mod tests {
         // A different parent hash
         assert_eq!(
             ds.on_approval_message_internal(
-                &Approval::new(hash(&[2]), 2, 4, &signers[1]),
+                &Approval::new(hash(&[2]), 2, 4, &signers[1]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -1063,13 +1068,13 @@ mod tests {
         let clock = FakeClock::new(Utc::UNIX_EPOCH);
         let mut tracker = DoomslugApprovalsTrackersAtHeight::new(clock.clock());
 
-        let a1_1 = Approval::new(hash(&[1]), 1, 4, &signers[0]);
-        let a1_2 = Approval::new(hash(&[1]), 1, 4, &signers[1]);
-        let a1_3 = Approval::new(hash(&[1]), 1, 4, &signers[2]);
+        let a1_1 = Approval::new(hash(&[1]), 1, 4, &signers[0]).unwrap();
+        let a1_2 = Approval::new(hash(&[1]), 1, 4, &signers[1]).unwrap();
+        let a1_3 = Approval::new(hash(&[1]), 1, 4, &signers[2]).unwrap();
 
-        let a2_1 = Approval::new(hash(&[3]), 3, 4, &signers[0]);
-        let a2_2 = Approval::new(hash(&[3]), 3, 4, &signers[1]);
-        let a2_3 = Approval::new(hash(&[3]), 3, 4, &signers[2]);
+        let a2_1 = Approval::new(hash(&[3]), 3, 4, &signers[0]).unwrap();
+        let a2_2 = Approval::new(hash(&[3]), 3, 4, &signers[1]).unwrap();
+        let a2_3 = Approval::new(hash(&[3]), 3, 4, &signers[2]).unwrap();
 
         // Process first approval, and then process it again and make sure it works
         tracker.process_approval(&a1_1, &stakes, DoomslugThresholdMode::TwoThirds);
@@ -2,21 +2,80 @@ use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::{fmt, io};
 
-use near_chain_configs::GCConfig;
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_chain_configs::{GCConfig, GCRetainedColumn, GCRetentionPolicy};
 use near_chain_primitives::Error;
 use near_epoch_manager::EpochManagerAdapter;
 use near_primitives::block::Block;
+use near_primitives::errors::StorageError;
 use near_primitives::hash::CryptoHash;
-use near_primitives::shard_layout::get_block_shard_uid;
+use near_primitives::shard_layout::{get_block_shard_uid, get_block_shard_uid_rev};
 use near_primitives::state_sync::{StateHeaderKey, StatePartKey};
-use near_primitives::types::{BlockHeight, BlockHeightDelta, EpochId, NumBlocks, ShardId};
+use near_primitives::types::{
+    BlockHeight, BlockHeightDelta, EpochHeight, EpochId, NumBlocks, ShardId,
+};
 use near_primitives::utils::{get_block_shard_id, get_outcome_id_block_hash, index_to_bytes};
+use near_store::adapter::trie_store::get_shard_uid_mapping;
 use near_store::adapter::{StoreAdapter, StoreUpdateAdapter};
-use near_store::{DBCol, KeyForStateChanges, ShardTries, ShardUId};
+use near_store::flat::FlatStorageStatus;
+use near_store::{DBCol, KeyForStateChanges, ShardTries, ShardUId, StoreUpdate};
 
 use crate::types::RuntimeAdapter;
 use crate::{metrics, Chain, ChainStore, ChainStoreAccess, ChainStoreUpdate};
 
+const NANOS_PER_DAY: u64 = 86_400 * 1_000_000_000;
+
+/// Columns which may have an extended retention, see [`GCConfig::column_retention`].
+const RETAINABLE_COLUMNS: [DBCol; 5] = [
+    DBCol::Transactions,
+    DBCol::Receipts,
+    DBCol::TransactionResultForBlock,
+    DBCol::OutcomeIds,
+    DBCol::StateChanges,
+];
+
+/// Keys which were left in place when a block was garbage collected because
+/// their columns have an extended retention. Stored in `DBCol::GCRetainedKeys`.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+struct RetainedGCKeys {
+    /// Epoch height of the garbage collected block.
+    epoch_height: EpochHeight,
+    /// Timestamp of the garbage collected block, in nanoseconds.
+    timestamp: u64,
+    /// Pairs of column name and key.
+    keys: Vec<(String, Vec<u8>)>,
+}
+
+/// Removal of the flat storage and State of shards which the node stopped
+/// tracking at an epoch start. Stored in `DBCol::UntrackedShardsCleanup` by
+/// block processing and run by garbage collection.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub(crate) struct UntrackedShardsCleanup {
+    /// Shards whose flat storage and State are removed.
+    pub untracked_shards: Vec<ShardUId>,
+    /// Shards tracked in the new epoch or the one after it. The State of an
+    /// untracked shard is kept if it shares its State prefix with one of them.
+    pub tracked_shards: Vec<ShardUId>,
+}
+
+fn retained_column(column: GCRetainedColumn) -> DBCol {
+    match column {
+        GCRetainedColumn::Transactions => DBCol::Transactions,
+        GCRetainedColumn::Receipts => DBCol::Receipts,
+        GCRetainedColumn::TransactionResultForBlock => DBCol::TransactionResultForBlock,
+        GCRetainedColumn::OutcomeIds => DBCol::OutcomeIds,
+        GCRetainedColumn::StateChanges => DBCol::StateChanges,
+    }
+}
+
+fn retention_policies(gc_config: &GCConfig) -> HashMap<DBCol, GCRetentionPolicy> {
+    gc_config
+        .column_retention
+        .iter()
+        .map(|(column, policy)| (retained_column(*column), *policy))
+        .collect()
+}
+
 #[derive(Clone)]
 pub enum GCMode {
     Fork(ShardTries),
@@ -167,6 +226,17 @@ impl ChainStore {
             fork_tail = gc_stop_height;
         }
         let mut gc_blocks_remaining = gc_config.gc_blocks_limit;
+        let retention_policies = retention_policies(gc_config);
+
+        // Data with extended retention
+        self.clear_retained_data(
+            &retention_policies,
+            gc_config.gc_blocks_limit,
+            epoch_manager.as_ref(),
+        )?;
+
+        // State of untracked shards
+        self.clear_untracked_shards(runtime_adapter.as_ref())?;
 
         // Forks Cleaning
         let gc_fork_clean_step = gc_config.gc_fork_clean_step;
@@ -208,11 +278,15 @@ impl ChainStore {
                     break;
                 } else if prev_block_refcount == 1 {
                     debug_assert_eq!(blocks_current_height.len(), 1);
-                    chain_store_update.clear_block_data(
+                    chain_store_update.retained_gc_columns =
+                        retention_policies.keys().copied().collect();
+                    let gc_block_hash = chain_store_update.clear_block_data(
                         epoch_manager.as_ref(),
                         *block_hash,
                         GCMode::Canonical(tries.clone()),
                     )?;
+                    chain_store_update
+                        .save_retained_gc_keys(epoch_manager.as_ref(), &gc_block_hash)?;
                     gc_blocks_remaining -= 1;
                 } else {
                     return Err(Error::GCError(
@@ -226,6 +300,188 @@ impl ChainStore {
         Ok(())
     }
 
+    /// Runs the removals of untracked shards scheduled by block processing,
+    /// see [`UntrackedShardsCleanup`]. A removal runs once its block, the last
+    /// block of an epoch, is final, and is dropped if the block is on a fork.
+    ///
+    /// Shards tracked according to a later scheduled removal are kept, they
+    /// may be tracked again after a change of the tracked shards.
+    fn clear_untracked_shards(
+        &mut self,
+        runtime_adapter: &dyn RuntimeAdapter,
+    ) -> Result<(), Error> {
+        let mut cleanups = vec![];
+        for item in self.store().iter_ser::<UntrackedShardsCleanup>(DBCol::UntrackedShardsCleanup) {
+            let (key, cleanup) = item?;
+            let block_hash = CryptoHash::try_from(key.as_ref())
+                .map_err(|err| Error::Other(format!("invalid cleanup key: {err}")))?;
+            let height = self.get_block_header(&block_hash)?.height();
+            cleanups.push((height, block_hash, cleanup));
+        }
+        if cleanups.is_empty() {
+            return Ok(());
+        }
+        cleanups.sort_by_key(|(height, _, _)| *height);
+
+        let final_height = self.final_head()?.height;
+        for (index, (height, block_hash, cleanup)) in cleanups.iter().enumerate() {
+            if *height > final_height {
+                break;
+            }
+            let mut store_update = self.store().store_update();
+            store_update.delete(DBCol::UntrackedShardsCleanup, block_hash.as_ref());
+            if self.get_block_hash_by_height(*height).ok() == Some(*block_hash) {
+                let tracked_shards: HashSet<ShardUId> = cleanups[index..]
+                    .iter()
+                    .flat_map(|(_, _, cleanup)| cleanup.tracked_shards.iter().copied())
+                    .collect();
+                self.clear_shards_state(
+                    runtime_adapter,
+                    &cleanup.untracked_shards,
+                    &tracked_shards,
+                    &mut store_update,
+                )?;
+            }
+            store_update.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Removes the flat storage of `untracked_shards` and their State, unless
+    /// it shares its State prefix with a tracked shard. Their trie changes are
+    /// removed with the State, because garbage collection of their blocks would
+    /// otherwise apply them to the removed trie nodes.
+    fn clear_shards_state(
+        &self,
+        runtime_adapter: &dyn RuntimeAdapter,
+        untracked_shards: &[ShardUId],
+        tracked_shards: &HashSet<ShardUId>,
+        store_update: &mut StoreUpdate,
+    ) -> Result<(), Error> {
+        let store = self.store();
+        let flat_storage_manager = runtime_adapter.get_flat_storage_manager();
+        let tracked_state_prefixes: HashSet<ShardUId> = tracked_shards
+            .iter()
+            .map(|shard_uid| get_shard_uid_mapping(store, *shard_uid))
+            .collect();
+        let mut removed_state_shards = HashSet::new();
+        for shard_uid in untracked_shards {
+            if tracked_shards.contains(shard_uid) {
+                continue;
+            }
+            // The flat storage may not be loaded, e.g. after a restart.
+            if !flat_storage_manager
+                .remove_flat_storage_for_shard(*shard_uid, &mut store_update.flat_store_update())?
+            {
+                let status = store
+                    .flat_store()
+                    .get_flat_storage_status(*shard_uid)
+                    .map_err(StorageError::from)?;
+                if !matches!(status, FlatStorageStatus::Empty) {
+                    let mut flat_store_update = store_update.flat_store_update();
+                    flat_store_update.remove_all_values(*shard_uid);
+                    flat_store_update.remove_all_deltas(*shard_uid);
+                    flat_store_update.set_flat_storage_status(*shard_uid, FlatStorageStatus::Empty);
+                }
+            }
+            if !tracked_state_prefixes.contains(&get_shard_uid_mapping(store, *shard_uid)) {
+                store_update.trie_store_update().delete_shard_state(*shard_uid);
+                removed_state_shards.insert(*shard_uid);
+            }
+        }
+        for item in store.iter(DBCol::TrieChanges) {
+            let (key, _) = item?;
+            let (_, shard_uid) =
+                get_block_shard_uid_rev(&key).map_err(|err| Error::Other(err.to_string()))?;
+            if removed_state_shards.contains(&shard_uid) {
+                store_update.delete(DBCol::TrieChanges, &key);
+            }
+        }
+        tracing::info!(target: "garbage_collection", ?untracked_shards, ?removed_state_shards, "Removed flat storage and state of untracked shards");
+        Ok(())
+    }
+
+    /// Removes the data with extended retention whose retention period has
+    /// passed, see [`GCConfig::column_retention`].
+    ///
+    /// Records in `DBCol::GCRetainedKeys` are processed in height order and
+    /// the pass stops at the first record with nothing to remove, so at most
+    /// `limit` records are processed. Keys of columns which no longer have a
+    /// retention policy are removed right away.
+    fn clear_retained_data(
+        &mut self,
+        retention_policies: &HashMap<DBCol, GCRetentionPolicy>,
+        limit: NumBlocks,
+        epoch_manager: &dyn EpochManagerAdapter,
+    ) -> Result<(), Error> {
+        // The heights are little-endian, so the rows are not iterated in
+        // height order.
+        let mut record_keys = vec![];
+        for item in self.store().iter(DBCol::GCRetainedKeys) {
+            let (key, _) = item?;
+            let height = <[u8; 8]>::try_from(key.as_ref())
+                .map(BlockHeight::from_le_bytes)
+                .map_err(|_| Error::Other(format!("invalid retained keys key: {key:?}")))?;
+            record_keys.push((height, key));
+        }
+        if record_keys.is_empty() {
+            return Ok(());
+        }
+        record_keys.sort();
+        record_keys.truncate(limit as usize);
+        let mut records = Vec::with_capacity(record_keys.len());
+        for (_, key) in record_keys {
+            let record: RetainedGCKeys = self
+                .store()
+                .get_ser(DBCol::GCRetainedKeys, &key)?
+                .ok_or_else(|| Error::DBNotFoundErr(format!("GCRetainedKeys {key:?}")))?;
+            records.push((key, record));
+        }
+        let head = self.head()?;
+        let head_epoch_height = epoch_manager.get_epoch_info(&head.epoch_id)?.epoch_height();
+        let head_timestamp = self.get_block_header(&head.last_block_hash)?.raw_timestamp();
+        let is_expired = |record: &RetainedGCKeys, col: DBCol| match retention_policies.get(&col) {
+            None => true,
+            Some(GCRetentionPolicy::Epochs(epochs)) => {
+                head_epoch_height.saturating_sub(record.epoch_height) >= *epochs
+            }
+            Some(GCRetentionPolicy::Days(days)) => {
+                head_timestamp.saturating_sub(record.timestamp)
+                    >= days.saturating_mul(NANOS_PER_DAY)
+            }
+        };
+
+        let mut chain_store_update = self.store_update();
+        for (record_key, mut record) in records {
+            let mut expired = vec![];
+            let mut kept = vec![];
+            for (name, key) in std::mem::take(&mut record.keys) {
+                match RETAINABLE_COLUMNS.into_iter().find(|col| <&str>::from(*col) == name) {
+                    Some(col) if !is_expired(&record, col) => kept.push((name, key)),
+                    Some(col) => expired.push((col, key)),
+                    None => {
+                        tracing::warn!(target: "garbage_collection", %name, "unknown retained column");
+                    }
+                }
+            }
+            if expired.is_empty() && !kept.is_empty() {
+                break;
+            }
+            for (col, key) in expired {
+                chain_store_update.gc_col(col, &key);
+            }
+            let mut store_update = chain_store_update.store().store_update();
+            if kept.is_empty() {
+                store_update.delete(DBCol::GCRetainedKeys, &record_key);
+            } else {
+                record.keys = kept;
+                store_update.set_ser(DBCol::GCRetainedKeys, &record_key, &record)?;
+            }
+            chain_store_update.merge(store_update);
+        }
+        chain_store_update.commit()
+    }
+
     /// Garbage collect data which archival node doesn’t need to keep.
     ///
     /// Normally, archival nodes keep all the data from the genesis block and
@@ -535,12 +791,13 @@ impl<'a> ChainStoreUpdate<'a> {
 
     // Clearing block data of `block_hash`, if on a fork.
     // Clearing block data of `block_hash.prev`, if on the Canonical Chain.
+    // Returns the hash of the block whose data was cleared.
     pub fn clear_block_data(
         &mut self,
         epoch_manager: &dyn EpochManagerAdapter,
         mut block_hash: CryptoHash,
         gc_mode: GCMode,
-    ) -> Result<(), Error> {
+    ) -> Result<CryptoHash, Error> {
         let mut store_update = self.store().trie_store().store_update();
 
         tracing::debug!(target: "garbage_collection", ?gc_mode, ?block_hash, "GC block_hash");
@@ -676,7 +933,7 @@ impl<'a> ChainStoreUpdate<'a> {
             }
         };
         self.merge(store_update.into());
-        Ok(())
+        Ok(block_hash)
     }
 
     // Delete all data in rocksdb that are partially or wholly indexed and can be looked up by hash of the current head of the chain
@@ -874,7 +1131,35 @@ impl<'a> ChainStoreUpdate<'a> {
         Ok(())
     }
 
+    /// Saves the keys spared by garbage collection of `block_hash` because of
+    /// the extended retention of their columns, and resets the retention.
+    fn save_retained_gc_keys(
+        &mut self,
+        epoch_manager: &dyn EpochManagerAdapter,
+        block_hash: &CryptoHash,
+    ) -> Result<(), Error> {
+        self.retained_gc_columns.clear();
+        let keys = std::mem::take(&mut self.retained_gc_keys);
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let header = self.get_block_header(block_hash)?;
+        let record = RetainedGCKeys {
+            epoch_height: epoch_manager.get_epoch_info(header.epoch_id())?.epoch_height(),
+            timestamp: header.raw_timestamp(),
+            keys: keys.into_iter().map(|(col, key)| (<&str>::from(col).to_string(), key)).collect(),
+        };
+        let mut store_update = self.store().store_update();
+        store_update.set_ser(DBCol::GCRetainedKeys, &index_to_bytes(header.height()), &record)?;
+        self.merge(store_update);
+        Ok(())
+    }
+
     fn gc_col(&mut self, col: DBCol, key: &[u8]) {
+        if self.retained_gc_columns.contains(&col) {
+            self.retained_gc_keys.push((col, key.to_vec()));
+            return;
+        }
         let mut store_update = self.store().store_update();
         match col {
             DBCol::OutgoingReceipts => {
@@ -1026,6 +1311,10 @@ impl<'a> ChainStoreUpdate<'a> {
             // Note that StateSyncHashes should not ever have too many keys in them
             // because we remove unneeded keys as we add new ones.
             | DBCol::StateSyncHashes
+            // Retained keys are removed by `clear_retained_data`.
+            | DBCol::GCRetainedKeys
+            // Removed by `clear_untracked_shards`.
+            | DBCol::UntrackedShardsCleanup
             => unreachable!(),
         }
         self.merge(store_update);
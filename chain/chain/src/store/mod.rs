@@ -1460,6 +1460,10 @@ pub struct ChainStoreUpdate<'a> {
     add_state_sync_infos: Vec<StateSyncInfo>,
     remove_state_sync_infos: Vec<CryptoHash>,
     challenged_blocks: HashSet<CryptoHash>,
+    /// Columns whose keys are collected into `retained_gc_keys` instead of
+    /// being deleted by garbage collection.
+    pub(crate) retained_gc_columns: HashSet<DBCol>,
+    pub(crate) retained_gc_keys: Vec<(DBCol, Vec<u8>)>,
 }
 
 impl<'a> ChainStoreUpdate<'a> {
@@ -1483,6 +1487,8 @@ impl<'a> ChainStoreUpdate<'a> {
             add_state_sync_infos: vec![],
             remove_state_sync_infos: vec![],
             challenged_blocks: HashSet::default(),
+            retained_gc_columns: HashSet::default(),
+            retained_gc_keys: vec![],
         }
     }
 }
@@ -75,6 +75,8 @@ pub struct StoreValidator {
     inner: StoreValidatorCache,
     timeout: Option<i64>,
     start_time: Instant,
+    max_read_bytes_per_sec: Option<u64>,
+    read_bytes: u64,
     pub is_archival: bool,
     // If present, the node was bootstrapped with epoch sync, and this block height
     // represents the first block of the target epoch that we epoch synced to.
@@ -110,6 +112,8 @@ impl StoreValidator {
             inner: StoreValidatorCache::new(),
             timeout: None,
             start_time: Clock::real().now(),
+            max_read_bytes_per_sec: None,
+            read_bytes: 0,
             is_archival,
             epoch_sync_boundary,
             errors: vec![],
@@ -119,6 +123,11 @@ impl StoreValidator {
     pub fn set_timeout(&mut self, timeout: i64) {
         self.timeout = Some(timeout)
     }
+    /// Limits the rate at which the columns are read, to reduce the impact of
+    /// the validation on a running node.
+    pub fn set_max_read_bytes_per_sec(&mut self, max_read_bytes_per_sec: u64) {
+        self.max_read_bytes_per_sec = Some(max_read_bytes_per_sec)
+    }
     pub fn is_failed(&self) -> bool {
         self.tests == 0 || !self.errors.is_empty()
     }
@@ -131,9 +140,22 @@ impl StoreValidator {
     fn process_error<K: std::fmt::Debug>(&mut self, err: StoreValidatorError, key: K, col: DBCol) {
         self.errors.push(ErrorMessage { key: format!("{key:?}"), col: col.to_string(), err })
     }
+    fn throttle_read(&mut self, bytes: usize) {
+        let Some(max_read_bytes_per_sec) = self.max_read_bytes_per_sec else {
+            return;
+        };
+        self.read_bytes += bytes as u64;
+        let expected =
+            Duration::seconds_f64(self.read_bytes as f64 / max_read_bytes_per_sec.max(1) as f64);
+        let elapsed = self.start_time.elapsed();
+        if expected > elapsed {
+            std::thread::sleep((expected - elapsed).unsigned_abs());
+        }
+    }
     fn validate_col(&mut self, col: DBCol) -> Result<(), StoreValidatorError> {
         for item in self.store.clone().iter_raw_bytes(col) {
             let (key, value) = item?;
+            self.throttle_read(key.len() + value.len());
             let key_ref = key.as_ref();
             let value_ref = value.as_ref();
             match col {
@@ -342,6 +364,7 @@ impl StoreValidator {
 
     pub fn validate(&mut self) {
         self.start_time = Clock::real().now();
+        self.read_bytes = 0;
 
         // Init checks
         // Check Head-Tail validity and fill cache with their values
@@ -3,6 +3,7 @@ use near_async::time::{Duration, FakeClock, Instant, Utc};
 use near_crypto::{KeyType, SecretKey};
 use near_primitives::block::Approval;
 use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::signing_journal::SigningJournal;
 use near_primitives::test_utils::create_test_signer;
 use near_primitives::types::{ApprovalStake, BlockHeight};
 use rand::{thread_rng, Rng};
@@ -283,6 +284,46 @@ fn one_iter(
     (clock.now().signed_duration_since(started), largest_produced_height)
 }
 
+#[test]
+fn test_doomslug_refuses_conflicting_approval_across_failover() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = SigningJournal::open(&dir.path().join("signing_journal")).unwrap();
+    let signer = Some(Arc::new(create_test_signer("test1").with_signing_journal(journal)));
+    let clock = FakeClock::new(Utc::UNIX_EPOCH);
+    let new_doomslug = || {
+        Doomslug::new(
+            clock.clock(),
+            0,
+            Duration::milliseconds(200),
+            Duration::milliseconds(1000),
+            Duration::milliseconds(100),
+            Duration::milliseconds(3000),
+            DoomslugThresholdMode::TwoThirds,
+        )
+    };
+
+    // The active node endorses block 1.
+    let mut active = new_doomslug();
+    active.set_tip(block_hash(1, 0), 1, 1);
+    clock.advance(Duration::milliseconds(200));
+    let approvals = active.process_timer(&signer);
+    assert_eq!(approvals.len(), 1);
+    assert_eq!(approvals[0].target_height, 2);
+
+    // A backup with the same key that saw another block at height 1 must not
+    // send an approval for the same target height.
+    let mut backup = new_doomslug();
+    backup.set_tip(block_hash(1, 1), 1, 1);
+    clock.advance(Duration::milliseconds(200));
+    assert!(backup.process_timer(&signer).is_empty());
+
+    // Signing the same approval again is fine.
+    let mut restarted = new_doomslug();
+    restarted.set_tip(block_hash(1, 0), 1, 1);
+    clock.advance(Duration::milliseconds(200));
+    assert_eq!(restarted.process_timer(&signer), approvals);
+}
+
 #[test]
 fn ultra_slow_test_fuzzy_doomslug_liveness_and_safety() {
     for (time_to_gst_millis, height_goal) in
@@ -1,9 +1,9 @@
-use near_async::time::Clock;
+use near_async::time::{Clock, Duration, FakeClock};
 use rand::Rng;
 use std::sync::Arc;
 
 use crate::chain::Chain;
-use crate::garbage_collection::GCMode;
+use crate::garbage_collection::{GCMode, UntrackedShardsCleanup};
 use crate::test_utils::{
     get_chain, get_chain_with_epoch_length, get_chain_with_epoch_length_and_num_shards,
     get_chain_with_num_shards,
@@ -11,17 +11,31 @@ use crate::test_utils::{
 use crate::types::Tip;
 use crate::{ChainStoreAccess, StoreValidator};
 
-use near_chain_configs::{GCConfig, GenesisConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP};
+use near_chain_configs::{
+    GCConfig, GCRetainedColumn, GCRetentionPolicy, GenesisConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
+};
 use near_epoch_manager::EpochManagerAdapter;
+use near_primitives::bandwidth_scheduler::BandwidthRequests;
 use near_primitives::block::Block;
+use near_primitives::congestion_info::CongestionInfo;
 use near_primitives::epoch_block_info::BlockInfo;
 use near_primitives::merkle::PartialMerkleTree;
+use near_primitives::receipt::{Receipt, ReceiptPriority};
 use near_primitives::shard_layout::ShardUId;
+use near_primitives::sharding::{
+    PartialEncodedChunk, PartialEncodedChunkV2, ReceiptProof, ShardChunk, ShardChunkHeader,
+    ShardChunkHeaderV3, ShardChunkV2, ShardProof,
+};
+use near_primitives::state::FlatStateValue;
 use near_primitives::test_utils::{create_test_signer, TestBlockBuilder};
-use near_primitives::types::{BlockHeight, NumBlocks, StateRoot};
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::{BlockHeight, NumBlocks, ShardId, StateRoot};
 use near_primitives::validator_signer::ValidatorSigner;
+use near_primitives::version::{ProtocolFeature, PROTOCOL_VERSION};
+use near_store::adapter::{StoreAdapter, StoreUpdateAdapter};
+use near_store::flat::{BlockInfo as FlatBlockInfo, FlatStorageReadyStatus, FlatStorageStatus};
 use near_store::test_utils::gen_changes;
-use near_store::{DBCol, ShardTries, Trie, WrappedTrieChanges};
+use near_store::{DBCol, KeyForStateChanges, ShardTries, Trie, WrappedTrieChanges};
 
 // Build a chain of num_blocks on top of prev_block
 fn do_fork(
@@ -720,6 +734,322 @@ fn test_clear_old_data() {
     }
 }
 
+/// Test that state changes with extended retention survive garbage collection
+/// of their blocks and are removed once the retention policy is dropped.
+#[test]
+fn test_clear_old_data_with_column_retention() {
+    let max_height = 14usize;
+    let mut chain = get_chain_with_epoch_length(Clock::real(), 1);
+    let epoch_manager = chain.epoch_manager.clone();
+    let genesis = chain.get_block_by_height(0).unwrap();
+    let signer = Arc::new(create_test_signer("test1"));
+    let mut prev_block = genesis;
+    let mut blocks = vec![prev_block.clone()];
+    for i in 1..=max_height {
+        add_block(
+            &mut chain,
+            epoch_manager.as_ref(),
+            &mut prev_block,
+            &mut blocks,
+            signer.clone(),
+            i as BlockHeight,
+        );
+    }
+    let store = chain.chain_store().store().clone();
+    let state_changes_key =
+        |block: &Block| KeyForStateChanges::for_block(block.hash()).as_ref().to_vec();
+    let mut store_update = store.store_update();
+    for block in &blocks {
+        store_update.set(DBCol::StateChanges, &state_changes_key(block), &[1]);
+    }
+    store_update.commit().unwrap();
+
+    let gc_config = GCConfig {
+        gc_blocks_limit: 100,
+        column_retention: [(GCRetainedColumn::StateChanges, GCRetentionPolicy::Epochs(1000))]
+            .into_iter()
+            .collect(),
+        ..GCConfig::default()
+    };
+    chain.clear_data(&gc_config).unwrap();
+
+    for i in 0..=max_height {
+        let removed = i < max_height - DEFAULT_GC_NUM_EPOCHS_TO_KEEP as usize;
+        assert_eq!(chain.get_block(blocks[i].hash()).is_err(), removed);
+        assert!(store.exists(DBCol::StateChanges, &state_changes_key(&blocks[i])).unwrap());
+    }
+    assert!(store.iter(DBCol::GCRetainedKeys).next().is_some());
+
+    chain.clear_data(&GCConfig { gc_blocks_limit: 100, ..GCConfig::default() }).unwrap();
+
+    for i in 0..=max_height {
+        let removed = i < max_height - DEFAULT_GC_NUM_EPOCHS_TO_KEEP as usize;
+        assert_eq!(
+            store.exists(DBCol::StateChanges, &state_changes_key(&blocks[i])).unwrap(),
+            !removed
+        );
+    }
+    assert!(store.iter(DBCol::GCRetainedKeys).next().is_none());
+}
+
+/// Test that state changes retained for a number of days survive garbage
+/// collection until that many days have passed since their block.
+#[test]
+fn test_clear_old_data_with_days_retention() {
+    let max_height = 14usize;
+    let clock = FakeClock::default();
+    let mut chain = get_chain_with_epoch_length(clock.clock(), 1);
+    let epoch_manager = chain.epoch_manager.clone();
+    let genesis = chain.get_block_by_height(0).unwrap();
+    let signer = Arc::new(create_test_signer("test1"));
+    let mut prev_block = genesis;
+    let mut blocks = vec![prev_block.clone()];
+    for i in 1..=max_height {
+        add_block(
+            &mut chain,
+            epoch_manager.as_ref(),
+            &mut prev_block,
+            &mut blocks,
+            signer.clone(),
+            i as BlockHeight,
+        );
+    }
+    let store = chain.chain_store().store().clone();
+    let state_changes_key =
+        |block: &Block| KeyForStateChanges::for_block(block.hash()).as_ref().to_vec();
+    let mut store_update = store.store_update();
+    for block in &blocks {
+        store_update.set(DBCol::StateChanges, &state_changes_key(block), &[1]);
+    }
+    store_update.commit().unwrap();
+
+    let gc_config = GCConfig {
+        gc_blocks_limit: 100,
+        column_retention: [(GCRetainedColumn::StateChanges, GCRetentionPolicy::Days(1))]
+            .into_iter()
+            .collect(),
+        ..GCConfig::default()
+    };
+    chain.clear_data(&gc_config).unwrap();
+    chain.clear_data(&gc_config).unwrap();
+
+    for i in 0..=max_height {
+        let removed = i < max_height - DEFAULT_GC_NUM_EPOCHS_TO_KEEP as usize;
+        assert_eq!(chain.get_block(blocks[i].hash()).is_err(), removed);
+        assert!(store.exists(DBCol::StateChanges, &state_changes_key(&blocks[i])).unwrap());
+    }
+
+    // Once a day has passed, the state changes of the blocks collected above
+    // are removed. The block collected by this pass keeps its state changes
+    // until the next one.
+    clock.advance(Duration::days(2));
+    add_block(
+        &mut chain,
+        epoch_manager.as_ref(),
+        &mut prev_block,
+        &mut blocks,
+        signer.clone(),
+        max_height as BlockHeight + 1,
+    );
+    chain.clear_data(&gc_config).unwrap();
+
+    let last_removed = max_height - DEFAULT_GC_NUM_EPOCHS_TO_KEEP as usize;
+    for i in 0..=max_height {
+        assert_eq!(
+            store.exists(DBCol::StateChanges, &state_changes_key(&blocks[i])).unwrap(),
+            i >= last_removed,
+        );
+    }
+    assert!(chain.get_block(blocks[last_removed].hash()).is_err());
+    assert!(store.iter(DBCol::GCRetainedKeys).next().is_some());
+
+    chain.clear_data(&gc_config).unwrap();
+
+    assert!(!store.exists(DBCol::StateChanges, &state_changes_key(&blocks[last_removed])).unwrap());
+    assert!(store.iter(DBCol::GCRetainedKeys).next().is_none());
+}
+
+/// Test that transactions and receipts with extended retention survive
+/// garbage collection of their chunk.
+#[test]
+fn test_clear_old_data_with_transactions_and_receipts_retention() {
+    let max_height = 14usize;
+    let mut chain = get_chain_with_epoch_length(Clock::real(), 1);
+    let epoch_manager = chain.epoch_manager.clone();
+    let genesis = chain.get_block_by_height(0).unwrap();
+    let signer = Arc::new(create_test_signer("test1"));
+    let congestion_info = ProtocolFeature::CongestionControl
+        .enabled(PROTOCOL_VERSION)
+        .then_some(CongestionInfo::default());
+    let mut prev_block = genesis;
+    let mut blocks = vec![prev_block.clone()];
+    let mut chunk_header = None;
+    for i in 1..=max_height {
+        let mut header = ShardChunkHeader::V3(
+            ShardChunkHeaderV3::new(
+                PROTOCOL_VERSION,
+                *prev_block.hash(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                0,
+                i as BlockHeight,
+                ShardId::new(0),
+                0,
+                0,
+                0,
+                Default::default(),
+                Default::default(),
+                vec![],
+                congestion_info,
+                BandwidthRequests::default_for_protocol_version(PROTOCOL_VERSION),
+                &signer,
+            )
+            .unwrap(),
+        );
+        *header.height_included_mut() = i as BlockHeight;
+        chunk_header.get_or_insert_with(|| header.clone());
+        add_block_with_chunks(
+            &mut chain,
+            epoch_manager.as_ref(),
+            &mut prev_block,
+            &mut blocks,
+            signer.clone(),
+            i as BlockHeight,
+            Some(vec![header]),
+        );
+    }
+
+    // Save the transaction and the receipt of the chunk created at height 1.
+    let chunk_header = chunk_header.unwrap();
+    let chunk_hash = chunk_header.chunk_hash();
+    let transaction = SignedTransaction::empty(*blocks[0].hash());
+    let receipt =
+        Receipt::new_balance_refund(&"test1".parse().unwrap(), 1, ReceiptPriority::NoPriority);
+    let mut store_update = chain.mut_chain_store().store_update();
+    store_update.save_chunk(ShardChunk::V2(ShardChunkV2 {
+        chunk_hash: chunk_hash.clone(),
+        header: chunk_header.clone(),
+        transactions: vec![transaction.clone()],
+        prev_outgoing_receipts: vec![],
+    }));
+    store_update.save_partial_chunk(PartialEncodedChunk::V2(PartialEncodedChunkV2 {
+        header: chunk_header,
+        parts: vec![],
+        prev_outgoing_receipts: vec![ReceiptProof(
+            vec![receipt.clone()],
+            ShardProof {
+                from_shard_id: ShardId::new(0),
+                to_shard_id: ShardId::new(0),
+                proof: vec![],
+            },
+        )],
+    }));
+    store_update.commit().unwrap();
+
+    let store = chain.chain_store().store().clone();
+    let tx_key = transaction.get_hash();
+    let receipt_key = *receipt.receipt_id();
+    assert!(store.exists(DBCol::Chunks, chunk_hash.as_ref()).unwrap());
+    assert!(store.exists(DBCol::Transactions, tx_key.as_ref()).unwrap());
+    assert!(store.exists(DBCol::Receipts, receipt_key.as_ref()).unwrap());
+
+    let gc_config = GCConfig {
+        gc_blocks_limit: 100,
+        column_retention: [
+            (GCRetainedColumn::Transactions, GCRetentionPolicy::Epochs(1000)),
+            (GCRetainedColumn::Receipts, GCRetentionPolicy::Epochs(1000)),
+        ]
+        .into_iter()
+        .collect(),
+        ..GCConfig::default()
+    };
+    chain.clear_data(&gc_config).unwrap();
+
+    assert!(!store.exists(DBCol::Chunks, chunk_hash.as_ref()).unwrap());
+    assert!(!store.exists(DBCol::PartialChunks, chunk_hash.as_ref()).unwrap());
+    assert!(store.exists(DBCol::Transactions, tx_key.as_ref()).unwrap());
+    assert!(store.exists(DBCol::Receipts, receipt_key.as_ref()).unwrap());
+
+    chain.clear_data(&GCConfig { gc_blocks_limit: 100, ..GCConfig::default() }).unwrap();
+
+    assert!(!store.exists(DBCol::Transactions, tx_key.as_ref()).unwrap());
+    assert!(!store.exists(DBCol::Receipts, receipt_key.as_ref()).unwrap());
+    assert!(store.iter(DBCol::GCRetainedKeys).next().is_none());
+}
+
+/// Test that the removal of an untracked shard scheduled at an epoch start
+/// removes its flat storage and State once the block is final, and leaves the
+/// shard which is still tracked intact.
+#[test]
+fn test_clear_untracked_shards() {
+    let mut chain = get_chain_with_epoch_length_and_num_shards(Clock::real(), 5, 2);
+    let epoch_manager = chain.epoch_manager.clone();
+    let genesis = chain.get_block_by_height(0).unwrap();
+    let signer = Arc::new(create_test_signer("test1"));
+    let mut prev_block = genesis.clone();
+    let mut blocks = vec![prev_block.clone()];
+    for i in 1..=6 {
+        add_block(
+            &mut chain,
+            epoch_manager.as_ref(),
+            &mut prev_block,
+            &mut blocks,
+            signer.clone(),
+            i as BlockHeight,
+        );
+    }
+    let shard_layout = epoch_manager.get_shard_layout(genesis.header().epoch_id()).unwrap();
+    let shard_uids: Vec<ShardUId> = shard_layout.shard_uids().collect();
+    let (tracked, untracked) = (shard_uids[0], shard_uids[1]);
+
+    let store = chain.chain_store().store().clone();
+    let mut store_update = store.store_update();
+    for shard_uid in [tracked, untracked] {
+        let state_key = [shard_uid.to_bytes().as_slice(), &[1; 32]].concat();
+        store_update.increment_refcount(DBCol::State, &state_key, &[1]);
+        let mut flat_store_update = store_update.flat_store_update();
+        flat_store_update.set(shard_uid, vec![1], Some(FlatStateValue::inlined(&[1])));
+        flat_store_update.set_flat_storage_status(
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus {
+                flat_head: FlatBlockInfo::genesis(*genesis.hash(), 0),
+            }),
+        );
+    }
+    // The removal scheduled at height 3 is final, the one at height 6 isn't.
+    let cleanup =
+        UntrackedShardsCleanup { untracked_shards: vec![untracked], tracked_shards: vec![tracked] };
+    for block in [&blocks[3], &blocks[6]] {
+        store_update
+            .set_ser(DBCol::UntrackedShardsCleanup, block.hash().as_ref(), &cleanup)
+            .unwrap();
+    }
+    store_update.commit().unwrap();
+    let mut chain_store_update = chain.mut_chain_store().store_update();
+    chain_store_update.save_final_head(&Tip::from_header(blocks[4].header())).unwrap();
+    chain_store_update.commit().unwrap();
+
+    chain.clear_data(&GCConfig::default()).unwrap();
+
+    let has_state = |shard_uid: ShardUId| {
+        store.iter_prefix(DBCol::State, &shard_uid.to_bytes()).next().is_some()
+    };
+    let has_flat_state = |shard_uid: ShardUId| {
+        store.iter_prefix(DBCol::FlatState, &shard_uid.to_bytes()).next().is_some()
+    };
+    let flat_storage_status =
+        |shard_uid: ShardUId| store.flat_store().get_flat_storage_status(shard_uid).unwrap();
+    assert!(has_state(tracked));
+    assert!(has_flat_state(tracked));
+    assert!(matches!(flat_storage_status(tracked), FlatStorageStatus::Ready(_)));
+    assert!(!has_state(untracked));
+    assert!(!has_flat_state(untracked));
+    assert_eq!(flat_storage_status(untracked), FlatStorageStatus::Empty);
+    assert!(!store.exists(DBCol::UntrackedShardsCleanup, blocks[3].hash().as_ref()).unwrap());
+    assert!(store.exists(DBCol::UntrackedShardsCleanup, blocks[6].hash().as_ref()).unwrap());
+}
+
 // Adds block to the chain at given height after prev_block.
 fn add_block(
     chain: &mut Chain,
@@ -728,26 +1058,44 @@ fn add_block(
     blocks: &mut Vec<Block>,
     signer: Arc<ValidatorSigner>,
     height: u64,
+) {
+    add_block_with_chunks(chain, epoch_manager, prev_block, blocks, signer, height, None);
+}
+
+// Adds block to the chain at given height after prev_block. The block reuses
+// the chunks of prev_block unless `chunks` are given.
+fn add_block_with_chunks(
+    chain: &mut Chain,
+    epoch_manager: &dyn EpochManagerAdapter,
+    prev_block: &mut Block,
+    blocks: &mut Vec<Block>,
+    signer: Arc<ValidatorSigner>,
+    height: u64,
+    chunks: Option<Vec<ShardChunkHeader>>,
 ) {
     let next_epoch_id = epoch_manager
         .get_next_epoch_id_from_prev_block(prev_block.hash())
         .expect("block must exist");
+    let clock = chain.clock.clone();
     let mut store_update = chain.mut_chain_store().store_update();
 
-    let block = if next_epoch_id == *prev_block.header().next_epoch_id() {
-        TestBlockBuilder::new(Clock::real(), &prev_block, signer).height(height).build()
+    let mut builder = if next_epoch_id == *prev_block.header().next_epoch_id() {
+        TestBlockBuilder::new(clock, &prev_block, signer).height(height)
     } else {
         let prev_hash = prev_block.hash();
         let epoch_id = *prev_block.header().next_epoch_id();
         let next_bp_hash =
             Chain::compute_bp_hash(epoch_manager, next_epoch_id, epoch_id, &prev_hash).unwrap();
-        TestBlockBuilder::new(Clock::real(), &prev_block, signer)
+        TestBlockBuilder::new(clock, &prev_block, signer)
             .height(height)
             .epoch_id(epoch_id)
             .next_epoch_id(next_epoch_id)
             .next_bp_hash(next_bp_hash)
-            .build()
     };
+    if let Some(chunks) = chunks {
+        builder = builder.chunks(chunks);
+    }
+    let block = builder.build();
     blocks.push(block.clone());
     store_update.save_block(block.clone());
     store_update.inc_block_refcount(block.header().prev_hash()).unwrap();
@@ -596,7 +596,7 @@ mod tests {
         assert!(b1.header().verify_block_producer(&signer.public_key()));
         let other_signer = create_test_signer("other2");
         let approvals =
-            vec![Some(Box::new(Approval::new(*b1.hash(), 1, 2, &other_signer).signature))];
+            vec![Some(Box::new(Approval::new(*b1.hash(), 1, 2, &other_signer).unwrap().signature))];
         let b2 =
             TestBlockBuilder::new(Clock::real(), &b1, signer.clone()).approvals(approvals).build();
         b2.header().verify_block_producer(&signer.public_key());
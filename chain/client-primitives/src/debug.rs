@@ -21,6 +21,11 @@ use std::collections::HashMap;
 pub struct TrackedShardsView {
     pub shards_tracked_this_epoch: Vec<bool>,
     pub shards_tracked_next_epoch: Vec<bool>,
+    /// Tracked config used for the epochs after the next one. It may differ
+    /// from the config used in this and the next epoch if it was updated
+    /// while the node was running.
+    #[serde(default)]
+    pub tracked_config: String,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
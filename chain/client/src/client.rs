@@ -42,7 +42,7 @@ use near_chunks::logic::{
 use near_chunks::shards_manager_actor::ShardsManagerActor;
 use near_client_primitives::debug::ChunkProduction;
 use near_client_primitives::types::{Error, StateSyncStatus};
-use near_epoch_manager::shard_tracker::ShardTracker;
+use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
 use near_epoch_manager::EpochManagerAdapter;
 use near_network::client::ProcessTxResponse;
 use near_network::types::{AccountKeys, ChainInfo, PeerManagerMessageRequest, SetChainInfo};
@@ -220,9 +220,31 @@ impl Client {
             .config
             .produce_chunk_add_transactions_time_limit
             .update(update_client_config.produce_chunk_add_transactions_time_limit);
+        is_updated |= self.update_tracked_config(&update_client_config);
         is_updated
     }
 
+    /// Updates the tracked shards. The shards tracked in the current and the
+    /// next epoch stay the same, the new config applies to later epochs.
+    fn update_tracked_config(&self, update_client_config: &UpdateableClientConfig) -> bool {
+        let tracked_config =
+            TrackedConfig::from_updateable_config(&self.config, update_client_config);
+        let pinned_epochs = match self.chain.head() {
+            Ok(tip) => vec![tip.epoch_id, tip.next_epoch_id],
+            Err(err) => {
+                error!(target: "client", ?err, "Failed to get chain head, not updating tracked config");
+                return false;
+            }
+        };
+        match self.shard_tracker.update_tracked_config(tracked_config, &pinned_epochs) {
+            Ok(is_updated) => is_updated,
+            Err(err) => {
+                error!(target: "client", ?err, "Failed to update tracked config");
+                false
+            }
+        }
+    }
+
     /// Updates client's mutable validator signer.
     /// It will update all validator signers that synchronize with it.
     pub(crate) fn update_validator_signer(&self, signer: Option<Arc<ValidatorSigner>>) -> bool {
@@ -818,7 +840,8 @@ impl Client {
             block_merkle_root,
             self.clock.clone(),
             sandbox_delta_time,
-        );
+        )
+        .map_err(|err| Error::BlockProducer(err.to_string()))?;
 
         // Update latest known even before returning block out, to prevent race conditions.
         self.chain
@@ -1104,7 +1127,13 @@ impl Client {
     ) {
         if let Some(validator_signer) = &signer {
             for body in challenges {
-                let challenge = Challenge::produce(body, &**validator_signer);
+                let challenge = match Challenge::produce(body, &**validator_signer) {
+                    Ok(challenge) => challenge,
+                    Err(err) => {
+                        warn!(target: "client", ?err, "Failed to sign challenge");
+                        continue;
+                    }
+                };
                 self.challenges.insert(challenge.hash, challenge.clone());
                 self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
                     NetworkRequests::Challenge(challenge),
@@ -1337,24 +1366,24 @@ impl Client {
         // Send out challenge if the block was found to be invalid.
         if let Some(signer) = signer {
             if let Err(e) = &result {
-                match e {
+                let body = match e {
                     near_chain::Error::InvalidChunkProofs(chunk_proofs) => {
-                        self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
-                            NetworkRequests::Challenge(Challenge::produce(
-                                ChallengeBody::ChunkProofs(*chunk_proofs.clone()),
-                                &*signer,
-                            )),
-                        ));
+                        Some(ChallengeBody::ChunkProofs(*chunk_proofs.clone()))
                     }
                     near_chain::Error::InvalidChunkState(chunk_state) => {
-                        self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
-                            NetworkRequests::Challenge(Challenge::produce(
-                                ChallengeBody::ChunkState(*chunk_state.clone()),
-                                &*signer,
-                            )),
-                        ));
+                        Some(ChallengeBody::ChunkState(*chunk_state.clone()))
+                    }
+                    _ => None,
+                };
+                if let Some(body) = body {
+                    match Challenge::produce(body, &*signer) {
+                        Ok(challenge) => {
+                            self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+                                NetworkRequests::Challenge(challenge),
+                            ));
+                        }
+                        Err(err) => warn!(target: "client", ?err, "Failed to sign challenge"),
                     }
-                    _ => {}
                 }
             }
         }
@@ -2772,7 +2801,7 @@ impl Client {
         // convert config tracked shards
         // runtime will track all shards if config tracked shards is not empty
         // https://github.com/near/nearcore/issues/4930
-        let tracked_shards = if self.config.tracked_shards.is_empty() {
+        let tracked_shards = if !self.shard_tracker.tracks_all_shards() {
             vec![]
         } else {
             self.epoch_manager.shard_ids(&tip.epoch_id)?
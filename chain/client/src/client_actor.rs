@@ -968,7 +968,13 @@ impl ClientActorInner {
             self.last_validator_announce_time = Some(now);
 
             let announce_account =
-                AnnounceAccount::new(signer.as_ref(), self.node_id.clone(), next_epoch_id);
+                match AnnounceAccount::new(signer.as_ref(), self.node_id.clone(), next_epoch_id) {
+                    Ok(announce_account) => announce_account,
+                    Err(err) => {
+                        warn!(target: "client", ?err, "Failed to sign announce account");
+                        return;
+                    }
+                };
             self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
                 NetworkRequests::AnnounceAccount(announce_account),
             ));
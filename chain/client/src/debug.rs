@@ -383,7 +383,12 @@ impl ClientActorInner {
                 )
             })
             .collect();
-        Ok(TrackedShardsView { shards_tracked_this_epoch, shards_tracked_next_epoch })
+        let tracked_config = format!("{:?}", self.client.shard_tracker.tracked_config());
+        Ok(TrackedShardsView {
+            shards_tracked_this_epoch,
+            shards_tracked_next_epoch,
+            tracked_config,
+        })
     }
 
     fn get_recent_epoch_info(
@@ -620,7 +620,10 @@ impl InfoHelper {
         // Sign telemetry if there is a signer present.
         if let Some(signer) = signer {
             let content = serde_json::to_string(&json).expect("Telemetry must serialize to JSON");
-            json["signature"] = signer.sign_bytes(content.as_bytes()).to_string().into();
+            match signer.sign_telemetry(&content) {
+                Ok(signature) => json["signature"] = signature.to_string().into(),
+                Err(err) => tracing::warn!(target: "telemetry", ?err, "Failed to sign telemetry"),
+            }
         }
         json
     }
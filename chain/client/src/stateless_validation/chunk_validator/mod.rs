@@ -208,7 +208,13 @@ pub(crate) fn send_chunk_endorsement_to_block_producers(
         "send_chunk_endorsement",
     );
 
-    let endorsement = ChunkEndorsement::new(epoch_id, chunk_header, signer);
+    let endorsement = match ChunkEndorsement::new(epoch_id, chunk_header, signer) {
+        Ok(endorsement) => endorsement,
+        Err(err) => {
+            tracing::error!(target: "client", %err, "failed to sign chunk endorsement");
+            return;
+        }
+    };
     for block_producer in block_producers {
         network_sender.send(PeerManagerMessageRequest::NetworkRequests(
             NetworkRequests::ChunkEndorsement(block_producer, endorsement.clone()),
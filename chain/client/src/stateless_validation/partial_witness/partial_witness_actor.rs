@@ -232,7 +232,7 @@ impl PartialWitnessActor {
                 },
                 &chunk_validators,
                 &signer,
-            );
+            )?;
         }
 
         let witness_bytes = compress_witness(&state_witness)?;
@@ -242,7 +242,7 @@ impl PartialWitnessActor {
             witness_bytes,
             &chunk_validators,
             &signer,
-        );
+        )?;
 
         if !contract_deploys.is_empty() {
             self.send_chunk_contract_deploys_parts(key, contract_deploys)?;
@@ -258,7 +258,7 @@ impl PartialWitnessActor {
         witness_bytes: EncodedChunkStateWitness,
         chunk_validators: &[AccountId],
         signer: &ValidatorSigner,
-    ) -> Vec<(AccountId, PartialEncodedStateWitness)> {
+    ) -> Result<Vec<(AccountId, PartialEncodedStateWitness)>, Error> {
         tracing::debug!(
             target: "client",
             chunk_hash=?chunk_header.chunk_hash(),
@@ -274,7 +274,7 @@ impl PartialWitnessActor {
             .iter()
             .zip_eq(parts)
             .enumerate()
-            .map(|(part_ord, (chunk_validator, part))| {
+            .map(|(part_ord, (chunk_validator, part))| -> Result<_, Error> {
                 // It's fine to unwrap part here as we just constructed the parts above and we expect
                 // all of them to be present.
                 let partial_witness = PartialEncodedStateWitness::new(
@@ -284,10 +284,10 @@ impl PartialWitnessActor {
                     part.unwrap().to_vec(),
                     encoded_length,
                     signer,
-                );
-                (chunk_validator.clone(), partial_witness)
+                )?;
+                Ok((chunk_validator.clone(), partial_witness))
             })
-            .collect_vec()
+            .collect()
     }
 
     fn generate_contract_deploys_parts(
@@ -306,11 +306,11 @@ impl PartialWitnessActor {
         let (parts, encoded_length) = encoder.encode(&deploys);
         let signer = self.my_validator_signer()?;
 
-        Ok(validators
+        validators
             .into_iter()
             .zip_eq(parts)
             .enumerate()
-            .map(|(part_ord, (validator, part))| {
+            .map(|(part_ord, (validator, part))| -> Result<_, Error> {
                 let partial_deploys = PartialEncodedContractDeploys::new(
                     key.clone(),
                     PartialEncodedContractDeploysPart {
@@ -319,10 +319,10 @@ impl PartialWitnessActor {
                         encoded_length,
                     },
                     &signer,
-                );
-                (validator, partial_deploys)
+                )?;
+                Ok((validator, partial_deploys))
             })
-            .collect_vec())
+            .collect()
     }
 
     // Break the state witness into parts and send each part to the corresponding chunk validator owner.
@@ -335,7 +335,7 @@ impl PartialWitnessActor {
         witness_bytes: EncodedChunkStateWitness,
         chunk_validators: &[AccountId],
         signer: &ValidatorSigner,
-    ) {
+    ) -> Result<(), Error> {
         // Capture these values first, as the sources are consumed before calling record_witness_sent.
         let chunk_hash = chunk_header.chunk_hash();
         let witness_size_in_bytes = witness_bytes.size_bytes();
@@ -351,7 +351,7 @@ impl PartialWitnessActor {
             witness_bytes,
             chunk_validators,
             signer,
-        );
+        )?;
         encode_timer.observe_duration();
 
         // Record the witness in order to match the incoming acks for measuring round-trip times.
@@ -366,6 +366,7 @@ impl PartialWitnessActor {
         self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
             NetworkRequests::PartialEncodedStateWitness(validator_witness_tuple),
         ));
+        Ok(())
     }
 
     /// Function to handle receiving partial_encoded_state_witness message from chunk producer.
@@ -632,7 +633,7 @@ impl PartialWitnessActor {
             missing_contract_hashes,
             accesses.main_transition().clone(),
             &signer,
-        );
+        )?;
         self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
             NetworkRequests::ContractCodeRequest(random_chunk_producer, request),
         ));
@@ -649,7 +650,7 @@ impl PartialWitnessActor {
         main_transition: MainTransitionKey,
         chunk_validators: &[AccountId],
         my_signer: &ValidatorSigner,
-    ) {
+    ) -> Result<(), Error> {
         let chunk_producers: HashSet<AccountId> = self
             .epoch_manager
             .get_epoch_chunk_producers_for_shard(&key.epoch_id, key.shard_id)
@@ -663,12 +664,12 @@ impl PartialWitnessActor {
             .filter(|validator| !chunk_producers.contains(*validator))
             .cloned()
             .collect();
+        let accesses =
+            ChunkContractAccesses::new(key, contract_accesses, main_transition, my_signer)?;
         self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
-            NetworkRequests::ChunkContractAccesses(
-                target_chunk_validators,
-                ChunkContractAccesses::new(key, contract_accesses, main_transition, my_signer),
-            ),
+            NetworkRequests::ChunkContractAccesses(target_chunk_validators, accesses),
         ));
+        Ok(())
     }
 
     /// Retrieves the code for the given contract hashes and distributes them to validator in parts.
@@ -679,7 +679,40 @@ impl EpochSync {
             return Ok(());
         }
 
-        self.verify_proof(&proof, epoch_manager)?;
+        Self::verify_and_store_proof(chain, &self.genesis, proof, epoch_manager)?;
+
+        *status = SyncStatus::EpochSyncDone;
+        Ok(())
+    }
+
+    /// Bootstraps a node that is still at genesis from a proof obtained out of
+    /// band, e.g. exported to a file by another node. The proof goes through the
+    /// same verification as a proof received from a peer; only the checks about
+    /// the height of the peer are skipped since there is no peer.
+    pub fn import_proof(
+        chain: &mut Chain,
+        proof: EpochSyncProof,
+        epoch_manager: &dyn EpochManagerAdapter,
+    ) -> Result<(), Error> {
+        let tip_height = chain.chain_store().header_head()?.height;
+        if tip_height != chain.genesis().height() {
+            return Err(Error::Other(format!(
+                "Epoch sync proof can only be imported at genesis, but header head is at height {}",
+                tip_height
+            )));
+        }
+        let genesis = chain.genesis().clone();
+        Self::verify_and_store_proof(chain, &genesis, proof.into_v1(), epoch_manager)
+    }
+
+    /// Verifies the proof and initializes the chain and the epoch manager from it.
+    fn verify_and_store_proof(
+        chain: &mut Chain,
+        genesis: &BlockHeader,
+        proof: EpochSyncProofV1,
+        epoch_manager: &dyn EpochManagerAdapter,
+    ) -> Result<(), Error> {
+        Self::verify_proof(&proof, genesis, epoch_manager)?;
 
         let mut store_update = chain.chain_store.store().store_update();
 
@@ -707,7 +740,7 @@ impl EpochSync {
                 .clone(),
         )?;
         update.force_save_header_head(&Tip::from_header(&last_header))?;
-        update.save_final_head(&Tip::from_header(&self.genesis))?;
+        update.save_final_head(&Tip::from_header(genesis))?;
 
         epoch_manager.init_after_epoch_sync(
             &mut store_update,
@@ -761,15 +794,14 @@ impl EpochSync {
         update.merge(store_update);
         update.commit()?;
 
-        *status = SyncStatus::EpochSyncDone;
         tracing::info!(epoch_id=?last_header.epoch_id(), "Bootstrapped from epoch sync");
 
         Ok(())
     }
 
     fn verify_proof(
-        &self,
         proof: &EpochSyncProofV1,
+        genesis: &BlockHeader,
         epoch_manager: &dyn EpochManagerAdapter,
     ) -> Result<(), Error> {
         let EpochSyncProofV1 { all_epochs, last_epoch, current_epoch } = proof;
@@ -780,7 +812,7 @@ impl EpochSync {
         }
 
         // Verify block producer handoff to the second epoch after genesis.
-        let second_next_epoch_id_after_genesis = EpochId(*self.genesis.hash());
+        let second_next_epoch_id_after_genesis = EpochId(*genesis.hash());
         let second_next_epoch_info_after_genesis =
             epoch_manager.get_epoch_info(&second_next_epoch_id_after_genesis)?;
         if all_epochs[0].block_producers
@@ -9,8 +9,14 @@ use std::time::Instant;
 
 #[derive(Debug, Clone)]
 pub enum StateFileType {
-    StatePart { part_id: u64, num_parts: u64 },
+    StatePart {
+        part_id: u64,
+        num_parts: u64,
+    },
     StateHeader,
+    /// Signed list of the hashes and sizes of all state parts of a shard.
+    /// Stored next to the header, because the parts directory must only contain parts.
+    StateManifest,
 }
 
 impl ToString for StateFileType {
@@ -18,6 +24,7 @@ impl ToString for StateFileType {
         match self {
             StateFileType::StatePart { .. } => StateFileType::part_str(),
             StateFileType::StateHeader => StateFileType::header_str(),
+            StateFileType::StateManifest => StateFileType::manifest_str(),
         }
     }
 }
@@ -31,12 +38,17 @@ impl StateFileType {
         String::from("header")
     }
 
+    pub fn manifest_str() -> String {
+        String::from("manifest")
+    }
+
     pub fn filename(&self) -> String {
         match self {
             StateFileType::StatePart { part_id, num_parts } => {
                 format!("state_part_{:06}_of_{:06}", part_id, num_parts)
             }
             StateFileType::StateHeader => "header".to_string(),
+            StateFileType::StateManifest => "manifest".to_string(),
         }
     }
 }
@@ -58,12 +70,28 @@ pub enum ExternalConnection {
         reqwest_client: Arc<reqwest::Client>,
         bucket: String,
     },
+    HTTP {
+        reqwest_client: Arc<reqwest::Client>,
+        /// URL without the trailing slash.
+        base_url: String,
+    },
 }
 
+/// Name of the file listing the files in a directory of an HTTP location.
+/// It contains a JSON array of the file names and is written by `put_listing()`.
+pub const HTTP_LISTING_FILENAME: &str = "listing.json";
+
 const GCS_ENCODE_SET: &percent_encoding::AsciiSet =
     &percent_encoding::NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_');
 
 impl ExternalConnection {
+    pub fn new_http(base_url: &str) -> Self {
+        ExternalConnection::HTTP {
+            reqwest_client: Arc::new(reqwest::Client::default()),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
     pub async fn get_file(
         &self,
         shard_id: ShardId,
@@ -113,6 +141,24 @@ impl ExternalConnection {
                     }
                 }
             }
+            ExternalConnection::HTTP { reqwest_client, base_url, .. } => {
+                let url = format!("{}/{}", base_url, location);
+                let response = reqwest_client.get(&url).send().await?.error_for_status();
+                match response {
+                    Err(e) => {
+                        tracing::debug!(target: "sync", %shard_id, location, error = ?e, "HTTP state_part request failed");
+                        Err(e.into())
+                    }
+                    Ok(r) => {
+                        let bytes = r.bytes().await?.to_vec();
+                        tracing::debug!(target: "sync", %shard_id, location, num_bytes = bytes.len(), "HTTP state_part request finished");
+                        metrics::STATE_SYNC_EXTERNAL_PARTS_SIZE_DOWNLOADED
+                            .with_label_values(&[&shard_id.to_string(), &file_type.to_string()])
+                            .inc_by(bytes.len() as u64);
+                        Ok(bytes)
+                    }
+                }
+            }
         }
     }
 
@@ -171,6 +217,40 @@ impl ExternalConnection {
                 tracing::debug!(target: "state_sync_dump", ?shard_id, part_length = data.len(), ?location, ?file_type, "Wrote a state part to GCS");
                 Ok(())
             }
+            ExternalConnection::HTTP { reqwest_client, base_url } => {
+                let url = format!("{}/{}", base_url, location);
+                reqwest_client.put(&url).body(data.to_vec()).send().await?.error_for_status()?;
+                tracing::debug!(target: "state_sync_dump", ?shard_id, part_length = data.len(), ?location, ?file_type, "Wrote a state part over HTTP");
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the list of files stored in `directory_path`. Only HTTP locations need it, as they
+    /// can't be listed otherwise; for other locations this is a no-op.
+    /// Called once per directory after all of its files are uploaded, so that the listing
+    /// isn't rewritten for every file.
+    pub async fn put_listing(
+        &self,
+        shard_id: ShardId,
+        directory_path: &str,
+        file_names: &[String],
+    ) -> Result<(), anyhow::Error> {
+        match self {
+            ExternalConnection::S3 { .. }
+            | ExternalConnection::Filesystem { .. }
+            | ExternalConnection::GCS { .. } => Ok(()),
+            ExternalConnection::HTTP { reqwest_client, base_url } => {
+                let url = format!("{}/{}/{}", base_url, directory_path, HTTP_LISTING_FILENAME);
+                reqwest_client
+                    .put(&url)
+                    .body(serde_json::to_vec(file_names)?)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                tracing::debug!(target: "state_sync_dump", ?shard_id, ?directory_path, num_files = file_names.len(), "Wrote a listing over HTTP");
+                Ok(())
+            }
         }
     }
 
@@ -241,6 +321,21 @@ impl ExternalConnection {
                     .flatten()
                     .collect())
             }
+            ExternalConnection::HTTP { reqwest_client, base_url, .. } => {
+                let url = format!("{}/{}/{}", base_url, directory_path, HTTP_LISTING_FILENAME);
+                tracing::debug!(target: "state_sync_dump", ?shard_id, ?directory_path, "List state parts over HTTP");
+                let response = reqwest_client.get(&url).send().await?;
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    // Nothing has been uploaded to the directory yet.
+                    return Ok(vec![]);
+                }
+                let file_names: Vec<String> =
+                    serde_json::from_slice(&response.error_for_status()?.bytes().await?)?;
+                Ok(file_names
+                    .into_iter()
+                    .filter(|file_name| file_name != HTTP_LISTING_FILENAME)
+                    .collect())
+            }
         }
     }
 
@@ -312,7 +407,7 @@ pub fn location_prefix(
             "chain_id={}/epoch_height={}/epoch_id={}/shard_id={}",
             chain_id, epoch_height, epoch_id.0, shard_id
         ),
-        StateFileType::StateHeader => format!(
+        StateFileType::StateHeader | StateFileType::StateManifest => format!(
             "chain_id={}/epoch_height={}/epoch_id={}/headers/shard_id={}",
             chain_id, epoch_height, epoch_id.0, shard_id
         ),
@@ -410,11 +505,13 @@ fn create_bucket(
 mod test {
     use crate::sync::external::{
         get_num_parts_from_filename, get_part_id_from_filename, is_part_filename,
-        ExternalConnection, StateFileType,
+        ExternalConnection, StateFileType, HTTP_LISTING_FILENAME,
     };
     use near_o11y::testonly::init_test_logger;
     use near_primitives::types::ShardId;
     use rand::distributions::{Alphanumeric, DistString};
+    use std::io::{BufRead, Write};
+    use std::path::PathBuf;
 
     fn random_string(rand_len: usize) -> String {
         Alphanumeric.sample_string(&mut rand::thread_rng(), rand_len)
@@ -433,6 +530,78 @@ mod test {
         assert_eq!(get_part_id_from_filename("123123"), None);
     }
 
+    /// Serves the files in `root_dir` over HTTP, like a static file server or a CDN would.
+    /// Returns the base URL of the server.
+    fn start_static_file_server(root_dir: PathBuf) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                // Skip the headers.
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let path = request_line.split_whitespace().nth(1).unwrap();
+                let response = match std::fs::read(root_dir.join(path.trim_start_matches('/'))) {
+                    Ok(data) => {
+                        let mut response = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                            data.len()
+                        )
+                        .into_bytes();
+                        response.extend(data);
+                        response
+                    }
+                    Err(_) => {
+                        b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_vec()
+                    }
+                };
+                stream.write_all(&response).unwrap();
+            }
+        });
+        format!("http://{addr}/")
+    }
+
+    #[test]
+    fn test_http_list_download() {
+        init_test_logger();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        let root_dir = tempfile::tempdir().unwrap();
+        let dir = "chain_id=test/epoch_height=1/epoch_id=test/shard_id=0";
+        let file_type = StateFileType::StatePart { part_id: 0, num_parts: 1 };
+        let full_filename = format!("{}/{}", dir, file_type.filename());
+        std::fs::create_dir_all(root_dir.path().join(dir)).unwrap();
+        std::fs::write(root_dir.path().join(&full_filename), b"state part").unwrap();
+        std::fs::write(
+            root_dir.path().join(dir).join(HTTP_LISTING_FILENAME),
+            serde_json::to_vec(&[file_type.filename()]).unwrap(),
+        )
+        .unwrap();
+
+        let connection =
+            ExternalConnection::new_http(&start_static_file_server(root_dir.path().to_path_buf()));
+        let shard_id = ShardId::new(0);
+        rt.block_on(async {
+            let files = connection.list_objects(shard_id, dir).await.unwrap();
+            assert_eq!(files, vec![file_type.filename()]);
+            // A directory without a listing is empty.
+            let files = connection.list_objects(shard_id, "chain_id=test").await.unwrap();
+            assert!(files.is_empty());
+
+            let data = connection.get_file(shard_id, &full_filename, &file_type).await.unwrap();
+            assert_eq!(data, b"state part");
+            let missing = format!("{}/{}", dir, random_string(8));
+            assert!(connection.get_file(shard_id, &missing, &file_type).await.is_err());
+        });
+    }
+
     /// This test should be ignored by default, as it requires gcloud credentials to run.
     /// Specify the path to service account json  in `SERVICE_ACCOUNT` variable to run the test.
     #[test]
@@ -6,15 +6,30 @@ use crate::sync::state::util::increment_download_count;
 use borsh::BorshDeserialize;
 use futures::future::BoxFuture;
 use futures::FutureExt;
-use near_async::time::{Clock, Duration};
+use near_async::time::{Clock, Duration, Instant};
+use near_crypto::PublicKey;
 use near_primitives::hash::CryptoHash;
-use near_primitives::state_sync::ShardStateSyncResponseHeader;
-use near_primitives::types::ShardId;
+use near_primitives::state_sync::{
+    decompress_state_part, ShardStateSyncResponseHeader, SignedStatePartsManifest,
+};
+use near_primitives::types::{EpochId, ShardId};
 use near_store::Store;
+use std::num::NonZeroUsize;
 use std::sync::Arc;
 use tokio_util::sync::CancellationToken;
 use tracing::Instrument;
 
+/// Manifests of the state parts, by shard and sync hash.
+type ManifestCache = tokio::sync::Mutex<lru::LruCache<(ShardId, CryptoHash), CachedManifest>>;
+
+enum CachedManifest {
+    Valid(Arc<SignedStatePartsManifest>),
+    /// The dumper didn't upload a valid manifest as of the given time, so
+    /// parts are only validated against the state root. The manifest is
+    /// downloaded again once `MISSING_MANIFEST_TTL` has passed.
+    Missing(Instant),
+}
+
 /// Logic for downloading state sync headers and parts from an external source.
 pub(super) struct StateSyncDownloadSourceExternal {
     pub clock: Clock,
@@ -23,9 +38,109 @@ pub(super) struct StateSyncDownloadSourceExternal {
     pub conn: ExternalConnection,
     pub timeout: Duration,
     pub backoff: Duration,
+    pub manifests: Arc<ManifestCache>,
+    /// Keys of the dumpers whose manifests are trusted.
+    pub trusted_dumper_keys: Arc<[PublicKey]>,
 }
 
+/// Error message of a download that didn't finish in time.
+const TIMEOUT_ERROR: &str = "Timeout";
+
+fn is_timeout(err: &near_chain::Error) -> bool {
+    matches!(err, near_chain::Error::Other(msg) if msg == TIMEOUT_ERROR)
+}
+
+/// Number of shards for which the manifests of the state parts are cached.
+const MANIFEST_CACHE_SIZE: usize = 64;
+
+/// How long a missing or invalid manifest is remembered before it's
+/// downloaded again, in case the dumper uploads it later.
+const MISSING_MANIFEST_TTL: Duration = Duration::seconds(60);
+
 impl StateSyncDownloadSourceExternal {
+    pub fn new_manifest_cache() -> Arc<ManifestCache> {
+        Arc::new(tokio::sync::Mutex::new(lru::LruCache::new(
+            NonZeroUsize::new(MANIFEST_CACHE_SIZE).unwrap(),
+        )))
+    }
+
+    /// Returns the manifest of the state parts of the shard, downloading it if it isn't cached yet
+    /// or was missing more than `MISSING_MANIFEST_TTL` ago. The cache isn't locked during the
+    /// download, so concurrent part downloads may fetch the same manifest.
+    async fn get_manifest(
+        clock: Clock,
+        timeout: Duration,
+        conn: ExternalConnection,
+        chain_id: &str,
+        manifests: &ManifestCache,
+        trusted_dumper_keys: &[PublicKey],
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+        epoch_id: EpochId,
+        epoch_height: u64,
+        num_parts: u64,
+        cancel: CancellationToken,
+    ) -> Option<Arc<SignedStatePartsManifest>> {
+        if trusted_dumper_keys.is_empty() {
+            // No manifest can be trusted.
+            return None;
+        }
+        match manifests.lock().await.get(&(shard_id, sync_hash)) {
+            Some(CachedManifest::Valid(manifest)) => return Some(manifest.clone()),
+            Some(CachedManifest::Missing(since)) if clock.now() < *since + MISSING_MANIFEST_TTL => {
+                return None;
+            }
+            _ => {}
+        }
+        let location = external_storage_location(
+            chain_id,
+            &epoch_id,
+            epoch_height,
+            shard_id,
+            &StateFileType::StateManifest,
+        );
+        let manifest = match Self::get_file_with_timeout(
+            clock.clone(),
+            timeout,
+            // The manifest is optional, don't delay the part downloads if it is missing.
+            Duration::ZERO,
+            cancel.clone(),
+            conn,
+            shard_id,
+            location,
+            StateFileType::StateManifest,
+        )
+        .await
+        {
+            Ok(data) => match SignedStatePartsManifest::try_from_slice(&data) {
+                Ok(manifest)
+                    if manifest.verify_signature(trusted_dumper_keys)
+                        && manifest.manifest.shard_id == shard_id
+                        && manifest.manifest.epoch_id == epoch_id
+                        && manifest.manifest.parts.len() as u64 == num_parts =>
+                {
+                    Some(Arc::new(manifest))
+                }
+                _ => {
+                    tracing::warn!(target: "sync", %shard_id, ?sync_hash, "Ignoring invalid state parts manifest");
+                    None
+                }
+            },
+            // Don't remember the result of an interrupted download, it may succeed later.
+            Err(err) if cancel.is_cancelled() || is_timeout(&err) => return None,
+            Err(err) => {
+                tracing::debug!(target: "sync", %shard_id, ?sync_hash, ?err, "No state parts manifest available");
+                None
+            }
+        };
+        let cached = match &manifest {
+            Some(manifest) => CachedManifest::Valid(manifest.clone()),
+            None => CachedManifest::Missing(clock.now()),
+        };
+        manifests.lock().await.put((shard_id, sync_hash), cached);
+        manifest
+    }
+
     async fn get_file_with_timeout(
         clock: Clock,
         timeout: Duration,
@@ -41,11 +156,12 @@ impl StateSyncDownloadSourceExternal {
         let typ = match &file_type {
             StateFileType::StateHeader => "header",
             StateFileType::StatePart { .. } => "part",
+            StateFileType::StateManifest => "manifest",
         };
         tokio::select! {
             _ = clock.sleep_until(deadline) => {
                 increment_download_count(shard_id, typ, "external", "timeout");
-                Err(near_chain::Error::Other("Timeout".to_owned()))
+                Err(near_chain::Error::Other(TIMEOUT_ERROR.to_owned()))
             }
             _ = cancellation.cancelled() => {
                 increment_download_count(shard_id, typ, "external", "cancelled");
@@ -134,6 +250,8 @@ impl StateSyncDownloadSource for StateSyncDownloadSourceExternal {
         let chain_id = self.chain_id.clone();
         let conn = self.conn.clone();
         let store = self.store.clone();
+        let manifests = self.manifests.clone();
+        let trusted_dumper_keys = self.trusted_dumper_keys.clone();
         async move {
             handle.set_status("Preparing download");
             let (epoch_id, epoch_height) = query_epoch_id_and_height_for_block(&store, sync_hash)?;
@@ -149,6 +267,22 @@ impl StateSyncDownloadSource for StateSyncDownloadSourceExternal {
                 shard_id,
                 &StateFileType::StatePart { part_id, num_parts },
             );
+            handle.set_status("Downloading manifest");
+            let manifest = Self::get_manifest(
+                clock.clone(),
+                timeout,
+                conn.clone(),
+                &chain_id,
+                &manifests,
+                &trusted_dumper_keys,
+                shard_id,
+                sync_hash,
+                epoch_id,
+                epoch_height,
+                num_parts,
+                cancel.clone(),
+            )
+            .await;
             handle.set_status("Downloading file");
             let data = Self::get_file_with_timeout(
                 clock,
@@ -161,6 +295,16 @@ impl StateSyncDownloadSource for StateSyncDownloadSourceExternal {
                 StateFileType::StatePart { part_id, num_parts },
             )
             .await?;
+            if let Some(manifest) = manifest {
+                manifest.verify_part(part_id, &data).map_err(|err| {
+                    increment_download_count(shard_id, "part", "external", "manifest_mismatch");
+                    near_chain::Error::Other(format!("State part doesn't match manifest: {}", err))
+                })?;
+            }
+            let data = decompress_state_part(data).map_err(|err| {
+                increment_download_count(shard_id, "part", "external", "decompression_error");
+                near_chain::Error::Other(format!("Failed to decompress state part: {}", err))
+            })?;
             increment_download_count(shard_id, "part", "external", "success");
             Ok(data)
         }
@@ -114,6 +114,7 @@ impl StateSync {
                 num_concurrent_requests,
                 num_concurrent_requests_during_catchup,
                 external_storage_fallback_threshold,
+                trusted_dumper_keys,
             }) = sync_config
             {
                 let external = match location {
@@ -136,6 +137,9 @@ impl StateSync {
                         reqwest_client: Arc::new(reqwest::Client::default()),
                         bucket: bucket.clone(),
                     },
+                    ExternalStorageLocation::HTTP { base_url } => {
+                        ExternalConnection::new_http(base_url)
+                    }
                 };
                 let num_concurrent_requests = if catchup {
                     *num_concurrent_requests_during_catchup
@@ -149,6 +153,8 @@ impl StateSync {
                     conn: external,
                     timeout: external_timeout,
                     backoff: external_backoff,
+                    manifests: StateSyncDownloadSourceExternal::new_manifest_cache(),
+                    trusted_dumper_keys: trusted_dumper_keys.clone().into(),
                 }) as Arc<dyn StateSyncDownloadSource>;
                 (
                     Some(fallback_source),
@@ -250,7 +250,7 @@ pub fn create_chunk(
 
     let signer = client.validator_signer.get().unwrap();
     let endorsement =
-        ChunkEndorsement::new(EpochId::default(), &chunk.cloned_header(), signer.as_ref());
+        ChunkEndorsement::new(EpochId::default(), &chunk.cloned_header(), signer.as_ref()).unwrap();
     block_merkle_tree.insert(*last_block.hash());
     let block = Block::produce(
         PROTOCOL_VERSION,
@@ -276,7 +276,8 @@ pub fn create_chunk(
         block_merkle_tree.root(),
         client.clock.clone(),
         None,
-    );
+    )
+    .unwrap();
     (
         ProduceChunkResult {
             chunk,
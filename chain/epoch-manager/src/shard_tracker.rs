@@ -1,14 +1,16 @@
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
 use crate::EpochManagerAdapter;
 use itertools::Itertools;
 use near_cache::SyncLruCache;
-use near_chain_configs::ClientConfig;
+use near_chain_configs::{ClientConfig, UpdateableClientConfig};
 use near_primitives::errors::EpochError;
 use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::types::{AccountId, EpochId, ShardId};
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum TrackedConfig {
     /// Tracks shards that contain one of the given account.
     Accounts(Vec<AccountId>),
@@ -18,6 +20,9 @@ pub enum TrackedConfig {
     AllShards,
     /// Rotates between sets of shards to track.
     Schedule(Vec<Vec<ShardId>>),
+    /// Tracks the given shards and, after resharding, the shards they split
+    /// into. The shard uids may refer to any past or current shard layout.
+    ShardLineage(Vec<ShardUId>),
 }
 
 impl TrackedConfig {
@@ -26,14 +31,36 @@ impl TrackedConfig {
     }
 
     pub fn from_config(config: &ClientConfig) -> Self {
-        if !config.tracked_shards.is_empty() {
+        Self::from_config_parts(config, &config.tracked_shards, &config.tracked_accounts)
+    }
+
+    /// Same as `from_config`, but takes the tracked accounts and shards from the dynamic config.
+    pub fn from_updateable_config(
+        config: &ClientConfig,
+        updateable_config: &UpdateableClientConfig,
+    ) -> Self {
+        Self::from_config_parts(
+            config,
+            &updateable_config.tracked_shards,
+            &updateable_config.tracked_accounts,
+        )
+    }
+
+    fn from_config_parts(
+        config: &ClientConfig,
+        tracked_shards: &[ShardId],
+        tracked_accounts: &[AccountId],
+    ) -> Self {
+        if !tracked_shards.is_empty() {
             TrackedConfig::AllShards
         } else if !config.tracked_shard_schedule.is_empty() {
             TrackedConfig::Schedule(config.tracked_shard_schedule.clone())
+        } else if !config.tracked_shard_lineage.is_empty() {
+            TrackedConfig::ShardLineage(config.tracked_shard_lineage.clone())
         } else if let Some(account_id) = config.tracked_shadow_validator.as_ref() {
             TrackedConfig::ShadowValidator(account_id.clone())
         } else {
-            TrackedConfig::Accounts(config.tracked_accounts.clone())
+            TrackedConfig::Accounts(tracked_accounts.to_vec())
         }
     }
 }
@@ -46,16 +73,29 @@ type BitMask = Vec<bool>;
 /// TrackedConfig::AllShards: track all shards
 #[derive(Clone)]
 pub struct ShardTracker {
-    tracked_config: TrackedConfig,
-    /// Stores shard tracking information by epoch, only useful if TrackedState == Accounts
+    tracked_state: Arc<RwLock<TrackedState>>,
+    /// Stores shard tracking information by epoch, only useful if TrackedConfig is Accounts or ShardLineage
     tracking_shards_cache: Arc<SyncLruCache<EpochId, BitMask>>,
     epoch_manager: Arc<dyn EpochManagerAdapter>,
 }
 
+/// The tracked config can be updated while the node is running. The shards
+/// tracked in the epochs for which the node already has (or is preparing)
+/// state must not change though, so the decisions made with the previous
+/// config for those epochs are pinned when the config is updated.
+struct TrackedState {
+    config: TrackedConfig,
+    /// Shards tracked in the given epochs, as decided by a previous config.
+    pinned_epochs: HashMap<EpochId, BitMask>,
+}
+
 impl ShardTracker {
     pub fn new(tracked_config: TrackedConfig, epoch_manager: Arc<dyn EpochManagerAdapter>) -> Self {
         ShardTracker {
-            tracked_config,
+            tracked_state: Arc::new(RwLock::new(TrackedState {
+                config: tracked_config,
+                pinned_epochs: HashMap::new(),
+            })),
             // 1024 epochs on mainnet is about 512 days which is more than enough,
             // and this is a cache anyway. The data size is pretty small as well,
             // only one bit per shard per epoch.
@@ -68,12 +108,82 @@ impl ShardTracker {
         Self::new(TrackedConfig::new_empty(), epoch_manager)
     }
 
+    /// Returns the currently used tracked config.
+    pub fn tracked_config(&self) -> TrackedConfig {
+        self.tracked_state.read().unwrap().config.clone()
+    }
+
+    /// Whether the node is configured to track all shards in new epochs.
+    pub fn tracks_all_shards(&self) -> bool {
+        matches!(self.tracked_state.read().unwrap().config, TrackedConfig::AllShards)
+    }
+
+    /// Replaces the tracked config. The shards tracked in `pinned_epochs`
+    /// (usually the current and the next epoch) stay as decided by the
+    /// previous config, every other epoch uses the new config. This means
+    /// that newly tracked shards are caught up with during the epoch
+    /// preceding the first epoch in which they are tracked, and that state
+    /// of the shards that are no longer tracked can be dropped once the
+    /// pinned epochs are over.
+    /// Returns whether the config changed.
+    pub fn update_tracked_config(
+        &self,
+        new_config: TrackedConfig,
+        pinned_epochs: &[EpochId],
+    ) -> Result<bool, EpochError> {
+        let mut state = self.tracked_state.write().unwrap();
+        if state.config == new_config {
+            return Ok(false);
+        }
+        let mut new_pinned_epochs = HashMap::new();
+        for epoch_id in pinned_epochs {
+            let tracking_mask = match state.pinned_epochs.get(epoch_id) {
+                Some(tracking_mask) => tracking_mask.clone(),
+                None => {
+                    let shard_layout = self.epoch_manager.get_shard_layout(epoch_id)?;
+                    shard_layout
+                        .shard_ids()
+                        .map(|shard_id| {
+                            self.tracks_shard_at_epoch_with_config(
+                                &state.config,
+                                shard_id,
+                                epoch_id,
+                            )
+                        })
+                        .collect::<Result<BitMask, EpochError>>()?
+                }
+            };
+            new_pinned_epochs.insert(*epoch_id, tracking_mask);
+        }
+        tracing::info!(target: "shard_tracker", old_config = ?state.config, ?new_config, ?pinned_epochs, "Updating tracked config");
+        state.config = new_config;
+        state.pinned_epochs = new_pinned_epochs;
+        // The cached decisions were made with the previous config.
+        self.tracking_shards_cache.lock().clear();
+        Ok(true)
+    }
+
     fn tracks_shard_at_epoch(
         &self,
         shard_id: ShardId,
         epoch_id: &EpochId,
     ) -> Result<bool, EpochError> {
-        match &self.tracked_config {
+        let state = self.tracked_state.read().unwrap();
+        if let Some(tracking_mask) = state.pinned_epochs.get(epoch_id) {
+            let shard_layout = self.epoch_manager.get_shard_layout(epoch_id)?;
+            let shard_index = shard_layout.get_shard_index(shard_id)?;
+            return Ok(tracking_mask.get(shard_index).copied().unwrap_or(false));
+        }
+        self.tracks_shard_at_epoch_with_config(&state.config, shard_id, epoch_id)
+    }
+
+    fn tracks_shard_at_epoch_with_config(
+        &self,
+        tracked_config: &TrackedConfig,
+        shard_id: ShardId,
+        epoch_id: &EpochId,
+    ) -> Result<bool, EpochError> {
+        match tracked_config {
             TrackedConfig::Accounts(tracked_accounts) => {
                 let shard_layout = self.epoch_manager.get_shard_layout(epoch_id)?;
                 let tracking_mask = self.tracking_shards_cache.get_or_try_put(
@@ -104,9 +214,59 @@ impl ShardTracker {
             TrackedConfig::ShadowValidator(account_id) => {
                 self.epoch_manager.cares_about_shard_in_epoch(epoch_id, account_id, shard_id)
             }
+            TrackedConfig::ShardLineage(shard_uids) => {
+                let shard_layout = self.epoch_manager.get_shard_layout(epoch_id)?;
+                let tracking_mask = self.tracking_shards_cache.get_or_try_put(
+                    *epoch_id,
+                    |_| -> Result<Vec<bool>, EpochError> {
+                        let ancestor_layouts = self.get_ancestor_shard_layouts(epoch_id)?;
+                        shard_layout
+                            .shard_ids()
+                            .map(|shard_id| -> Result<bool, EpochError> {
+                                let lineage = shard_layout
+                                    .get_shard_uid_lineage(shard_id, &ancestor_layouts)?;
+                                Ok(lineage.iter().any(|shard_uid| shard_uids.contains(shard_uid)))
+                            })
+                            .collect()
+                    },
+                )?;
+                let shard_index = shard_layout.get_shard_index(shard_id)?;
+                Ok(tracking_mask.get(shard_index).copied().unwrap_or(false))
+            }
         }
     }
 
+    /// Returns the shard layouts that preceded the shard layout of the epoch,
+    /// from the most recent to the oldest one, as long as they are connected
+    /// by resharding. The layouts are derived from the epoch configs of the
+    /// preceding protocol versions.
+    fn get_ancestor_shard_layouts(
+        &self,
+        epoch_id: &EpochId,
+    ) -> Result<Vec<ShardLayout>, EpochError> {
+        let mut shard_layout = self.epoch_manager.get_shard_layout(epoch_id)?;
+        let mut protocol_version = self.epoch_manager.get_epoch_protocol_version(epoch_id)?;
+        let mut ancestor_layouts = vec![];
+        while shard_layout.has_parent_layout() && protocol_version > 0 {
+            protocol_version -= 1;
+            let parent_layout =
+                self.epoch_manager.get_shard_layout_from_protocol_version(protocol_version);
+            if parent_layout != shard_layout {
+                ancestor_layouts.push(parent_layout.clone());
+                shard_layout = parent_layout;
+            }
+        }
+        Ok(ancestor_layouts)
+    }
+
+    /// Whether all shards are tracked regardless of the epoch, i.e. no epoch
+    /// is pinned to a previous config that didn't track all shards.
+    fn tracks_all_shards_in_all_epochs(&self) -> bool {
+        let state = self.tracked_state.read().unwrap();
+        matches!(state.config, TrackedConfig::AllShards)
+            && state.pinned_epochs.values().all(|tracking_mask| tracking_mask.iter().all(|t| *t))
+    }
+
     fn tracks_shard(&self, shard_id: ShardId, prev_hash: &CryptoHash) -> Result<bool, EpochError> {
         let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(prev_hash)?;
         self.tracks_shard_at_epoch(shard_id, &epoch_id)
@@ -153,13 +313,11 @@ impl ShardTracker {
                 // We have access to the node config. Use the config to find a definite answer.
             }
         }
-        match self.tracked_config {
-            TrackedConfig::AllShards => {
-                // Avoid looking up EpochId as a performance optimization.
-                true
-            }
-            _ => self.tracks_shard(shard_id, parent_hash).unwrap_or(false),
+        if self.tracks_all_shards_in_all_epochs() {
+            // Avoid looking up EpochId as a performance optimization.
+            return true;
         }
+        self.tracks_shard(shard_id, parent_hash).unwrap_or(false)
     }
 
     /// Whether the client cares about some shard in the next epoch.
@@ -196,15 +354,11 @@ impl ShardTracker {
                 // We have access to the node config. Use the config to find a definite answer.
             }
         }
-        match self.tracked_config {
-            TrackedConfig::AllShards => {
-                // Avoid looking up EpochId as a performance optimization.
-                true
-            }
-            _ => {
-                self.tracks_shard_next_epoch_from_prev_block(shard_id, parent_hash).unwrap_or(false)
-            }
+        if self.tracks_all_shards_in_all_epochs() {
+            // Avoid looking up EpochId as a performance optimization.
+            return true;
         }
+        self.tracks_shard_next_epoch_from_prev_block(shard_id, parent_hash).unwrap_or(false)
     }
 }
 
@@ -219,7 +373,7 @@ mod tests {
     use near_primitives::epoch_block_info::BlockInfo;
     use near_primitives::epoch_manager::{AllEpochConfig, EpochConfig};
     use near_primitives::hash::CryptoHash;
-    use near_primitives::shard_layout::ShardLayout;
+    use near_primitives::shard_layout::{ShardLayout, ShardUId};
     use near_primitives::types::validator_stake::ValidatorStake;
     use near_primitives::types::{BlockHeight, EpochId, NumShards, ProtocolVersion, ShardId};
     use near_primitives::version::ProtocolFeature::SimpleNightshade;
@@ -433,6 +587,45 @@ mod tests {
         assert_eq!(get_all_shards_will_care_about(&tracker, &shard_ids, &h[7]), subset3);
     }
 
+    #[test]
+    fn test_update_tracked_config() {
+        // Starts tracking all shards in the epoch after the next one.
+        let shard_ids = (0..4).map(ShardId::new).collect_vec();
+
+        let epoch_manager =
+            Arc::new(get_epoch_manager(PROTOCOL_VERSION, shard_ids.len() as NumShards, false));
+        let tracker = ShardTracker::new(TrackedConfig::new_empty(), epoch_manager.clone());
+
+        let h = hash_range(8);
+        {
+            let mut epoch_manager = epoch_manager.write();
+            for i in 0..8 {
+                record_block(
+                    &mut epoch_manager,
+                    if i > 0 { h[i - 1] } else { CryptoHash::default() },
+                    h[i],
+                    i as u64,
+                    vec![],
+                    PROTOCOL_VERSION,
+                );
+            }
+        }
+        let pinned_epochs = [
+            epoch_manager.get_epoch_id_from_prev_block(&h[4]).unwrap(),
+            epoch_manager.get_next_epoch_id_from_prev_block(&h[4]).unwrap(),
+        ];
+        assert!(tracker.update_tracked_config(TrackedConfig::AllShards, &pinned_epochs).unwrap());
+        assert!(!tracker.update_tracked_config(TrackedConfig::AllShards, &pinned_epochs).unwrap());
+        assert!(tracker.tracks_all_shards());
+
+        let all_shards: HashSet<_> = shard_ids.iter().cloned().collect();
+        assert_eq!(get_all_shards_care_about(&tracker, &shard_ids, &h[4]), HashSet::new());
+        assert_eq!(get_all_shards_will_care_about(&tracker, &shard_ids, &h[4]), HashSet::new());
+        assert_eq!(get_all_shards_care_about(&tracker, &shard_ids, &h[5]), HashSet::new());
+        assert_eq!(get_all_shards_will_care_about(&tracker, &shard_ids, &h[5]), all_shards);
+        assert_eq!(get_all_shards_care_about(&tracker, &shard_ids, &h[6]), all_shards);
+    }
+
     #[test]
     fn test_track_shards_shard_layout_change() {
         let simple_nightshade_version = SimpleNightshade.protocol_version();
@@ -511,4 +704,67 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_track_shard_lineage() {
+        let simple_nightshade_version = SimpleNightshade.protocol_version();
+        let epoch_manager = get_epoch_manager(simple_nightshade_version - 1, 1, true);
+        // The only shard of the genesis layout and one of the shards it splits into.
+        let genesis_shard = ShardUId::new(0, ShardId::new(0));
+        let child_shard = ShardUId::new(1, ShardId::new(1));
+        let genesis_tracker = ShardTracker::new(
+            TrackedConfig::ShardLineage(vec![genesis_shard]),
+            Arc::new(epoch_manager.clone()),
+        );
+        let child_tracker = ShardTracker::new(
+            TrackedConfig::ShardLineage(vec![child_shard]),
+            Arc::new(epoch_manager.clone()),
+        );
+
+        let h = hash_range(8);
+        {
+            let mut epoch_manager = epoch_manager.write();
+            record_block(
+                &mut epoch_manager,
+                CryptoHash::default(),
+                h[0],
+                0,
+                vec![],
+                simple_nightshade_version,
+            );
+            for i in 1..8 {
+                record_block(
+                    &mut epoch_manager,
+                    h[i - 1],
+                    h[i],
+                    i as u64,
+                    vec![],
+                    simple_nightshade_version,
+                );
+            }
+        }
+
+        for i in 1..8 {
+            let epoch_id = epoch_manager.get_epoch_id_from_prev_block(&h[i - 1]).unwrap();
+            let shard_layout = epoch_manager.get_shard_layout(&epoch_id).unwrap();
+            let shard_ids = shard_layout.shard_ids().collect_vec();
+
+            // All shards descend from the genesis shard.
+            assert_eq!(
+                get_all_shards_care_about(&genesis_tracker, &shard_ids, &h[i - 1]),
+                shard_ids.iter().cloned().collect()
+            );
+            let expected_child_shards = if shard_layout.version() == child_shard.version {
+                HashSet::from([child_shard.shard_id()])
+            } else {
+                HashSet::new()
+            };
+            assert_eq!(
+                get_all_shards_care_about(&child_tracker, &shard_ids, &h[i - 1]),
+                expected_child_shards
+            );
+        }
+        let epoch_id = epoch_manager.get_epoch_id_from_prev_block(&h[7]).unwrap();
+        assert_eq!(epoch_manager.get_shard_layout(&epoch_id).unwrap().version(), 1);
+    }
 }
@@ -3315,25 +3315,28 @@ fn test_chunk_header(h: &[CryptoHash], signer: &ValidatorSigner) -> ShardChunkHe
     let congestion_info = ProtocolFeature::CongestionControl
         .enabled(PROTOCOL_VERSION)
         .then_some(CongestionInfo::default());
-    ShardChunkHeader::V3(ShardChunkHeaderV3::new(
-        PROTOCOL_VERSION,
-        h[0],
-        h[2],
-        h[2],
-        h[2],
-        0,
-        1,
-        ShardId::new(0),
-        0,
-        0,
-        0,
-        h[2],
-        h[2],
-        vec![],
-        congestion_info,
-        BandwidthRequests::default_for_protocol_version(PROTOCOL_VERSION),
-        signer,
-    ))
+    ShardChunkHeader::V3(
+        ShardChunkHeaderV3::new(
+            PROTOCOL_VERSION,
+            h[0],
+            h[2],
+            h[2],
+            h[2],
+            0,
+            1,
+            ShardId::new(0),
+            0,
+            0,
+            0,
+            h[2],
+            h[2],
+            vec![],
+            congestion_info,
+            BandwidthRequests::default_for_protocol_version(PROTOCOL_VERSION),
+            signer,
+        )
+        .unwrap(),
+    )
 }
 
 #[test]
@@ -3370,7 +3373,8 @@ fn test_verify_partial_witness_signature() {
         "witness".bytes().collect(),
         7,
         signer.as_ref(),
-    );
+    )
+    .unwrap();
     let chunk_producer =
         epoch_manager.get_chunk_producer_info(&partial_witness.chunk_production_key()).unwrap();
     assert!(partial_witness.verify(chunk_producer.public_key()));
@@ -3388,7 +3392,8 @@ fn test_verify_partial_witness_signature() {
         "witness".bytes().collect(),
         7,
         bad_signer.as_ref(),
-    );
+    )
+    .unwrap();
     assert!(!bad_partial_witness.verify(chunk_producer.public_key()));
 }
 
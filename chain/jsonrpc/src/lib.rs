@@ -47,6 +47,9 @@ use tracing::{error, info};
 
 mod api;
 mod metrics;
+mod state_parts;
+
+pub use state_parts::start_http_for_state_parts;
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug)]
 pub struct RpcPollingConfig {
@@ -96,6 +99,10 @@ pub struct RpcConfig {
     // be read from this directory, instead of the contents compiled into the binary. This allows
     // for quick iterative development.
     pub experimental_debug_pages_src_path: Option<String>,
+    // If provided, and the node dumps state parts to the local filesystem, will start a read-only
+    // http server serving them on that address, so that other nodes can state sync from it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_parts_addr: Option<String>,
 }
 
 impl Default for RpcConfig {
@@ -108,6 +115,7 @@ impl Default for RpcConfig {
             limits_config: Default::default(),
             enable_debug_rpc: false,
             experimental_debug_pages_src_path: None,
+            state_parts_addr: None,
         }
     }
 }
@@ -1604,6 +1612,8 @@ pub fn start_http(
         limits_config,
         enable_debug_rpc,
         experimental_debug_pages_src_path: debug_pages_src_path,
+        // Served by `start_http_for_state_parts`.
+        state_parts_addr: _,
     } = config;
     let prometheus_addr = prometheus_addr.filter(|it| it != &addr.to_string());
     let cors_allowed_origins_clone = cors_allowed_origins.clone();
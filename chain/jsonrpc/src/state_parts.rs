@@ -0,0 +1,318 @@
+//! Read-only HTTP server for the state parts that the node dumps to the local filesystem.
+//!
+//! Files are served with the same path layout as the one used by `ExternalConnection`, so that
+//! other nodes can state sync from this node by configuring an `ExternalStorageLocation::HTTP`
+//! pointing at it. Directory listings are generated on the fly as `listing.json` files.
+//!
+//! Only files that resolve to a path under the root directory are served, symlinks pointing
+//! outside of it are treated as missing.
+
+use actix_web::http::header;
+use actix_web::{middleware, web, App, HttpRequest, HttpResponse, HttpServer};
+use near_client::sync::external::HTTP_LISTING_FILENAME;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+
+/// Starts the server serving the files under `root_dir` on `addr`.
+pub fn start_http_for_state_parts(
+    addr: &str,
+    root_dir: PathBuf,
+) -> Option<(&'static str, actix_web::dev::ServerHandle)> {
+    info!(target: "network", "Starting state parts http server at {} serving {}", addr, root_dir.display());
+    let listener = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(root_dir.clone()))
+            .wrap(middleware::Logger::default())
+            .service(
+                web::resource("/{path:.*}")
+                    .route(web::get().to(state_parts_handler))
+                    .route(web::head().to(state_parts_handler)),
+            )
+    });
+    match listener.bind(addr) {
+        Ok(s) => {
+            let server = s.workers(2).shutdown_timeout(5).disable_signals().run();
+            let handle = server.handle();
+            tokio::spawn(server);
+            Some(("State Parts", handle))
+        }
+        Err(e) => {
+            error!(target: "network", "Can't serve state parts at {} due to {:?}", addr, e);
+            None
+        }
+    }
+}
+
+async fn state_parts_handler(request: HttpRequest, root_dir: web::Data<PathBuf>) -> HttpResponse {
+    let Some(path) = resolve_path(&root_dir, request.match_info().query("path")) else {
+        return HttpResponse::BadRequest().finish();
+    };
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .map(|range| range.to_string());
+    let root_dir = root_dir.into_inner();
+    let result = web::block(move || serve_path(&root_dir, &path, range.as_deref())).await;
+    match result {
+        Ok(Ok(served)) => served.into_response(),
+        Ok(Err(err)) => {
+            error!(target: "network", ?err, "Failed to serve state parts request");
+            HttpResponse::InternalServerError().finish()
+        }
+        Err(err) => {
+            error!(target: "network", ?err, "Failed to run state parts request");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// What [`serve_path`] read from the filesystem, turned into a response on the
+/// server thread.
+enum Served {
+    NotFound,
+    Listing(Vec<String>),
+    File(Vec<u8>),
+    Range { data: Vec<u8>, start: u64, end: u64, len: u64 },
+    RangeNotSatisfiable { len: u64 },
+}
+
+impl Served {
+    fn into_response(self) -> HttpResponse {
+        match self {
+            Served::NotFound => HttpResponse::NotFound().finish(),
+            Served::Listing(file_names) => HttpResponse::Ok().json(file_names),
+            Served::File(data) => HttpResponse::Ok()
+                .insert_header((header::ACCEPT_RANGES, "bytes"))
+                .content_type("application/octet-stream")
+                .body(data),
+            Served::Range { data, start, end, len } => HttpResponse::PartialContent()
+                .insert_header((header::ACCEPT_RANGES, "bytes"))
+                .insert_header((header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len)))
+                .content_type("application/octet-stream")
+                .body(data),
+            Served::RangeNotSatisfiable { len } => HttpResponse::RangeNotSatisfiable()
+                .insert_header((header::CONTENT_RANGE, format!("bytes */{}", len)))
+                .finish(),
+        }
+    }
+}
+
+/// Reads a file or a directory listing, blocks on the filesystem. Anything that
+/// doesn't resolve to a path under the root directory is not found.
+fn serve_path(root_dir: &Path, path: &Path, range: Option<&str>) -> std::io::Result<Served> {
+    let root_dir = match root_dir.canonicalize() {
+        Ok(root_dir) => root_dir,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Served::NotFound),
+        Err(err) => return Err(err),
+    };
+    if let Some(path) = canonicalize_under(&root_dir, path)? {
+        if path.is_dir() {
+            return read_listing(&path);
+        }
+        if path.is_file() {
+            return read_file(&path, range);
+        }
+        return Ok(Served::NotFound);
+    }
+    if path.file_name().is_some_and(|name| name == HTTP_LISTING_FILENAME) {
+        if let Some(dir) = path.parent() {
+            if let Some(dir) = canonicalize_under(&root_dir, dir)? {
+                if dir.is_dir() {
+                    return read_listing(&dir);
+                }
+            }
+        }
+    }
+    Ok(Served::NotFound)
+}
+
+/// Resolves symlinks in the path. Returns `None` if the path doesn't exist or
+/// resolves to a path outside of `root_dir`, which must be canonical.
+fn canonicalize_under(root_dir: &Path, path: &Path) -> std::io::Result<Option<PathBuf>> {
+    match path.canonicalize() {
+        Ok(path) if path.starts_with(root_dir) => Ok(Some(path)),
+        Ok(_) => Ok(None),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Maps the requested path to a path under `root_dir`.
+/// Returns `None` if the request tries to escape `root_dir`.
+fn resolve_path(root_dir: &Path, requested: &str) -> Option<PathBuf> {
+    let mut path = root_dir.to_path_buf();
+    for segment in requested.split('/').filter(|segment| !segment.is_empty()) {
+        if segment == "." || segment == ".." || segment.contains('\\') {
+            return None;
+        }
+        path.push(segment);
+    }
+    Some(path)
+}
+
+/// Returns the names of the files in the directory.
+fn read_listing(dir: &Path) -> std::io::Result<Served> {
+    let mut file_names = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        // Symlinks are not listed, they may point outside of the root directory.
+        if entry.file_type()?.is_file() {
+            file_names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    file_names.sort();
+    Ok(Served::Listing(file_names))
+}
+
+fn read_file(path: &Path, range: Option<&str>) -> std::io::Result<Served> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let (start, end) = match range.map(|range| parse_range(range, len)) {
+        None | Some(RangeRequest::Ignored) => {
+            let mut data = Vec::with_capacity(len as usize);
+            file.read_to_end(&mut data)?;
+            return Ok(Served::File(data));
+        }
+        Some(RangeRequest::Unsatisfiable) => return Ok(Served::RangeNotSatisfiable { len }),
+        Some(RangeRequest::Satisfiable { start, end }) => (start, end),
+    };
+    let mut data = vec![0; (end - start + 1) as usize];
+    file.seek(SeekFrom::Start(start))?;
+    file.read_exact(&mut data)?;
+    Ok(Served::Range { data, start, end, len })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RangeRequest {
+    /// The header is malformed or requests multiple ranges; the whole file is served.
+    Ignored,
+    /// The range lies outside of the file.
+    Unsatisfiable,
+    /// Inclusive range of bytes to serve.
+    Satisfiable { start: u64, end: u64 },
+}
+
+/// Parses a `Range` header for a file of `len` bytes. Only a single byte range is supported.
+fn parse_range(range: &str, len: u64) -> RangeRequest {
+    let Some(spec) = range.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Ignored;
+    };
+    let Some((start, end)) = spec.split_once('-') else {
+        return RangeRequest::Ignored;
+    };
+    if end.contains(',') {
+        return RangeRequest::Ignored;
+    }
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", "") => return RangeRequest::Ignored,
+        ("", suffix) => {
+            let Ok(suffix) = suffix.parse::<u64>() else {
+                return RangeRequest::Ignored;
+            };
+            if suffix == 0 || len == 0 {
+                return RangeRequest::Unsatisfiable;
+            }
+            (len.saturating_sub(suffix), len - 1)
+        }
+        (start, end) => {
+            let Ok(start) = start.parse::<u64>() else {
+                return RangeRequest::Ignored;
+            };
+            let end = match end {
+                "" => u64::MAX,
+                end => match end.parse::<u64>() {
+                    Ok(end) => end,
+                    Err(_) => return RangeRequest::Ignored,
+                },
+            };
+            if end < start {
+                return RangeRequest::Ignored;
+            }
+            if start >= len {
+                return RangeRequest::Unsatisfiable;
+            }
+            (start, end.min(len - 1))
+        }
+    };
+    RangeRequest::Satisfiable { start, end }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_range, resolve_path, state_parts_handler, RangeRequest};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, web, App};
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_range() {
+        let satisfiable = |start, end| RangeRequest::Satisfiable { start, end };
+        assert_eq!(parse_range("bytes=0-9", 100), satisfiable(0, 9));
+        assert_eq!(parse_range("bytes=90-", 100), satisfiable(90, 99));
+        assert_eq!(parse_range("bytes=90-200", 100), satisfiable(90, 99));
+        assert_eq!(parse_range("bytes=-10", 100), satisfiable(90, 99));
+        assert_eq!(parse_range("bytes=-200", 100), satisfiable(0, 99));
+        assert_eq!(parse_range("bytes=100-", 100), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 100), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-1,5-6", 100), RangeRequest::Ignored);
+        assert_eq!(parse_range("bytes=9-0", 100), RangeRequest::Ignored);
+        assert_eq!(parse_range("items=0-9", 100), RangeRequest::Ignored);
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let root = Path::new("/dump");
+        assert_eq!(
+            resolve_path(root, "chain_id=test/epoch_height=1/listing.json"),
+            Some(root.join("chain_id=test").join("epoch_height=1").join("listing.json"))
+        );
+        assert_eq!(resolve_path(root, ""), Some(root.to_path_buf()));
+        assert_eq!(resolve_path(root, "chain_id=test/../../etc/passwd"), None);
+        assert_eq!(resolve_path(root, "./header"), None);
+    }
+
+    #[actix_web::test]
+    async fn test_state_parts_handler() {
+        let dir = tempfile::tempdir().unwrap();
+        let root_dir = dir.path().join("dump");
+        let part_dir = root_dir.join("chain_id=test").join("epoch_height=1");
+        std::fs::create_dir_all(&part_dir).unwrap();
+        std::fs::write(part_dir.join("state_part_000000"), b"part").unwrap();
+        std::fs::write(dir.path().join("secret"), b"secret").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir.path().join("secret"), root_dir.join("escape")).unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(root_dir))
+                .service(web::resource("/{path:.*}").route(web::get().to(state_parts_handler))),
+        )
+        .await;
+        let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+        let response =
+            test::call_service(&app, get("/chain_id=test/epoch_height=1/state_part_000000")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(test::read_body(response).await, "part");
+
+        let response =
+            test::call_service(&app, get("/chain_id=test/epoch_height=1/listing.json")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(test::read_body(response).await, r#"["state_part_000000"]"#);
+
+        let response =
+            test::call_service(&app, get("/chain_id=test/epoch_height=1/state_part_000001")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = test::call_service(&app, get("/chain_id=test/../../secret")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        #[cfg(unix)]
+        {
+            let response = test::call_service(&app, get("/escape")).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+}
@@ -199,6 +199,9 @@ pub struct NetworkConfig {
     /// Whether the Proof of Response protocol is enabled
     pub por_enabled: bool,
 
+    /// File to record all received and sent messages to, see `crate::recorder`.
+    pub record_messages_path: Option<std::path::PathBuf>,
+
     #[cfg(test)]
     pub(crate) event_sink:
         near_async::messaging::Sender<crate::peer_manager::peer_manager_actor::Event>,
@@ -384,6 +387,7 @@ impl NetworkConfig {
             // Use a preset to configure rate limits and override entries with user defined values later.
             received_messages_rate_limits: messages_limits::Config::standard_preset(),
             por_enabled: true,
+            record_messages_path: cfg.experimental.record_messages_path,
             #[cfg(test)]
             event_sink: near_async::messaging::IntoSender::into_sender(
                 near_async::messaging::noop(),
@@ -463,6 +467,7 @@ impl NetworkConfig {
             }),
             skip_tombstones: None,
             received_messages_rate_limits: messages_limits::Config::default(),
+            record_messages_path: None,
             #[cfg(test)]
             event_sink: near_async::messaging::IntoSender::into_sender(
                 near_async::messaging::noop(),
@@ -269,6 +269,12 @@ pub struct ExperimentalConfig {
     /// Fields set here will override the NetworkConfig fields.
     #[serde(default)]
     pub network_config_overrides: NetworkConfigOverrides,
+
+    /// If set, every message received from or sent to a peer is appended to
+    /// this file, for replaying it with `mock-node --replay`. Recordings grow
+    /// quickly, so only enable this while reproducing an issue.
+    #[serde(default)]
+    pub record_messages_path: Option<std::path::PathBuf>,
 }
 
 /// Overrides values from NetworkConfig.
@@ -300,6 +306,7 @@ impl Default for ExperimentalConfig {
             tier1_connect_interval: default_tier1_connect_interval(),
             tier1_new_connections_per_attempt: default_tier1_new_connections_per_attempt(),
             network_config_overrides: Default::default(),
+            record_messages_path: None,
         }
     }
 }
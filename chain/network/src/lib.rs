@@ -24,6 +24,7 @@ pub mod config;
 pub mod config_json;
 pub mod debug;
 pub mod raw;
+pub mod recorder;
 pub mod routing;
 pub mod shards_manager;
 pub mod state_sync;
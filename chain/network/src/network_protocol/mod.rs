@@ -6,6 +6,7 @@ mod edge;
 mod peer;
 mod proto_conv;
 mod state_sync;
+use crate::por::PorMessage;
 pub use edge::*;
 use near_primitives::stateless_validation::chunk_endorsement::ChunkEndorsement;
 use near_primitives::stateless_validation::contract_distribution::ChunkContractAccesses;
@@ -16,7 +17,6 @@ use near_primitives::stateless_validation::partial_witness::PartialEncodedStateW
 use near_primitives::stateless_validation::state_witness::ChunkStateWitnessAck;
 pub use peer::*;
 pub use state_sync::*;
-use crate::por::PorMessage;
 
 #[cfg(test)]
 pub(crate) mod testonly;
@@ -195,7 +195,7 @@ impl VersionedAccountData {
                 MAX_ACCOUNT_DATA_SIZE_BYTES
             );
         }
-        let signature = signer.sign_bytes(&payload);
+        let signature = signer.sign_account_key_payload(&payload)?;
         Ok(SignedAccountData {
             account_data: self,
             payload: AccountKeySignedPayload { payload, signature },
@@ -267,19 +267,36 @@ impl OwnedAccount {
     /// Serializes OwnedAccount to proto and signs it using `signer`.
     /// Panics if OwnedAccount.account_key doesn't match signer.public_key(),
     /// as this would likely be a bug.
-    pub fn sign(self, signer: &ValidatorSigner) -> SignedOwnedAccount {
+    /// Returns an error if a remote signer failed to sign.
+    pub fn sign(self, signer: &ValidatorSigner) -> std::io::Result<SignedOwnedAccount> {
         assert_eq!(
             self.account_key,
             signer.public_key(),
             "OwnedAccount.account_key doesn't match the signer's account_key"
         );
         let payload = proto::AccountKeyPayload::from(&self).write_to_bytes().unwrap();
-        let signature = signer.sign_bytes(&payload);
-        SignedOwnedAccount {
+        let signature = signer.sign_account_key_payload(&payload)?;
+        Ok(SignedOwnedAccount {
             owned_account: self,
             payload: AccountKeySignedPayload { payload, signature },
-        }
+        })
+    }
+}
+
+/// Checks that `payload` is a serialized AccountData or OwnedAccount of
+/// `account_key`, which can be broadcasted. Used by remote signers to check
+/// a payload before signing it.
+pub fn check_account_key_payload(payload: &[u8], account_key: &PublicKey) -> anyhow::Result<()> {
+    if payload.len() > MAX_ACCOUNT_DATA_SIZE_BYTES {
+        anyhow::bail!("payload size = {}, max is {}", payload.len(), MAX_ACCOUNT_DATA_SIZE_BYTES);
     }
+    let payload = proto::AccountKeyPayload::parse_from_bytes(payload)?;
+    let key = match VersionedAccountData::try_from(&payload) {
+        Ok(account_data) => account_data.account_key,
+        Err(_) => OwnedAccount::try_from(&payload)?.account_key,
+    };
+    anyhow::ensure!(&key == account_key, "payload is signed by {key}, not by {account_key}");
+    Ok(())
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Hash)]
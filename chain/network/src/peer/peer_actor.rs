@@ -24,6 +24,7 @@ use crate::peer_manager::peer_manager_actor::Event;
 use crate::peer_manager::peer_manager_actor::MAX_TIER2_PEERS;
 use crate::private_actix::{RegisterPeerError, SendMessage};
 use crate::rate_limits::messages_limits;
+use crate::recorder::MessageDirection;
 use crate::routing::edge::verify_nonce;
 #[cfg(feature = "distance_vector_routing")]
 use crate::routing::NetworkTopologyChange;
@@ -413,6 +414,14 @@ impl PeerActor {
         if let (PeerStatus::Ready(conn), PeerMessage::PeersRequest(_)) = (&self.peer_status, msg) {
             conn.last_time_peer_requested.store(Some(self.clock.now()));
         }
+        if let Some(recorder) = &self.network_state.recorder {
+            recorder.record(
+                self.clock.now_utc(),
+                MessageDirection::Outbound,
+                self.other_peer_id(),
+                msg,
+            );
+        }
         if let Some(enc) = self.encoding() {
             return self.send_message_with_encoding(msg, enc);
         }
@@ -486,13 +495,19 @@ impl PeerActor {
                 archival: self.network_state.config.archive,
             },
             partial_edge_info: spec.partial_edge_info,
-            owned_account: self.network_state.config.validator.signer.get().map(|signer| {
-                OwnedAccount {
+            owned_account: self.network_state.config.validator.signer.get().and_then(|signer| {
+                let owned_account = OwnedAccount {
                     account_key: signer.public_key(),
                     peer_id: self.network_state.config.node_id(),
                     timestamp: self.clock.now_utc(),
+                };
+                match owned_account.sign(&signer) {
+                    Ok(owned_account) => Some(owned_account),
+                    Err(err) => {
+                        tracing::warn!(target: "network", ?err, "failed to sign owned account");
+                        None
+                    }
                 }
-                .sign(&signer)
             }),
         };
         let msg = match spec.tier {
@@ -1713,6 +1728,14 @@ impl actix::Handler<stream::Frame> for PeerActor {
                 return;
             }
         };
+        if let Some(recorder) = &self.network_state.recorder {
+            recorder.record(
+                self.clock.now_utc(),
+                MessageDirection::Inbound,
+                self.other_peer_id(),
+                &peer_msg,
+            );
+        }
 
         tracing::trace!(target: "network", "Received message: {}", peer_msg);
 
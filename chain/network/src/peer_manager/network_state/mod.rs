@@ -17,6 +17,7 @@ use crate::peer_manager::connection;
 use crate::peer_manager::connection_store;
 use crate::peer_manager::peer_store;
 use crate::private_actix::RegisterPeerError;
+use crate::recorder::MessageRecorder;
 use crate::routing::route_back_cache::RouteBackCache;
 #[cfg(feature = "distance_vector_routing")]
 use crate::routing::NetworkTopologyChange;
@@ -94,7 +95,9 @@ pub(crate) struct WhitelistNode {
 pub(crate) struct NetworkState {
     /// Handler for Proof of Response protocol messages
     pub(crate) por_handler: Option<Arc<crate::por::PorHandler>>,
-    
+    /// Records the messages of all connections, if enabled in the config.
+    pub(crate) recorder: Option<MessageRecorder>,
+
     /// Dedicated runtime for `NetworkState` which runs in a separate thread.
     /// Async methods of NetworkState are not cancellable,
     /// so calling them from, for example, PeerActor is dangerous because
@@ -209,10 +212,17 @@ impl NetworkState {
             None
         };
 
+        let recorder = config.record_messages_path.as_ref().map(|path| {
+            MessageRecorder::create(path).unwrap_or_else(|err| {
+                panic!("failed to open {} for recording messages: {}", path.display(), err)
+            })
+        });
+
         // Create NetworkState with the handler
         let state = Self {
             runtime: Runtime::new(),
             por_handler,
+            recorder,
             graph: Arc::new(crate::routing::Graph::new(crate::routing::GraphConfig {
                 node_id: node_id.clone(),
                 prune_unreachable_peers_after: PRUNE_UNREACHABLE_PEERS_AFTER,
@@ -10,7 +10,7 @@ use crate::tcp;
 use crate::testonly::{make_rng, Rng};
 use near_async::time;
 use near_o11y::testonly::init_test_logger;
-use near_primitives::block_header::{Approval, ApprovalInner};
+use near_primitives::block_header::Approval;
 use near_primitives::validator_signer::ValidatorSigner;
 use near_store::db::TestDB;
 use rand::Rng as _;
@@ -19,14 +19,8 @@ use std::sync::Arc;
 
 /// Constructs a random TIER1 message.
 fn make_block_approval(rng: &mut Rng, signer: &ValidatorSigner) -> Approval {
-    let inner = ApprovalInner::Endorsement(data::make_hash(rng));
-    let target_height = rng.gen_range(0..100000);
-    Approval {
-        signature: signer.sign_bytes(&Approval::get_data_for_sig(&inner, target_height)),
-        account_id: signer.validator_id().clone(),
-        target_height,
-        inner,
-    }
+    let target_height = rng.gen_range(1..100000);
+    Approval::new(data::make_hash(rng), target_height - 1, target_height, signer).unwrap()
 }
 
 async fn establish_connections(clock: &time::Clock, pms: &[&peer_manager::testonly::ActorHandler]) {
@@ -3,6 +3,7 @@ use crate::network_protocol::{
     Encoding, Handshake, HandshakeFailureReason, PartialEdgeInfo, PeerChainInfoV2, PeerIdOrHash,
     PeerMessage, Ping, Pong, RawRoutedMessage, RoutedMessageBody, RoutingTableUpdate,
 };
+use crate::recorder::RecordedMessage;
 use crate::tcp;
 use crate::types::{
     Edge, PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg, PeerInfo,
@@ -97,6 +98,29 @@ impl fmt::Display for DirectMessage {
     }
 }
 
+impl DirectMessage {
+    pub(crate) fn into_peer_message(self) -> PeerMessage {
+        match self {
+            DirectMessage::AnnounceAccounts(accounts) => {
+                PeerMessage::SyncRoutingTable(RoutingTableUpdate { edges: Vec::new(), accounts })
+            }
+            DirectMessage::BlockRequest(h) => PeerMessage::BlockRequest(h),
+            DirectMessage::Block(b) => PeerMessage::Block(b),
+            DirectMessage::BlockHeadersRequest(h) => PeerMessage::BlockHeadersRequest(h),
+            DirectMessage::BlockHeaders(h) => PeerMessage::BlockHeaders(h),
+            DirectMessage::StateRequestHeader(shard_id, sync_hash) => {
+                PeerMessage::StateRequestHeader(shard_id, sync_hash)
+            }
+            DirectMessage::StateRequestPart(shard_id, sync_hash, part_id) => {
+                PeerMessage::StateRequestPart(shard_id, sync_hash, part_id)
+            }
+            DirectMessage::VersionedStateResponse(request) => {
+                PeerMessage::VersionedStateResponse(*request)
+            }
+        }
+    }
+}
+
 impl fmt::Debug for DirectMessage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -377,26 +401,7 @@ impl Connection {
 
     // Try to send a PeerMessage corresponding to the given DirectMessage
     pub async fn send_message(&mut self, msg: DirectMessage) -> io::Result<()> {
-        let peer_msg = match msg {
-            DirectMessage::AnnounceAccounts(accounts) => {
-                PeerMessage::SyncRoutingTable(RoutingTableUpdate { edges: Vec::new(), accounts })
-            }
-            DirectMessage::BlockRequest(h) => PeerMessage::BlockRequest(h),
-            DirectMessage::Block(b) => PeerMessage::Block(b),
-            DirectMessage::BlockHeadersRequest(h) => PeerMessage::BlockHeadersRequest(h),
-            DirectMessage::BlockHeaders(h) => PeerMessage::BlockHeaders(h),
-            DirectMessage::StateRequestHeader(shard_id, sync_hash) => {
-                PeerMessage::StateRequestHeader(shard_id, sync_hash)
-            }
-            DirectMessage::StateRequestPart(shard_id, sync_hash, part_id) => {
-                PeerMessage::StateRequestPart(shard_id, sync_hash, part_id)
-            }
-            DirectMessage::VersionedStateResponse(request) => {
-                PeerMessage::VersionedStateResponse(*request)
-            }
-        };
-
-        self.stream.write_message(&peer_msg).await
+        self.stream.write_message(&msg.into_peer_message()).await
     }
 
     // Try to send a routed PeerMessage corresponding to the given RoutedMessage
@@ -429,6 +434,12 @@ impl Connection {
         self.stream.write_message(&PeerMessage::Routed(Box::new(msg))).await
     }
 
+    /// Sends a message of a recording as it was recorded. Routed messages keep
+    /// their original author, signature and target.
+    pub async fn send_recorded_message(&mut self, msg: &RecordedMessage) -> io::Result<()> {
+        self.stream.write_message(&msg.peer_message()?).await
+    }
+
     fn target_is_for_me(&mut self, target: &PeerIdOrHash) -> bool {
         match target {
             PeerIdOrHash::PeerId(peer_id) => peer_id == &self.my_peer_id,
@@ -0,0 +1,223 @@
+//! Recording of the network traffic of a node.
+//!
+//! When `experimental.record_messages_path` is set in the network config, every
+//! `PeerMessage` received from or sent to a peer is appended to that file,
+//! together with the time and the peer. The recording can be replayed into a
+//! node with `mock-node --replay`, to reproduce issues that depend on the exact
+//! order and timing of the messages a node saw.
+use crate::network_protocol::{Encoding, PeerMessage};
+use crate::raw::DirectMessage;
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_async::time;
+use near_primitives::network::PeerId;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc;
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageDirection {
+    Inbound,
+    Outbound,
+}
+
+/// A single entry of a recording.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct RecordedMessage {
+    /// Unix timestamp in nanoseconds of when the message was received or sent.
+    pub timestamp_nanos: i128,
+    pub direction: MessageDirection,
+    /// The other end of the connection. Unknown for the messages exchanged
+    /// before the handshake completes.
+    pub peer_id: Option<PeerId>,
+    /// The message in the protobuf encoding.
+    message: Vec<u8>,
+}
+
+impl RecordedMessage {
+    /// Creates an entry of a direct message, e.g. to build recordings in tests.
+    pub fn new_direct(
+        timestamp: time::Utc,
+        direction: MessageDirection,
+        peer_id: Option<PeerId>,
+        msg: DirectMessage,
+    ) -> Self {
+        Self {
+            timestamp_nanos: timestamp.unix_timestamp_nanos(),
+            direction,
+            peer_id,
+            message: msg.into_peer_message().serialize(Encoding::Proto),
+        }
+    }
+
+    /// Name of the message variant, or `None` if the message can't be parsed
+    /// by this binary.
+    pub fn variant(&self) -> Option<&'static str> {
+        self.peer_message().ok().map(|msg| msg.msg_variant())
+    }
+
+    /// Whether the message can be replayed over another connection. Messages
+    /// managing the recorded connection itself, like handshakes, can't.
+    pub fn is_replayable(&self) -> bool {
+        match self.peer_message() {
+            Ok(
+                PeerMessage::Tier1Handshake(_)
+                | PeerMessage::Tier2Handshake(_)
+                | PeerMessage::Tier3Handshake(_)
+                | PeerMessage::HandshakeFailure(..)
+                | PeerMessage::LastEdge(_)
+                | PeerMessage::RequestUpdateNonce(_)
+                | PeerMessage::Disconnect(_),
+            ) => false,
+            Ok(_) => true,
+            Err(_) => false,
+        }
+    }
+
+    pub(crate) fn peer_message(&self) -> io::Result<PeerMessage> {
+        PeerMessage::deserialize(Encoding::Proto, &self.message)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+    }
+}
+
+/// Maximal number of messages waiting to be written. Messages recorded while
+/// the queue is full are dropped rather than blocking the network threads.
+const RECORDER_QUEUE_SIZE: usize = 10_000;
+
+/// Appends the messages of all connections of the node to a file. The file is
+/// written by a background thread, so that recording doesn't block the peer
+/// actors on disk I/O.
+pub(crate) struct MessageRecorder {
+    sender: Option<mpsc::SyncSender<RecordedMessage>>,
+    writer_thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl MessageRecorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(path)?;
+        let (sender, receiver) = mpsc::sync_channel(RECORDER_QUEUE_SIZE);
+        let writer_thread = std::thread::Builder::new()
+            .name("message_recorder".to_string())
+            .spawn(move || run_writer(BufWriter::new(file), receiver))?;
+        Ok(Self { sender: Some(sender), writer_thread: Some(writer_thread) })
+    }
+
+    pub fn record(
+        &self,
+        now: time::Utc,
+        direction: MessageDirection,
+        peer_id: Option<&PeerId>,
+        msg: &PeerMessage,
+    ) {
+        let entry = RecordedMessage {
+            timestamp_nanos: now.unix_timestamp_nanos(),
+            direction,
+            peer_id: peer_id.cloned(),
+            message: msg.serialize(Encoding::Proto),
+        };
+        match self.sender.as_ref().unwrap().try_send(entry) {
+            Ok(()) => {}
+            Err(mpsc::TrySendError::Full(_)) => {
+                tracing::warn!(target: "network", "message recorder queue is full, dropping message");
+            }
+            Err(mpsc::TrySendError::Disconnected(_)) => {
+                tracing::warn!(target: "network", "message recorder stopped, dropping message");
+            }
+        }
+    }
+}
+
+impl Drop for MessageRecorder {
+    /// Waits until all recorded messages are written.
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(writer_thread) = self.writer_thread.take() {
+            let _ = writer_thread.join();
+        }
+    }
+}
+
+/// Writes the messages until all senders are dropped. The file is flushed
+/// whenever the queue is drained, so that the recording is usable even if the
+/// node is killed.
+fn run_writer(mut writer: BufWriter<File>, receiver: mpsc::Receiver<RecordedMessage>) {
+    while let Ok(entry) = receiver.recv() {
+        let result = std::iter::once(entry)
+            .chain(receiver.try_iter())
+            .try_for_each(|entry| borsh::to_writer(&mut writer, &entry))
+            .and_then(|()| writer.flush());
+        if let Err(err) = result {
+            tracing::warn!(target: "network", ?err, "failed to record messages");
+        }
+    }
+}
+
+/// Writes the messages to a new recording, replacing the file if it exists.
+pub fn write_recording(path: &Path, messages: &[RecordedMessage]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for msg in messages {
+        borsh::to_writer(&mut writer, msg)?;
+    }
+    writer.flush()
+}
+
+/// Reads the messages of a recording in the order they were recorded.
+pub struct RecordingReader {
+    reader: BufReader<File>,
+}
+
+impl RecordingReader {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self { reader: BufReader::new(File::open(path)?) })
+    }
+}
+
+impl Iterator for RecordingReader {
+    type Item = io::Result<RecordedMessage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.reader.fill_buf() {
+            Ok([]) => None,
+            Ok(_) => Some(RecordedMessage::deserialize_reader(&mut self.reader)),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MessageDirection, MessageRecorder, RecordingReader};
+    use crate::network_protocol::testonly as data;
+    use crate::network_protocol::PeerMessage;
+    use near_async::time;
+
+    #[test]
+    fn test_record_and_read() {
+        let mut rng = crate::testonly::make_rng(89028037453);
+        let clock = time::FakeClock::default();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recording");
+
+        let peer_id = data::make_peer_id(&mut rng);
+        let messages = [
+            PeerMessage::BlockRequest(data::make_hash(&mut rng)),
+            PeerMessage::BlockHeadersRequest(vec![data::make_hash(&mut rng)]),
+        ];
+        let recorder = MessageRecorder::create(&path).unwrap();
+        recorder.record(clock.now_utc(), MessageDirection::Inbound, None, &messages[0]);
+        clock.advance(time::Duration::seconds(1));
+        recorder.record(clock.now_utc(), MessageDirection::Outbound, Some(&peer_id), &messages[1]);
+        drop(recorder);
+
+        let recording: Vec<_> =
+            RecordingReader::open(&path).unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(recording.len(), 2);
+        assert_eq!(recording[0].direction, MessageDirection::Inbound);
+        assert_eq!(recording[0].peer_id, None);
+        assert_eq!(recording[1].peer_id, Some(peer_id));
+        assert_eq!(recording[1].timestamp_nanos - recording[0].timestamp_nanos, 1_000_000_000);
+        for (recorded, msg) in recording.iter().zip(&messages) {
+            assert_eq!(&recorded.peer_message().unwrap(), msg);
+        }
+    }
+}
@@ -1,7 +1,7 @@
 /// Type that belong to the network protocol.
 pub use crate::network_protocol::{
-    Disconnect, Encoding, Handshake, HandshakeFailureReason, PeerMessage, RoutingTableUpdate,
-    SignedAccountData,
+    check_account_key_payload, Disconnect, Encoding, Handshake, HandshakeFailureReason,
+    PeerMessage, RoutingTableUpdate, SignedAccountData,
 };
 /// Exported types, which are part of network protocol.
 pub use crate::network_protocol::{
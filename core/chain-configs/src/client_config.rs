@@ -2,12 +2,15 @@
 use crate::ExternalStorageLocation::GCS;
 use crate::MutableConfigValue;
 use bytesize::ByteSize;
+use near_crypto::PublicKey;
+use near_primitives::shard_layout::ShardUId;
 use near_primitives::types::{
     AccountId, BlockHeight, BlockHeightDelta, Gas, NumBlocks, NumSeats, ShardId,
 };
 use near_primitives::version::Version;
 use near_time::Duration;
 use std::cmp::{max, min};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
@@ -54,6 +57,13 @@ pub struct GCConfig {
     /// How often gc should be run
     #[serde(with = "near_time::serde_duration_as_std")]
     pub gc_step_period: Duration,
+
+    /// Extended retention for specific columns. Data in these columns is kept
+    /// for the given period even after the block it belongs to has been
+    /// garbage collected. A policy can only extend the retention, data is
+    /// never removed before `gc_num_epochs_to_keep` epochs have passed.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub column_retention: BTreeMap<GCRetainedColumn, GCRetentionPolicy>,
 }
 
 impl Default for GCConfig {
@@ -63,10 +73,34 @@ impl Default for GCConfig {
             gc_fork_clean_step: 100,
             gc_num_epochs_to_keep: DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
             gc_step_period: Duration::seconds(1),
+            column_retention: BTreeMap::new(),
         }
     }
 }
 
+/// Columns whose retention can be configured separately in
+/// [`GCConfig::column_retention`]. Names match the corresponding `DBCol`s.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum GCRetainedColumn {
+    Transactions,
+    Receipts,
+    TransactionResultForBlock,
+    OutcomeIds,
+    StateChanges,
+}
+
+/// How long the data of a [`GCRetainedColumn`] is kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GCRetentionPolicy {
+    /// Keep the data for the given number of epochs.
+    Epochs(u64),
+    /// Keep the data for the given number of days, measured by block timestamps.
+    Days(u64),
+}
+
 impl GCConfig {
     pub fn gc_num_epochs_to_keep(&self) -> u64 {
         max(MIN_GC_NUM_EPOCHS_TO_KEEP, self.gc_num_epochs_to_keep)
@@ -101,6 +135,11 @@ pub struct ExternalStorageConfig {
     /// the network before it fetches from external storage.
     #[serde(default = "default_external_storage_fallback_threshold")]
     pub external_storage_fallback_threshold: u64,
+    /// Public keys of the nodes trusted to dump state parts. Manifests of the
+    /// state parts signed by other keys are ignored, in which case the parts
+    /// are only validated against the state root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_dumper_keys: Vec<PublicKey>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
@@ -117,6 +156,15 @@ pub enum ExternalStorageLocation {
     GCS {
         bucket: String,
     },
+    /// A generic HTTP(S) server, e.g. a CDN or a static file server, serving
+    /// the files under `base_url` with the same path layout as the other
+    /// locations. Directories are listed with a `listing.json` file holding
+    /// a JSON array of the file names in the directory.
+    /// When dumping, files are uploaded with `PUT` requests and the listing of
+    /// a directory is written once after all of its files are dumped.
+    HTTP {
+        base_url: String,
+    },
 }
 
 /// Configures how to dump state to external storage.
@@ -137,6 +185,12 @@ pub struct DumpConfig {
     /// Location of a json file with credentials allowing write access to the bucket.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub credentials_file: Option<PathBuf>,
+    /// If set, state parts are compressed with zstd at this level before being
+    /// written to the external storage. Syncing nodes detect compressed parts
+    /// automatically.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub compression_level: Option<i32>,
 }
 
 /// Configures how to fetch state parts during state sync.
@@ -174,6 +228,7 @@ impl StateSyncConfig {
                 num_concurrent_requests_during_catchup:
                     DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_ON_CATCHUP_EXTERNAL,
                 external_storage_fallback_threshold: DEFAULT_EXTERNAL_STORAGE_FALLBACK_THRESHOLD,
+                trusted_dumper_keys: vec![],
             }),
         }
     }
@@ -506,6 +561,9 @@ pub struct ClientConfig {
     /// Used to simulate the behavior of chunk only producers without staking tokens.
     /// This field is only used if `tracked_shards` is empty.
     pub tracked_shard_schedule: Vec<Vec<ShardId>>,
+    /// Track these shards and the shards they split into after resharding.
+    /// This field is only used if `tracked_shards` and `tracked_shard_schedule` are empty.
+    pub tracked_shard_lineage: Vec<ShardUId>,
     /// Not clear old data, set `true` for archive nodes.
     pub archive: bool,
     /// save_trie_changes should be set to true iff
@@ -631,6 +689,7 @@ impl ClientConfig {
             tracked_shadow_validator: None,
             tracked_shards: vec![],
             tracked_shard_schedule: vec![],
+            tracked_shard_lineage: vec![],
             archive,
             save_trie_changes,
             log_summary_style: LogSummaryStyle::Colored,
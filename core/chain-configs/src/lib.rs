@@ -22,9 +22,9 @@ pub use client_config::{
     default_trie_viewer_state_size_limit, default_tx_routing_height_horizon,
     default_view_client_threads, default_view_client_throttle_period,
     ChunkDistributionNetworkConfig, ChunkDistributionUris, ClientConfig, DumpConfig,
-    EpochSyncConfig, ExternalStorageConfig, ExternalStorageLocation, GCConfig, LogSummaryStyle,
-    ReshardingConfig, ReshardingHandle, StateSyncConfig, SyncConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
-    DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_EXTERNAL,
+    EpochSyncConfig, ExternalStorageConfig, ExternalStorageLocation, GCConfig, GCRetainedColumn,
+    GCRetentionPolicy, LogSummaryStyle, ReshardingConfig, ReshardingHandle, StateSyncConfig,
+    SyncConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP, DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_EXTERNAL,
     DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_ON_CATCHUP_EXTERNAL, MIN_GC_NUM_EPOCHS_TO_KEEP,
     TEST_STATE_SYNC_TIMEOUT,
 };
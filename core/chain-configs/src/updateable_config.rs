@@ -1,4 +1,4 @@
-use near_primitives::types::BlockHeight;
+use near_primitives::types::{AccountId, BlockHeight, ShardId};
 use near_primitives::validator_signer::ValidatorSigner;
 #[cfg(feature = "metrics")]
 use near_time::Clock;
@@ -108,6 +108,16 @@ pub struct UpdateableClientConfig {
     #[serde(default)]
     #[serde(with = "near_time::serde_opt_duration_as_std")]
     pub produce_chunk_add_transactions_time_limit: Option<Duration>,
+
+    /// Accounts whose shards are tracked, see `ClientConfig::tracked_accounts`.
+    /// Changes apply starting from the epoch after the next one.
+    #[serde(default)]
+    pub tracked_accounts: Vec<AccountId>,
+
+    /// Tracks all shards if not empty, see `ClientConfig::tracked_shards`.
+    /// Changes apply starting from the epoch after the next one.
+    #[serde(default)]
+    pub tracked_shards: Vec<ShardId>,
 }
 
 pub type UpdateableValidatorSigner = Option<Arc<ValidatorSigner>>;
@@ -1,3 +1,4 @@
+use crate::KeyType;
 use near_account_id::AccountId;
 
 #[derive(Debug, Clone, thiserror::Error)]
@@ -51,3 +52,15 @@ pub enum ImplicitPublicKeyError {
     #[error("'{account_id}' is not a NEAR-implicit account")]
     AccountIsNotNearImplicit { account_id: AccountId },
 }
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum SeedPhraseError {
+    #[error("invalid seed phrase: {error_message}")]
+    InvalidSeedPhrase { error_message: String },
+    #[error("invalid number of words {num_words}, expected 12, 15, 18, 21 or 24")]
+    InvalidNumWords { num_words: usize },
+    #[error("invalid derivation path '{path}': {error_message}")]
+    InvalidDerivationPath { path: String, error_message: String },
+    #[error("{key_type} keys can only be derived along hardened paths")]
+    NonHardenedDerivation { key_type: KeyType },
+}
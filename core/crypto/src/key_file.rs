@@ -1,11 +1,21 @@
 use crate::{PublicKey, SecretKey};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
 use near_account_id::AccountId;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io;
 use std::io::{Read, Write};
 use std::path::Path;
+use std::sync::Mutex;
 
-#[derive(serde::Serialize, serde::Deserialize)]
+/// Environment variable with the passphrase of encrypted key files.
+pub const KEY_FILE_PASSPHRASE_ENV: &str = "NEAR_KEY_FILE_PASSPHRASE";
+/// Environment variable with a file descriptor to read the passphrase of
+/// encrypted key files from, e.g. a pipe set up by the parent process.
+pub const KEY_FILE_PASSPHRASE_FD_ENV: &str = "NEAR_KEY_FILE_PASSPHRASE_FD";
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct KeyFile {
     pub account_id: AccountId,
     pub public_key: PublicKey,
@@ -23,6 +33,16 @@ impl KeyFile {
         file.write_all(data.as_bytes())
     }
 
+    /// Writes the key file with the secret key encrypted with the passphrase.
+    /// [`KeyFile::from_file`] detects the format and asks for the passphrase.
+    #[cfg(feature = "rand")]
+    pub fn write_encrypted_to_file(&self, path: &Path, passphrase: &str) -> io::Result<()> {
+        let encrypted = EncryptedKeyFile::encrypt(self, passphrase, SCRYPT_LOG_N)?;
+        let data = serde_json::to_string_pretty(&encrypted)?;
+        let mut file = Self::create(path)?;
+        file.write_all(data.as_bytes())
+    }
+
     #[cfg(unix)]
     fn create(path: &Path) -> io::Result<File> {
         use std::os::unix::fs::OpenOptionsExt;
@@ -34,17 +54,281 @@ impl KeyFile {
         std::fs::File::create(path)
     }
 
+    /// Reads a plaintext or an encrypted key file. The passphrase of an
+    /// encrypted file is read from the default [`PassphraseSource`].
+    ///
+    /// An encrypted file is decrypted only once per process, reading a file
+    /// with the same contents again returns the key decrypted before. This way
+    /// reloading the validator key doesn't ask for the passphrase or run the
+    /// key derivation again, unless the key file changed.
     pub fn from_file(path: &Path) -> io::Result<Self> {
+        static DECRYPTED: Mutex<BTreeMap<String, KeyFile>> = Mutex::new(BTreeMap::new());
+        let json_str = Self::read_json(path)?;
+        if !Self::is_encrypted_json(&json_str)? {
+            return Ok(serde_json::from_str(&json_str)?);
+        }
+        let mut decrypted = DECRYPTED.lock().unwrap();
+        if let Some(key_file) = decrypted.get(&json_str) {
+            return Ok(key_file.clone());
+        }
+        let key_file = Self::decrypt_json(path, &json_str, &PassphraseSource::from_env())?;
+        decrypted.insert(json_str, key_file.clone());
+        Ok(key_file)
+    }
+
+    pub fn from_file_with_passphrase(
+        path: &Path,
+        passphrase: &PassphraseSource,
+    ) -> io::Result<Self> {
+        let json_str = Self::read_json(path)?;
+        if Self::is_encrypted_json(&json_str)? {
+            Self::decrypt_json(path, &json_str, passphrase)
+        } else {
+            Ok(serde_json::from_str(&json_str)?)
+        }
+    }
+
+    fn decrypt_json(
+        path: &Path,
+        json_str: &str,
+        passphrase: &PassphraseSource,
+    ) -> io::Result<Self> {
+        let encrypted: EncryptedKeyFile = serde_json::from_str(json_str)?;
+        let passphrase =
+            passphrase.read(&format!("Passphrase for key file {}: ", path.display()))?;
+        encrypted.decrypt(&passphrase)
+    }
+
+    /// Whether the key file at the path is in the encrypted format.
+    pub fn is_encrypted(path: &Path) -> io::Result<bool> {
+        Self::is_encrypted_json(&Self::read_json(path)?)
+    }
+
+    fn read_json(path: &Path) -> io::Result<String> {
         let mut file = File::open(path)?;
         let mut json_config_str = String::new();
         file.read_to_string(&mut json_config_str)?;
-        let json_str_without_comments: String =
-            near_config_utils::strip_comments_from_json_str(&json_config_str)?;
+        near_config_utils::strip_comments_from_json_str(&json_config_str)
+    }
+
+    fn is_encrypted_json(json_str: &str) -> io::Result<bool> {
+        #[derive(serde::Deserialize)]
+        struct Probe {
+            encrypted_secret_key: Option<serde::de::IgnoredAny>,
+        }
+        let probe: Probe = serde_json::from_str(json_str)?;
+        Ok(probe.encrypted_secret_key.is_some())
+    }
+}
+
+/// Where to get the passphrase of an encrypted key file from.
+#[derive(Clone, Debug)]
+pub enum PassphraseSource {
+    /// The value of the environment variable.
+    Env(String),
+    /// The first line read from the file descriptor.
+    Fd(u32),
+    /// Ask on the terminal.
+    Prompt,
+}
+
+impl PassphraseSource {
+    /// [`KEY_FILE_PASSPHRASE_ENV`] if set, otherwise [`KEY_FILE_PASSPHRASE_FD_ENV`]
+    /// if set, otherwise a prompt.
+    pub fn from_env() -> Self {
+        Self::from_env_vars(KEY_FILE_PASSPHRASE_ENV, KEY_FILE_PASSPHRASE_FD_ENV)
+    }
 
-        Ok(serde_json::from_str(&json_str_without_comments)?)
+    /// Like [`PassphraseSource::from_env`], with custom variable names.
+    pub fn from_env_vars(var: &str, fd_var: &str) -> Self {
+        if std::env::var_os(var).is_some() {
+            Self::Env(var.to_string())
+        } else if let Some(fd) = std::env::var(fd_var).ok().and_then(|fd| fd.parse().ok()) {
+            Self::Fd(fd)
+        } else {
+            Self::Prompt
+        }
+    }
+
+    pub fn read(&self, prompt: &str) -> io::Result<String> {
+        match self {
+            Self::Env(var) => std::env::var(var).map_err(|err| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("{var}: {err}"))
+            }),
+            Self::Fd(fd) => Self::read_fd(*fd),
+            Self::Prompt => rpassword::prompt_password(prompt),
+        }
+    }
+
+    /// Reads the passphrase from the file descriptor only once per process.
+    /// A pipe can't be read again, while several key files may be decrypted
+    /// with the same passphrase, e.g. the node key after the validator key, or
+    /// the validator key again when it was replaced and is reloaded.
+    fn read_fd(fd: u32) -> io::Result<String> {
+        static FD_PASSPHRASES: Mutex<BTreeMap<u32, String>> = Mutex::new(BTreeMap::new());
+        let mut passphrases = FD_PASSPHRASES.lock().unwrap();
+        if let Some(passphrase) = passphrases.get(&fd) {
+            return Ok(passphrase.clone());
+        }
+        let passphrase = Self::read_fd_line(fd)?;
+        passphrases.insert(fd, passphrase.clone());
+        Ok(passphrase)
+    }
+
+    #[cfg(unix)]
+    fn read_fd_line(fd: u32) -> io::Result<String> {
+        use std::io::BufRead;
+
+        let file = File::open(format!("/dev/fd/{fd}"))?;
+        let mut line = String::new();
+        io::BufReader::new(file).read_line(&mut line)?;
+        Ok(line.trim_end_matches(['\n', '\r']).to_string())
+    }
+
+    #[cfg(not(unix))]
+    fn read_fd_line(_fd: u32) -> io::Result<String> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "reading the passphrase from a file descriptor is only supported on unix",
+        ))
     }
 }
 
+/// Version of the encrypted key file format written by this binary.
+const ENCRYPTED_KEY_FILE_VERSION: u32 = 1;
+/// Cost parameter of scrypt for new files, 2^17 iterations need 128 MiB of
+/// memory and take a fraction of a second.
+#[cfg(feature = "rand")]
+const SCRYPT_LOG_N: u8 = 17;
+#[cfg(feature = "rand")]
+const SCRYPT_R: u32 = 8;
+#[cfg(feature = "rand")]
+const SCRYPT_P: u32 = 1;
+#[cfg(feature = "rand")]
+const SALT_LEN: usize = 32;
+
+/// A key file with the secret key encrypted with a key derived from a
+/// passphrase. The account id and public key stay readable, and are
+/// authenticated together with the secret key.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct EncryptedKeyFile {
+    account_id: AccountId,
+    public_key: PublicKey,
+    encrypted_secret_key: EncryptedSecretKey,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct EncryptedSecretKey {
+    version: u32,
+    kdf: Kdf,
+    cipher: Cipher,
+    #[serde(with = "hex")]
+    nonce: Vec<u8>,
+    #[serde(with = "hex")]
+    ciphertext: Vec<u8>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "name", rename_all = "snake_case")]
+enum Kdf {
+    Scrypt {
+        log_n: u8,
+        r: u32,
+        p: u32,
+        #[serde(with = "hex")]
+        salt: Vec<u8>,
+    },
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+enum Cipher {
+    #[serde(rename = "xchacha20-poly1305")]
+    XChaCha20Poly1305,
+}
+
+impl EncryptedKeyFile {
+    #[cfg(feature = "rand")]
+    fn encrypt(key_file: &KeyFile, passphrase: &str, log_n: u8) -> io::Result<Self> {
+        use rand::RngCore;
+
+        let mut salt = vec![0; SALT_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+        let kdf = Kdf::Scrypt { log_n, r: SCRYPT_R, p: SCRYPT_P, salt };
+        let mut nonce = XNonce::default();
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+        let plaintext = key_file.secret_key.to_string();
+        let aad = Self::associated_data(
+            ENCRYPTED_KEY_FILE_VERSION,
+            &key_file.account_id,
+            &key_file.public_key,
+        );
+        let ciphertext = Self::cipher(&kdf, passphrase)?
+            .encrypt(&nonce, Payload { msg: plaintext.as_bytes(), aad: &aad })
+            .map_err(|_| invalid_data("failed to encrypt the secret key"))?;
+        Ok(Self {
+            account_id: key_file.account_id.clone(),
+            public_key: key_file.public_key.clone(),
+            encrypted_secret_key: EncryptedSecretKey {
+                version: ENCRYPTED_KEY_FILE_VERSION,
+                kdf,
+                cipher: Cipher::XChaCha20Poly1305,
+                nonce: nonce.to_vec(),
+                ciphertext,
+            },
+        })
+    }
+
+    fn decrypt(self, passphrase: &str) -> io::Result<KeyFile> {
+        let encrypted = &self.encrypted_secret_key;
+        if encrypted.version != ENCRYPTED_KEY_FILE_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported encrypted key file version {}",
+                encrypted.version
+            )));
+        }
+        let Cipher::XChaCha20Poly1305 = encrypted.cipher;
+        if encrypted.nonce.len() != XNonce::default().len() {
+            return Err(invalid_data("invalid nonce length"));
+        }
+        let aad = Self::associated_data(encrypted.version, &self.account_id, &self.public_key);
+        let plaintext = Self::cipher(&encrypted.kdf, passphrase)?
+            .decrypt(
+                XNonce::from_slice(&encrypted.nonce),
+                Payload { msg: &encrypted.ciphertext, aad: &aad },
+            )
+            .map_err(|_| invalid_data("wrong passphrase or corrupted key file"))?;
+        let secret_key: SecretKey = std::str::from_utf8(&plaintext)
+            .map_err(|_| invalid_data("decrypted secret key is not valid UTF-8"))?
+            .parse()
+            .map_err(|err| invalid_data(format!("invalid decrypted secret key: {err}")))?;
+        if secret_key.public_key() != self.public_key {
+            return Err(invalid_data("secret key doesn't match the public key"));
+        }
+        Ok(KeyFile { account_id: self.account_id, public_key: self.public_key, secret_key })
+    }
+
+    fn cipher(kdf: &Kdf, passphrase: &str) -> io::Result<XChaCha20Poly1305> {
+        let Kdf::Scrypt { log_n, r, p, salt } = kdf;
+        let params = scrypt::Params::new(*log_n, *r, *p, 32)
+            .map_err(|err| invalid_data(format!("invalid scrypt parameters: {err}")))?;
+        let mut key = [0; 32];
+        scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+            .map_err(|err| invalid_data(format!("scrypt failed: {err}")))?;
+        Ok(XChaCha20Poly1305::new(&key.into()))
+    }
+
+    /// Binds the plaintext fields and the format version to the ciphertext, so
+    /// that they can't be changed without the passphrase.
+    fn associated_data(version: u32, account_id: &AccountId, public_key: &PublicKey) -> Vec<u8> {
+        borsh::to_vec(&(version, account_id, public_key)).unwrap()
+    }
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -111,4 +395,82 @@ mod test {
         let inner_msg = err.into_inner().unwrap().to_string();
         assert!(inner_msg.contains("duplicate field"));
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_passphrase_fd_read_once() {
+        use std::os::fd::AsRawFd;
+
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("passphrase");
+        std::fs::write(&path, "correct horse\n").unwrap();
+        let file = File::open(&path).unwrap();
+        let passphrase = PassphraseSource::Fd(file.as_raw_fd() as u32);
+        assert_eq!(passphrase.read("").unwrap(), "correct horse");
+        // The passphrase is cached, the file descriptor isn't read again.
+        std::fs::write(&path, "wrong horse\n").unwrap();
+        assert_eq!(passphrase.read("").unwrap(), "correct horse");
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn test_encrypted() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("key-file");
+        let passphrase = PassphraseSource::Env("TEST_ENCRYPTED_KEY_FILE_PASSPHRASE".to_string());
+        std::env::set_var("TEST_ENCRYPTED_KEY_FILE_PASSPHRASE", "correct horse");
+
+        let account_id = ACCOUNT_ID.parse().unwrap();
+        let secret_key: SecretKey = SECRET_KEY.parse().unwrap();
+        let public_key = secret_key.public_key();
+        let key = KeyFile { account_id, public_key, secret_key };
+        // Low scrypt cost to keep the test fast.
+        let encrypted = EncryptedKeyFile::encrypt(&key, "correct horse", 4).unwrap();
+        let encrypted = serde_json::to_string_pretty(&encrypted).unwrap();
+        std::fs::write(&path, &encrypted).unwrap();
+
+        assert!(KeyFile::is_encrypted(&path).unwrap());
+        assert!(!encrypted.contains(SECRET_KEY));
+        let got = KeyFile::from_file_with_passphrase(&path, &passphrase).unwrap();
+        assert_eq!(got.account_id, key.account_id);
+        assert_eq!(got.secret_key, key.secret_key);
+
+        std::env::set_var("TEST_ENCRYPTED_KEY_FILE_PASSPHRASE", "wrong horse");
+        let err = KeyFile::from_file_with_passphrase(&path, &passphrase).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // The account id is authenticated with the secret key.
+        std::env::set_var("TEST_ENCRYPTED_KEY_FILE_PASSPHRASE", "correct horse");
+        std::fs::write(&path, encrypted.replace(ACCOUNT_ID, "attacker")).unwrap();
+        let err = KeyFile::from_file_with_passphrase(&path, &passphrase).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn test_encrypted_decrypted_once() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("key-file");
+        std::env::set_var(KEY_FILE_PASSPHRASE_ENV, "correct horse");
+
+        let account_id = ACCOUNT_ID.parse().unwrap();
+        let secret_key: SecretKey = SECRET_KEY.parse().unwrap();
+        let public_key = secret_key.public_key();
+        let key = KeyFile { account_id, public_key, secret_key };
+        let write = |key: &KeyFile| {
+            let encrypted = EncryptedKeyFile::encrypt(key, "correct horse", 4).unwrap();
+            std::fs::write(&path, serde_json::to_string_pretty(&encrypted).unwrap()).unwrap();
+        };
+        write(&key);
+        assert_eq!(KeyFile::from_file(&path).unwrap().secret_key, key.secret_key);
+
+        // The same file isn't decrypted again, so the passphrase isn't needed.
+        std::env::set_var(KEY_FILE_PASSPHRASE_ENV, "wrong horse");
+        assert_eq!(KeyFile::from_file(&path).unwrap().secret_key, key.secret_key);
+
+        // A replaced file is, with a new salt and nonce.
+        write(&key);
+        let err = KeyFile::from_file(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
 }
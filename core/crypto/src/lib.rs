@@ -1,8 +1,15 @@
 #![cfg_attr(enable_const_type_id, feature(const_type_id))]
 #![deny(clippy::arithmetic_side_effects)]
 
-pub use errors::{ParseKeyError, ParseKeyTypeError, ParseSignatureError};
-pub use key_file::KeyFile;
+pub use errors::{ParseKeyError, ParseKeyTypeError, ParseSignatureError, SeedPhraseError};
+pub use key_file::{
+    KeyFile, PassphraseSource, KEY_FILE_PASSPHRASE_ENV, KEY_FILE_PASSPHRASE_FD_ENV,
+};
+#[cfg(unix)]
+pub use remote_signer::RemoteSigner;
+#[cfg(feature = "rand")]
+pub use seed_phrase::generate_seed_phrase;
+pub use seed_phrase::{DerivationPath, NEAR_DERIVATION_PATH};
 pub use signature::{
     ED25519PublicKey, ED25519SecretKey, KeyType, PublicKey, Secp256K1PublicKey, Secp256K1Signature,
     SecretKey, Signature,
@@ -19,6 +26,8 @@ mod util;
 mod errors;
 pub mod key_conversion;
 mod key_file;
+pub mod remote_signer;
+mod seed_phrase;
 mod signature;
 mod signer;
 mod test_utils;
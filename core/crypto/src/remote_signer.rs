@@ -0,0 +1,151 @@
+//! Signing with a key held by a separate process.
+//!
+//! A [`RemoteSigner`] talks to the signer process over a Unix socket. This
+//! keeps the validator key out of the neard process, so that it can live in a
+//! hardened sidecar. `near-remote-signer` in `tools/remote-signer` is a
+//! reference implementation of the signer process.
+//!
+//! # Protocol
+//!
+//! The client connects to the socket on its first request, so the signer
+//! doesn't have to be running when neard starts. On every new connection it
+//! first sends a `public_key` request to check that the signer holds the
+//! expected key. Requests are sent one at a time per connection. Every
+//! request and response is a JSON object on a single line, terminated by `\n`.
+//! Binary fields are hex encoded, keys and signatures use their usual string
+//! encoding.
+//!
+//! The signer is never asked to sign opaque bytes. Every object signed by a
+//! validator has its own request, which carries the object, so that the signer
+//! can check what it signs, e.g. that it doesn't sign two different blocks at
+//! the same height. Objects are borsh serialized `near_primitives` types, see
+//! [`RemoteSignerRequest`] for the types and the signed data of each request.
+//!
+//! | Request                                                  | Response                                           |
+//! |----------------------------------------------------------|----------------------------------------------------|
+//! | `{"method":"public_key"}`                                | `{"public_key":{"account_id":"…","public_key":"ed25519:…"}}` |
+//! | `{"method":"sign_approval","inner":"<hex>","target_height":1}` and the other `sign_*` requests | `{"signatures":["ed25519:…"]}` |
+//! | `{"method":"compute_vrf_with_proof","prev_random_value":"<hex>"}` | `{"vrf":{"value":"<base58>","proof":"<base58>"}}`  |
+//!
+//! Any request can be answered with `{"error":"<message>"}`, e.g. when the
+//! signer refuses to sign. The client reconnects if the connection is closed,
+//! so the signer may close it at any time between requests.
+//!
+//! # Key file
+//!
+//! A validator key file that points to a remote signer has a `remote_signer`
+//! entry instead of the secret key:
+//!
+//! ```json
+//! {
+//!   "account_id": "validator.near",
+//!   "public_key": "ed25519:…",
+//!   "remote_signer": { "socket_path": "/run/near-signer/signer.sock" }
+//! }
+//! ```
+use crate::{PublicKey, Signature};
+use near_account_id::AccountId;
+use std::path::PathBuf;
+
+#[cfg(unix)]
+mod client;
+
+#[cfg(unix)]
+pub use client::RemoteSigner;
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum RemoteSignerRequest {
+    PublicKey,
+    /// Signs the hash of a block header, computed from `prev_hash` and the
+    /// borsh serialized `BlockHeaderInnerLite` and inner rest of the header.
+    SignBlockHeader {
+        #[serde(with = "hex")]
+        prev_hash: Vec<u8>,
+        #[serde(with = "hex")]
+        inner_lite: Vec<u8>,
+        #[serde(with = "hex")]
+        inner_rest: Vec<u8>,
+    },
+    /// Signs an `ApprovalInner` followed by the little endian target height.
+    SignApproval {
+        #[serde(with = "hex")]
+        inner: Vec<u8>,
+        target_height: u64,
+    },
+    /// Signs the hash of a chunk header with the given `ShardChunkHeaderInner`.
+    SignChunkHeader {
+        #[serde(with = "hex")]
+        inner: Vec<u8>,
+    },
+    /// Signs a `ChunkEndorsementInner` and a `ChunkEndorsementMetadata`, the
+    /// response has the two signatures in this order.
+    SignChunkEndorsement {
+        #[serde(with = "hex")]
+        inner: Vec<u8>,
+        #[serde(with = "hex")]
+        metadata: Vec<u8>,
+    },
+    /// Signs a `PartialEncodedStateWitnessInner`.
+    SignPartialStateWitness {
+        #[serde(with = "hex")]
+        inner: Vec<u8>,
+    },
+    /// Signs a `ChunkContractAccessesInner`.
+    SignChunkContractAccesses {
+        #[serde(with = "hex")]
+        inner: Vec<u8>,
+    },
+    /// Signs a `ContractCodeRequestInner`.
+    SignContractCodeRequest {
+        #[serde(with = "hex")]
+        inner: Vec<u8>,
+    },
+    /// Signs a `PartialEncodedContractDeploysInner`.
+    SignPartialEncodedContractDeploys {
+        #[serde(with = "hex")]
+        inner: Vec<u8>,
+    },
+    /// Signs the hash of an `AnnounceAccount` of the signer's account.
+    SignAnnounceAccount {
+        peer_id: PublicKey,
+        #[serde(with = "hex")]
+        epoch_id: Vec<u8>,
+    },
+    /// Signs the hash of a `ChallengeBody`.
+    SignChallenge {
+        #[serde(with = "hex")]
+        body: Vec<u8>,
+    },
+    /// Signs a protobuf serialized `AccountKeyPayload` of the network
+    /// protocol, which announces the validator's peers or proves that a peer
+    /// owns the account key.
+    SignAccountKeyPayload {
+        #[serde(with = "hex")]
+        payload: Vec<u8>,
+    },
+    /// Signs a JSON serialized `TelemetryInfo`.
+    SignTelemetry {
+        content: String,
+    },
+    /// Computes the VRF of the random value of the previous block.
+    ComputeVrfWithProof {
+        #[serde(with = "hex")]
+        prev_random_value: Vec<u8>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteSignerResponse {
+    PublicKey { account_id: AccountId, public_key: PublicKey },
+    Signatures(Vec<Signature>),
+    Vrf { value: crate::vrf::Value, proof: crate::vrf::Proof },
+    Error(String),
+}
+
+/// Where the validator key file points to, see the module docs.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct RemoteSignerConfig {
+    pub socket_path: PathBuf,
+}
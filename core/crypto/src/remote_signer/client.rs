@@ -0,0 +1,330 @@
+//! Client side of the remote signer protocol, see the parent module.
+use super::{RemoteSignerConfig, RemoteSignerRequest, RemoteSignerResponse};
+use crate::key_conversion::convert_public_key;
+use crate::{PublicKey, Signature};
+use near_account_id::AccountId;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a request, including its retries, may take. Requests are sent
+/// from the threads which produce blocks, chunks and approvals, so this is
+/// kept well below the block time.
+const REQUEST_DEADLINE: Duration = Duration::from_millis(300);
+/// Delay before the first retry, doubled after every failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Contents of a validator key file that points to a remote signer.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RemoteSignerFile {
+    account_id: AccountId,
+    public_key: PublicKey,
+    remote_signer: RemoteSignerConfig,
+}
+
+/// Signer that forwards requests to a separate process over a Unix socket.
+///
+/// The signer is connected to on the first request, and every new connection
+/// checks that the signer holds the key of the account. If the signer can't be
+/// reached, requests are retried with a backoff until [`REQUEST_DEADLINE`].
+/// Errors returned by the signer, e.g. when it refuses to sign, are not
+/// retried.
+#[derive(Clone)]
+pub struct RemoteSigner {
+    pub account_id: AccountId,
+    pub public_key: PublicKey,
+    pub config: RemoteSignerConfig,
+    /// Idle connections. A connection is taken out for the duration of a
+    /// request, so concurrent requests don't wait for each other.
+    connections: Arc<Mutex<Vec<BufReader<UnixStream>>>>,
+}
+
+impl RemoteSigner {
+    /// Doesn't connect to the signer, so that it doesn't have to be running
+    /// yet, see [`RemoteSigner`].
+    pub fn new(account_id: AccountId, public_key: PublicKey, config: RemoteSignerConfig) -> Self {
+        Self { account_id, public_key, config, connections: Default::default() }
+    }
+
+    /// Reads a validator key file. Returns `None` if it holds a secret key
+    /// instead of pointing to a remote signer.
+    pub fn from_key_file(path: &Path) -> io::Result<Option<Self>> {
+        #[derive(serde::Deserialize)]
+        struct Probe {
+            remote_signer: Option<serde::de::IgnoredAny>,
+        }
+        let json_str = std::fs::read_to_string(path)?;
+        let json_str = near_config_utils::strip_comments_from_json_str(&json_str)?;
+        let probe: Probe = serde_json::from_str(&json_str)?;
+        if probe.remote_signer.is_none() {
+            return Ok(None);
+        }
+        let file: RemoteSignerFile = serde_json::from_str(&json_str)?;
+        Ok(Some(Self::new(file.account_id, file.public_key, file.remote_signer)))
+    }
+
+    pub fn write_to_file(&self, path: &Path) -> io::Result<()> {
+        let file = RemoteSignerFile {
+            account_id: self.account_id.clone(),
+            public_key: self.public_key.clone(),
+            remote_signer: self.config.clone(),
+        };
+        std::fs::write(path, serde_json::to_string_pretty(&file)?)
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.public_key.clone()
+    }
+
+    /// Sends a `sign_*` request. `data` is what the request is expected to
+    /// sign, the returned signatures are checked against it.
+    pub fn sign(
+        &self,
+        request: &RemoteSignerRequest,
+        data: &[&[u8]],
+    ) -> io::Result<Vec<Signature>> {
+        match self.request(request)? {
+            RemoteSignerResponse::Signatures(signatures)
+                if signatures.len() == data.len()
+                    && signatures
+                        .iter()
+                        .zip(data)
+                        .all(|(signature, data)| signature.verify(data, &self.public_key)) =>
+            {
+                Ok(signatures)
+            }
+            response => Err(unexpected_response(&response)),
+        }
+    }
+
+    pub fn compute_vrf_with_proof(
+        &self,
+        prev_random_value: &[u8],
+    ) -> io::Result<(crate::vrf::Value, crate::vrf::Proof)> {
+        let request = RemoteSignerRequest::ComputeVrfWithProof {
+            prev_random_value: prev_random_value.to_vec(),
+        };
+        let vrf_public_key = match &self.public_key {
+            PublicKey::ED25519(key) => convert_public_key(key),
+            _ => None,
+        };
+        match self.request(&request)? {
+            RemoteSignerResponse::Vrf { value, proof }
+                if vrf_public_key
+                    .is_some_and(|key| key.is_vrf_valid(&prev_random_value, &value, &proof)) =>
+            {
+                Ok((value, proof))
+            }
+            response => Err(unexpected_response(&response)),
+        }
+    }
+
+    /// Sends the request, retrying with a backoff if the signer can't be
+    /// reached or closed the connection, until the deadline has passed.
+    fn request(&self, request: &RemoteSignerRequest) -> io::Result<RemoteSignerResponse> {
+        let start = Instant::now();
+        let mut backoff = INITIAL_BACKOFF;
+        let result = loop {
+            let result = self.try_request(request, start);
+            match &result {
+                Err(err) if err.kind() != io::ErrorKind::InvalidData => {}
+                _ => break result,
+            }
+            if start.elapsed().saturating_add(backoff) >= REQUEST_DEADLINE {
+                break result;
+            }
+            std::thread::sleep(backoff);
+            backoff = backoff.saturating_mul(2);
+        };
+        match result {
+            Ok(RemoteSignerResponse::Error(err)) => {
+                Err(io::Error::other(format!("{self:?} refused the request: {err}")))
+            }
+            Ok(response) => Ok(response),
+            Err(err) => Err(io::Error::new(err.kind(), format!("{self:?} failed: {err}"))),
+        }
+    }
+
+    /// Sends the request over an idle connection or a new one. The lock is
+    /// only held to take and return the connection.
+    fn try_request(
+        &self,
+        request: &RemoteSignerRequest,
+        start: Instant,
+    ) -> io::Result<RemoteSignerResponse> {
+        let idle = self.connections.lock().unwrap().pop();
+        let mut stream = match idle {
+            Some(stream) => stream,
+            None => self.open(start)?,
+        };
+        let response = Self::send(&mut stream, request, start)?;
+        self.connections.lock().unwrap().push(stream);
+        Ok(response)
+    }
+
+    /// Connects to the signer and checks that it holds the key of the account.
+    fn open(&self, start: Instant) -> io::Result<BufReader<UnixStream>> {
+        let mut stream = BufReader::new(UnixStream::connect(&self.config.socket_path)?);
+        match Self::send(&mut stream, &RemoteSignerRequest::PublicKey, start)? {
+            RemoteSignerResponse::PublicKey { account_id, public_key }
+                if account_id == self.account_id && public_key == self.public_key =>
+            {
+                Ok(stream)
+            }
+            RemoteSignerResponse::PublicKey { account_id, public_key } => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "remote signer holds key {} for {} but expected key {} for {}",
+                    public_key, account_id, self.public_key, self.account_id
+                ),
+            )),
+            response => Err(unexpected_response(&response)),
+        }
+    }
+
+    /// Sends the request and reads the response, within the time left until
+    /// [`REQUEST_DEADLINE`].
+    fn send(
+        stream: &mut BufReader<UnixStream>,
+        request: &RemoteSignerRequest,
+        start: Instant,
+    ) -> io::Result<RemoteSignerResponse> {
+        let timeout = REQUEST_DEADLINE.saturating_sub(start.elapsed());
+        if timeout.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        stream.get_ref().set_read_timeout(Some(timeout))?;
+        stream.get_ref().set_write_timeout(Some(timeout))?;
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        stream.get_mut().write_all(&line)?;
+        let mut line = String::new();
+        if stream.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+fn unexpected_response(response: &RemoteSignerResponse) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("unexpected response {response:?}"))
+}
+
+impl fmt::Debug for RemoteSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RemoteSigner(account_id: {}, public_key: {}, socket_path: {})",
+            self.account_id,
+            self.public_key,
+            self.config.socket_path.display()
+        )
+    }
+}
+
+impl PartialEq for RemoteSigner {
+    fn eq(&self, other: &Self) -> bool {
+        self.account_id == other.account_id
+            && self.public_key == other.public_key
+            && self.config.socket_path == other.config.socket_path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RemoteSigner, RemoteSignerConfig, RemoteSignerRequest, RemoteSignerResponse};
+    use crate::{InMemorySigner, KeyType, Signer};
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixListener;
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn test_remote_signer() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("signer.sock");
+        let Signer::InMemory(key) =
+            InMemorySigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "test")
+        else {
+            unreachable!()
+        };
+        let config = RemoteSignerConfig { socket_path: socket_path.clone() };
+        let signer = RemoteSigner::new(key.account_id.clone(), key.public_key(), config.clone());
+        let sign = |content: &str| {
+            let request = RemoteSignerRequest::SignTelemetry { content: content.to_string() };
+            signer.sign(&request, &[content.as_bytes()])
+        };
+
+        // The signer isn't running yet.
+        sign("data").unwrap_err();
+
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        let served_key = key.clone();
+        std::thread::spawn(move || {
+            // Close the connection after every signing request to check
+            // reconnecting.
+            for stream in listener.incoming() {
+                let mut stream = BufReader::new(stream.unwrap());
+                loop {
+                    let mut line = String::new();
+                    if stream.read_line(&mut line).unwrap() == 0 {
+                        break;
+                    }
+                    let request = serde_json::from_str(&line).unwrap();
+                    let is_public_key = request == RemoteSignerRequest::PublicKey;
+                    let response = match request {
+                        RemoteSignerRequest::PublicKey => RemoteSignerResponse::PublicKey {
+                            account_id: served_key.account_id.clone(),
+                            public_key: served_key.public_key(),
+                        },
+                        RemoteSignerRequest::SignTelemetry { content } if content == "bad" => {
+                            RemoteSignerResponse::Signatures(vec![served_key.sign(b"other")])
+                        }
+                        RemoteSignerRequest::SignTelemetry { content } => {
+                            RemoteSignerResponse::Signatures(vec![
+                                served_key.sign(content.as_bytes())
+                            ])
+                        }
+                        RemoteSignerRequest::ComputeVrfWithProof { prev_random_value } => {
+                            let (value, proof) =
+                                served_key.compute_vrf_with_proof(&prev_random_value);
+                            RemoteSignerResponse::Vrf { value, proof }
+                        }
+                        request => RemoteSignerResponse::Error(format!("unsupported {request:?}")),
+                    };
+                    let mut line = serde_json::to_vec(&response).unwrap();
+                    line.push(b'\n');
+                    stream.get_mut().write_all(&line).unwrap();
+                    if !is_public_key {
+                        break;
+                    }
+                }
+            }
+        });
+
+        assert_eq!(sign("data").unwrap(), vec![key.sign(b"data")]);
+        assert_eq!(sign("more data").unwrap(), vec![key.sign(b"more data")]);
+        assert_eq!(
+            signer.compute_vrf_with_proof(b"data").unwrap(),
+            key.compute_vrf_with_proof(b"data")
+        );
+
+        // Signatures of other data and refused requests are errors.
+        sign("bad").unwrap_err();
+        let request = RemoteSignerRequest::SignChallenge { body: vec![] };
+        signer.sign(&request, &[b""]).unwrap_err();
+
+        // Signing with the wrong key fails.
+        let other = InMemorySigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "other");
+        let request = RemoteSignerRequest::SignTelemetry { content: "data".to_string() };
+        RemoteSigner::new(key.account_id.clone(), other.public_key(), config)
+            .sign(&request, &[b"data"])
+            .unwrap_err();
+
+        // Once the signer is gone, requests fail instead of panicking.
+        drop(dir);
+        sign("data").unwrap_err();
+    }
+}
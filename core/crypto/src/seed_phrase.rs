@@ -0,0 +1,305 @@
+//! Deterministic keys derived from a BIP-39 seed phrase along a SLIP-10
+//! derivation path, compatible with the keys of NEAR wallets.
+
+use crate::errors::SeedPhraseError;
+use crate::signature::{ED25519SecretKey, KeyType, SecretKey};
+use hmac::{Hmac, Mac};
+use std::fmt;
+use std::str::FromStr;
+
+/// Derivation path of the keys of NEAR wallets.
+pub const NEAR_DERIVATION_PATH: &str = "m/44'/397'/0'";
+
+const HARDENED_BIT: u32 = 0x8000_0000;
+
+/// A SLIP-10 derivation path, e.g. `m/44'/397'/0'`. Hardened indices are
+/// marked with `'` or `h`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// The path of NEAR wallets, [`NEAR_DERIVATION_PATH`].
+    pub fn near() -> Self {
+        NEAR_DERIVATION_PATH.parse().unwrap()
+    }
+
+    /// The same path with `offset` added to the last index, e.g.
+    /// `m/44'/397'/1'` is the path of the second key of `m/44'/397'/0'`.
+    pub fn with_offset(&self, offset: u32) -> Result<Self, SeedPhraseError> {
+        let mut indices = self.0.clone();
+        let last = indices.last_mut().ok_or_else(|| SeedPhraseError::InvalidDerivationPath {
+            path: self.to_string(),
+            error_message: "the path is empty".to_string(),
+        })?;
+        let hardened = *last & HARDENED_BIT;
+        let index = (*last & !HARDENED_BIT)
+            .checked_add(offset)
+            .filter(|index| index & HARDENED_BIT == 0)
+            .ok_or_else(|| SeedPhraseError::InvalidDerivationPath {
+                path: self.to_string(),
+                error_message: format!("index overflow with offset {offset}"),
+            })?;
+        *last = index | hardened;
+        Ok(Self(indices))
+    }
+
+    /// The offset such that `base.with_offset(offset)` is this path, `None` if
+    /// this path is not one of the paths derived from `base`.
+    pub fn offset_from(&self, base: &Self) -> Option<u32> {
+        let (last, parent) = self.0.split_last()?;
+        let (base_last, base_parent) = base.0.split_last()?;
+        if parent != base_parent || last & HARDENED_BIT != base_last & HARDENED_BIT {
+            return None;
+        }
+        last.checked_sub(*base_last)
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = SeedPhraseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |error_message: &str| SeedPhraseError::InvalidDerivationPath {
+            path: s.to_string(),
+            error_message: error_message.to_string(),
+        };
+        let mut components = s.split('/');
+        if components.next() != Some("m") {
+            return Err(invalid("the path must start with 'm'"));
+        }
+        let mut indices = vec![];
+        for component in components {
+            let (index, hardened) = match component.strip_suffix(['\'', 'h', 'H']) {
+                Some(index) => (index, true),
+                None => (component, false),
+            };
+            let index: u32 = index.parse().map_err(|_| invalid("invalid index"))?;
+            if index & HARDENED_BIT != 0 {
+                return Err(invalid("index is too large"));
+            }
+            indices.push(if hardened { index | HARDENED_BIT } else { index });
+        }
+        Ok(Self(indices))
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            if index & HARDENED_BIT != 0 {
+                write!(f, "/{}'", index & !HARDENED_BIT)?;
+            } else {
+                write!(f, "/{index}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Generates a random English seed phrase of 12, 15, 18, 21 or 24 words.
+#[cfg(feature = "rand")]
+pub fn generate_seed_phrase(num_words: usize) -> Result<String, SeedPhraseError> {
+    use secp256k1::rand::rngs::OsRng;
+    use secp256k1::rand::RngCore;
+
+    let entropy_len = match num_words {
+        12 => 16,
+        15 => 20,
+        18 => 24,
+        21 => 28,
+        24 => 32,
+        _ => return Err(SeedPhraseError::InvalidNumWords { num_words }),
+    };
+    let mut entropy = [0u8; 32];
+    OsRng.fill_bytes(&mut entropy[..entropy_len]);
+    let mnemonic = bip39::Mnemonic::from_entropy(&entropy[..entropy_len])
+        .map_err(|err| SeedPhraseError::InvalidSeedPhrase { error_message: err.to_string() })?;
+    Ok(mnemonic.to_string())
+}
+
+impl SecretKey {
+    /// Derives the key from a BIP-39 seed phrase and an optional passphrase,
+    /// e.g. the key of a NEAR wallet with [`DerivationPath::near`].
+    pub fn from_seed_phrase(
+        key_type: KeyType,
+        seed_phrase: &str,
+        passphrase: &str,
+        path: &DerivationPath,
+    ) -> Result<Self, SeedPhraseError> {
+        let mnemonic = bip39::Mnemonic::parse(seed_phrase)
+            .map_err(|err| SeedPhraseError::InvalidSeedPhrase { error_message: err.to_string() })?;
+        Self::from_slip10_seed(key_type, &mnemonic.to_seed(passphrase), path)
+    }
+
+    /// Derives the key from a SLIP-10 master seed. Ed25519 keys can only be
+    /// derived along hardened paths.
+    pub fn from_slip10_seed(
+        key_type: KeyType,
+        seed: &[u8],
+        path: &DerivationPath,
+    ) -> Result<Self, SeedPhraseError> {
+        match key_type {
+            KeyType::ED25519 => {
+                let (mut key, mut chain_code) = split(hmac_sha512(b"ed25519 seed", &[seed]));
+                for &index in &path.0 {
+                    if index & HARDENED_BIT == 0 {
+                        return Err(SeedPhraseError::NonHardenedDerivation { key_type });
+                    }
+                    (key, chain_code) =
+                        split(hmac_sha512(&chain_code, &[&[0], &key, &index.to_be_bytes()]));
+                }
+                let signing_key = ed25519_dalek::SigningKey::from_bytes(&key);
+                Ok(Self::ED25519(ED25519SecretKey(signing_key.to_keypair_bytes())))
+            }
+            KeyType::SECP256K1 => {
+                let mut i = hmac_sha512(b"Bitcoin seed", &[seed]);
+                let mut key = loop {
+                    match secp256k1::SecretKey::from_slice(&i[..32]) {
+                        Ok(key) => break key,
+                        Err(_) => i = hmac_sha512(b"Bitcoin seed", &[&i]),
+                    }
+                };
+                let mut chain_code = split(i).1;
+                for &index in &path.0 {
+                    let mut i = if index & HARDENED_BIT != 0 {
+                        hmac_sha512(&chain_code, &[&[0], &key.secret_bytes(), &index.to_be_bytes()])
+                    } else {
+                        let public_key = secp256k1::PublicKey::from_secret_key(
+                            &crate::signature::SECP256K1,
+                            &key,
+                        );
+                        hmac_sha512(&chain_code, &[&public_key.serialize(), &index.to_be_bytes()])
+                    };
+                    // SLIP-10 retries with the next value in the unlikely case
+                    // the derived key is invalid.
+                    key = loop {
+                        let (tweak, next_chain_code) = split(i);
+                        let child = secp256k1::Scalar::from_be_bytes(tweak)
+                            .ok()
+                            .and_then(|tweak| key.add_tweak(&tweak).ok());
+                        match child {
+                            Some(child) => {
+                                chain_code = next_chain_code;
+                                break child;
+                            }
+                            None => {
+                                i = hmac_sha512(
+                                    &chain_code,
+                                    &[&[1], &next_chain_code, &index.to_be_bytes()],
+                                )
+                            }
+                        }
+                    };
+                }
+                Ok(Self::SECP256K1(key))
+            }
+        }
+    }
+}
+
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> [u8; 64] {
+    let mut mac =
+        Hmac::<sha2::Sha512>::new_from_slice(key).expect("HMAC can take a key of any size");
+    for data in data {
+        mac.update(data);
+    }
+    let mut result = [0; 64];
+    result.copy_from_slice(&mac.finalize().into_bytes());
+    result
+}
+
+/// Splits an HMAC result into the key and the chain code.
+fn split(i: [u8; 64]) -> ([u8; 32], [u8; 32]) {
+    let mut key = [0; 32];
+    let mut chain_code = [0; 32];
+    key.copy_from_slice(&i[..32]);
+    chain_code.copy_from_slice(&i[32..]);
+    (key, chain_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED_PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_derivation_path() {
+        let path = DerivationPath::near();
+        assert_eq!(path.to_string(), NEAR_DERIVATION_PATH);
+        assert_eq!("m/44h/397h/0h".parse::<DerivationPath>().unwrap(), path);
+        assert_eq!(path.with_offset(2).unwrap().to_string(), "m/44'/397'/2'");
+        assert_eq!(path.with_offset(2).unwrap().offset_from(&path), Some(2));
+        assert_eq!(path.offset_from(&path.with_offset(2).unwrap()), None);
+        for other in ["m/44'/397'/0'/1'", "m/44'/397'", "m/44'/398'/1'", "m/44'/397'/1", "m"] {
+            assert_eq!(
+                other.parse::<DerivationPath>().unwrap().offset_from(&path),
+                None,
+                "{other}"
+            );
+        }
+        assert_eq!("m".parse::<DerivationPath>().unwrap().to_string(), "m");
+        for invalid in ["", "44'/397'", "m/", "m/x'", "m/2147483648"] {
+            assert!(invalid.parse::<DerivationPath>().is_err(), "{invalid}");
+        }
+    }
+
+    /// Test vector 1 of SLIP-10.
+    #[test]
+    fn test_slip10_vectors() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let ed25519 = |path: &str| {
+            let key = SecretKey::from_slip10_seed(KeyType::ED25519, &seed, &path.parse().unwrap())
+                .unwrap();
+            hex::encode(&key.unwrap_as_ed25519().0[..32])
+        };
+        assert_eq!(
+            ed25519("m"),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+        assert_eq!(
+            ed25519("m/0'/1'"),
+            "b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2"
+        );
+        assert!(
+            SecretKey::from_slip10_seed(KeyType::ED25519, &seed, &"m/0".parse().unwrap()).is_err()
+        );
+
+        let SecretKey::SECP256K1(key) =
+            SecretKey::from_slip10_seed(KeyType::SECP256K1, &seed, &"m/0'/1".parse().unwrap())
+                .unwrap()
+        else {
+            panic!("expected a secp256k1 key");
+        };
+        assert_eq!(
+            hex::encode(key.secret_bytes()),
+            "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368"
+        );
+    }
+
+    #[test]
+    fn test_from_seed_phrase() {
+        let path = DerivationPath::near();
+        let key = SecretKey::from_seed_phrase(KeyType::ED25519, SEED_PHRASE, "", &path).unwrap();
+        assert_eq!(
+            key.public_key().to_string(),
+            "ed25519:6j4b6zUaty6fD1awqcGCCU9JYGCWYUgdJhQrzfZhqE25"
+        );
+        let key = SecretKey::from_seed_phrase(KeyType::SECP256K1, SEED_PHRASE, "", &path).unwrap();
+        assert_eq!(
+            key.public_key().to_string(),
+            "secp256k1:3rcYVfQ8TW4n79fsoQhrUHgXYV8tnn2E8Py7fjA5Q3iAgokHrjayyxqbH3urdxWTvfH5rJEK2zqAqgm7z5vSDHp2"
+        );
+        assert!(SecretKey::from_seed_phrase(KeyType::ED25519, "abandon about", "", &path).is_err());
+    }
+
+    #[test]
+    fn test_generate_seed_phrase() {
+        let seed_phrase = generate_seed_phrase(24).unwrap();
+        assert_eq!(seed_phrase.split(' ').count(), 24);
+        let path = DerivationPath::near();
+        SecretKey::from_seed_phrase(KeyType::SECP256K1, &seed_phrase, "", &path).unwrap();
+        assert!(generate_seed_phrase(13).is_err());
+    }
+}
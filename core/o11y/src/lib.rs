@@ -17,6 +17,7 @@ pub mod env_filter;
 mod io_tracer;
 pub mod log_config;
 mod log_counter;
+mod log_file;
 pub mod macros;
 pub mod metrics;
 mod opentelemetry;
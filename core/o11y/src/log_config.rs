@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::{fs::File, io::Write};
 
 /// Configures logging.
@@ -20,6 +20,46 @@ pub struct LogConfig {
     /// individual spans with something like `debug,store::trie=trace` to have specific targets be
     /// more verbose than the default.
     pub opentelemetry: Option<String>,
+    /// Write logs to a file instead of stderr.
+    ///
+    /// Unlike the other fields, this is only read at startup and changing it requires a restart.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_file: Option<LogFileConfig>,
+}
+
+/// Configures the log file destination and its rotation.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LogFileConfig {
+    /// Path of the active log file. Relative paths are resolved against the
+    /// current working directory.
+    pub path: PathBuf,
+    /// When to rotate the active log file.
+    #[serde(default)]
+    pub rotation: LogRotation,
+    /// Number of rotated files to keep in addition to the active one. The
+    /// oldest files are deleted once this number is exceeded.
+    #[serde(default = "default_max_rotated_files")]
+    pub max_rotated_files: usize,
+    /// Compress rotated files with zstd. Compressed files get a `.zst` suffix.
+    #[serde(default)]
+    pub compress: bool,
+}
+
+fn default_max_rotated_files() -> usize {
+    10
+}
+
+/// Rotation policy of the log file.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogRotation {
+    /// Never rotate, the file grows indefinitely.
+    #[default]
+    Never,
+    /// Rotate once the active file exceeds the given number of bytes.
+    Size { max_bytes: u64 },
+    /// Rotate at midnight UTC.
+    Daily,
 }
 
 impl LogConfig {
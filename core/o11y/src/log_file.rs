@@ -0,0 +1,221 @@
+//! A log file writer with size or time based rotation.
+//!
+//! The writer is meant to be wrapped in a [`tracing_appender::non_blocking`]
+//! writer, so that rotation and compression happen on the background worker
+//! thread rather than on the threads emitting the events.
+//!
+//! Rotated files are named after the active file with a numeric suffix, where
+//! `.1` is the most recent one: `neard.log`, `neard.log.1`, `neard.log.2.zst`
+//! and so on.
+
+use crate::log_config::{LogFileConfig, LogRotation};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+const COMPRESSED_SUFFIX: &str = "zst";
+const COMPRESSION_LEVEL: i32 = 3;
+
+pub struct RotatingFileWriter {
+    config: LogFileConfig,
+    file: File,
+    /// Number of bytes in the active file.
+    size: u64,
+    /// Day (number of days since the UNIX epoch) when the active file was opened.
+    day: u64,
+    /// Compression of the most recently rotated file, if it's still running.
+    compression: Option<JoinHandle<()>>,
+}
+
+impl RotatingFileWriter {
+    pub fn new(config: LogFileConfig) -> io::Result<Self> {
+        if let Some(parent) = config.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        let file = open_append(&config.path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { config, file, size, day: current_day(), compression: None })
+    }
+
+    fn should_rotate(&self, incoming: usize) -> bool {
+        match self.config.rotation {
+            LogRotation::Never => false,
+            LogRotation::Size { max_bytes } => {
+                self.size > 0 && self.size.saturating_add(incoming as u64) > max_bytes
+            }
+            LogRotation::Daily => current_day() != self.day,
+        }
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        // The previously rotated file is about to be renamed, make sure nobody else is using it.
+        self.wait_for_compression();
+
+        let max_files = self.config.max_rotated_files;
+        if max_files == 0 {
+            std::fs::remove_file(&self.config.path)?;
+        } else {
+            // Shift `path.N` to `path.N+1`, dropping the files beyond the retention limit.
+            remove_rotated(&self.config.path, max_files)?;
+            for index in (1..max_files).rev() {
+                for compressed in [false, true] {
+                    let from = rotated_path(&self.config.path, index, compressed);
+                    if from.exists() {
+                        std::fs::rename(
+                            &from,
+                            rotated_path(&self.config.path, index.saturating_add(1), compressed),
+                        )?;
+                    }
+                }
+            }
+            let rotated = rotated_path(&self.config.path, 1, false);
+            std::fs::rename(&self.config.path, &rotated)?;
+            if self.config.compress {
+                self.compression = Some(std::thread::spawn(move || {
+                    if let Err(err) = compress_file(&rotated) {
+                        eprintln!(
+                            "Failed to compress rotated log file {}: {err}",
+                            rotated.display()
+                        );
+                    }
+                }));
+            }
+        }
+
+        self.file = open_append(&self.config.path)?;
+        self.size = 0;
+        self.day = current_day();
+        Ok(())
+    }
+
+    fn wait_for_compression(&mut self) {
+        if let Some(handle) = self.compression.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Write for RotatingFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.should_rotate(buf.len()) {
+            if let Err(err) = self.rotate() {
+                // Keep writing into the current file rather than losing the logs.
+                eprintln!("Failed to rotate log file {}: {err}", self.config.path.display());
+                self.day = current_day();
+            }
+        }
+        let written = self.file.write(buf)?;
+        self.size = self.size.saturating_add(written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for RotatingFileWriter {
+    fn drop(&mut self) {
+        self.wait_for_compression();
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn current_day() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() / SECONDS_PER_DAY)
+}
+
+fn rotated_path(path: &Path, index: usize, compressed: bool) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{index}"));
+    if compressed {
+        name.push(format!(".{COMPRESSED_SUFFIX}"));
+    }
+    PathBuf::from(name)
+}
+
+fn remove_rotated(path: &Path, index: usize) -> io::Result<()> {
+    for compressed in [false, true] {
+        match std::fs::remove_file(rotated_path(path, index, compressed)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Replaces `path` with its zstd-compressed version at `path.zst`.
+fn compress_file(path: &Path) -> io::Result<()> {
+    let mut compressed_name = path.as_os_str().to_owned();
+    compressed_name.push(format!(".{COMPRESSED_SUFFIX}"));
+    let compressed_path = PathBuf::from(compressed_name);
+
+    let mut input = File::open(path)?;
+    let output = File::create(&compressed_path)?;
+    let mut encoder = zstd::stream::Encoder::new(output, COMPRESSION_LEVEL)?;
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    std::fs::remove_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(
+        dir: &Path,
+        max_bytes: u64,
+        max_rotated_files: usize,
+        compress: bool,
+    ) -> LogFileConfig {
+        LogFileConfig {
+            path: dir.join("neard.log"),
+            rotation: LogRotation::Size { max_bytes },
+            max_rotated_files,
+            compress,
+        }
+    }
+
+    #[test]
+    fn test_rotation_by_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(dir.path(), 10, 2, false);
+        let mut writer = RotatingFileWriter::new(config.clone()).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+        drop(writer);
+
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(config.path.clone()), "fourth\n");
+        assert_eq!(read(rotated_path(&config.path, 1, false)), "third\n");
+        assert_eq!(read(rotated_path(&config.path, 2, false)), "second\n");
+        // "first" is beyond the retention limit.
+        assert!(!rotated_path(&config.path, 3, false).exists());
+    }
+
+    #[test]
+    fn test_rotation_with_compression() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(dir.path(), 10, 3, true);
+        let mut writer = RotatingFileWriter::new(config.clone()).unwrap();
+        for line in ["first\n", "second\n", "third\n"] {
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+        drop(writer);
+
+        let decompress = |path: PathBuf| zstd::decode_all(File::open(path).unwrap()).unwrap();
+        assert_eq!(decompress(rotated_path(&config.path, 1, true)), b"second\n");
+        assert_eq!(decompress(rotated_path(&config.path, 2, true)), b"first\n");
+        assert!(!rotated_path(&config.path, 1, false).exists());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::log_config::LogFileConfig;
+use crate::log_file::RotatingFileWriter;
 use crate::opentelemetry::add_opentelemetry_layer;
 use crate::reload::{
     set_default_otlp_level, set_log_layer_handle, set_otlp_layer_handle, LogLayer, SimpleLogLayer,
@@ -236,19 +238,33 @@ pub fn default_subscriber(
 ///
 /// The subscriber enables logging, tracing and io tracing.
 /// Subscriber creation needs an async runtime.
+///
+/// Logs are written to `log_file` if it is set, and to stderr otherwise.
 pub async fn default_subscriber_with_opentelemetry(
     env_filter: EnvFilter,
     options: &Options,
     chain_id: String,
     node_public_key: PublicKey,
     account_id: Option<AccountId>,
+    log_file: Option<&LogFileConfig>,
 ) -> DefaultSubscriberGuard<impl tracing::Subscriber + Send + Sync> {
-    let color_output = use_color_output(options);
-
-    // Do not lock the `stderr` here to allow for things like `dbg!()` work during development.
-    let stderr = std::io::stderr();
-    let lined_stderr = std::io::LineWriter::new(stderr);
-    let (writer, writer_guard) = tracing_appender::non_blocking(lined_stderr);
+    let (writer, writer_guard, color_output) = match log_file {
+        Some(config) => {
+            let file_writer = RotatingFileWriter::new(config.clone()).unwrap_or_else(|err| {
+                panic!("unable to open log file {}: {err}", config.path.display())
+            });
+            let (writer, writer_guard) = tracing_appender::non_blocking(file_writer);
+            // Escape codes only make sense when the output is a terminal.
+            (writer, writer_guard, matches!(options.color, ColorOutput::Always))
+        }
+        None => {
+            // Do not lock the `stderr` here to allow for things like `dbg!()` work during development.
+            let stderr = std::io::stderr();
+            let lined_stderr = std::io::LineWriter::new(stderr);
+            let (writer, writer_guard) = tracing_appender::non_blocking(lined_stderr);
+            (writer, writer_guard, use_color_output(options))
+        }
+    };
 
     let subscriber = tracing_subscriber::registry();
     // Installs LogCounter as the innermost layer.
@@ -305,7 +305,7 @@ impl Block {
         block_merkle_root: CryptoHash,
         clock: near_time::Clock,
         sandbox_delta_time: Option<near_time::Duration>,
-    ) -> Self {
+    ) -> std::io::Result<Self> {
         use itertools::Itertools;
         use near_primitives_core::version::ProtocolFeature;
 
@@ -347,7 +347,7 @@ impl Block {
         debug_assert!(sandbox_delta_time.is_none());
         let time = if now <= prev.raw_timestamp() { prev.raw_timestamp() + 1 } else { now };
 
-        let (vrf_value, vrf_proof) = signer.compute_vrf_with_proof(prev.random_value().as_ref());
+        let (vrf_value, vrf_proof) = signer.compute_vrf_with_proof(prev.random_value())?;
         let random_value = hash(vrf_value.0.as_ref());
 
         let last_ds_final_block =
@@ -433,14 +433,14 @@ impl Block {
             block_merkle_root,
             prev.height(),
             chunk_endorsements_bitmap,
-        );
+        )?;
 
-        Self::block_from_protocol_version(
+        Ok(Self::block_from_protocol_version(
             this_epoch_protocol_version,
             next_epoch_protocol_version,
             header,
             body,
-        )
+        ))
     }
 
     pub fn verify_total_supply(
@@ -8,6 +8,7 @@ use crate::types::{AccountId, Balance, BlockHeight, EpochId, MerkleHash, NumBloc
 use crate::validator_signer::ValidatorSigner;
 use crate::version::ProtocolVersion;
 use borsh::{BorshDeserialize, BorshSerialize};
+use near_crypto::remote_signer::RemoteSignerRequest;
 use near_crypto::{KeyType, PublicKey, Signature};
 use near_primitives_core::version::ProtocolFeature;
 use near_schema_checker_lib::ProtocolSchema;
@@ -345,11 +346,16 @@ impl Approval {
         parent_height: BlockHeight,
         target_height: BlockHeight,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> std::io::Result<Self> {
         let inner = ApprovalInner::new(&parent_hash, parent_height, target_height);
 
-        let signature = signer.sign_bytes(&Approval::get_data_for_sig(&inner, target_height));
-        Approval { inner, target_height, signature, account_id: signer.validator_id().clone() }
+        let data = Approval::get_data_for_sig(&inner, target_height);
+        let signature =
+            signer.sign_approval(target_height, &data, || RemoteSignerRequest::SignApproval {
+                inner: borsh::to_vec(&inner).unwrap(),
+                target_height,
+            })?;
+        Ok(Approval { inner, target_height, signature, account_id: signer.validator_id().clone() })
     }
 
     pub fn get_data_for_sig(inner: &ApprovalInner, target_height: BlockHeight) -> Vec<u8> {
@@ -604,7 +610,7 @@ impl BlockHeader {
         block_merkle_root: CryptoHash,
         prev_height: BlockHeight,
         chunk_endorsements: Option<ChunkEndorsementsBitmap>,
-    ) -> Self {
+    ) -> std::io::Result<Self> {
         Self::new_impl(
             this_epoch_protocol_version,
             next_epoch_protocol_version,
@@ -705,7 +711,8 @@ impl BlockHeader {
             block_merkle_root,
             prev_height,
             chunk_endorsements,
-        );
+        )
+        .expect("only signing with a signer can fail");
         // Note: We do not panic but only log if the hash of the created header does not match the expected hash (From the view)
         // because there are tests that check if we can downgrade a BlockHeader's view a previous version, in which case the hash
         // of the header changes.
@@ -748,7 +755,7 @@ impl BlockHeader {
         block_merkle_root: CryptoHash,
         prev_height: BlockHeight,
         chunk_endorsements: Option<ChunkEndorsementsBitmap>,
-    ) -> Self {
+    ) -> std::io::Result<Self> {
         let inner_lite = BlockHeaderInnerLite {
             height,
             epoch_id,
@@ -786,14 +793,14 @@ impl BlockHeader {
                 chunk_endorsements,
             };
             let (hash, signature) =
-                Self::compute_hash_and_sign(signature_source, prev_hash, &inner_lite, &inner_rest);
-            Self::BlockHeaderV5(Arc::new(BlockHeaderV5 {
+                Self::compute_hash_and_sign(signature_source, prev_hash, &inner_lite, &inner_rest)?;
+            Ok(Self::BlockHeaderV5(Arc::new(BlockHeaderV5 {
                 prev_hash,
                 inner_lite,
                 inner_rest,
                 signature,
                 hash,
-            }))
+            })))
         } else if ProtocolFeature::BlockHeaderV4.enabled(this_epoch_protocol_version) {
             let inner_rest = BlockHeaderInnerRestV4 {
                 block_body_hash,
@@ -816,14 +823,14 @@ impl BlockHeader {
                 latest_protocol_version,
             };
             let (hash, signature) =
-                Self::compute_hash_and_sign(signature_source, prev_hash, &inner_lite, &inner_rest);
-            Self::BlockHeaderV4(Arc::new(BlockHeaderV4 {
+                Self::compute_hash_and_sign(signature_source, prev_hash, &inner_lite, &inner_rest)?;
+            Ok(Self::BlockHeaderV4(Arc::new(BlockHeaderV4 {
                 prev_hash,
                 inner_lite,
                 inner_rest,
                 signature,
                 hash,
-            }))
+            })))
         } else {
             // Build BlockHeaderV1-V3.
             Self::old_impl(
@@ -880,7 +887,7 @@ impl BlockHeader {
         epoch_sync_data_hash: Option<CryptoHash>,
         approvals: Vec<Option<Box<Signature>>>,
         prev_height: BlockHeight,
-    ) -> Self {
+    ) -> std::io::Result<Self> {
         let last_header_v2_version = ProtocolFeature::BlockHeaderV3.protocol_version() - 1;
         // Previously we passed next_epoch_protocol_version here, which is incorrect, but we need
         // to preserve this for archival nodes
@@ -907,14 +914,14 @@ impl BlockHeader {
                 latest_protocol_version,
             };
             let (hash, signature) =
-                Self::compute_hash_and_sign(signature_source, prev_hash, &inner_lite, &inner_rest);
-            Self::BlockHeaderV1(Arc::new(BlockHeaderV1 {
+                Self::compute_hash_and_sign(signature_source, prev_hash, &inner_lite, &inner_rest)?;
+            Ok(Self::BlockHeaderV1(Arc::new(BlockHeaderV1 {
                 prev_hash,
                 inner_lite,
                 inner_rest,
                 signature,
                 hash,
-            }))
+            })))
         } else if this_epoch_protocol_version <= last_header_v2_version {
             let inner_rest = BlockHeaderInnerRestV2 {
                 prev_chunk_outgoing_receipts_root,
@@ -936,14 +943,14 @@ impl BlockHeader {
                 latest_protocol_version,
             };
             let (hash, signature) =
-                Self::compute_hash_and_sign(signature_source, prev_hash, &inner_lite, &inner_rest);
-            Self::BlockHeaderV2(Arc::new(BlockHeaderV2 {
+                Self::compute_hash_and_sign(signature_source, prev_hash, &inner_lite, &inner_rest)?;
+            Ok(Self::BlockHeaderV2(Arc::new(BlockHeaderV2 {
                 prev_hash,
                 inner_lite,
                 inner_rest,
                 signature,
                 hash,
-            }))
+            })))
         } else {
             let inner_rest = BlockHeaderInnerRestV3 {
                 prev_chunk_outgoing_receipts_root,
@@ -965,14 +972,14 @@ impl BlockHeader {
                 latest_protocol_version,
             };
             let (hash, signature) =
-                Self::compute_hash_and_sign(signature_source, prev_hash, &inner_lite, &inner_rest);
-            Self::BlockHeaderV3(Arc::new(BlockHeaderV3 {
+                Self::compute_hash_and_sign(signature_source, prev_hash, &inner_lite, &inner_rest)?;
+            Ok(Self::BlockHeaderV3(Arc::new(BlockHeaderV3 {
                 prev_hash,
                 inner_lite,
                 inner_rest,
                 signature,
                 hash,
-            }))
+            })))
         }
     }
 
@@ -985,18 +992,26 @@ impl BlockHeader {
         prev_hash: CryptoHash,
         inner_lite: &BlockHeaderInnerLite,
         inner_rest: &T,
-    ) -> (CryptoHash, Signature)
+    ) -> std::io::Result<(CryptoHash, Signature)>
     where
         T: BorshSerialize + ?Sized,
     {
-        let hash = BlockHeader::compute_hash(
-            prev_hash,
-            &borsh::to_vec(&inner_lite).expect("Failed to serialize"),
-            &borsh::to_vec(&inner_rest).expect("Failed to serialize"),
-        );
+        let height = inner_lite.height;
+        let inner_lite = borsh::to_vec(&inner_lite).expect("Failed to serialize");
+        let inner_rest = borsh::to_vec(&inner_rest).expect("Failed to serialize");
+        let hash = BlockHeader::compute_hash(prev_hash, &inner_lite, &inner_rest);
         match signature_source {
-            SignatureSource::Signer(signer) => (hash, signer.sign_bytes(hash.as_ref())),
-            SignatureSource::Signature(signature) => (hash, signature),
+            SignatureSource::Signer(signer) => {
+                let signature = signer.sign_block_header(height, &hash, || {
+                    RemoteSignerRequest::SignBlockHeader {
+                        prev_hash: prev_hash.as_bytes().to_vec(),
+                        inner_lite,
+                        inner_rest,
+                    }
+                })?;
+                Ok((hash, signature))
+            }
+            SignatureSource::Signature(signature) => Ok((hash, signature)),
         }
     }
 
@@ -1049,6 +1064,7 @@ impl BlockHeader {
             0,                     // prev_height
             Some(ChunkEndorsementsBitmap::genesis()),
         )
+        .expect("only signing with a signer can fail")
     }
 
     #[inline]
@@ -4,6 +4,7 @@ use crate::sharding::{EncodedShardChunk, ShardChunk, ShardChunkHeader};
 use crate::types::AccountId;
 use crate::validator_signer::ValidatorSigner;
 use borsh::{BorshDeserialize, BorshSerialize};
+use near_crypto::remote_signer::RemoteSignerRequest;
 use near_crypto::Signature;
 use near_schema_checker_lib::ProtocolSchema;
 use std::fmt::{Debug, Formatter};
@@ -123,10 +124,12 @@ impl Challenge {
         self.hash = CryptoHash::hash_borsh(&self.body);
     }
 
-    pub fn produce(body: ChallengeBody, signer: &ValidatorSigner) -> Self {
+    pub fn produce(body: ChallengeBody, signer: &ValidatorSigner) -> std::io::Result<Self> {
         let hash = CryptoHash::hash_borsh(&body);
-        let signature = signer.sign_bytes(hash.as_ref());
-        Self { body, account_id: signer.validator_id().clone(), signature, hash }
+        let signature = signer.sign(hash.as_ref(), || RemoteSignerRequest::SignChallenge {
+            body: borsh::to_vec(&body).unwrap(),
+        })?;
+        Ok(Self { body, account_id: signer.validator_id().clone(), signature, hash })
     }
 }
 
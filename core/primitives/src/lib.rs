@@ -33,6 +33,7 @@ pub mod sandbox;
 pub mod shard_layout;
 pub mod sharding;
 pub mod signable_message;
+pub mod signing_journal;
 pub mod state;
 pub mod state_part;
 pub mod state_record;
@@ -2,6 +2,7 @@ use crate::hash::CryptoHash;
 use crate::types::{AccountId, EpochId};
 use crate::validator_signer::ValidatorSigner;
 use borsh::{BorshDeserialize, BorshSerialize};
+use near_crypto::remote_signer::RemoteSignerRequest;
 use near_crypto::{PublicKey, Signature};
 use near_schema_checker_lib::ProtocolSchema;
 use std::fmt;
@@ -67,23 +68,39 @@ pub struct AnnounceAccount {
 }
 
 impl AnnounceAccount {
-    pub fn new(signer: &ValidatorSigner, peer_id: PeerId, epoch_id: EpochId) -> Self {
-        let signature = Self::sign(signer, &peer_id, &epoch_id);
-        Self { account_id: signer.validator_id().clone(), peer_id: peer_id, epoch_id, signature }
+    pub fn new(
+        signer: &ValidatorSigner,
+        peer_id: PeerId,
+        epoch_id: EpochId,
+    ) -> std::io::Result<Self> {
+        let signature = Self::sign(signer, &peer_id, &epoch_id)?;
+        Ok(Self {
+            account_id: signer.validator_id().clone(),
+            peer_id: peer_id,
+            epoch_id,
+            signature,
+        })
     }
 
     pub fn hash(&self) -> CryptoHash {
         Self::build_header_hash(&self.account_id, &self.peer_id, &self.epoch_id)
     }
 
-    fn sign(signer: &ValidatorSigner, peer_id: &PeerId, epoch_id: &EpochId) -> Signature {
+    fn sign(
+        signer: &ValidatorSigner,
+        peer_id: &PeerId,
+        epoch_id: &EpochId,
+    ) -> std::io::Result<Signature> {
         let hash = Self::build_header_hash(signer.validator_id(), peer_id, epoch_id);
-        signer.sign_bytes(hash.as_ref())
+        signer.sign(hash.as_ref(), || RemoteSignerRequest::SignAnnounceAccount {
+            peer_id: peer_id.public_key().clone(),
+            epoch_id: epoch_id.0.as_bytes().to_vec(),
+        })
     }
 
     /// We hash only (account_id, peer_id, epoch_id). There is no need hash the signature
     /// as it's uniquely determined the triple.
-    fn build_header_hash(
+    pub fn build_header_hash(
         account_id: &AccountId,
         peer_id: &PeerId,
         epoch_id: &EpochId,
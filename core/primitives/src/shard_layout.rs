@@ -679,6 +679,42 @@ impl ShardLayout {
         parent_shard_id.ok_or(ShardLayoutError::NoParentError { shard_id })
     }
 
+    /// Whether this shard layout was created by resharding the previous one,
+    /// i.e. whether the shards in this layout have parent shards.
+    pub fn has_parent_layout(&self) -> bool {
+        match self {
+            Self::V0(_) => false,
+            Self::V1(v1) => v1.to_parent_shard_map.is_some(),
+            Self::V2(v2) => v2.shards_parent_map.is_some(),
+        }
+    }
+
+    /// Returns the shard uids of the shard and of all its ancestors, starting
+    /// from the shard itself. `ancestor_layouts` are the shard layouts that
+    /// preceded this one, from the most recent to the oldest one.
+    /// Stops at the first layout without a parent layout.
+    pub fn get_shard_uid_lineage(
+        &self,
+        shard_id: ShardId,
+        ancestor_layouts: &[ShardLayout],
+    ) -> Result<Vec<ShardUId>, ShardLayoutError> {
+        let mut lineage = vec![ShardUId::from_shard_id_and_layout(shard_id, self)];
+        let mut shard_layout = self;
+        let mut shard_id = shard_id;
+        for parent_layout in ancestor_layouts {
+            let Some(parent_shard_id) = shard_layout.try_get_parent_shard_id(shard_id)? else {
+                break;
+            };
+            if !parent_layout.shard_ids().any(|id| id == parent_shard_id) {
+                return Err(ShardLayoutError::InvalidShardIdError { shard_id: parent_shard_id });
+            }
+            lineage.push(ShardUId::from_shard_id_and_layout(parent_shard_id, parent_layout));
+            shard_layout = parent_layout;
+            shard_id = parent_shard_id;
+        }
+        Ok(lineage)
+    }
+
     /// Derive new shard layout from an existing one
     pub fn derive_shard_layout(
         base_shard_layout: &ShardLayout,
@@ -1608,6 +1644,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_shard_uid_lineage() {
+        // [0] -> [1, 2] -> [1, 3, 4]
+        let layout0 = ShardLayout::single_shard();
+        let layout1 = ShardLayout::derive_shard_layout(&layout0, "test1.near".parse().unwrap());
+        let layout2 = ShardLayout::derive_shard_layout(&layout1, "test3.near".parse().unwrap());
+        let ancestors = [layout1.clone(), layout0.clone()];
+
+        let lineage =
+            |shard_id| layout2.get_shard_uid_lineage(ShardId::new(shard_id), &ancestors).unwrap();
+        assert_eq!(
+            lineage(3),
+            vec![
+                ShardUId::new(3, ShardId::new(3)),
+                ShardUId::new(3, ShardId::new(2)),
+                ShardUId::new(0, ShardId::new(0))
+            ]
+        );
+        assert_eq!(
+            lineage(1),
+            vec![
+                ShardUId::new(3, ShardId::new(1)),
+                ShardUId::new(3, ShardId::new(1)),
+                ShardUId::new(0, ShardId::new(0))
+            ]
+        );
+        assert!(!layout0.has_parent_layout());
+        assert!(layout2.has_parent_layout());
+
+        // The ancestors must match the parent shards.
+        assert!(layout2.get_shard_uid_lineage(ShardId::new(3), &[layout0]).is_err());
+    }
+
     // Check that the ShardLayout::multi_shard method returns interesting shard
     // layouts. A shard layout is interesting if it has non-contiguous shard
     // ids.
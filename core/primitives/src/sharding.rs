@@ -9,6 +9,7 @@ use crate::types::{Balance, BlockHeight, Gas, MerkleHash, ShardId, StateRoot};
 use crate::validator_signer::ValidatorSigner;
 use crate::version::{ProtocolFeature, ProtocolVersion, SHARD_CHUNK_HEADER_UPGRADE_VERSION};
 use borsh::{BorshDeserialize, BorshSerialize};
+use near_crypto::remote_signer::RemoteSignerRequest;
 use near_crypto::Signature;
 use near_fmt::AbbrBytes;
 use near_schema_checker_lib::ProtocolSchema;
@@ -198,7 +199,7 @@ impl ShardChunkHeaderV2 {
         tx_root: CryptoHash,
         prev_validator_proposals: Vec<ValidatorStakeV1>,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> std::io::Result<Self> {
         let inner = ShardChunkHeaderInnerV1 {
             prev_block_hash,
             prev_state_root,
@@ -215,8 +216,8 @@ impl ShardChunkHeaderV2 {
             prev_validator_proposals,
         };
         let hash = Self::compute_hash(&inner);
-        let signature = signer.sign_bytes(hash.as_ref());
-        Self { inner, height_included: 0, signature, hash }
+        let signature = signer.sign_legacy(hash.as_ref())?;
+        Ok(Self { inner, height_included: 0, signature, hash })
     }
 }
 
@@ -265,7 +266,7 @@ impl ShardChunkHeaderV3 {
         congestion_info: Option<CongestionInfo>,
         bandwidth_requests: Option<BandwidthRequests>,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> std::io::Result<Self> {
         let inner = if let Some(bandwidth_requests) = bandwidth_requests {
             // `bandwidth_requests` can only be `Some` when bandwidth scheduler is enabled.
             assert!(ProtocolFeature::BandwidthScheduler.enabled(protocol_version));
@@ -329,10 +330,16 @@ impl ShardChunkHeaderV3 {
         Self::from_inner(inner, signer)
     }
 
-    pub fn from_inner(inner: ShardChunkHeaderInner, signer: &ValidatorSigner) -> Self {
+    pub fn from_inner(
+        inner: ShardChunkHeaderInner,
+        signer: &ValidatorSigner,
+    ) -> std::io::Result<Self> {
         let hash = Self::compute_hash(&inner);
-        let signature = signer.sign_bytes(hash.as_ref());
-        Self { inner, height_included: 0, signature, hash }
+        let signature =
+            signer.sign_chunk_header(inner.height_created(), inner.shard_id(), &hash.0, || {
+                RemoteSignerRequest::SignChunkHeader { inner: borsh::to_vec(&inner).unwrap() }
+            })?;
+        Ok(Self { inner, height_included: 0, signature, hash })
     }
 }
 
@@ -673,7 +680,7 @@ impl ShardChunkHeaderV1 {
         tx_root: CryptoHash,
         prev_validator_proposals: Vec<ValidatorStakeV1>,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> std::io::Result<Self> {
         let inner = ShardChunkHeaderInnerV1 {
             prev_block_hash,
             prev_state_root,
@@ -690,8 +697,8 @@ impl ShardChunkHeaderV1 {
             prev_validator_proposals,
         };
         let hash = Self::compute_hash(&inner);
-        let signature = signer.sign_bytes(hash.as_ref());
-        Self { inner, height_included: 0, signature, hash }
+        let signature = signer.sign_legacy(hash.as_ref())?;
+        Ok(Self { inner, height_included: 0, signature, hash })
     }
 }
 
@@ -1255,7 +1262,7 @@ impl EncodedShardChunk {
                 tx_root,
                 prev_validator_proposals,
                 signer,
-            );
+            )?;
             let chunk = EncodedShardChunkV1 { header, content };
             Ok((Self::V1(chunk), merkle_paths))
         } else if block_header_v3_version.is_none()
@@ -1278,7 +1285,7 @@ impl EncodedShardChunk {
                 tx_root,
                 validator_proposals,
                 signer,
-            );
+            )?;
             let chunk = EncodedShardChunkV2 { header: ShardChunkHeader::V2(header), content };
             Ok((Self::V2(chunk), merkle_paths))
         } else {
@@ -1300,7 +1307,7 @@ impl EncodedShardChunk {
                 congestion_info,
                 bandwidth_requests,
                 signer,
-            );
+            )?;
             let chunk = EncodedShardChunkV2 { header: ShardChunkHeader::V3(header), content };
             Ok((Self::V2(chunk), merkle_paths))
         }
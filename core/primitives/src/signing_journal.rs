@@ -0,0 +1,328 @@
+//! Persistent protection against double signing.
+//!
+//! When a validator fails over to a standby node with the same key, both nodes
+//! could sign conflicting blocks, chunks, approvals or chunk endorsements for
+//! the same height. The signing journal records what the validator released
+//! for every height and refuses a second, different item for the same height.
+//! It is consulted by [`crate::validator_signer::ValidatorSigner`] before it
+//! signs one of these items.
+//!
+//! The journal is a file with one JSON entry per line, appended to and synced
+//! before the item is released. To fail over, export the journal of the old
+//! node and import it into the journal of the new node before it starts
+//! signing.
+use crate::hash::{hash, CryptoHash};
+use crate::types::{BlockHeight, ShardId};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+
+/// Entries this many heights below the highest entry are dropped, and signing
+/// at these heights is refused, since they can't be checked any more.
+const RETAINED_HEIGHTS: BlockHeight = 100_000;
+/// The file is compacted after this many entries were appended to it.
+const COMPACT_AFTER_APPENDS: usize = 1_000_000;
+
+/// Kind of a signed item. Items of different kinds never conflict.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SignedItemKind {
+    Block,
+    Chunk,
+    Approval,
+    ChunkEndorsement,
+}
+
+/// An item about to be released by the validator.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SignedItem {
+    pub kind: SignedItemKind,
+    pub height: BlockHeight,
+    /// Set for per shard items, chunks and chunk endorsements.
+    pub shard_id: Option<ShardId>,
+    /// Identifies the content. Two items with the same kind, height and shard
+    /// conflict unless their hashes match.
+    pub hash: CryptoHash,
+}
+
+impl SignedItem {
+    pub fn block(height: BlockHeight, block_hash: CryptoHash) -> Self {
+        Self { kind: SignedItemKind::Block, height, shard_id: None, hash: block_hash }
+    }
+
+    pub fn chunk(height_created: BlockHeight, shard_id: ShardId, chunk_hash: CryptoHash) -> Self {
+        Self {
+            kind: SignedItemKind::Chunk,
+            height: height_created,
+            shard_id: Some(shard_id),
+            hash: chunk_hash,
+        }
+    }
+
+    /// `data` is the signed data of the approval, it only depends on the
+    /// approval inner and the target height.
+    pub fn approval(target_height: BlockHeight, data: &[u8]) -> Self {
+        Self {
+            kind: SignedItemKind::Approval,
+            height: target_height,
+            shard_id: None,
+            hash: hash(data),
+        }
+    }
+
+    pub fn chunk_endorsement(
+        height_created: BlockHeight,
+        shard_id: ShardId,
+        chunk_hash: CryptoHash,
+    ) -> Self {
+        Self {
+            kind: SignedItemKind::ChunkEndorsement,
+            height: height_created,
+            shard_id: Some(shard_id),
+            hash: chunk_hash,
+        }
+    }
+
+    fn key(&self) -> EntryKey {
+        (self.kind, self.height, self.shard_id)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SigningJournalError {
+    #[error("refusing to sign {item:?}, {existing} was already signed at this height")]
+    Conflict { item: SignedItem, existing: CryptoHash },
+    #[error("refusing to sign {item:?}, heights below {floor} are no longer tracked")]
+    TooOld { item: SignedItem, floor: BlockHeight },
+    #[error("failed to write the signing journal: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Open journals by path, so that all validator signers loaded from the same
+/// config, e.g. when the key is hot swapped, share one instance.
+static OPEN_JOURNALS: LazyLock<Mutex<HashMap<PathBuf, Weak<SigningJournal>>>> =
+    LazyLock::new(Default::default);
+
+pub struct SigningJournal {
+    path: PathBuf,
+    state: Mutex<JournalState>,
+}
+
+type EntryKey = (SignedItemKind, BlockHeight, Option<ShardId>);
+
+struct JournalState {
+    file: File,
+    entries: HashMap<EntryKey, CryptoHash>,
+    max_height: BlockHeight,
+    appends: usize,
+}
+
+impl SigningJournal {
+    /// Opens the journal at the path, creating it if it doesn't exist.
+    pub fn open(path: &Path) -> io::Result<Arc<Self>> {
+        let mut open_journals = OPEN_JOURNALS.lock().unwrap();
+        if let Some(journal) = open_journals.get(path).and_then(Weak::upgrade) {
+            return Ok(journal);
+        }
+        let entries = if path.exists() { read_entries(path)? } else { vec![] };
+        let mut state = JournalState {
+            file: File::options().create(true).append(true).open(path)?,
+            entries: HashMap::new(),
+            max_height: 0,
+            appends: 0,
+        };
+        for item in entries {
+            state.insert(item);
+        }
+        let journal = Arc::new(Self { path: path.to_path_buf(), state: Mutex::new(state) });
+        journal.compact(&mut journal.state.lock().unwrap())?;
+        open_journals.insert(path.to_path_buf(), Arc::downgrade(&journal));
+        Ok(journal)
+    }
+
+    /// Records the item, unless a conflicting item was recorded before. The
+    /// item must only be released if this succeeds.
+    pub fn record(&self, item: &SignedItem) -> Result<(), SigningJournalError> {
+        let mut state = self.state.lock().unwrap();
+        let floor = state.floor();
+        if item.height < floor {
+            return Err(SigningJournalError::TooOld { item: item.clone(), floor });
+        }
+        match state.entries.get(&item.key()) {
+            Some(existing) if *existing == item.hash => return Ok(()),
+            Some(existing) => {
+                return Err(SigningJournalError::Conflict {
+                    item: item.clone(),
+                    existing: *existing,
+                })
+            }
+            None => {}
+        }
+        state.append(item)?;
+        state.insert(item.clone());
+        if state.appends >= COMPACT_AFTER_APPENDS {
+            self.compact(&mut state)?;
+        }
+        Ok(())
+    }
+
+    /// Copies the entries of the journal at `path` to a file that can be
+    /// imported by another node. Only reads the journal, so it's safe to run
+    /// while the node is running, but entries added after this returns are not
+    /// exported, so stop the node first when failing over.
+    pub fn export(path: &Path, output: &Path) -> io::Result<usize> {
+        let mut entries = HashMap::new();
+        for item in read_entries(path)? {
+            entries.insert(item.key(), item.hash);
+        }
+        write_entries(output, &entries)?;
+        Ok(entries.len())
+    }
+
+    /// Adds the entries of an exported journal. Fails without importing
+    /// anything if an entry conflicts with an entry of this journal, which
+    /// means that the validator already double signed.
+    pub fn import(&self, path: &Path) -> Result<usize, SigningJournalError> {
+        let items = read_entries(path)?;
+        let mut state = self.state.lock().unwrap();
+        for item in &items {
+            match state.entries.get(&item.key()) {
+                Some(existing) if *existing != item.hash => {
+                    return Err(SigningJournalError::Conflict {
+                        item: item.clone(),
+                        existing: *existing,
+                    });
+                }
+                _ => {}
+            }
+        }
+        let mut imported = 0;
+        for item in items {
+            if !state.entries.contains_key(&item.key()) {
+                state.append(&item)?;
+                state.insert(item);
+                imported += 1;
+            }
+        }
+        Ok(imported)
+    }
+
+    /// Rewrites the file with the retained entries only.
+    fn compact(&self, state: &mut JournalState) -> io::Result<()> {
+        let floor = state.floor();
+        state.entries.retain(|(_, height, _), _| *height >= floor);
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        write_entries(&tmp_path, &state.entries)?;
+        std::fs::rename(&tmp_path, &self.path)?;
+        state.file = File::options().append(true).open(&self.path)?;
+        state.appends = 0;
+        Ok(())
+    }
+}
+
+impl JournalState {
+    fn floor(&self) -> BlockHeight {
+        self.max_height.saturating_sub(RETAINED_HEIGHTS)
+    }
+
+    fn insert(&mut self, item: SignedItem) {
+        self.max_height = self.max_height.max(item.height);
+        self.entries.insert(item.key(), item.hash);
+    }
+
+    fn append(&mut self, item: &SignedItem) -> io::Result<()> {
+        let mut line = serde_json::to_vec(item)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.file.sync_data()?;
+        self.appends += 1;
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for SigningJournal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningJournal").field("path", &self.path).finish()
+    }
+}
+
+impl PartialEq for SigningJournal {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+fn read_entries(path: &Path) -> io::Result<Vec<SignedItem>> {
+    let mut items = vec![];
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        items.push(serde_json::from_str(&line)?);
+    }
+    Ok(items)
+}
+
+fn write_entries(path: &Path, entries: &HashMap<EntryKey, CryptoHash>) -> io::Result<()> {
+    let mut file = io::BufWriter::new(File::create(path)?);
+    let mut entries: Vec<_> = entries.iter().collect();
+    entries.sort_by_key(|(key, _)| **key);
+    for (&(kind, height, shard_id), &hash) in entries {
+        serde_json::to_writer(&mut file, &SignedItem { kind, height, shard_id, hash })?;
+        file.write_all(b"\n")?;
+    }
+    let file = file.into_inner().map_err(|err| err.into_error())?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SignedItem, SigningJournal, SigningJournalError, RETAINED_HEIGHTS};
+    use crate::hash::hash;
+    use crate::types::ShardId;
+
+    #[test]
+    fn test_signing_journal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal");
+        let journal = SigningJournal::open(&path).unwrap();
+
+        let block = SignedItem::block(10, hash(b"a"));
+        journal.record(&block).unwrap();
+        // Signing the same item again is fine.
+        journal.record(&block).unwrap();
+        assert!(matches!(
+            journal.record(&SignedItem::block(10, hash(b"b"))),
+            Err(SigningJournalError::Conflict { .. })
+        ));
+        // Other kinds and shards don't conflict.
+        journal.record(&SignedItem::approval(10, b"b")).unwrap();
+        journal.record(&SignedItem::chunk(10, ShardId::new(0), hash(b"b"))).unwrap();
+        journal.record(&SignedItem::chunk(10, ShardId::new(1), hash(b"c"))).unwrap();
+
+        // A new node importing the journal of the old one refuses to double sign.
+        let export_path = dir.path().join("export");
+        assert_eq!(SigningJournal::export(&path, &export_path).unwrap(), 4);
+        let new_journal = SigningJournal::open(&dir.path().join("new_journal")).unwrap();
+        assert_eq!(new_journal.import(&export_path).unwrap(), 4);
+        assert!(new_journal.record(&SignedItem::chunk(10, ShardId::new(1), hash(b"d"))).is_err());
+
+        // Entries survive reopening the journal.
+        drop(journal);
+        let journal = SigningJournal::open(&path).unwrap();
+        assert!(journal.record(&SignedItem::block(10, hash(b"b"))).is_err());
+
+        journal.record(&SignedItem::block(10 + RETAINED_HEIGHTS + 1, hash(b"a"))).unwrap();
+        assert!(matches!(
+            journal.record(&SignedItem::block(0, hash(b"a"))),
+            Err(SigningJournalError::TooOld { .. })
+        ));
+    }
+}
@@ -5,8 +5,10 @@ use crate::sharding::{
 };
 use crate::types::{BlockHeight, EpochId, ShardId, StateRoot, StateRootNode};
 use borsh::{BorshDeserialize, BorshSerialize};
+use near_crypto::{PublicKey, SecretKey, Signature};
 use near_primitives_core::types::EpochHeight;
 use near_schema_checker_lib::ProtocolSchema;
+use std::io::Read;
 use std::sync::Arc;
 
 #[derive(PartialEq, Eq, Clone, Debug, BorshSerialize, BorshDeserialize, ProtocolSchema)]
@@ -304,9 +306,116 @@ pub enum StateSyncDumpProgress {
     },
 }
 
+/// Hash and size of a state part file as it is stored in external storage,
+/// i.e. after compression if the part was compressed.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StatePartsManifestEntry {
+    pub hash: CryptoHash,
+    pub size: u64,
+}
+
+/// Lists all state parts dumped to external storage for one shard and epoch.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StatePartsManifest {
+    pub epoch_id: EpochId,
+    pub epoch_height: EpochHeight,
+    pub shard_id: ShardId,
+    pub state_root: StateRoot,
+    /// Entry `i` describes the part with part id `i`.
+    pub parts: Vec<StatePartsManifestEntry>,
+}
+
+/// A `StatePartsManifest` signed by the node that dumped the state parts.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SignedStatePartsManifest {
+    pub manifest: StatePartsManifest,
+    pub public_key: PublicKey,
+    pub signature: Signature,
+}
+
+impl SignedStatePartsManifest {
+    pub fn new(manifest: StatePartsManifest, secret_key: &SecretKey) -> Self {
+        let signature = secret_key.sign(&borsh::to_vec(&manifest).unwrap());
+        Self { manifest, public_key: secret_key.public_key(), signature }
+    }
+
+    /// Checks that the manifest is signed by one of the trusted dumper keys.
+    /// A manifest signed by any other key is rejected even if its signature is valid.
+    pub fn verify_signature(&self, trusted_keys: &[PublicKey]) -> bool {
+        trusted_keys.contains(&self.public_key)
+            && self.signature.verify(&borsh::to_vec(&self.manifest).unwrap(), &self.public_key)
+    }
+
+    /// Checks that the stored bytes of the given part match the manifest.
+    pub fn verify_part(&self, part_id: u64, data: &[u8]) -> Result<(), String> {
+        let Some(entry) = self.manifest.parts.get(part_id as usize) else {
+            return Err(format!(
+                "part {} is not listed in the manifest of {} parts",
+                part_id,
+                self.manifest.parts.len()
+            ));
+        };
+        if entry.size != data.len() as u64 {
+            return Err(format!(
+                "part {} has size {}, manifest says {}",
+                part_id,
+                data.len(),
+                entry.size
+            ));
+        }
+        let hash = CryptoHash::hash_bytes(data);
+        if entry.hash != hash {
+            return Err(format!(
+                "part {} has hash {}, manifest says {}",
+                part_id, hash, entry.hash
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Every zstd frame starts with these bytes. Borsh-serialized state parts
+/// start with the `PartialState` variant index, so they can never be confused
+/// with compressed ones.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Upper bound on the size of a decompressed state part, protecting the
+/// syncing node from maliciously crafted uploads.
+pub const MAX_DECOMPRESSED_STATE_PART_SIZE: bytesize::ByteSize =
+    bytesize::ByteSize(16 * STATE_PART_MEMORY_LIMIT.0);
+
+/// Compresses a state part with zstd before it gets dumped to external storage.
+pub fn compress_state_part(data: &[u8], level: i32) -> std::io::Result<Vec<u8>> {
+    zstd::encode_all(data, level)
+}
+
+/// Returns the borsh-serialized state part, decompressing it if it was dumped
+/// compressed. Uncompressed parts are returned as is.
+pub fn decompress_state_part(data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+    if !data.starts_with(&ZSTD_MAGIC) {
+        return Ok(data);
+    }
+    let limit = MAX_DECOMPRESSED_STATE_PART_SIZE.as_u64();
+    let mut decompressed = Vec::new();
+    zstd::stream::Decoder::new(data.as_slice())?.take(limit + 1).read_to_end(&mut decompressed)?;
+    if decompressed.len() as u64 > limit {
+        return Err(std::io::Error::other(format!(
+            "Decompressed state part exceeded limit of {}",
+            MAX_DECOMPRESSED_STATE_PART_SIZE
+        )));
+    }
+    Ok(decompressed)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::state_sync::{get_num_state_parts, STATE_PART_MEMORY_LIMIT};
+    use crate::hash::CryptoHash;
+    use crate::state_sync::{
+        compress_state_part, decompress_state_part, get_num_state_parts, SignedStatePartsManifest,
+        StatePartsManifest, StatePartsManifestEntry, STATE_PART_MEMORY_LIMIT,
+    };
+    use crate::types::EpochId;
+    use near_crypto::{KeyType, SecretKey};
 
     #[test]
     fn test_get_num_state_parts() {
@@ -317,4 +426,47 @@ mod tests {
         assert_eq!(get_num_state_parts(STATE_PART_MEMORY_LIMIT.as_u64() * 100), 100);
         assert_eq!(get_num_state_parts(STATE_PART_MEMORY_LIMIT.as_u64() * 100 + 1), 101);
     }
+
+    #[test]
+    fn test_compress_state_part() {
+        let part = borsh::to_vec(&vec![vec![7u8; 1000]; 10]).unwrap();
+        assert_eq!(decompress_state_part(part.clone()).unwrap(), part);
+        let compressed = compress_state_part(&part, 3).unwrap();
+        assert!(compressed.len() < part.len());
+        assert_eq!(decompress_state_part(compressed).unwrap(), part);
+    }
+
+    #[test]
+    fn test_signed_state_parts_manifest() {
+        let parts: Vec<Vec<u8>> = vec![vec![1, 2, 3], vec![4, 5]];
+        let manifest = StatePartsManifest {
+            epoch_id: EpochId::default(),
+            epoch_height: 1,
+            shard_id: 0.into(),
+            state_root: CryptoHash::default(),
+            parts: parts
+                .iter()
+                .map(|part| StatePartsManifestEntry {
+                    hash: CryptoHash::hash_bytes(part),
+                    size: part.len() as u64,
+                })
+                .collect(),
+        };
+        let secret_key = SecretKey::from_seed(KeyType::ED25519, "dumper");
+        let signed = SignedStatePartsManifest::new(manifest, &secret_key);
+        let trusted_keys = [secret_key.public_key()];
+        assert!(signed.verify_signature(&trusted_keys));
+        let other_key = SecretKey::from_seed(KeyType::ED25519, "other").public_key();
+        assert!(!signed.verify_signature(&[other_key]));
+        assert!(!signed.verify_signature(&[]));
+        assert!(signed.verify_part(0, &parts[0]).is_ok());
+        assert!(signed.verify_part(1, &parts[1]).is_ok());
+        assert!(signed.verify_part(1, &parts[1][..1]).is_err());
+        assert!(signed.verify_part(0, &[3, 2, 1]).is_err());
+        assert!(signed.verify_part(2, &parts[0]).is_err());
+
+        let mut tampered = signed;
+        tampered.manifest.epoch_height = 2;
+        assert!(!tampered.verify_signature(&trusted_keys));
+    }
 }
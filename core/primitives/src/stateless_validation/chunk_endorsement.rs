@@ -4,11 +4,12 @@ use crate::sharding::{ChunkHash, ShardChunkHeader};
 use crate::types::EpochId;
 use crate::validator_signer::ValidatorSigner;
 use borsh::{BorshDeserialize, BorshSerialize};
+use near_crypto::remote_signer::RemoteSignerRequest;
 use near_crypto::{PublicKey, Signature};
 use near_primitives_core::types::{AccountId, BlockHeight, ShardId};
 use near_schema_checker_lib::ProtocolSchema;
 
-use super::{ChunkProductionKey, SignatureDifferentiator};
+use super::{ChunkProductionKey, SignatureDifferentiator, SignedInner};
 
 /// The endorsement of a chunk by a chunk validator. By providing this, a
 /// chunk validator has verified that the chunk state witness is correct.
@@ -24,7 +25,7 @@ impl ChunkEndorsement {
         epoch_id: EpochId,
         chunk_header: &ShardChunkHeader,
         signer: &ValidatorSigner,
-    ) -> ChunkEndorsement {
+    ) -> std::io::Result<ChunkEndorsement> {
         let inner = ChunkEndorsementInner::new(chunk_header.chunk_hash());
         let metadata = ChunkEndorsementMetadata {
             account_id: signer.validator_id().clone(),
@@ -32,10 +33,22 @@ impl ChunkEndorsement {
             epoch_id,
             height_created: chunk_header.height_created(),
         };
-        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap());
-        let metadata_signature = signer.sign_bytes(&borsh::to_vec(&metadata).unwrap());
+        let inner_data = borsh::to_vec(&inner).unwrap();
+        let metadata_data = borsh::to_vec(&metadata).unwrap();
+        let mut signatures = signer.sign_chunk_endorsement(
+            metadata.height_created,
+            metadata.shard_id,
+            &chunk_header.chunk_hash().0,
+            &[&inner_data, &metadata_data],
+            || RemoteSignerRequest::SignChunkEndorsement {
+                inner: inner_data.clone(),
+                metadata: metadata_data.clone(),
+            },
+        )?;
+        let metadata_signature = signatures.pop().unwrap();
+        let signature = signatures.pop().unwrap();
         let endorsement = ChunkEndorsementV2 { inner, signature, metadata, metadata_signature };
-        ChunkEndorsement::V2(endorsement)
+        Ok(ChunkEndorsement::V2(endorsement))
     }
 
     pub fn chunk_production_key(&self) -> ChunkProductionKey {
@@ -125,6 +138,20 @@ pub struct ChunkEndorsementMetadata {
     height_created: BlockHeight,
 }
 
+impl ChunkEndorsementMetadata {
+    pub fn account_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    pub fn shard_id(&self) -> ShardId {
+        self.shard_id
+    }
+
+    pub fn height_created(&self) -> BlockHeight {
+        self.height_created
+    }
+}
+
 /// This is the part of the chunk endorsement that is actually being signed.
 #[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize, ProtocolSchema)]
 pub struct ChunkEndorsementInner {
@@ -134,6 +161,18 @@ pub struct ChunkEndorsementInner {
 
 impl ChunkEndorsementInner {
     fn new(chunk_hash: ChunkHash) -> Self {
-        Self { chunk_hash, signature_differentiator: "ChunkEndorsement".to_owned() }
+        Self { chunk_hash, signature_differentiator: Self::SIGNATURE_DIFFERENTIATOR.to_owned() }
+    }
+
+    pub fn chunk_hash(&self) -> &ChunkHash {
+        &self.chunk_hash
+    }
+}
+
+impl SignedInner for ChunkEndorsementInner {
+    const SIGNATURE_DIFFERENTIATOR: &'static str = "ChunkEndorsement";
+
+    fn signature_differentiator(&self) -> &str {
+        &self.signature_differentiator
     }
 }
@@ -3,6 +3,7 @@ use std::sync::Arc;
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use bytesize::ByteSize;
+use near_crypto::remote_signer::RemoteSignerRequest;
 use near_crypto::{PublicKey, Signature};
 use near_primitives_core::code::ContractCode;
 use near_primitives_core::hash::{hash, CryptoHash};
@@ -13,7 +14,7 @@ use near_schema_checker_lib::ProtocolSchema;
 use crate::reed_solomon::{ReedSolomonEncoderDeserialize, ReedSolomonEncoderSerialize};
 use crate::{utils::compression::CompressedData, validator_signer::ValidatorSigner};
 
-use super::{ChunkProductionKey, SignatureDifferentiator};
+use super::{ChunkProductionKey, SignatureDifferentiator, SignedInner};
 
 // Data structures for chunk producers to send accessesed contracts to chunk validators.
 
@@ -38,8 +39,8 @@ impl ChunkContractAccesses {
         contracts: HashSet<CodeHash>,
         main_transition: MainTransitionKey,
         signer: &ValidatorSigner,
-    ) -> Self {
-        Self::V1(ChunkContractAccessesV1::new(next_chunk, contracts, main_transition, signer))
+    ) -> std::io::Result<Self> {
+        Ok(Self::V1(ChunkContractAccessesV1::new(next_chunk, contracts, main_transition, signer)?))
     }
 
     pub fn contracts(&self) -> &[CodeHash] {
@@ -80,10 +81,13 @@ impl ChunkContractAccessesV1 {
         contracts: HashSet<CodeHash>,
         main_transition: MainTransitionKey,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> std::io::Result<Self> {
         let inner = ChunkContractAccessesInner::new(next_chunk, contracts, main_transition);
-        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap());
-        Self { inner, signature }
+        let data = borsh::to_vec(&inner).unwrap();
+        let signature = signer.sign(&data, || RemoteSignerRequest::SignChunkContractAccesses {
+            inner: data.clone(),
+        })?;
+        Ok(Self { inner, signature })
     }
 
     fn verify_signature(&self, public_key: &PublicKey) -> bool {
@@ -115,11 +119,19 @@ impl ChunkContractAccessesInner {
             next_chunk,
             contracts: contracts.into_iter().collect(),
             main_transition,
-            signature_differentiator: "ChunkContractAccessesInner".to_owned(),
+            signature_differentiator: Self::SIGNATURE_DIFFERENTIATOR.to_owned(),
         }
     }
 }
 
+impl SignedInner for ChunkContractAccessesInner {
+    const SIGNATURE_DIFFERENTIATOR: &'static str = "ChunkContractAccessesInner";
+
+    fn signature_differentiator(&self) -> &str {
+        &self.signature_differentiator
+    }
+}
+
 // Data structures for chunk validators to request contract code from chunk producers.
 
 /// Message to request missing code for a set of contracts.
@@ -135,8 +147,8 @@ impl ContractCodeRequest {
         contracts: HashSet<CodeHash>,
         main_transition: MainTransitionKey,
         signer: &ValidatorSigner,
-    ) -> Self {
-        Self::V1(ContractCodeRequestV1::new(next_chunk, contracts, main_transition, signer))
+    ) -> std::io::Result<Self> {
+        Ok(Self::V1(ContractCodeRequestV1::new(next_chunk, contracts, main_transition, signer)?))
     }
 
     pub fn requester(&self) -> &AccountId {
@@ -183,15 +195,17 @@ impl ContractCodeRequestV1 {
         contracts: HashSet<CodeHash>,
         main_transition: MainTransitionKey,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> std::io::Result<Self> {
         let inner = ContractCodeRequestInner::new(
             signer.validator_id().clone(),
             next_chunk,
             contracts,
             main_transition,
         );
-        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap());
-        Self { inner, signature }
+        let data = borsh::to_vec(&inner).unwrap();
+        let signature = signer
+            .sign(&data, || RemoteSignerRequest::SignContractCodeRequest { inner: data.clone() })?;
+        Ok(Self { inner, signature })
     }
 
     pub fn verify_signature(&self, public_key: &PublicKey) -> bool {
@@ -227,9 +241,21 @@ impl ContractCodeRequestInner {
             next_chunk,
             contracts: contracts.into_iter().collect(),
             main_transition,
-            signature_differentiator: "ContractCodeRequestInner".to_owned(),
+            signature_differentiator: Self::SIGNATURE_DIFFERENTIATOR.to_owned(),
         }
     }
+
+    pub fn requester(&self) -> &AccountId {
+        &self.requester
+    }
+}
+
+impl SignedInner for ContractCodeRequestInner {
+    const SIGNATURE_DIFFERENTIATOR: &'static str = "ContractCodeRequestInner";
+
+    fn signature_differentiator(&self) -> &str {
+        &self.signature_differentiator
+    }
 }
 
 // Data structures for chunk producers to send contract code to chunk validators as response to ContractCodeRequest.
@@ -418,8 +444,8 @@ impl PartialEncodedContractDeploys {
         key: ChunkProductionKey,
         part: PartialEncodedContractDeploysPart,
         signer: &ValidatorSigner,
-    ) -> Self {
-        Self::V1(PartialEncodedContractDeploysV1::new(key, part, signer))
+    ) -> std::io::Result<Self> {
+        Ok(Self::V1(PartialEncodedContractDeploysV1::new(key, part, signer)?))
     }
 
     pub fn chunk_production_key(&self) -> &ChunkProductionKey {
@@ -464,10 +490,13 @@ impl PartialEncodedContractDeploysV1 {
         key: ChunkProductionKey,
         part: PartialEncodedContractDeploysPart,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> std::io::Result<Self> {
         let inner = PartialEncodedContractDeploysInner::new(key, part);
-        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap());
-        Self { inner, signature }
+        let data = borsh::to_vec(&inner).unwrap();
+        let signature = signer.sign(&data, || {
+            RemoteSignerRequest::SignPartialEncodedContractDeploys { inner: data.clone() }
+        })?;
+        Ok(Self { inner, signature })
     }
 
     pub fn verify_signature(&self, public_key: &PublicKey) -> bool {
@@ -504,7 +533,15 @@ impl PartialEncodedContractDeploysInner {
         Self {
             next_chunk,
             part,
-            signature_differentiator: "PartialEncodedContractDeploysInner".to_owned(),
+            signature_differentiator: Self::SIGNATURE_DIFFERENTIATOR.to_owned(),
         }
     }
 }
+
+impl SignedInner for PartialEncodedContractDeploysInner {
+    const SIGNATURE_DIFFERENTIATOR: &'static str = "PartialEncodedContractDeploysInner";
+
+    fn signature_differentiator(&self) -> &str {
+        &self.signature_differentiator
+    }
+}
@@ -20,6 +20,15 @@ pub mod validator_assignment;
 /// This is a messy workaround until we know what to do with NEP 483.
 type SignatureDifferentiator = String;
 
+/// The signed part of a message with a [`SignatureDifferentiator`]. Lets a
+/// remote signer check that the data it is asked to sign is a message of the
+/// expected type.
+pub trait SignedInner: BorshDeserialize {
+    const SIGNATURE_DIFFERENTIATOR: &'static str;
+
+    fn signature_differentiator(&self) -> &str;
+}
+
 /// This struct contains combination of fields that uniquely identify chunk production.
 /// It means that for a given instance only one chunk could be produced.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, BorshSerialize, BorshDeserialize, ProtocolSchema)]
@@ -1,11 +1,12 @@
 use std::fmt::{Debug, Formatter};
 
-use super::{ChunkProductionKey, SignatureDifferentiator};
+use super::{ChunkProductionKey, SignatureDifferentiator, SignedInner};
 use crate::sharding::ShardChunkHeader;
 use crate::types::EpochId;
 use crate::validator_signer::ValidatorSigner;
 use borsh::{BorshDeserialize, BorshSerialize};
 use bytesize::ByteSize;
+use near_crypto::remote_signer::RemoteSignerRequest;
 use near_crypto::{PublicKey, Signature};
 use near_primitives_core::types::{BlockHeight, ShardId};
 use near_schema_checker_lib::ProtocolSchema;
@@ -47,7 +48,7 @@ impl PartialEncodedStateWitness {
         part: Vec<u8>,
         encoded_length: usize,
         signer: &ValidatorSigner,
-    ) -> Self {
+    ) -> std::io::Result<Self> {
         let inner = PartialEncodedStateWitnessInner::new(
             epoch_id,
             chunk_header,
@@ -55,8 +56,10 @@ impl PartialEncodedStateWitness {
             part,
             encoded_length,
         );
-        let signature = signer.sign_bytes(&borsh::to_vec(&inner).unwrap());
-        Self { inner, signature }
+        let data = borsh::to_vec(&inner).unwrap();
+        let signature = signer
+            .sign(&data, || RemoteSignerRequest::SignPartialStateWitness { inner: data.clone() })?;
+        Ok(Self { inner, signature })
     }
 
     pub fn chunk_production_key(&self) -> ChunkProductionKey {
@@ -115,7 +118,15 @@ impl PartialEncodedStateWitnessInner {
             part_ord,
             part: part.into_boxed_slice(),
             encoded_length,
-            signature_differentiator: "PartialEncodedStateWitness".to_owned(),
+            signature_differentiator: Self::SIGNATURE_DIFFERENTIATOR.to_owned(),
         }
     }
 }
+
+impl SignedInner for PartialEncodedStateWitnessInner {
+    const SIGNATURE_DIFFERENTIATOR: &'static str = "PartialEncodedStateWitness";
+
+    fn signature_differentiator(&self) -> &str {
+        &self.signature_differentiator
+    }
+}
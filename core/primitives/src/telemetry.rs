@@ -4,7 +4,7 @@ use crate::types::AccountId;
 use crate::types::BlockHeight;
 use near_primitives_core::hash::CryptoHash;
 
-#[derive(serde::Serialize, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct TelemetryAgentInfo {
     pub name: String,
     pub version: String,
@@ -12,7 +12,7 @@ pub struct TelemetryAgentInfo {
     pub protocol_version: u32,
 }
 
-#[derive(serde::Serialize, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct TelemetrySystemInfo {
     pub bandwidth_download: u64,
     pub bandwidth_upload: u64,
@@ -21,7 +21,7 @@ pub struct TelemetrySystemInfo {
     pub boot_time_seconds: i64,
 }
 
-#[derive(serde::Serialize, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct TelemetryChainInfo {
     pub chain_id: String,
     pub node_id: String,
@@ -37,7 +37,7 @@ pub struct TelemetryChainInfo {
     pub max_block_wait_delay: f64,
 }
 
-#[derive(serde::Serialize, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct TelemetryInfo {
     pub agent: TelemetryAgentInfo,
     pub system: TelemetrySystemInfo,
@@ -19,6 +19,7 @@ use crate::types::{AccountId, Balance, EpochId, EpochInfoProvider, Gas, Nonce};
 use crate::validator_signer::ValidatorSigner;
 use crate::version::PROTOCOL_VERSION;
 use crate::views::{ExecutionStatusView, FinalExecutionOutcomeView, FinalExecutionStatus};
+use near_crypto::remote_signer::RemoteSignerRequest;
 use near_crypto::vrf::Value;
 use near_crypto::{EmptySigner, PublicKey, SecretKey, Signature, Signer};
 use near_primitives_core::types::{BlockHeight, MerkleHash, ProtocolVersion, ShardId};
@@ -401,12 +402,17 @@ impl BlockHeader {
     }
 
     pub fn resign(&mut self, signer: &ValidatorSigner) {
-        let hash = BlockHeader::compute_hash(
-            *self.prev_hash(),
-            &self.inner_lite_bytes(),
-            &self.inner_rest_bytes(),
-        );
-        let signature = signer.sign_bytes(hash.as_ref());
+        let prev_hash = *self.prev_hash();
+        let inner_lite = self.inner_lite_bytes();
+        let inner_rest = self.inner_rest_bytes();
+        let hash = BlockHeader::compute_hash(prev_hash, &inner_lite, &inner_rest);
+        let signature = signer
+            .sign(hash.as_ref(), || RemoteSignerRequest::SignBlockHeader {
+                prev_hash: prev_hash.as_bytes().to_vec(),
+                inner_lite,
+                inner_rest,
+            })
+            .unwrap();
         match self {
             BlockHeader::BlockHeaderV1(header) => {
                 let header = Arc::make_mut(header);
@@ -786,6 +792,7 @@ pub struct TestBlockBuilder {
     next_bp_hash: CryptoHash,
     approvals: Vec<Option<Box<near_crypto::Signature>>>,
     block_merkle_root: CryptoHash,
+    chunks: Option<Vec<ShardChunkHeader>>,
 }
 
 #[cfg(feature = "clock")]
@@ -808,6 +815,7 @@ impl TestBlockBuilder {
             next_bp_hash: *prev.header().next_bp_hash(),
             approvals: vec![],
             block_merkle_root: tree.root(),
+            chunks: None,
         }
     }
     pub fn height(mut self, height: u64) -> Self {
@@ -830,6 +838,12 @@ impl TestBlockBuilder {
         self.approvals = approvals;
         self
     }
+    /// Sets the chunk headers of the block. By default the block reuses the
+    /// chunks of the previous block.
+    pub fn chunks(mut self, chunks: Vec<ShardChunkHeader>) -> Self {
+        self.chunks = Some(chunks);
+        self
+    }
 
     /// Updates the merkle tree by adding the previous hash, and updates the new block's merkle_root.
     pub fn block_merkle_tree(
@@ -843,6 +857,9 @@ impl TestBlockBuilder {
 
     pub fn build(self) -> Block {
         tracing::debug!(target: "test", height=self.height, ?self.epoch_id, "produce block");
+        let chunks =
+            self.chunks.unwrap_or_else(|| self.prev.chunks().iter_deprecated().cloned().collect());
+        let num_chunks = chunks.len();
         Block::produce(
             PROTOCOL_VERSION,
             PROTOCOL_VERSION,
@@ -850,8 +867,8 @@ impl TestBlockBuilder {
             self.prev.header(),
             self.height,
             self.prev.header().block_ordinal() + 1,
-            self.prev.chunks().iter_deprecated().cloned().collect(),
-            vec![vec![]; self.prev.chunks().len()],
+            chunks,
+            vec![vec![]; num_chunks],
             self.epoch_id,
             self.next_epoch_id,
             None,
@@ -868,6 +885,7 @@ impl TestBlockBuilder {
             self.clock,
             None,
         )
+        .unwrap()
     }
 }
 
@@ -2,9 +2,14 @@ use std::fmt::Debug;
 use std::path::Path;
 use std::sync::Arc;
 
+use near_crypto::remote_signer::RemoteSignerRequest;
+#[cfg(unix)]
+use near_crypto::RemoteSigner;
 use near_crypto::{InMemorySigner, KeyType, PublicKey, Signature, Signer};
 
-use crate::types::AccountId;
+use crate::hash::CryptoHash;
+use crate::signing_journal::{SignedItem, SigningJournal};
+use crate::types::{AccountId, BlockHeight, ShardId};
 
 /// Enum for validator signer, that holds validator id and key used for signing data.
 #[derive(Clone, Debug, PartialEq)]
@@ -13,6 +18,9 @@ pub enum ValidatorSigner {
     Empty(EmptyValidatorSigner),
     /// Default validator signer that holds data in memory.
     InMemory(InMemoryValidatorSigner),
+    /// Validator signer that holds the key in a separate process.
+    #[cfg(unix)]
+    Remote(RemoteValidatorSigner),
 }
 
 /// Validator signer that is used to sign blocks and approvals.
@@ -22,6 +30,8 @@ impl ValidatorSigner {
         match self {
             ValidatorSigner::Empty(signer) => signer.validator_id(),
             ValidatorSigner::InMemory(signer) => signer.validator_id(),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => signer.validator_id(),
         }
     }
 
@@ -30,23 +40,123 @@ impl ValidatorSigner {
         match self {
             ValidatorSigner::Empty(signer) => signer.public_key(),
             ValidatorSigner::InMemory(signer) => signer.public_key(),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => signer.public_key(),
         }
     }
 
-    pub fn sign_bytes(&self, data: &[u8]) -> Signature {
+    /// Signs `data`. With a remote signer, `request` is sent instead, which
+    /// carries the object that `data` is derived from, see
+    /// [`near_crypto::remote_signer`]. Only fails with a remote signer.
+    pub(crate) fn sign(
+        &self,
+        data: &[u8],
+        request: impl FnOnce() -> RemoteSignerRequest,
+    ) -> std::io::Result<Signature> {
+        let mut signatures = self.sign_many(&[data], request)?;
+        Ok(signatures.remove(0))
+    }
+
+    /// Same as [`Self::sign`], for requests that sign several pieces of data.
+    pub(crate) fn sign_many(
+        &self,
+        data: &[&[u8]],
+        request: impl FnOnce() -> RemoteSignerRequest,
+    ) -> std::io::Result<Vec<Signature>> {
         match self {
-            ValidatorSigner::Empty(signer) => signer.noop_signature(),
-            ValidatorSigner::InMemory(signer) => signer.sign_bytes(data),
+            ValidatorSigner::Empty(signer) => Ok(vec![signer.noop_signature(); data.len()]),
+            ValidatorSigner::InMemory(signer) => Ok(signer.sign_many(data)),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => signer.sign_many(data, request),
         }
     }
 
-    pub fn compute_vrf_with_proof(
+    /// Signs the hash of a block header at `height`.
+    pub(crate) fn sign_block_header(
+        &self,
+        height: BlockHeight,
+        hash: &CryptoHash,
+        request: impl FnOnce() -> RemoteSignerRequest,
+    ) -> std::io::Result<Signature> {
+        self.record_signed(SignedItem::block(height, *hash))?;
+        self.sign(hash.as_ref(), request)
+    }
+
+    /// Signs the hash of a chunk header.
+    pub(crate) fn sign_chunk_header(
+        &self,
+        height_created: BlockHeight,
+        shard_id: ShardId,
+        hash: &CryptoHash,
+        request: impl FnOnce() -> RemoteSignerRequest,
+    ) -> std::io::Result<Signature> {
+        self.record_signed(SignedItem::chunk(height_created, shard_id, *hash))?;
+        self.sign(hash.as_ref(), request)
+    }
+
+    /// Signs the data of an approval for `target_height`.
+    pub(crate) fn sign_approval(
         &self,
+        target_height: BlockHeight,
         data: &[u8],
-    ) -> (near_crypto::vrf::Value, near_crypto::vrf::Proof) {
+        request: impl FnOnce() -> RemoteSignerRequest,
+    ) -> std::io::Result<Signature> {
+        self.record_signed(SignedItem::approval(target_height, data))?;
+        self.sign(data, request)
+    }
+
+    /// Signs the inner and the metadata of an endorsement of a chunk.
+    pub(crate) fn sign_chunk_endorsement(
+        &self,
+        height_created: BlockHeight,
+        shard_id: ShardId,
+        chunk_hash: &CryptoHash,
+        data: &[&[u8]],
+        request: impl FnOnce() -> RemoteSignerRequest,
+    ) -> std::io::Result<Vec<Signature>> {
+        self.record_signed(SignedItem::chunk_endorsement(height_created, shard_id, *chunk_hash))?;
+        self.sign_many(data, request)
+    }
+
+    /// Signs objects that are only produced for old protocol versions, which
+    /// a remote signer doesn't support.
+    pub(crate) fn sign_legacy(&self, data: &[u8]) -> std::io::Result<Signature> {
+        match self {
+            ValidatorSigner::Empty(signer) => Ok(signer.noop_signature()),
+            ValidatorSigner::InMemory(signer) => Ok(signer.sign_legacy(data)),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "remote signers only sign objects of current protocol versions",
+            )),
+        }
+    }
+
+    /// Signs a protobuf serialized `AccountKeyPayload` of the network protocol.
+    pub fn sign_account_key_payload(&self, payload: &[u8]) -> std::io::Result<Signature> {
+        self.sign(payload, || RemoteSignerRequest::SignAccountKeyPayload {
+            payload: payload.to_vec(),
+        })
+    }
+
+    /// Signs the JSON serialized telemetry info.
+    pub fn sign_telemetry(&self, content: &str) -> std::io::Result<Signature> {
+        self.sign(content.as_bytes(), || RemoteSignerRequest::SignTelemetry {
+            content: content.to_string(),
+        })
+    }
+
+    pub fn compute_vrf_with_proof(
+        &self,
+        prev_random_value: &CryptoHash,
+    ) -> std::io::Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof)> {
         match self {
             ValidatorSigner::Empty(_) => unimplemented!(),
-            ValidatorSigner::InMemory(signer) => signer.compute_vrf_with_proof(data),
+            ValidatorSigner::InMemory(signer) => {
+                Ok(signer.compute_vrf_with_proof(prev_random_value))
+            }
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => signer.compute_vrf_with_proof(prev_random_value),
         }
     }
 
@@ -55,7 +165,55 @@ impl ValidatorSigner {
         match self {
             ValidatorSigner::Empty(_) => unimplemented!(),
             ValidatorSigner::InMemory(signer) => signer.write_to_file(path),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => signer.signer.write_to_file(path),
+        }
+    }
+
+    /// Records a block, chunk, approval or chunk endorsement in the signing
+    /// journal before it is signed. Fails if a conflicting item was signed for
+    /// the same height before, in which case the item must not be signed.
+    /// Always succeeds without a signing journal.
+    fn record_signed(&self, item: SignedItem) -> std::io::Result<()> {
+        let journal = match self {
+            ValidatorSigner::Empty(_) => None,
+            ValidatorSigner::InMemory(signer) => signer.signing_journal.as_ref(),
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => signer.signing_journal.as_ref(),
+        };
+        match journal {
+            Some(journal) => journal.record(&item).map_err(std::io::Error::other),
+            None => Ok(()),
+        }
+    }
+
+    /// Makes the signer consult the journal before signing blocks, chunks,
+    /// approvals and chunk endorsements.
+    pub fn with_signing_journal(self, journal: Arc<SigningJournal>) -> Self {
+        match self {
+            ValidatorSigner::Empty(signer) => ValidatorSigner::Empty(signer),
+            ValidatorSigner::InMemory(signer) => {
+                ValidatorSigner::InMemory(InMemoryValidatorSigner {
+                    signing_journal: Some(journal),
+                    ..signer
+                })
+            }
+            #[cfg(unix)]
+            ValidatorSigner::Remote(signer) => ValidatorSigner::Remote(RemoteValidatorSigner {
+                signing_journal: Some(journal),
+                ..signer
+            }),
+        }
+    }
+
+    /// Reads the validator key file, which can also point to a remote signer,
+    /// see [`near_crypto::remote_signer`].
+    pub fn from_file(path: &Path) -> std::io::Result<ValidatorSigner> {
+        #[cfg(unix)]
+        if let Some(signer) = RemoteSigner::from_key_file(path)? {
+            return Ok(RemoteValidatorSigner::new(signer));
         }
+        InMemoryValidatorSigner::from_file(path)
     }
 }
 
@@ -96,19 +254,20 @@ impl EmptyValidatorSigner {
 pub struct InMemoryValidatorSigner {
     account_id: AccountId,
     signer: Arc<Signer>,
+    signing_journal: Option<Arc<SigningJournal>>,
 }
 
 impl InMemoryValidatorSigner {
     #[cfg(feature = "rand")]
     pub fn from_random(account_id: AccountId, key_type: KeyType) -> ValidatorSigner {
         let signer = Arc::new(InMemorySigner::from_random(account_id.clone(), key_type).into());
-        ValidatorSigner::InMemory(Self { account_id, signer })
+        ValidatorSigner::InMemory(Self { account_id, signer, signing_journal: None })
     }
 
     #[cfg(feature = "rand")]
     pub fn from_seed(account_id: AccountId, key_type: KeyType, seed: &str) -> ValidatorSigner {
         let signer = Arc::new(InMemorySigner::from_seed(account_id.clone(), key_type, seed));
-        ValidatorSigner::InMemory(Self { account_id, signer })
+        ValidatorSigner::InMemory(Self { account_id, signer, signing_journal: None })
     }
 
     pub fn public_key(&self) -> PublicKey {
@@ -119,6 +278,7 @@ impl InMemoryValidatorSigner {
         ValidatorSigner::InMemory(Self {
             account_id: signer.get_account_id(),
             signer: Arc::new(signer),
+            signing_journal: None,
         })
     }
 
@@ -131,18 +291,61 @@ impl InMemoryValidatorSigner {
         &self.account_id
     }
 
-    fn sign_bytes(&self, bytes: &[u8]) -> Signature {
-        self.signer.sign(bytes)
+    fn sign_many(&self, data: &[&[u8]]) -> Vec<Signature> {
+        data.iter().map(|data| self.signer.sign(data)).collect()
+    }
+
+    fn sign_legacy(&self, data: &[u8]) -> Signature {
+        self.signer.sign(data)
     }
 
     fn compute_vrf_with_proof(
         &self,
-        data: &[u8],
+        prev_random_value: &CryptoHash,
     ) -> (near_crypto::vrf::Value, near_crypto::vrf::Proof) {
-        self.signer.compute_vrf_with_proof(data)
+        self.signer.compute_vrf_with_proof(prev_random_value.as_ref())
     }
 
     fn write_to_file(&self, path: &Path) -> std::io::Result<()> {
         self.signer.write_to_file(path)
     }
 }
+
+/// Validator signer that sends the objects to sign to a separate process, see
+/// [`near_crypto::remote_signer`].
+#[cfg(unix)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemoteValidatorSigner {
+    signer: RemoteSigner,
+    signing_journal: Option<Arc<SigningJournal>>,
+}
+
+#[cfg(unix)]
+impl RemoteValidatorSigner {
+    pub fn new(signer: RemoteSigner) -> ValidatorSigner {
+        ValidatorSigner::Remote(Self { signer, signing_journal: None })
+    }
+
+    fn validator_id(&self) -> &AccountId {
+        &self.signer.account_id
+    }
+
+    fn public_key(&self) -> PublicKey {
+        self.signer.public_key()
+    }
+
+    fn sign_many(
+        &self,
+        data: &[&[u8]],
+        request: impl FnOnce() -> RemoteSignerRequest,
+    ) -> std::io::Result<Vec<Signature>> {
+        self.signer.sign(&request(), data)
+    }
+
+    fn compute_vrf_with_proof(
+        &self,
+        prev_random_value: &CryptoHash,
+    ) -> std::io::Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof)> {
+        self.signer.compute_vrf_with_proof(prev_random_value.as_ref())
+    }
+}
@@ -171,6 +171,15 @@ impl<'a> TrieStoreUpdateAdapter<'a> {
     pub fn delete_all_state(&mut self) {
         self.store_update.delete_all(DBCol::State)
     }
+
+    /// Deletes all trie nodes stored under the State prefix of `shard_uid`, i.e. the prefix
+    /// of the ancestor it is mapped to. The caller must make sure that no shard in use is
+    /// mapped to the same prefix.
+    pub fn delete_shard_state(&mut self, shard_uid: ShardUId) {
+        let key_from = get_shard_uid_mapping(&self.store_update.store, shard_uid).to_bytes();
+        let key_to = ShardUId::get_upper_bound_db_key(&key_from);
+        self.store_update.delete_range(DBCol::State, &key_from, &key_to);
+    }
 }
 
 /// Get the `ShardUId` mapping for child_shard_uid. If the mapping does not exist, map the shard to itself.
@@ -8,9 +8,11 @@ use near_primitives::block::{Block, BlockHeader, Tip};
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::sharding::ShardChunk;
-use near_primitives::types::BlockHeight;
+use near_primitives::transaction::ExecutionOutcomeWithProof;
+use near_primitives::trie_key::trie_key_parsers::parse_account_id_from_raw_key;
+use near_primitives::types::{AccountId, BlockHeight};
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io;
 use std::sync::Arc;
 use strum::IntoEnumIterator;
@@ -57,6 +59,153 @@ struct BatchTransaction {
     threshold_transaction_size: usize,
 }
 
+/// Restricts the history copied to cold storage to the data touching a set of
+/// accounts, see `SplitStorageConfig::archived_accounts`.
+///
+/// Only transactions and receipts sent by or to one of the accounts, their
+/// execution outcomes, state changes of the accounts and the chunks including
+/// them are copied. Block level data is always copied, so that the chain in
+/// the cold storage stays complete. The `State` column is not copied at all,
+/// the history of the accounts' state is available through `StateChanges`.
+#[derive(Clone, Debug)]
+pub struct ColdStoreAccountFilter {
+    accounts: HashSet<AccountId>,
+}
+
+impl ColdStoreAccountFilter {
+    pub fn new(accounts: impl IntoIterator<Item = AccountId>) -> Self {
+        Self { accounts: accounts.into_iter().collect() }
+    }
+
+    pub fn contains(&self, account_id: &AccountId) -> bool {
+        self.accounts.contains(account_id)
+    }
+
+    /// Adds the hashes of the transactions and receipts of the chunk which
+    /// touch any of the accounts, and of the chunk itself if there are any.
+    fn add_chunk(&self, chunk: &ShardChunk, hashes: &mut ArchivedHashes) {
+        let chunk_hash = chunk.chunk_hash().as_bytes().to_vec();
+        for transaction in chunk.transactions() {
+            let tx = &transaction.transaction;
+            if self.contains(tx.signer_id()) || self.contains(tx.receiver_id()) {
+                hashes.transactions.insert(transaction.get_hash().as_bytes().to_vec());
+                hashes.chunks.insert(chunk_hash.clone());
+            }
+        }
+        for receipt in chunk.prev_outgoing_receipts() {
+            if self.contains(receipt.receiver_id()) || self.contains(receipt.predecessor_id()) {
+                hashes.receipts.insert(receipt.get_hash().as_bytes().to_vec());
+                hashes.chunks.insert(chunk_hash.clone());
+            }
+        }
+    }
+
+    /// Whether the execution outcome touches any of the accounts. `outcome`
+    /// is only read if the outcome id doesn't match.
+    fn keep_outcome(
+        &self,
+        hashes: &ArchivedHashes,
+        outcome_id: &[u8],
+        outcome: impl FnOnce() -> io::Result<Option<ExecutionOutcomeWithProof>>,
+    ) -> io::Result<bool> {
+        Ok(hashes.transactions.contains(outcome_id)
+            || hashes.receipts.contains(outcome_id)
+            || outcome()?.is_some_and(|outcome| self.contains(&outcome.outcome.executor_id)))
+    }
+
+    fn keep_trie_key(&self, trie_key: &[u8]) -> bool {
+        parse_account_id_from_raw_key(trie_key)
+            .ok()
+            .flatten()
+            .is_some_and(|account_id| self.contains(&account_id))
+    }
+
+    /// Removes the keys which don't touch any of the accounts from the keys
+    /// computed by `get_keys_from_store`.
+    fn filter_keys(
+        &self,
+        store: &Store,
+        block_hash_key: &[u8],
+        chunks: &[ShardChunk],
+        key_type_to_keys: &mut HashMap<DBKeyType, Vec<StoreKey>>,
+    ) -> io::Result<()> {
+        let mut hashes = ArchivedHashes::default();
+        for chunk in chunks {
+            self.add_chunk(chunk, &mut hashes);
+        }
+
+        let mut outcome_ids = vec![];
+        for outcome_id in key_type_to_keys.remove(&DBKeyType::OutcomeId).unwrap_or_default() {
+            let keep = self.keep_outcome(&hashes, &outcome_id, || {
+                store.get_ser(
+                    DBCol::TransactionResultForBlock,
+                    &join_two_keys(&outcome_id, block_hash_key),
+                )
+            })?;
+            if keep {
+                outcome_ids.push(outcome_id);
+            }
+        }
+        key_type_to_keys.insert(DBKeyType::OutcomeId, outcome_ids);
+
+        let mut retain = |key_type: DBKeyType, keep: &dyn Fn(&StoreKey) -> bool| {
+            if let Some(keys) = key_type_to_keys.get_mut(&key_type) {
+                keys.retain(|key| keep(key));
+            }
+        };
+        retain(DBKeyType::ChunkHash, &|key| hashes.chunks.contains(key));
+        retain(DBKeyType::TransactionHash, &|key| hashes.transactions.contains(key));
+        retain(DBKeyType::ReceiptHash, &|key| hashes.receipts.contains(key));
+        retain(DBKeyType::TrieKey, &|key| self.keep_trie_key(key));
+        Ok(())
+    }
+
+    /// Collects the hashes of everything touching the accounts from all the
+    /// chunks in the store, for `copy_all_data_to_cold`.
+    fn collect_hashes(&self, store: &Store) -> io::Result<ArchivedHashes> {
+        let mut hashes = ArchivedHashes::default();
+        for result in store.iter(DBCol::Chunks) {
+            let chunk = ShardChunk::try_from_slice(&result?.1)?;
+            self.add_chunk(&chunk, &mut hashes);
+        }
+        Ok(hashes)
+    }
+
+    /// Whether an entry of a cold column is copied by `copy_all_data_to_cold`.
+    /// Keeps the same data as `filter_keys` does for a single block.
+    fn keep_entry(
+        &self,
+        hashes: &ArchivedHashes,
+        col: DBCol,
+        key: &[u8],
+        value: &[u8],
+    ) -> io::Result<bool> {
+        Ok(match col {
+            DBCol::State => false,
+            DBCol::Chunks => hashes.chunks.contains(key),
+            DBCol::Transactions => hashes.transactions.contains(key),
+            DBCol::Receipts => hashes.receipts.contains(key),
+            DBCol::TransactionResultForBlock => {
+                let outcome_id = &key[..CryptoHash::LENGTH];
+                self.keep_outcome(hashes, outcome_id, || {
+                    Ok(Some(ExecutionOutcomeWithProof::try_from_slice(value)?))
+                })?
+            }
+            DBCol::StateChanges => self.keep_trie_key(&key[CryptoHash::LENGTH..]),
+            _ => true,
+        })
+    }
+}
+
+/// Hashes of the chunks, transactions and receipts touching the accounts of a
+/// `ColdStoreAccountFilter`.
+#[derive(Default)]
+struct ArchivedHashes {
+    chunks: HashSet<StoreKey>,
+    transactions: HashSet<StoreKey>,
+    receipts: HashSet<StoreKey>,
+}
+
 /// Updates provided cold database from provided hot store with information about block at `height`.
 /// Block at `height` has to be final and present in `hot_store`.
 ///
@@ -75,6 +224,8 @@ struct BatchTransaction {
 /// 1. add it to `DBCol::is_cold` list
 /// 2. define `DBCol::key_type` for it (if it isn't already defined)
 /// 3. add new clause in `get_keys_from_store` for new key types used for this column (if there are any)
+///
+/// If `account_filter` is set, only the history of the filtered accounts is copied.
 pub fn update_cold_db(
     cold_db: &ColdDB,
     hot_store: &Store,
@@ -82,6 +233,7 @@ pub fn update_cold_db(
     height: &BlockHeight,
     is_last_block_in_epoch: bool,
     num_threads: usize,
+    account_filter: Option<&ColdStoreAccountFilter>,
 ) -> io::Result<()> {
     let _span = tracing::debug_span!(target: "cold_store", "update cold db", height = height);
     let _timer = metrics::COLD_COPY_DURATION.start_timer();
@@ -91,12 +243,15 @@ pub fn update_cold_db(
     let block_hash_key = block_hash_vec.as_slice();
 
     let key_type_to_keys =
-        get_keys_from_store(&hot_store, shard_layout, &height_key, block_hash_key)?;
+        get_keys_from_store(&hot_store, shard_layout, &height_key, block_hash_key, account_filter)?;
     let columns_to_update = DBCol::iter()
         .filter(|col| {
             if !col.is_cold() {
                 return false;
             }
+            if col == &DBCol::State && account_filter.is_some() {
+                return false;
+            }
             if col == &DBCol::StateShardUIdMapping && !is_last_block_in_epoch {
                 return false;
             }
@@ -335,12 +490,22 @@ pub enum CopyAllDataToColdStatus {
 
 /// Copies all contents of all cold columns from `hot_store` to `cold_db`.
 /// Does it column by column, and because columns can be huge, writes in batches of ~`batch_size`.
+///
+/// If `account_filter` is set, only the history of the filtered accounts is
+/// copied, like in `update_cold_db`.
 pub fn copy_all_data_to_cold(
     cold_db: Arc<ColdDB>,
     hot_store: &Store,
     batch_size: usize,
     keep_going: &Arc<std::sync::atomic::AtomicBool>,
+    account_filter: Option<&ColdStoreAccountFilter>,
 ) -> io::Result<CopyAllDataToColdStatus> {
+    let archived_hashes = account_filter
+        .map(|account_filter| {
+            tracing::info!(target: "cold_store", "Collecting the history of the archived accounts");
+            account_filter.collect_hashes(hot_store).map(|hashes| (account_filter, hashes))
+        })
+        .transpose()?;
     for col in DBCol::iter() {
         if col.is_cold() {
             tracing::info!(target: "cold_store", ?col, "Started column migration");
@@ -352,6 +517,11 @@ pub fn copy_all_data_to_cold(
                 }
                 // TODO(resharding) Should do mapping here?
                 let (key, value) = result?;
+                if let Some((account_filter, hashes)) = &archived_hashes {
+                    if !account_filter.keep_entry(hashes, col, &key, &value)? {
+                        continue;
+                    }
+                }
                 transaction.set_and_write_if_full(col, key.to_vec(), value.to_vec())?;
             }
             transaction.write()?;
@@ -400,11 +570,13 @@ pub fn test_get_store_initial_writes(column: DBCol) -> u64 {
 /// So, for every KeyType we need to capture all the keys that are related to that block.
 /// For BlockHash it is just one key -- block hash of that height.
 /// But for TransactionHash, for example, it is all of the tx hashes in that block.
+/// If `account_filter` is set, only the keys touching the filtered accounts are returned.
 fn get_keys_from_store(
     store: &Store,
     shard_layout: &ShardLayout,
     height_key: &[u8],
     block_hash_key: &[u8],
+    account_filter: Option<&ColdStoreAccountFilter>,
 ) -> io::Result<HashMap<DBKeyType, Vec<StoreKey>>> {
     let mut key_type_to_keys = HashMap::new();
 
@@ -499,6 +671,10 @@ fn get_keys_from_store(
         );
     }
 
+    if let Some(account_filter) = account_filter {
+        account_filter.filter_keys(store, block_hash_key, &chunks, &mut key_type_to_keys)?;
+    }
+
     Ok(key_type_to_keys)
 }
 
@@ -317,6 +317,18 @@ pub enum DBCol {
     /// - *Rows*: `CryptoHash`
     /// - *Column type*: `Vec<u8>`
     StateSyncNewChunks,
+    /// Keys of columns with extended retention (see `GCConfig::column_retention`)
+    /// which were left in place when their block was garbage collected. They are
+    /// deleted once their retention period has passed.
+    /// - *Rows*: height of the garbage collected block (u64)
+    /// - *Column type*: `RetainedGCKeys`
+    GCRetainedKeys,
+    /// Removals of the flat storage and State of shards which the node stopped
+    /// tracking. They are scheduled at the first block of an epoch and run by
+    /// garbage collection once the last block of the previous epoch is final.
+    /// - *Rows*: hash of the last block of the previous epoch
+    /// - *Column type*: `UntrackedShardsCleanup`
+    UntrackedShardsCleanup,
 }
 
 /// Defines different logical parts of a db key.
@@ -522,7 +534,9 @@ impl DBCol {
             | DBCol::FlatStorageStatus
             | DBCol::EpochSyncProof
             | DBCol::StateSyncHashes
-            | DBCol::StateSyncNewChunks => false,
+            | DBCol::StateSyncNewChunks
+            | DBCol::GCRetainedKeys
+            | DBCol::UntrackedShardsCleanup => false,
         }
     }
 
@@ -598,6 +612,8 @@ impl DBCol {
             DBCol::StateShardUIdMapping => &[DBKeyType::ShardUId],
             DBCol::StateSyncHashes => &[DBKeyType::EpochId],
             DBCol::StateSyncNewChunks => &[DBKeyType::BlockHash],
+            DBCol::GCRetainedKeys => &[DBKeyType::BlockHeight],
+            DBCol::UntrackedShardsCleanup => &[DBKeyType::BlockHash],
         }
     }
 }
@@ -382,6 +382,14 @@ pub struct SplitStorageConfig {
 
     #[serde(default = "default_num_cold_store_read_threads")]
     pub num_cold_store_read_threads: usize,
+
+    /// If set, the node keeps the history only for the given accounts: only
+    /// the transactions, receipts, outcomes and state changes touching them
+    /// are copied to the cold storage. The state itself is not archived.
+    /// Block level data is copied for all blocks. This applies to the initial
+    /// migration of the hot storage too.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_accounts: Option<Vec<AccountId>>,
 }
 
 impl Default for SplitStorageConfig {
@@ -394,6 +402,7 @@ impl Default for SplitStorageConfig {
                 default_cold_store_initial_migration_loop_sleep_duration(),
             cold_store_loop_sleep_duration: default_cold_store_loop_sleep_duration(),
             num_cold_store_read_threads: default_num_cold_store_read_threads(),
+            archived_accounts: None,
         }
     }
 }
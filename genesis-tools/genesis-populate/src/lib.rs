@@ -1,14 +1,16 @@
 //! Tools for creating a genesis block.
 
+pub mod spec;
 pub mod state_dump;
 
+use crate::spec::{AccountPopulation, PopulateSpec};
 use crate::state_dump::StateDump;
 use indicatif::{ProgressBar, ProgressStyle};
 use near_chain::chain::get_genesis_congestion_infos;
 use near_chain::types::RuntimeAdapter;
 use near_chain::{Block, Chain, ChainStore};
 use near_chain_configs::Genesis;
-use near_crypto::InMemorySigner;
+use near_crypto::{InMemorySigner, KeyType};
 use near_epoch_manager::{EpochManager, EpochManagerAdapter, EpochManagerHandle};
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::block::{genesis_chunks, Tip};
@@ -17,6 +19,7 @@ use near_primitives::epoch_block_info::BlockInfo;
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state_record::StateRecord;
+use near_primitives::trie_key::TrieKey;
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{AccountId, Balance, EpochId, ShardId, StateChangeCause, StateRoot};
 use near_primitives::utils::to_timestamp;
@@ -32,6 +35,8 @@ use near_vm_runner::logic::ProtocolVersion;
 use near_vm_runner::ContractCode;
 use nearcore::{NearConfig, NightshadeRuntime, NightshadeRuntimeExt};
 pub use node_runtime::bootstrap_congestion_info;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
@@ -79,8 +84,9 @@ pub struct GenesisBuilder {
 
     // Things that can be set.
     additional_accounts_num: u64,
-    additional_accounts_code: Option<Vec<u8>>,
+    additional_accounts_code: Option<Arc<Vec<u8>>>,
     additional_accounts_code_hash: CryptoHash,
+    spec: Option<PopulateSpec>,
 
     print_progress: bool,
 }
@@ -111,6 +117,7 @@ impl GenesisBuilder {
             additional_accounts_num: 0,
             additional_accounts_code: None,
             additional_accounts_code_hash: CryptoHash::default(),
+            spec: None,
             print_progress: false,
         }
     }
@@ -127,7 +134,13 @@ impl GenesisBuilder {
 
     pub fn add_additional_accounts_contract(mut self, contract_code: Vec<u8>) -> Self {
         self.additional_accounts_code_hash = hash(&contract_code);
-        self.additional_accounts_code = Some(contract_code);
+        self.additional_accounts_code = Some(Arc::new(contract_code));
+        self
+    }
+
+    /// Adds the accounts described by the spec after the additional accounts.
+    pub fn add_spec_accounts(mut self, spec: PopulateSpec) -> Self {
+        self.spec = Some(spec);
         self
     }
 
@@ -160,17 +173,40 @@ impl GenesisBuilder {
             self.roots.keys().cloned().map(|shard_idx| (shard_idx, vec![])).collect();
 
         let shard_ids: Vec<_> = self.genesis.config.shard_layout.shard_ids().collect();
-        let total_accounts_num = self.additional_accounts_num * shard_ids.len() as u64;
+        let additional_accounts_num = self.additional_accounts_num * shard_ids.len() as u64;
+        let spec = self.spec.take();
+        let total_accounts_num =
+            additional_accounts_num + spec.as_ref().map_or(0, PopulateSpec::num_accounts);
         let bar = ProgressBar::new(total_accounts_num as _);
         bar.set_style(ProgressStyle::default_bar().template(
             "[elapsed {elapsed_precise} remaining {eta_precise}] Writing into storage {bar} {pos:>7}/{len:7}",
         ).unwrap());
         // Add records in chunks of 3000 per shard for memory efficiency reasons.
-        for i in 0..total_accounts_num {
+        for i in 0..additional_accounts_num {
             let account_id = get_account_id(i);
             self.add_additional_account(account_id)?;
             bar.inc(1);
         }
+        if let Some(spec) = spec {
+            let mut rng = StdRng::seed_from_u64(spec.seed);
+            let mut account_index = additional_accounts_num;
+            for population in &spec.populations {
+                let contract = match &population.contract {
+                    Some(path) => {
+                        let code = std::fs::read(path)
+                            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+                        Some((hash(&code), Arc::new(code)))
+                    }
+                    None => None,
+                };
+                for _ in 0..population.count {
+                    let account_id = get_account_id(account_index);
+                    account_index += 1;
+                    self.add_population_account(account_id, population, &contract, &mut rng)?;
+                    bar.inc(1);
+                }
+            }
+        }
 
         for shard_id in shard_ids {
             self.flush_shard_records(shard_id)?;
@@ -331,42 +367,104 @@ impl GenesisBuilder {
 
     fn add_additional_account(&mut self, account_id: AccountId) -> Result<()> {
         let testing_init_balance: Balance = 10u128.pow(30);
+        let contract = self
+            .additional_accounts_code
+            .clone()
+            .map(|code| (self.additional_accounts_code_hash, code));
+        self.add_account(account_id, testing_init_balance, &contract, 1, vec![])
+    }
+
+    fn add_population_account(
+        &mut self,
+        account_id: AccountId,
+        population: &AccountPopulation,
+        contract: &Option<(CryptoHash, Arc<Vec<u8>>)>,
+        rng: &mut StdRng,
+    ) -> Result<()> {
+        let balance = population.sample_balance(rng);
+        let num_keys = population.access_keys.sample(rng);
+        let mut storage_bytes = population.storage_bytes.sample(rng);
+        let mut data = vec![];
+        while storage_bytes > 0 {
+            let value_size = storage_bytes.min(population.storage_value_size);
+            let mut value = vec![0; value_size as usize];
+            rng.fill(&mut value[..]);
+            let key = hash(&(data.len() as u64).to_le_bytes()).as_bytes().to_vec();
+            data.push((key, value));
+            storage_bytes -= value_size;
+        }
+        self.add_account(account_id, balance, contract, num_keys, data)
+    }
+
+    /// Adds the account with `num_keys` full access keys, see [`spec`] for how
+    /// they are derived, and the contract with its `data`.
+    fn add_account(
+        &mut self,
+        account_id: AccountId,
+        balance: Balance,
+        contract: &Option<(CryptoHash, Arc<Vec<u8>>)>,
+        num_keys: u64,
+        data: Vec<(Vec<u8>, Vec<u8>)>,
+    ) -> Result<()> {
         let testing_init_stake: Balance = 0;
         let shard_id = self.genesis.config.shard_layout.account_id_to_shard_id(&account_id);
         let mut records = self.unflushed_records.remove(&shard_id).unwrap_or_default();
         let mut state_update =
             self.state_updates.remove(&shard_id).expect("State update should have been added");
 
-        let signer = InMemorySigner::test_signer(&account_id);
+        let code_hash =
+            contract.as_ref().map_or(CryptoHash::default(), |(code_hash, _)| *code_hash);
         let account = Account::new(
-            testing_init_balance,
+            balance,
             testing_init_stake,
             0,
-            self.additional_accounts_code_hash,
+            code_hash,
             0,
             self.genesis.config.protocol_version,
         );
         set_account(&mut state_update, account_id.clone(), &account);
         let account_record = StateRecord::Account { account_id: account_id.clone(), account };
         records.push(account_record);
-        let access_key_record = StateRecord::AccessKey {
-            account_id: account_id.clone(),
-            public_key: signer.public_key(),
-            access_key: AccessKey::full_access(),
-        };
-        set_access_key(
-            &mut state_update,
-            account_id.clone(),
-            signer.public_key(),
-            &AccessKey::full_access(),
-        );
-        records.push(access_key_record);
-        if let Some(wasm_binary) = self.additional_accounts_code.as_ref() {
-            let code = ContractCode::new(wasm_binary.clone(), None);
+        for key_index in 0..num_keys {
+            let signer = if key_index == 0 {
+                InMemorySigner::test_signer(&account_id)
+            } else {
+                let seed = format!("{account_id}_{key_index}");
+                InMemorySigner::from_seed(account_id.clone(), KeyType::ED25519, &seed)
+            };
+            let access_key_record = StateRecord::AccessKey {
+                account_id: account_id.clone(),
+                public_key: signer.public_key(),
+                access_key: AccessKey::full_access(),
+            };
+            set_access_key(
+                &mut state_update,
+                account_id.clone(),
+                signer.public_key(),
+                &AccessKey::full_access(),
+            );
+            records.push(access_key_record);
+        }
+        if let Some((code_hash, wasm_binary)) = contract {
+            let code = ContractCode::new(wasm_binary.to_vec(), Some(*code_hash));
             state_update.set_code(account_id.clone(), &code);
-            let contract_record = StateRecord::Contract { account_id, code: wasm_binary.clone() };
+            let contract_record = StateRecord::Contract {
+                account_id: account_id.clone(),
+                code: wasm_binary.to_vec(),
+            };
             records.push(contract_record);
         }
+        for (key, value) in data {
+            state_update.set(
+                TrieKey::ContractData { account_id: account_id.clone(), key: key.clone() },
+                value.clone(),
+            );
+            records.push(StateRecord::Data {
+                account_id: account_id.clone(),
+                data_key: key.into(),
+                value: value.into(),
+            });
+        }
 
         // Add records in chunks of 3000 per shard for memory efficiency reasons.
         const CHUNK_SIZE: usize = 3000;
@@ -1,4 +1,5 @@
 use clap::{Arg, Command};
+use genesis_populate::spec::PopulateSpec;
 use genesis_populate::GenesisBuilder;
 use near_chain_configs::GenesisValidationMode;
 use nearcore::{get_default_home, load_config};
@@ -17,20 +18,34 @@ fn main() {
         .arg(
             Arg::new("additional-accounts-num")
                 .long("additional-accounts-num")
-                .required(true)
+                .required_unless_present("spec")
                 .action(clap::ArgAction::Set)
                 .help(
                     "Number of additional accounts per shard to add directly to the trie \
                      (TESTING ONLY)",
                 ),
         )
+        .arg(
+            Arg::new("spec")
+                .long("spec")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help(
+                    "JSON file describing populations of accounts with contracts, access keys \
+                     and contract storage to add after the additional accounts (TESTING ONLY)",
+                )
+                .action(clap::ArgAction::Set),
+        )
         .get_matches();
 
     let home_dir = matches.get_one::<PathBuf>("home").unwrap();
     let additional_accounts_num = matches
         .get_one::<String>("additional-accounts-num")
         .map(|x| x.parse::<u64>().expect("Failed to parse number of additional accounts."))
-        .unwrap();
+        .unwrap_or(0);
+    let spec = matches.get_one::<PathBuf>("spec").map(|path| {
+        PopulateSpec::from_file(path)
+            .unwrap_or_else(|e| panic!("Error loading spec {}: {}", path.display(), e))
+    });
     let near_config = load_config(home_dir, GenesisValidationMode::Full)
         .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
 
@@ -42,12 +57,11 @@ fn main() {
     .open()
     .unwrap()
     .get_hot_store();
-    GenesisBuilder::from_config_and_store(home_dir, near_config, store)
+    let mut builder = GenesisBuilder::from_config_and_store(home_dir, near_config, store)
         .add_additional_accounts(additional_accounts_num)
-        .add_additional_accounts_contract(near_test_contracts::trivial_contract().to_vec())
-        .print_progress()
-        .build()
-        .unwrap()
-        .dump_state()
-        .unwrap();
+        .add_additional_accounts_contract(near_test_contracts::trivial_contract().to_vec());
+    if let Some(spec) = spec {
+        builder = builder.add_spec_accounts(spec);
+    }
+    builder.print_progress().build().unwrap().dump_state().unwrap();
 }
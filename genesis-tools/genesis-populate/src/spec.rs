@@ -0,0 +1,266 @@
+//! Spec file describing the accounts genesis-populate adds to the state.
+//!
+//! The spec is a JSON file with a list of account populations. Every
+//! population adds `count` accounts with properties sampled from the given
+//! distributions:
+//!
+//! ```json
+//! {
+//!   "seed": 42,
+//!   "populations": [
+//!     {
+//!       "count": 100000,
+//!       "balance_near": { "log_uniform": { "min": 1, "max": 1000000 } },
+//!       "access_keys": { "uniform": { "min": 1, "max": 3 } }
+//!     },
+//!     {
+//!       "count": 1000,
+//!       "balance_near": { "constant": 1000 },
+//!       "contract": "fungible_token.wasm",
+//!       "storage_bytes": { "log_uniform": { "min": 1000, "max": 10000000 } }
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Accounts are named by [`crate::get_account_id`], continuing after the
+//! accounts added by `--additional-accounts-num`, in the order of the
+//! populations. The first access key of an account is the key of
+//! `InMemorySigner::test_signer`, the `i`-th additional key is derived from the
+//! seed `"{account_id}_{i}"`, so that tools can sign for these accounts.
+use near_primitives::types::Balance;
+use rand::Rng;
+use std::path::{Path, PathBuf};
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PopulateSpec {
+    /// Seed for sampling the distributions, the same spec always produces the
+    /// same state.
+    #[serde(default)]
+    pub seed: u64,
+    pub populations: Vec<AccountPopulation>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AccountPopulation {
+    pub count: u64,
+    /// Balance of each account in NEAR. It must cover the storage staking of
+    /// the contract and its storage for the accounts to be usable.
+    pub balance_near: Distribution,
+    /// Number of full access keys of each account.
+    #[serde(default = "default_access_keys")]
+    pub access_keys: Distribution,
+    /// Wasm file deployed to each account, relative to the spec file.
+    #[serde(default)]
+    pub contract: Option<PathBuf>,
+    /// Total size of the values written to the contract storage of each
+    /// account. Values are random bytes under 32 byte keys.
+    #[serde(default)]
+    pub storage_bytes: Distribution,
+    /// Size of a single value in the contract storage, the last value of an
+    /// account may be smaller.
+    #[serde(default = "default_storage_value_size")]
+    pub storage_value_size: u64,
+}
+
+fn default_access_keys() -> Distribution {
+    Distribution::Constant(1)
+}
+
+fn default_storage_value_size() -> u64 {
+    1024
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Distribution {
+    Constant(u64),
+    /// Every value in `min..=max` is equally likely.
+    Uniform {
+        min: u64,
+        max: u64,
+    },
+    /// The logarithm of the value is uniformly distributed, so that small
+    /// values are much more common than large ones, like balances and state
+    /// sizes on a real chain.
+    LogUniform {
+        min: u64,
+        max: u64,
+    },
+}
+
+impl Default for Distribution {
+    fn default() -> Self {
+        Self::Constant(0)
+    }
+}
+
+impl Distribution {
+    pub fn sample(&self, rng: &mut impl Rng) -> u64 {
+        match *self {
+            Self::Constant(value) => value,
+            Self::Uniform { min, max } => rng.gen_range(min..=max),
+            Self::LogUniform { min, max } => {
+                let value = rng.gen_range((min as f64).ln()..=(max as f64).ln()).exp();
+                (value.round() as u64).clamp(min, max)
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match *self {
+            Self::Constant(_) => Ok(()),
+            Self::Uniform { min, max } if min <= max => Ok(()),
+            Self::LogUniform { min, max } if 0 < min && min <= max => Ok(()),
+            _ => Err(format!("invalid distribution {self:?}")),
+        }
+    }
+}
+
+impl AccountPopulation {
+    pub fn sample_balance(&self, rng: &mut impl Rng) -> Balance {
+        Balance::from(self.balance_near.sample(rng)) * near_chain_configs::NEAR_BASE
+    }
+}
+
+impl PopulateSpec {
+    pub fn from_file(path: &Path) -> crate::Result<Self> {
+        let mut spec: Self = serde_json::from_slice(&std::fs::read(path)?)?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        for population in &mut spec.populations {
+            for distribution in
+                [&population.balance_near, &population.access_keys, &population.storage_bytes]
+            {
+                distribution.validate()?;
+            }
+            if population.storage_value_size == 0 {
+                return Err("storage_value_size must be positive".into());
+            }
+            if let Some(contract) = &mut population.contract {
+                *contract = base_dir.join(contract.as_path());
+            }
+        }
+        Ok(spec)
+    }
+
+    pub fn num_accounts(&self) -> u64 {
+        self.populations.iter().map(|population| population.count).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Distribution, PopulateSpec};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn parse(json: &str) -> crate::Result<PopulateSpec> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spec.json");
+        std::fs::write(&path, json).unwrap();
+        PopulateSpec::from_file(&path)
+    }
+
+    fn parse_err(json: &str) -> String {
+        parse(json).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_parse_spec() {
+        let spec = parse(
+            r#"{
+                "seed": 7,
+                "populations": [
+                    { "count": 10, "balance_near": { "constant": 5 } },
+                    {
+                        "count": 3,
+                        "balance_near": { "log_uniform": { "min": 1, "max": 100 } },
+                        "contract": "contract.wasm",
+                        "storage_bytes": { "uniform": { "min": 0, "max": 10 } }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(spec.seed, 7);
+        assert_eq!(spec.num_accounts(), 13);
+        assert_eq!(spec.populations[0].access_keys, Distribution::Constant(1));
+        assert_eq!(spec.populations[0].storage_bytes, Distribution::Constant(0));
+        assert_eq!(spec.populations[0].storage_value_size, 1024);
+        let contract = spec.populations[1].contract.as_ref().unwrap();
+        assert!(contract.is_absolute());
+        assert!(contract.ends_with("contract.wasm"));
+    }
+
+    #[test]
+    fn test_invalid_spec() {
+        let invalid_distribution = [
+            r#"{ "uniform": { "min": 5, "max": 4 } }"#,
+            r#"{ "log_uniform": { "min": 0, "max": 4 } }"#,
+            r#"{ "log_uniform": { "min": 10, "max": 1 } }"#,
+        ];
+        for distribution in invalid_distribution {
+            for field in ["balance_near", "access_keys", "storage_bytes"] {
+                let balance = if field == "balance_near" {
+                    ""
+                } else {
+                    r#""balance_near": { "constant": 1 },"#
+                };
+                let json = format!(
+                    r#"{{ "populations": [{{ "count": 1, {balance} "{field}": {distribution} }}] }}"#
+                );
+                assert!(
+                    parse_err(&json).contains("invalid distribution"),
+                    "{field}: {distribution} must be rejected"
+                );
+            }
+        }
+
+        let err = parse_err(
+            r#"{ "populations": [{ "count": 1, "balance_near": { "constant": 1 }, "storage_value_size": 0 }] }"#,
+        );
+        assert!(err.contains("storage_value_size"), "{err}");
+
+        let err = parse_err(
+            r#"{ "populations": [{ "count": 1, "balance_near": { "constant": 1 }, "weight": 2 }] }"#,
+        );
+        assert!(err.contains("unknown field"), "{err}");
+
+        let err =
+            parse_err(r#"{ "populations": [{ "count": 1, "balance_near": { "normal": 1 } }] }"#);
+        assert!(err.contains("unknown variant"), "{err}");
+
+        let err = parse_err(r#"{ "populations": [{ "count": 1 }] }"#);
+        assert!(err.contains("balance_near"), "{err}");
+    }
+
+    #[test]
+    fn test_sample_bounds() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(Distribution::Constant(42).sample(&mut rng), 42);
+        assert_eq!(Distribution::Uniform { min: 3, max: 3 }.sample(&mut rng), 3);
+        assert_eq!(Distribution::LogUniform { min: 3, max: 3 }.sample(&mut rng), 3);
+
+        for (min, max) in [(0, 1), (1, 2), (1, 1_000_000), (1000, 10_000_000), (1, u64::MAX)] {
+            let uniform = Distribution::Uniform { min, max };
+            let log_uniform = Distribution::LogUniform { min: min.max(1), max };
+            for _ in 0..1000 {
+                let value = uniform.sample(&mut rng);
+                assert!((min..=max).contains(&value), "{uniform:?} sampled {value}");
+                let value = log_uniform.sample(&mut rng);
+                assert!((min.max(1)..=max).contains(&value), "{log_uniform:?} sampled {value}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_log_uniform_prefers_small_values() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let distribution = Distribution::LogUniform { min: 1, max: 1_000_000 };
+        let small = (0..10_000).filter(|_| distribution.sample(&mut rng) <= 1000).count();
+        // Half of the logarithm range lies below 1000.
+        assert!((4_500..5_500).contains(&small), "{small}");
+    }
+}
@@ -1,14 +1,151 @@
 use std::fs;
 use std::path::PathBuf;
 
-use clap::{Arg, Command};
+use clap::error::ErrorKind;
+use clap::{Arg, ArgMatches, Command};
 
-use near_crypto::{InMemorySigner, KeyType, SecretKey};
+use near_crypto::{
+    generate_seed_phrase, DerivationPath, KeyFile, KeyType, SecretKey, NEAR_DERIVATION_PATH,
+};
 use nearcore::get_default_home;
 
 fn generate_key_to_file(account_id: &str, key: SecretKey, path: &PathBuf) -> std::io::Result<()> {
-    let signer = InMemorySigner::from_secret_key(account_id.parse().unwrap(), key);
-    signer.write_to_file(path.as_path())
+    make_key_file(account_id, key).write_to_file(path.as_path())
+}
+
+fn make_key_file(account_id: &str, key: SecretKey) -> KeyFile {
+    KeyFile {
+        account_id: account_id.parse().unwrap(),
+        public_key: key.public_key(),
+        secret_key: key,
+    }
+}
+
+fn print_key_file(account_id: &str, key: SecretKey) {
+    println!("{}", serde_json::to_string_pretty(&make_key_file(account_id, key)).unwrap());
+}
+
+/// What a generated key is used for. Keys of different roles derived from the
+/// same seed phrase must use different derivation paths.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum KeyRole {
+    Signer,
+    Validator,
+    Node,
+}
+
+impl KeyRole {
+    const ALL: [KeyRole; 3] = [KeyRole::Signer, KeyRole::Validator, KeyRole::Node];
+
+    fn name(self) -> &'static str {
+        match self {
+            KeyRole::Signer => "signer",
+            KeyRole::Validator => "validator",
+            KeyRole::Node => "node",
+        }
+    }
+
+    /// Signer keys use the path of NEAR wallets, `m/44'/397'/<index>'`. The
+    /// validator and node keys are derived one level deeper, so they never
+    /// collide with the signer keys.
+    fn default_derivation_path(self) -> &'static str {
+        match self {
+            KeyRole::Signer => NEAR_DERIVATION_PATH,
+            KeyRole::Validator => "m/44'/397'/0'/1'",
+            KeyRole::Node => "m/44'/397'/0'/2'",
+        }
+    }
+
+    fn derivation_path_arg(self) -> Arg {
+        Arg::new("derivation-path")
+            .long("derivation-path")
+            .default_value(self.default_derivation_path())
+            .value_parser(|path: &str| path.parse::<DerivationPath>())
+            .help(format!(
+                "SLIP-10 derivation path of the {} key derived from the seed phrase. It must not overlap the paths of the other roles.",
+                self.name()
+            ))
+            .action(clap::ArgAction::Set)
+    }
+
+    /// Number of keys derived from the default path of the role. Any number of
+    /// signer keys can be generated, so they take every index of their path.
+    fn default_num_keys(self) -> u32 {
+        match self {
+            KeyRole::Signer => u32::MAX,
+            KeyRole::Validator | KeyRole::Node => 1,
+        }
+    }
+
+    /// Returns another role whose default keys overlap the `num_keys` keys
+    /// derived from `path` for this role.
+    fn overlapping_role(self, path: &DerivationPath, num_keys: u32) -> Option<KeyRole> {
+        let contains = |base: &DerivationPath, num_keys: u32, path: &DerivationPath| {
+            path.offset_from(base).is_some_and(|offset| offset < num_keys)
+        };
+        KeyRole::ALL.into_iter().find(|&other| {
+            let other_path: DerivationPath = other.default_derivation_path().parse().unwrap();
+            other != self
+                && (contains(path, num_keys, &other_path)
+                    || contains(&other_path, other.default_num_keys(), path))
+        })
+    }
+
+    /// The derivation path of the `num_keys` keys generated by the subcommand.
+    /// Fails if the keys overlap the default keys of another role, which would
+    /// give the same keys to both.
+    fn derivation_path(
+        self,
+        args: &ArgMatches,
+        num_keys: u32,
+    ) -> Result<DerivationPath, clap::Error> {
+        let path = args.get_one::<DerivationPath>("derivation-path").unwrap().clone();
+        match self.overlapping_role(&path, num_keys) {
+            None => Ok(path),
+            Some(other) => Err(clap::Error::raw(
+                ErrorKind::ArgumentConflict,
+                format!(
+                    "the derivation path {path} of the {} keys overlaps the derivation paths of the {} keys, starting at {}\n",
+                    self.name(),
+                    other.name(),
+                    other.default_derivation_path()
+                ),
+            )),
+        }
+    }
+}
+
+/// Where the generated keys come from: random keys, or keys derived from a
+/// seed phrase, which can be recreated later.
+struct KeySource {
+    seed_phrase: Option<SeedPhrase>,
+}
+
+struct SeedPhrase {
+    phrase: String,
+    passphrase: String,
+}
+
+impl KeySource {
+    /// The key with the given index. Keys derived from a seed phrase use the
+    /// derivation path with the index added to its last component.
+    fn key(&self, key_type: KeyType, derivation_path: &DerivationPath, index: u32) -> SecretKey {
+        match &self.seed_phrase {
+            None => SecretKey::from_random(key_type),
+            Some(seed_phrase) => {
+                let path = derivation_path
+                    .with_offset(index)
+                    .expect("Failed to make the derivation path.");
+                SecretKey::from_seed_phrase(
+                    key_type,
+                    &seed_phrase.phrase,
+                    &seed_phrase.passphrase,
+                    &path,
+                )
+                .expect("Failed to derive the key from the seed phrase.")
+            }
+        }
+    }
 }
 
 fn main() {
@@ -35,38 +172,96 @@ fn main() {
                 .help("Whether to generate a config file when generating keys. Requires account-id to be specified.")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("print-key-file")
+                .long("print-key-file")
+                .help("Whether to print the keys in the key file format. Requires account-id to be specified, except for the node key.")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("key-type")
+                .long("key-type")
+                .default_value("ed25519")
+                .help("Type of the signer keys: ed25519 or secp256k1. Validator and node keys are always ed25519.")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            Arg::new("seed-phrase")
+                .long("seed-phrase")
+                .help("BIP-39 seed phrase to derive the keys from, instead of generating random keys.")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            Arg::new("passphrase")
+                .long("passphrase")
+                .default_value("")
+                .help("BIP-39 passphrase of the seed phrase.")
+                .action(clap::ArgAction::Set),
+        )
         .subcommand(
-            Command::new("signer-keys").about("Generate signer keys.").arg(
-                Arg::new("num-keys")
-                    .long("num-keys")
-                    .action(clap::ArgAction::Set)
-                    .help("Number of signer keys to generate. (default 3)"),
-            ),
+            Command::new("signer-keys")
+                .about("Generate signer keys.")
+                .arg(
+                    Arg::new("num-keys")
+                        .long("num-keys")
+                        .action(clap::ArgAction::Set)
+                        .help("Number of signer keys to generate. (default 3)"),
+                )
+                .arg(KeyRole::Signer.derivation_path_arg().help("SLIP-10 derivation path of the first signer key derived from the seed phrase. The following keys increment the last index of the path.")),
+        )
+        .subcommand(
+            Command::new("node-key")
+                .about("Generate key for the node communication.")
+                .arg(KeyRole::Node.derivation_path_arg()),
         )
         .subcommand(
-            Command::new("node-key").about("Generate key for the node communication."),
+            Command::new("validator-key")
+                .about("Generate staking key.")
+                .arg(KeyRole::Validator.derivation_path_arg()),
+        )
+        .subcommand(
+            Command::new("seed-phrase").about("Generate a seed phrase to derive keys from.").arg(
+                Arg::new("num-words")
+                    .long("num-words")
+                    .action(clap::ArgAction::Set)
+                    .help("Number of words of the seed phrase: 12, 15, 18, 21 or 24. (default 12)"),
+            ),
         )
-        .subcommand(Command::new("validator-key").about("Generate staking key."))
         .get_matches();
 
     let home_dir = matches.get_one::<PathBuf>("home").unwrap();
     fs::create_dir_all(home_dir).expect("Failed to create directory");
     let account_id = matches.get_one::<String>("account-id");
     let generate_config = matches.get_flag("generate-config");
+    let print_key_file = matches.get_flag("print-key-file");
+    let key_type: KeyType =
+        matches.get_one::<String>("key-type").unwrap().parse().expect("Failed to parse key type.");
+    let seed_phrase = matches.get_one::<String>("seed-phrase").map(|phrase| SeedPhrase {
+        phrase: phrase.clone(),
+        passphrase: matches.get_one::<String>("passphrase").unwrap().clone(),
+    });
+    let key_source = KeySource { seed_phrase };
 
     match matches.subcommand() {
         Some(("signer-keys", args)) => {
             let num_keys = args
                 .get_one::<String>("num-keys")
                 .map(|x| x.parse().expect("Failed to parse number keys."))
-                .unwrap_or(3usize);
+                .unwrap_or(3u32);
+            let derivation_path =
+                KeyRole::Signer.derivation_path(args, num_keys).unwrap_or_else(|err| err.exit());
             let keys: Vec<SecretKey> =
-                (0..num_keys).map(|_| SecretKey::from_random(KeyType::ED25519)).collect();
+                (0..num_keys).map(|i| key_source.key(key_type, &derivation_path, i)).collect();
             let mut pks = vec![];
             for (i, key) in keys.into_iter().enumerate() {
                 println!("Key#{}", i);
                 println!("PK: {}", key.public_key());
                 println!();
+                if print_key_file {
+                    let account_id = account_id
+                        .expect("Account id must be specified if --print-key-file is used");
+                    print_key_file(account_id, key.clone());
+                }
                 if generate_config {
                     let account_id = account_id
                         .expect("Account id must be specified if --generate-config is used");
@@ -85,9 +280,17 @@ fn main() {
             println!("List of public keys:");
             println!("{}", pks.join(","));
         }
-        Some(("validator-key", _)) => {
-            let key = SecretKey::from_random(KeyType::ED25519);
+        Some(("validator-key", args)) => {
+            // Validator keys must be convertible to VRF keys.
+            let derivation_path =
+                KeyRole::Validator.derivation_path(args, 1).unwrap_or_else(|err| err.exit());
+            let key = key_source.key(KeyType::ED25519, &derivation_path, 0);
             println!("PK: {}", key.public_key());
+            if print_key_file {
+                let account_id =
+                    account_id.expect("Account id must be specified if --print-key-file is used");
+                print_key_file(account_id, key.clone());
+            }
             if generate_config {
                 let account_id =
                     account_id.expect("Account id must be specified if --generate-config is used");
@@ -99,9 +302,14 @@ fn main() {
                 }
             }
         }
-        Some(("node-key", _args)) => {
-            let key = SecretKey::from_random(KeyType::ED25519);
+        Some(("node-key", args)) => {
+            let derivation_path =
+                KeyRole::Node.derivation_path(args, 1).unwrap_or_else(|err| err.exit());
+            let key = key_source.key(KeyType::ED25519, &derivation_path, 0);
             println!("PK: {}", key.public_key());
+            if print_key_file {
+                print_key_file("node", key.clone());
+            }
             if generate_config {
                 let mut path = home_dir.to_path_buf();
                 path.push(nearcore::config::NODE_KEY_FILE);
@@ -111,6 +319,58 @@ fn main() {
                 }
             }
         }
+        Some(("seed-phrase", args)) => {
+            let num_words = args
+                .get_one::<String>("num-words")
+                .map(|x| x.parse().expect("Failed to parse number of words."))
+                .unwrap_or(12usize);
+            let seed_phrase =
+                generate_seed_phrase(num_words).expect("Failed to generate seed phrase.");
+            println!("{}", seed_phrase);
+        }
         _ => unreachable!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::KeyRole;
+    use near_crypto::DerivationPath;
+
+    fn overlapping_role(role: KeyRole, path: &str, num_keys: u32) -> Option<KeyRole> {
+        role.overlapping_role(&path.parse::<DerivationPath>().unwrap(), num_keys)
+    }
+
+    #[test]
+    fn test_overlapping_role() {
+        for role in KeyRole::ALL {
+            let num_keys = if role == KeyRole::Signer { 100 } else { 1 };
+            assert_eq!(overlapping_role(role, role.default_derivation_path(), num_keys), None);
+        }
+        // Any index of the signer path gives a signer key.
+        for path in ["m/44'/397'/0'", "m/44'/397'/1'", "m/44'/397'/1000'"] {
+            assert_eq!(overlapping_role(KeyRole::Validator, path, 1), Some(KeyRole::Signer));
+            assert_eq!(overlapping_role(KeyRole::Node, path, 1), Some(KeyRole::Signer));
+        }
+        assert_eq!(
+            overlapping_role(KeyRole::Validator, "m/44'/397'/0'/2'", 1),
+            Some(KeyRole::Node)
+        );
+        assert_eq!(
+            overlapping_role(KeyRole::Node, "m/44'/397'/0'/1'", 1),
+            Some(KeyRole::Validator)
+        );
+        assert_eq!(overlapping_role(KeyRole::Validator, "m/44'/397'/1'/1'", 1), None);
+        assert_eq!(overlapping_role(KeyRole::Validator, "m/44'/397'/0'/3'", 1), None);
+
+        // Signer keys derived one level deeper reach the validator key at
+        // their second key.
+        assert_eq!(overlapping_role(KeyRole::Signer, "m/44'/397'/0'/0'", 1), None);
+        assert_eq!(
+            overlapping_role(KeyRole::Signer, "m/44'/397'/0'/0'", 2),
+            Some(KeyRole::Validator)
+        );
+        assert_eq!(overlapping_role(KeyRole::Signer, "m/44'/397'/0'/2'", 1), Some(KeyRole::Node));
+        assert_eq!(overlapping_role(KeyRole::Signer, "m/44'/397'/0'/3'", 100), None);
+    }
+}
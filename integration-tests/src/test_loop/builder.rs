@@ -22,6 +22,7 @@ use near_client::client_actor::ClientActorInner;
 use near_client::gc_actor::GCActor;
 use near_client::sync_jobs_actor::SyncJobsActor;
 use near_client::{Client, PartialWitnessActor, ViewClientActorInner};
+use near_crypto::{KeyType, SecretKey};
 use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
 use near_epoch_manager::{EpochManager, EpochManagerAdapter};
 use near_network::test_loop::{TestLoopNetworkSharedState, TestLoopPeerManagerActor};
@@ -535,6 +536,7 @@ impl TestLoopBuilder {
                 location: external_storage_location.clone(),
                 credentials_file: None,
                 restart_dump_for_shards: None,
+                compression_level: None,
             }),
             sync: SyncConfig::ExternalStorage(ExternalStorageConfig {
                 location: external_storage_location,
@@ -545,6 +547,7 @@ impl TestLoopBuilder {
                 // the clients transfer state parts "peer to peer" but we wouldn't really
                 // gain anything over having them dump parts to a tempdir.
                 external_storage_fallback_threshold: 0,
+                trusted_dumper_keys: vec![],
             }),
         };
 
@@ -767,6 +770,7 @@ impl TestLoopBuilder {
             shard_tracker,
             runtime: runtime_adapter,
             validator: validator_signer,
+            node_key: SecretKey::from_seed(KeyType::ED25519, self.clients[idx].as_str()),
             dump_future_runner: Box::new(move |future| {
                 future_spawner.spawn_boxed("state_sync_dumper", future);
                 Box::new(|| {})
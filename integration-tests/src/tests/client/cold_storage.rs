@@ -15,7 +15,7 @@ use near_primitives_core::hash::CryptoHash;
 use near_primitives_core::types::AccountId;
 use near_store::archive::cold_storage::{
     copy_all_data_to_cold, test_cold_genesis_update, test_get_store_initial_writes,
-    test_get_store_reads, update_cold_db, update_cold_head,
+    test_get_store_reads, update_cold_db, update_cold_head, ColdStoreAccountFilter,
 };
 use near_store::metadata::DbKind;
 use near_store::metadata::DB_VERSION;
@@ -67,6 +67,10 @@ fn test1() -> AccountId {
     "test1".parse().unwrap()
 }
 
+fn test2() -> AccountId {
+    "test2".parse().unwrap()
+}
+
 fn create_tx_send_money(nonce: u64, signer: &Signer, block_hash: CryptoHash) -> SignedTransaction {
     SignedTransaction::send_money(nonce, test0(), test1(), signer, 1, block_hash)
 }
@@ -151,8 +155,16 @@ fn test_storage_after_commit_of_cold_update() {
         let shard_layout = client.epoch_manager.get_shard_layout(&epoch_id).unwrap();
         let is_last_block_in_epoch =
             client.epoch_manager.is_next_block_epoch_start(block.hash()).unwrap();
-        update_cold_db(cold_db, &client_store, &shard_layout, &height, is_last_block_in_epoch, 4)
-            .unwrap();
+        update_cold_db(
+            cold_db,
+            &client_store,
+            &shard_layout,
+            &height,
+            is_last_block_in_epoch,
+            4,
+            None,
+        )
+        .unwrap();
 
         last_hash = *block.hash();
     }
@@ -199,6 +211,99 @@ fn test_storage_after_commit_of_cold_update() {
     }
 }
 
+/// Sending money from two accounts every block and copying the blocks to cold
+/// storage with an account filter for one of them. Checks that only the
+/// transactions of the filtered account end up in the cold storage, while
+/// the block data is copied in full and the state is not copied at all. The
+/// same holds for the initial migration of the whole history.
+#[test]
+fn test_partial_archival_with_account_filter() {
+    init_test_logger();
+
+    let epoch_length = 5;
+    let max_height = epoch_length * 2;
+
+    let mut genesis = Genesis::test(vec![test0(), test1(), test2()], 1);
+    genesis.config.epoch_length = epoch_length;
+    genesis.config.min_gas_price = 0;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+
+    let (storage, ..) = create_test_node_storage_with_cold(DB_VERSION, DbKind::Hot);
+    let cold_db = storage.cold_db().unwrap();
+    let account_filter = ColdStoreAccountFilter::new([test0()]);
+
+    let mut last_hash = *env.clients[0].chain.genesis().hash();
+    let mut archived_txs = vec![];
+    let mut filtered_txs = vec![];
+    for height in 1..max_height {
+        if height + 2 < max_height {
+            let signer = InMemorySigner::test_signer(&test0());
+            let tx = create_tx_send_money(height, &signer, last_hash);
+            archived_txs.push(tx.get_hash());
+            assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+
+            let signer = InMemorySigner::test_signer(&test2());
+            let tx = SignedTransaction::send_money(height, test2(), test2(), &signer, 1, last_hash);
+            filtered_txs.push(tx.get_hash());
+            assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+        }
+
+        let block = env.clients[0].produce_block(height).unwrap().unwrap();
+        env.process_block(0, block.clone(), Provenance::PRODUCED);
+
+        let client = &env.clients[0];
+        let client_store = client.runtime_adapter.store();
+        let epoch_id = client.epoch_manager.get_epoch_id(block.hash()).unwrap();
+        let shard_layout = client.epoch_manager.get_shard_layout(&epoch_id).unwrap();
+        let is_last_block_in_epoch =
+            client.epoch_manager.is_next_block_epoch_start(block.hash()).unwrap();
+        update_cold_db(
+            cold_db,
+            &client_store,
+            &shard_layout,
+            &height,
+            is_last_block_in_epoch,
+            1,
+            Some(&account_filter),
+        )
+        .unwrap();
+
+        last_hash = *block.hash();
+    }
+
+    // The initial migration of the whole history keeps the same data.
+    let client_store = env.clients[0].runtime_adapter.store();
+    let (migrated_storage, ..) = create_test_node_storage_with_cold(DB_VERSION, DbKind::Archive);
+    let keep_going = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
+    copy_all_data_to_cold(
+        migrated_storage.cold_db().unwrap().clone(),
+        &client_store,
+        1000000,
+        &keep_going,
+        Some(&account_filter),
+    )
+    .unwrap();
+
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    for cold_store in
+        [storage.get_cold_store().unwrap(), migrated_storage.get_cold_store().unwrap()]
+    {
+        for tx_hash in &archived_txs {
+            check_key(client_store, &cold_store, DBCol::Transactions, tx_hash.as_bytes());
+        }
+        for tx_hash in &filtered_txs {
+            assert!(client_store.exists(DBCol::Transactions, tx_hash.as_bytes()).unwrap());
+            assert!(!cold_store.exists(DBCol::Transactions, tx_hash.as_bytes()).unwrap());
+        }
+        for (key, _) in client_store.iter(DBCol::Block).map(Result::unwrap) {
+            if key.as_ref() != genesis_hash.as_bytes() {
+                check_key(client_store, &cold_store, DBCol::Block, &key);
+            }
+        }
+        assert!(cold_store.iter(DBCol::State).next().is_none());
+    }
+}
+
 /// Producing 10 * 5 blocks and updating HEAD of cold storage after each one.
 /// After every update checking that HEAD in cold db, COLD_HEAD in hot db and HEAD in hot store are equal.
 #[test]
@@ -297,8 +402,16 @@ fn test_cold_db_copy_with_height_skips() {
         let shard_layout = client.epoch_manager.get_shard_layout(&epoch_id).unwrap();
         let is_last_block_in_epoch =
             client.epoch_manager.is_next_block_epoch_start(&block_hash).unwrap();
-        update_cold_db(&cold_db, hot_store, &shard_layout, &height, is_last_block_in_epoch, 1)
-            .unwrap();
+        update_cold_db(
+            &cold_db,
+            hot_store,
+            &shard_layout,
+            &height,
+            is_last_block_in_epoch,
+            1,
+            None,
+        )
+        .unwrap();
         last_hash = block_hash;
     }
 
@@ -367,7 +480,7 @@ fn test_initial_copy_to_cold(batch_size: usize) {
     let cold_db = storage.cold_db().unwrap();
     let cold_store = storage.get_cold_store().unwrap();
     let client_store = env.clients[0].runtime_adapter.store();
-    copy_all_data_to_cold(cold_db.clone(), &client_store, batch_size, &keep_going).unwrap();
+    copy_all_data_to_cold(cold_db.clone(), &client_store, batch_size, &keep_going, None).unwrap();
 
     for col in DBCol::iter() {
         if !col.is_cold() {
@@ -452,7 +565,7 @@ fn test_cold_loop_on_gc_boundary() {
     let keep_going = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
 
     let cold_db = storage.cold_db().unwrap();
-    copy_all_data_to_cold(cold_db.clone(), &hot_store, 1000000, &keep_going).unwrap();
+    copy_all_data_to_cold(cold_db.clone(), &hot_store, 1000000, &keep_going, None).unwrap();
 
     update_cold_head(cold_db, &hot_store, &(height_delta - 1)).unwrap();
 
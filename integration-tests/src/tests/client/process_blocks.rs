@@ -346,7 +346,8 @@ fn receive_network_block() {
                 block_merkle_tree.root(),
                 Clock::real(),
                 None,
-            );
+            )
+            .unwrap();
             actor_handles.client_actor.do_send(
                 BlockResponse { block, peer_id: PeerInfo::random().id, was_requested: false }
                     .with_span_context(),
@@ -434,7 +435,8 @@ fn produce_block_with_approvals() {
                 block_merkle_tree.root(),
                 Clock::real(),
                 None,
-            );
+            )
+            .unwrap();
             actor_handles.client_actor.do_send(
                 BlockResponse {
                     block: block.clone(),
@@ -457,7 +459,8 @@ fn produce_block_with_approvals() {
                     block.header().height(),
                     10, // the height at which "test1" is producing
                     &signer,
-                );
+                )
+                .unwrap();
                 actor_handles
                     .client_actor
                     .do_send(BlockApproval(approval, PeerInfo::random().id).with_span_context());
@@ -631,7 +634,8 @@ fn invalid_blocks_common(is_requested: bool) {
                 block_merkle_tree.root(),
                 Clock::real(),
                 None,
-            );
+            )
+            .unwrap();
             // Send block with invalid chunk mask
             let mut block = valid_block.clone();
             block.mut_header().set_chunk_mask(vec![]);
@@ -1477,6 +1481,7 @@ fn test_archival_gc_common(
                 &i,
                 is_last_block_in_epoch,
                 1,
+                None,
             )
             .unwrap();
             update_cold_head(storage.cold_db().unwrap(), &hot_store, &i).unwrap();
@@ -2318,7 +2323,7 @@ fn test_validate_chunk_extra() {
         let outcome_root = Block::compute_outcome_root(block.chunks().iter_deprecated());
         block.mut_header().set_prev_outcome_root(outcome_root);
         let endorsement =
-            ChunkEndorsement::new(EpochId::default(), &chunk_header, &validator_signer);
+            ChunkEndorsement::new(EpochId::default(), &chunk_header, &validator_signer).unwrap();
         block.set_chunk_endorsements(vec![vec![Some(Box::new(endorsement.signature()))]]);
         let body_hash = block.compute_block_body_hash().unwrap();
         block.mut_header().set_block_body_hash(body_hash);
@@ -3412,18 +3417,12 @@ fn test_header_version_downgrade() {
             BlockHeader::BlockHeaderV1(header) => {
                 let header = Arc::make_mut(header);
                 header.inner_rest.latest_protocol_version = PROTOCOL_VERSION;
-                let hash = BlockHeader::compute_hash(
-                    header.prev_hash,
-                    &borsh::to_vec(&header.inner_lite).expect("Failed to serialize"),
-                    &borsh::to_vec(&header.inner_rest).expect("Failed to serialize"),
-                );
-                header.hash = hash;
-                header.signature = validator_signer.sign_bytes(hash.as_ref());
             }
             _ => {
                 unreachable!();
             }
         }
+        header.resign(&validator_signer);
         *block.mut_header() = header;
         block
     };
@@ -1,4 +1,5 @@
 use assert_matches::assert_matches;
+use borsh::BorshDeserialize;
 
 use near_async::futures::ActixFutureSpawner;
 use near_async::time::{Clock, Duration};
@@ -9,12 +10,13 @@ use near_chain_configs::{DumpConfig, Genesis, MutableConfigValue, NEAR_BASE};
 use near_client::sync::external::{external_storage_location, StateFileType};
 use near_client::test_utils::TestEnv;
 use near_client::ProcessTxResponse;
-use near_crypto::InMemorySigner;
+use near_crypto::{InMemorySigner, KeyType, SecretKey};
 use near_o11y::testonly::init_test_logger;
 use near_primitives::block::Tip;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state::FlatStateValue;
 use near_primitives::state_part::PartId;
+use near_primitives::state_sync::SignedStatePartsManifest;
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{BlockHeight, ShardId};
 use near_primitives::validator_signer::{EmptyValidatorSigner, InMemoryValidatorSigner};
@@ -52,12 +54,14 @@ fn slow_test_state_dump() {
         restart_dump_for_shards: None,
         iteration_delay: Some(Duration::ZERO),
         credentials_file: None,
+        compression_level: None,
     });
 
     let validator = MutableConfigValue::new(
         Some(Arc::new(EmptyValidatorSigner::new("test0".parse().unwrap()))),
         "validator_signer",
     );
+    let node_key = SecretKey::from_seed(KeyType::ED25519, "test0");
     let mut state_sync_dumper = StateSyncDumper {
         clock: Clock::real(),
         client_config: config,
@@ -66,6 +70,7 @@ fn slow_test_state_dump() {
         shard_tracker,
         runtime,
         validator,
+        node_key: node_key.clone(),
         dump_future_runner: StateSyncDumper::arbiter_dump_future_runner(),
         future_spawner: Arc::new(ActixFutureSpawner),
         handle: None,
@@ -103,6 +108,32 @@ fn slow_test_state_dump() {
                     all_parts_present = false;
                 }
             }
+            let path = root_dir.path().join(external_storage_location(
+                "unittest",
+                &epoch_id,
+                epoch_height,
+                shard_id,
+                &StateFileType::StateManifest,
+            ));
+            let Ok(manifest) = std::fs::read(&path) else {
+                tracing::info!("Missing {:?}", path);
+                all_parts_present = false;
+                continue;
+            };
+            let manifest = SignedStatePartsManifest::try_from_slice(&manifest).unwrap();
+            assert!(manifest.verify_signature(&[node_key.public_key()]));
+            assert_eq!(manifest.manifest.parts.len() as u64, num_parts);
+            for part_id in 0..num_parts {
+                let part = std::fs::read(root_dir.path().join(external_storage_location(
+                    "unittest",
+                    &epoch_id,
+                    epoch_height,
+                    shard_id,
+                    &StateFileType::StatePart { part_id, num_parts },
+                )))
+                .unwrap();
+                manifest.verify_part(part_id, &part).unwrap();
+            }
         }
         if all_parts_present {
             break;
@@ -163,6 +194,7 @@ fn run_state_sync_with_dumped_parts(
         restart_dump_for_shards: None,
         iteration_delay: Some(Duration::ZERO),
         credentials_file: None,
+        compression_level: None,
     });
     let mut state_sync_dumper = StateSyncDumper {
         clock: Clock::real(),
@@ -172,6 +204,7 @@ fn run_state_sync_with_dumped_parts(
         shard_tracker,
         runtime,
         validator,
+        node_key: SecretKey::from_seed(KeyType::ED25519, "test0"),
         dump_future_runner: StateSyncDumper::arbiter_dump_future_runner(),
         future_spawner: Arc::new(ActixFutureSpawner),
         handle: None,
@@ -339,6 +339,7 @@ fn ultra_slow_test_sync_state_dump() {
                 restart_dump_for_shards: None,
                 iteration_delay: Some(Duration::milliseconds(500)),
                 credentials_file: None,
+                compression_level: None,
             });
             near1.config.store.state_snapshot_enabled = true;
 
@@ -389,6 +390,7 @@ fn ultra_slow_test_sync_state_dump() {
                                         num_concurrent_requests: 1,
                                         num_concurrent_requests_during_catchup: 1,
                                         external_storage_fallback_threshold: 0,
+                                        trusted_dumper_keys: vec![],
                                     });
 
                                 let nearcore::NearNode {
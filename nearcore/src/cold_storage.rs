@@ -8,7 +8,7 @@ use near_store::config::SplitStorageConfig;
 use near_store::{
     archive::cold_storage::{
         copy_all_data_to_cold, get_cold_head, update_cold_db, update_cold_head,
-        CopyAllDataToColdStatus,
+        ColdStoreAccountFilter, CopyAllDataToColdStatus,
     },
     db::ColdDB,
     DBCol, NodeStorage, Store, FINAL_HEAD_KEY, TAIL_KEY,
@@ -90,6 +90,7 @@ fn cold_store_copy(
     genesis_height: BlockHeight,
     epoch_manager: &EpochManagerHandle,
     num_threads: usize,
+    account_filter: Option<&ColdStoreAccountFilter>,
 ) -> anyhow::Result<ColdStoreCopyResult, ColdStoreError> {
     // If HEAD is not set for cold storage we default it to genesis_height.
     let cold_head = get_cold_head(cold_db)?;
@@ -143,6 +144,7 @@ fn cold_store_copy(
         &next_height,
         is_last_block_in_epoch,
         num_threads,
+        account_filter,
     )?;
     update_cold_head(cold_db, hot_store, &next_height)?;
 
@@ -296,7 +298,15 @@ fn cold_store_migration(
     tracing::info!(target: "cold_store", new_cold_height, "Determined cold storage head height after migration");
 
     let batch_size = split_storage_config.cold_store_initial_migration_batch_size;
-    match copy_all_data_to_cold(cold_db.clone(), hot_store, batch_size, keep_going)? {
+    let account_filter =
+        split_storage_config.archived_accounts.clone().map(ColdStoreAccountFilter::new);
+    match copy_all_data_to_cold(
+        cold_db.clone(),
+        hot_store,
+        batch_size,
+        keep_going,
+        account_filter.as_ref(),
+    )? {
         CopyAllDataToColdStatus::EverythingCopied => {
             tracing::info!(target: "cold_store", new_cold_height, "Cold storage population was successful, writing cold head.");
             update_cold_head(cold_db.as_ref(), hot_store, &new_cold_height)?;
@@ -368,6 +378,11 @@ fn cold_store_loop(
     epoch_manager: &EpochManagerHandle,
 ) {
     tracing::info!(target : "cold_store", "Starting the cold store loop");
+    let account_filter =
+        split_storage_config.archived_accounts.clone().map(ColdStoreAccountFilter::new);
+    if let Some(accounts) = &split_storage_config.archived_accounts {
+        tracing::info!(target : "cold_store", ?accounts, "Archiving history only for the configured accounts");
+    }
 
     loop {
         if !keep_going.load(std::sync::atomic::Ordering::Relaxed) {
@@ -382,6 +397,7 @@ fn cold_store_loop(
             genesis_height,
             epoch_manager,
             split_storage_config.num_cold_store_read_threads,
+            account_filter.as_ref(),
         );
         let duration = instant.elapsed();
 
@@ -30,6 +30,8 @@ use near_chain_configs::{
     PROTOCOL_UPGRADE_STAKE_THRESHOLD, TRANSACTION_VALIDITY_PERIOD,
 };
 use near_config_utils::{DownloadConfigType, ValidationError, ValidationErrors};
+#[cfg(unix)]
+use near_crypto::RemoteSigner;
 use near_crypto::{InMemorySigner, KeyFile, KeyType, PublicKey, Signer};
 use near_epoch_manager::EpochManagerHandle;
 #[cfg(feature = "json_rpc")]
@@ -38,14 +40,15 @@ use near_network::config::NetworkConfig;
 use near_network::tcp;
 use near_o11y::log_config::LogConfig;
 use near_primitives::hash::CryptoHash;
-use near_primitives::shard_layout::ShardLayout;
+use near_primitives::shard_layout::{ShardLayout, ShardUId};
+use near_primitives::signing_journal::SigningJournal;
 use near_primitives::test_utils::create_test_signer;
 use near_primitives::types::{
     AccountId, AccountInfo, Balance, BlockHeight, BlockHeightDelta, Gas, NumSeats, NumShards,
     ShardId,
 };
 use near_primitives::utils::{from_timestamp, get_num_seats_per_shard};
-use near_primitives::validator_signer::{InMemoryValidatorSigner, ValidatorSigner};
+use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::version::PROTOCOL_VERSION;
 #[cfg(feature = "rosetta_rpc")]
 use near_rosetta_rpc::RosettaRpcConfig;
@@ -230,6 +233,12 @@ pub struct Config {
     pub genesis_records_file: Option<String>,
     pub validator_key_file: String,
     pub node_key_file: String,
+    /// Journal of the blocks, chunks, approvals and chunk endorsements signed
+    /// by the validator, which refuses to sign conflicting ones for the same
+    /// height. Set it to protect against double signing when failing over to
+    /// another node with the same key, see `neard signing-journal`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signing_journal_file: Option<String>,
     #[cfg(feature = "json_rpc")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rpc: Option<RpcConfig>,
@@ -244,6 +253,10 @@ pub struct Config {
     pub tracked_shards: Vec<ShardId>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tracked_shard_schedule: Option<Vec<Vec<ShardId>>>,
+    /// Tracks these shards and, after resharding, the shards they split into.
+    /// Shard uids are written as e.g. `"s1.v3"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracked_shard_lineage: Option<Vec<ShardUId>>,
     #[serde(skip_serializing_if = "is_false")]
     pub archive: bool,
     /// If save_trie_changes is not set it will get inferred from the `archive` field as follows:
@@ -351,6 +364,7 @@ impl Default for Config {
             genesis_records_file: None,
             validator_key_file: VALIDATOR_KEY_FILE.to_string(),
             node_key_file: NODE_KEY_FILE.to_string(),
+            signing_journal_file: None,
             #[cfg(feature = "json_rpc")]
             rpc: Some(RpcConfig::default()),
             #[cfg(feature = "rosetta_rpc")]
@@ -362,6 +376,7 @@ impl Default for Config {
             tracked_shadow_validator: None,
             tracked_shards: vec![],
             tracked_shard_schedule: None,
+            tracked_shard_lineage: None,
             archive: false,
             save_trie_changes: None,
             log_summary_style: LogSummaryStyle::Colored,
@@ -544,6 +559,7 @@ impl NearConfig {
                 tracked_shards: config.tracked_shards,
                 tracked_shadow_validator: config.tracked_shadow_validator,
                 tracked_shard_schedule: config.tracked_shard_schedule.unwrap_or(vec![]),
+                tracked_shard_lineage: config.tracked_shard_lineage.unwrap_or(vec![]),
                 archive: config.archive,
                 save_trie_changes: config.save_trie_changes.unwrap_or(!config.archive),
                 log_summary_style: config.log_summary_style,
@@ -676,7 +692,9 @@ impl NightshadeRuntime {
 ///
 /// If the file already exists, loads the file (panicking if the file is
 /// invalid), checks that account id in the file matches `account_id` if it’s
-/// given and returns the key.  `test_seed` is ignored in this case.
+/// given and returns the key.  `test_seed` is ignored in this case.  A file
+/// which points to a remote signer is checked the same way, but `None` is
+/// returned as the key is held by the signer.
 ///
 /// If the file does not exist and `account_id` is not `None`, generates a new
 /// key, saves it in the file and returns it.  If `test_seed` is not `None`, the
@@ -689,6 +707,25 @@ fn generate_or_load_key(
     test_seed: Option<&str>,
 ) -> anyhow::Result<Option<Signer>> {
     let path = home_dir.join(filename);
+    #[cfg(unix)]
+    if path.exists() {
+        let remote_signer = RemoteSigner::from_key_file(&path)
+            .with_context(|| format!("Failed reading key file {}", path.display()))?;
+        if let Some(signer) = remote_signer {
+            if let Some(account_id) = account_id {
+                if account_id != signer.account_id {
+                    return Err(anyhow!(
+                        "‘{}’ points to a remote signer for {} but expecting key for {}",
+                        path.display(),
+                        signer.account_id,
+                        account_id
+                    ));
+                }
+            }
+            info!(target: "near", "Reusing remote signer key {} for {}", signer.public_key, signer.account_id);
+            return Ok(None);
+        }
+    }
     if path.exists() {
         let signer = InMemorySigner::from_file(&path)
             .with_context(|| format!("Failed initializing signer from {}", path.display()))?;
@@ -1329,6 +1366,7 @@ pub fn download_config(url: &str, path: &Path) -> Result<(), FileDownloadError>
 struct NodeKeyFile {
     account_id: String,
     public_key: PublicKey,
+    #[serde(alias = "private_key")]
     secret_key: near_crypto::SecretKey,
 }
 
@@ -1361,15 +1399,38 @@ impl From<NodeKeyFile> for KeyFile {
     }
 }
 
-pub fn load_validator_key(validator_file: &Path) -> anyhow::Result<Option<Arc<ValidatorSigner>>> {
+/// Reads a node key file, which may be encrypted, or in the legacy format with
+/// an empty account id.
+pub fn load_node_key(path: &Path) -> std::io::Result<KeyFile> {
+    match KeyFile::is_encrypted(path) {
+        Ok(true) => KeyFile::from_file(path),
+        _ => NodeKeyFile::from_file(path).map(KeyFile::from),
+    }
+}
+
+pub fn load_validator_key(
+    validator_file: &Path,
+    signing_journal_file: Option<&Path>,
+) -> anyhow::Result<Option<Arc<ValidatorSigner>>> {
     if !validator_file.exists() {
         return Ok(None);
     }
-    match InMemoryValidatorSigner::from_file(&validator_file) {
-        Ok(signer) => Ok(Some(Arc::new(signer))),
-        Err(_) => {
-            let error_message =
-                format!("Failed initializing validator signer from {}", validator_file.display());
+    match ValidatorSigner::from_file(&validator_file) {
+        Ok(signer) => {
+            let Some(signing_journal_file) = signing_journal_file else {
+                return Ok(Some(Arc::new(signer)));
+            };
+            let journal = SigningJournal::open(signing_journal_file).with_context(|| {
+                format!("Failed opening signing journal {}", signing_journal_file.display())
+            })?;
+            Ok(Some(Arc::new(signer.with_signing_journal(journal))))
+        }
+        Err(err) => {
+            let error_message = format!(
+                "Failed initializing validator signer from {}: {}",
+                validator_file.display(),
+                err
+            );
             Err(anyhow!(error_message))
         }
     }
@@ -1389,21 +1450,22 @@ pub fn load_config(
     };
 
     let validator_file: PathBuf = dir.join(&config.validator_key_file);
-    let validator_signer = match load_validator_key(&validator_file) {
-        Ok(validator_signer) => validator_signer,
-        Err(e) => {
-            validation_errors.push_validator_key_file_error(e.to_string());
-            None
-        }
-    };
+    let signing_journal_file = config.signing_journal_file.as_ref().map(|file| dir.join(file));
+    let validator_signer =
+        match load_validator_key(&validator_file, signing_journal_file.as_deref()) {
+            Ok(validator_signer) => validator_signer,
+            Err(e) => {
+                validation_errors.push_validator_key_file_error(e.to_string());
+                None
+            }
+        };
 
     let node_key_path = dir.join(&config.node_key_file);
-    let network_signer_result = NodeKeyFile::from_file(&node_key_path);
-    let network_signer = match network_signer_result {
+    let network_signer = match load_node_key(&node_key_path) {
         Ok(node_key_file) => Some(node_key_file),
-        Err(_) => {
+        Err(err) => {
             let error_message =
-                format!("Failed reading node key file from {}", node_key_path.display());
+                format!("Failed reading node key file from {}: {}", node_key_path.display(), err);
             validation_errors.push_node_key_file_error(error_message);
             None
         }
@@ -1442,7 +1504,7 @@ pub fn load_config(
     let near_config = NearConfig::new(
         config,
         genesis.unwrap(),
-        network_signer.unwrap().into(),
+        network_signer.unwrap(),
         MutableConfigValue::new(validator_signer, "validator_signer"),
     )?;
     Ok(near_config)
@@ -1482,12 +1544,13 @@ mod tests {
     use itertools::Itertools;
     use near_async::time::Duration;
     use near_chain_configs::{GCConfig, Genesis, GenesisValidationMode};
-    use near_crypto::InMemorySigner;
+    use near_crypto::{InMemorySigner, KeyType, SecretKey};
     use near_primitives::types::{AccountId, NumShards, ShardId};
     use tempfile::tempdir;
 
     use crate::config::{
-        create_localnet_configs, generate_or_load_key, init_configs, Config, CONFIG_FILENAME,
+        create_localnet_configs, generate_or_load_key, init_configs, load_node_key, Config,
+        CONFIG_FILENAME,
     };
 
     #[test]
@@ -1633,6 +1696,7 @@ mod tests {
                     gc_fork_clean_step: 420,
                     gc_num_epochs_to_keep: 24,
                     gc_step_period: Duration::seconds(1),
+                    column_retention: Default::default(),
                 }
             } else {
                 GCConfig {
@@ -1640,6 +1704,7 @@ mod tests {
                     gc_fork_clean_step: 100,
                     gc_num_epochs_to_keep: 5,
                     gc_step_period: Duration::seconds(1),
+                    column_retention: Default::default(),
                 }
             };
             assert_eq!(want_gc, config.gc);
@@ -1877,4 +1942,21 @@ mod tests {
         }
         test_err("bad_key", "fred", "");
     }
+
+    #[test]
+    fn test_load_node_key_empty_account_id() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("node_key.json");
+        let secret_key = SecretKey::from_seed(KeyType::ED25519, "node");
+        let contents = serde_json::json!({
+            "account_id": "",
+            "public_key": secret_key.public_key(),
+            "secret_key": secret_key,
+        });
+        std::fs::write(&path, contents.to_string()).unwrap();
+
+        let key_file = load_node_key(&path).unwrap();
+        assert_eq!(key_file.account_id.as_str(), "node");
+        assert_eq!(key_file.secret_key, secret_key);
+    }
 }
@@ -38,6 +38,7 @@ fn test_config_duration_all_std() {
         rpc: Some(RpcConfig {
             experimental_debug_pages_src_path: Some(Default::default()),
             prometheus_addr: Some(Default::default()),
+            state_parts_addr: Some(Default::default()),
             ..Default::default()
         }),
         rosetta_rpc: Some(Default::default()),
@@ -45,6 +46,7 @@ fn test_config_duration_all_std() {
         split_storage: Some(Default::default()),
         tracked_shadow_validator: Some(AccountId::from_str("test").unwrap()),
         tracked_shard_schedule: Some(Default::default()),
+        tracked_shard_lineage: Some(Default::default()),
         transaction_pool_size_limit: Some(Default::default()),
         state_sync: Some(Default::default()),
         trie_viewer_state_size_limit: Some(Default::default()),
@@ -1,4 +1,4 @@
-use near_chain_configs::{ExternalStorageLocation, SyncConfig};
+use near_chain_configs::{ExternalStorageLocation, GCRetentionPolicy, SyncConfig};
 use near_config_utils::{ValidationError, ValidationErrors};
 use std::collections::HashSet;
 use std::path::Path;
@@ -79,6 +79,15 @@ impl<'a> ConfigValidator<'a> {
             self.validation_errors.push_config_semantics_error(error_message);
         }
 
+        for (column, policy) in &self.config.gc.column_retention {
+            if matches!(policy, GCRetentionPolicy::Epochs(0) | GCRetentionPolicy::Days(0)) {
+                let error_message = format!(
+                    "'config.gc.column_retention' for {column:?} should be greater than 0, but is {policy:?}."
+                );
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+
         if let Some(state_sync) = &self.config.state_sync {
             if let Some(dump_config) = &state_sync.dump {
                 if let Some(restart_dump_for_shards) = &dump_config.restart_dump_for_shards {
@@ -108,6 +117,12 @@ impl<'a> ConfigValidator<'a> {
                             self.validation_errors.push_config_semantics_error(error_message);
                         }
                     }
+                    ExternalStorageLocation::HTTP { base_url } => {
+                        if !is_http_url(base_url) {
+                            let error_message = format!("'config.state_sync.dump.location.HTTP.base_url' needs to be an http:// or https:// URL, but is {base_url:?}.");
+                            self.validation_errors.push_config_semantics_error(error_message);
+                        }
+                    }
                 }
 
                 if let Some(credentials_file) = &dump_config.credentials_file {
@@ -116,6 +131,13 @@ impl<'a> ConfigValidator<'a> {
                         self.validation_errors.push_config_semantics_error(error_message);
                     }
                 }
+
+                if let Some(compression_level) = dump_config.compression_level {
+                    if !(1..=22).contains(&compression_level) {
+                        let error_message = format!("'config.state_sync.dump.compression_level' needs to be between 1 and 22, but is {compression_level}.");
+                        self.validation_errors.push_config_semantics_error(error_message);
+                    }
+                }
             }
             match &state_sync.sync {
                 SyncConfig::Peers => {}
@@ -139,6 +161,12 @@ impl<'a> ConfigValidator<'a> {
                                 self.validation_errors.push_config_semantics_error(error_message);
                             }
                         }
+                        ExternalStorageLocation::HTTP { base_url } => {
+                            if !is_http_url(base_url) {
+                                let error_message = format!("'config.state_sync.sync.ExternalStorage.location.HTTP.base_url' needs to be an http:// or https:// URL, but is {base_url:?}.");
+                                self.validation_errors.push_config_semantics_error(error_message);
+                            }
+                        }
                     }
                     if config.num_concurrent_requests == 0 {
                         let error_message = format!("'config.state_sync.sync.ExternalStorage.num_concurrent_requests' needs to be greater than 0");
@@ -169,6 +197,10 @@ impl<'a> ConfigValidator<'a> {
     }
 }
 
+fn is_http_url(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
 #[cfg(test)]
 mod tests {
     use near_primitives::types::ShardId;
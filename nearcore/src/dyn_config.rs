@@ -66,10 +66,12 @@ pub fn get_updateable_client_config(config: &Config) -> UpdateableClientConfig {
         expected_shutdown: config.expected_shutdown,
         resharding_config: config.resharding_config,
         produce_chunk_add_transactions_time_limit: config.produce_chunk_add_transactions_time_limit,
+        tracked_accounts: config.tracked_accounts.clone(),
+        tracked_shards: config.tracked_shards.clone(),
     }
 }
 
-fn read_log_config(home_dir: &Path) -> Result<Option<LogConfig>, UpdateableConfigLoaderError> {
+pub fn read_log_config(home_dir: &Path) -> Result<Option<LogConfig>, UpdateableConfigLoaderError> {
     read_json_config::<LogConfig>(&home_dir.join(LOG_CONFIG_FILENAME))
 }
 
@@ -105,12 +107,15 @@ where
     }
 }
 
+/// Reloads the validator key. An encrypted key file that didn't change since it
+/// was loaded isn't decrypted again, see [`near_crypto::KeyFile::from_file`].
 fn read_validator_key(
     home_dir: &Path,
     config: &Config,
 ) -> Result<Option<Arc<ValidatorSigner>>, UpdateableConfigLoaderError> {
     let validator_file: PathBuf = home_dir.join(&config.validator_key_file);
-    match crate::config::load_validator_key(&validator_file) {
+    let signing_journal_file = config.signing_journal_file.as_ref().map(|file| home_dir.join(file));
+    match crate::config::load_validator_key(&validator_file, signing_journal_file.as_deref()) {
         Ok(Some(validator_signer)) => {
             tracing::info!(target: "neard", "Hot loading validator key {}.", validator_file.display());
             Ok(Some(validator_signer))
@@ -292,10 +292,8 @@ pub fn start_with_config_and_synchronization(
                 &config.genesis.config,
                 Some(home_dir),
             );
-            let view_shard_tracker = ShardTracker::new(
-                TrackedConfig::from_config(&config.client_config),
-                epoch_manager.clone(),
-            );
+            // Shared with the client so that updates of the tracked config apply to both.
+            let view_shard_tracker = shard_tracker.clone();
             let view_runtime = NightshadeRuntime::from_config(
                 home_dir,
                 split_store.clone(),
@@ -435,6 +433,7 @@ pub fn start_with_config_and_synchronization(
         shard_tracker: shard_tracker.clone(),
         runtime,
         validator: config.validator_signer.clone(),
+        node_key: config.network_config.node_key.clone(),
         dump_future_runner: StateSyncDumper::arbiter_dump_future_runner(),
         future_spawner: state_sync_spawner,
         handle: None,
@@ -459,6 +458,20 @@ pub fn start_with_config_and_synchronization(
     network_adapter.bind(network_actor.clone().with_auto_span_context());
     #[cfg(feature = "json_rpc")]
     if let Some(rpc_config) = config.rpc_config {
+        if let Some(state_parts_addr) = &rpc_config.state_parts_addr {
+            let dump_location =
+                config.client_config.state_sync.dump.as_ref().map(|dump| &dump.location);
+            if let Some(near_chain_configs::ExternalStorageLocation::Filesystem { root_dir }) =
+                dump_location
+            {
+                rpc_servers.extend(near_jsonrpc::start_http_for_state_parts(
+                    state_parts_addr,
+                    root_dir.clone(),
+                ));
+            } else {
+                tracing::warn!(target: "neard", "'rpc.state_parts_addr' is set, but state parts are not dumped to the local filesystem. Not serving state parts.");
+            }
+        }
         let entity_debug_handler = EntityDebugHandlerImpl {
             epoch_manager: view_epoch_manager,
             runtime: view_runtime,
@@ -17,19 +17,23 @@ use near_client::sync::external::{
     external_storage_location_directory, get_part_id_from_filename, is_part_filename,
     ExternalConnection,
 };
+use near_crypto::SecretKey;
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::EpochManagerAdapter;
 use near_primitives::block::BlockHeader;
 use near_primitives::hash::CryptoHash;
 use near_primitives::state_part::PartId;
-use near_primitives::state_sync::StateSyncDumpProgress;
+use near_primitives::state_sync::{
+    compress_state_part, SignedStatePartsManifest, StatePartsManifest, StatePartsManifestEntry,
+    StateSyncDumpProgress,
+};
 use near_primitives::types::{EpochHeight, EpochId, ShardId, StateRoot};
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 use std::collections::{HashMap, HashSet};
 use std::i64;
 use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use tokio::sync::oneshot;
 use tokio::sync::Semaphore;
 
@@ -49,6 +53,8 @@ pub struct StateSyncDumper {
     /// Lock the value of mutable validator signer for the duration of a request to ensure consistency.
     /// Please note that the locked value should not be stored anywhere or passed through the thread boundary.
     pub validator: MutableValidatorSigner,
+    /// Key of this node, used to sign the manifests of the dumped state parts.
+    pub node_key: SecretKey,
     pub dump_future_runner: Box<dyn Fn(BoxFuture<'static, ()>) -> Box<dyn FnOnce()>>,
     pub future_spawner: Arc<dyn FutureSpawner>,
     pub handle: Option<StateSyncDumpHandle>,
@@ -90,6 +96,7 @@ impl StateSyncDumper {
                     bucket,
                 }
             },
+            ExternalStorageLocation::HTTP { base_url } => ExternalConnection::new_http(&base_url),
         };
 
         let chain_id = self.client_config.chain_id.clone();
@@ -122,6 +129,8 @@ impl StateSyncDumper {
                 external,
                 dump_config.iteration_delay.unwrap_or(Duration::seconds(10)),
                 self.validator.clone(),
+                self.node_key.clone(),
+                dump_config.compression_level,
                 keep_running.clone(),
                 self.future_spawner.clone(),
             )
@@ -220,6 +229,8 @@ struct ShardDump {
     // meaning they've already been dumped. We periodically check this (since other processes/machines
     // might have uploaded parts that we didn't) and avoid duplicating work for those parts that have already been updated.
     parts_missing: Arc<RwLock<HashSet<u64>>>,
+    // Manifest entries of the parts uploaded by this node, used to write the manifest once all parts are dumped.
+    part_entries: Arc<Mutex<HashMap<u64, StatePartsManifestEntry>>>,
     // This will give Ok(()) when they're all done, or Err() when one gives an error
     // For now the tasks never fail, since we just retry all errors like the old implementation did,
     // but we probably want to make a change to distinguish which errors are actually retriable
@@ -295,6 +306,9 @@ struct StateDumper {
     clock: Clock,
     chain_id: String,
     validator: MutableValidatorSigner,
+    node_key: SecretKey,
+    // zstd compression level of the dumped state parts, or None if they are not compressed
+    compression_level: Option<i32>,
     shard_tracker: ShardTracker,
     chain: Chain,
     epoch_manager: Arc<dyn EpochManagerAdapter>,
@@ -324,6 +338,8 @@ struct PartUploader {
     // know not to touch that metric anymore.
     parts_dumped: Arc<AtomicI64>,
     parts_missing: Arc<RwLock<HashSet<u64>>>,
+    part_entries: Arc<Mutex<HashMap<u64, StatePartsManifestEntry>>>,
+    compression_level: Option<i32>,
     obtain_parts: Arc<Semaphore>,
     canceled: Arc<AtomicBool>,
 }
@@ -391,6 +407,15 @@ impl PartUploader {
             }
         };
 
+        let state_part = match self.compression_level {
+            Some(level) => compress_state_part(&state_part, level)?,
+            None => state_part,
+        };
+        let entry = StatePartsManifestEntry {
+            hash: CryptoHash::hash_bytes(&state_part),
+            size: state_part.len() as u64,
+        };
+
         let file_type = StateFileType::StatePart { part_id: part_idx, num_parts: self.num_parts };
         let location = external_storage_location(
             &self.chain_id,
@@ -410,6 +435,7 @@ impl PartUploader {
             {
                 Ok(()) => {
                     self.inc_parts_dumped();
+                    self.part_entries.lock().unwrap().insert(part_idx, entry);
                     metrics::STATE_SYNC_DUMP_SIZE_TOTAL
                         .with_label_values(&[
                             &self.epoch_height.to_string(),
@@ -500,11 +526,143 @@ impl HeaderUploader {
     }
 }
 
+// Stores needed data for use in manifest upload futures
+struct ManifestUploader {
+    clock: Clock,
+    external: ExternalConnection,
+    chain_id: String,
+    epoch_id: EpochId,
+    epoch_height: EpochHeight,
+    shard_id: ShardId,
+    state_root: StateRoot,
+    num_parts: u64,
+    part_entries: Arc<Mutex<HashMap<u64, StatePartsManifestEntry>>>,
+    node_key: SecretKey,
+    canceled: Arc<AtomicBool>,
+}
+
+impl ManifestUploader {
+    /// Returns the manifest entry of the given part. Parts uploaded by other nodes or before a restart
+    /// are downloaded from the external storage to compute it.
+    async fn get_part_entry(&self, part_id: u64) -> anyhow::Result<StatePartsManifestEntry> {
+        let entry = self.part_entries.lock().unwrap().get(&part_id).cloned();
+        if let Some(entry) = entry {
+            return Ok(entry);
+        }
+        let file_type = StateFileType::StatePart { part_id, num_parts: self.num_parts };
+        let location = external_storage_location(
+            &self.chain_id,
+            &self.epoch_id,
+            self.epoch_height,
+            self.shard_id,
+            &file_type,
+        );
+        let data = self.external.get_file(self.shard_id, &location, &file_type).await?;
+        Ok(StatePartsManifestEntry { hash: CryptoHash::hash_bytes(&data), size: data.len() as u64 })
+    }
+
+    /// Signs and uploads the manifest listing all state parts of `self.shard_id`, unless it's already
+    /// stored in the external storage. Gives up if a new epoch starts before it's uploaded.
+    async fn upload_manifest(self) {
+        let file_type = StateFileType::StateManifest;
+        let directory_path = external_storage_location_directory(
+            &self.chain_id,
+            &self.epoch_id,
+            self.epoch_height,
+            self.shard_id,
+            &file_type,
+        );
+        let location = external_storage_location(
+            &self.chain_id,
+            &self.epoch_id,
+            self.epoch_height,
+            self.shard_id,
+            &file_type,
+        );
+        let parts_directory_path = external_storage_location_directory(
+            &self.chain_id,
+            &self.epoch_id,
+            self.epoch_height,
+            self.shard_id,
+            &StateFileType::StatePart { part_id: 0, num_parts: self.num_parts },
+        );
+        let mut manifest = None;
+        while !self.canceled.load(Ordering::Relaxed) {
+            let result = async {
+                let file_names = self.external.list_objects(self.shard_id, &directory_path).await?;
+                if file_names.contains(&file_type.filename()) {
+                    return Ok(true);
+                }
+                if manifest.is_none() {
+                    let mut parts = Vec::with_capacity(self.num_parts as usize);
+                    for part_id in 0..self.num_parts {
+                        parts.push(self.get_part_entry(part_id).await?);
+                    }
+                    let unsigned = StatePartsManifest {
+                        epoch_id: self.epoch_id,
+                        epoch_height: self.epoch_height,
+                        shard_id: self.shard_id,
+                        state_root: self.state_root,
+                        parts,
+                    };
+                    manifest = Some(borsh::to_vec(&SignedStatePartsManifest::new(
+                        unsigned,
+                        &self.node_key,
+                    ))?);
+                }
+                // All parts are dumped at this point, so the listings are written once per
+                // directory. The headers listing is written last, as it marks the manifest stored.
+                let part_file_names = (0..self.num_parts)
+                    .map(|part_id| {
+                        StateFileType::StatePart { part_id, num_parts: self.num_parts }.filename()
+                    })
+                    .collect::<Vec<_>>();
+                self.external
+                    .put_listing(self.shard_id, &parts_directory_path, &part_file_names)
+                    .await?;
+                self.external
+                    .put_file(
+                        file_type.clone(),
+                        manifest.as_ref().unwrap(),
+                        self.shard_id,
+                        &location,
+                    )
+                    .await?;
+                let header_file_names =
+                    vec![StateFileType::StateHeader.filename(), file_type.filename()];
+                self.external
+                    .put_listing(self.shard_id, &directory_path, &header_file_names)
+                    .await?;
+                anyhow::Ok(false)
+            }
+            .await;
+            match result {
+                Ok(already_stored) => {
+                    tracing::info!(
+                        target: "state_sync_dump", shard_id = %self.shard_id, epoch_height = %self.epoch_height, already_stored,
+                        "Manifest saved to external storage."
+                    );
+                    return;
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        target: "state_sync_dump", shard_id = %self.shard_id, epoch_height = %self.epoch_height, ?err,
+                        "Failed to put manifest into external storage. Retrying in 5 seconds."
+                    );
+                    self.clock.sleep(Duration::seconds(5)).await;
+                }
+            }
+        }
+    }
+}
+
 impl StateDumper {
     fn new(
         clock: Clock,
         chain_id: String,
         validator: MutableValidatorSigner,
+        node_key: SecretKey,
+        compression_level: Option<i32>,
         shard_tracker: ShardTracker,
         chain: Chain,
         epoch_manager: Arc<dyn EpochManagerAdapter>,
@@ -516,6 +674,8 @@ impl StateDumper {
             clock,
             chain_id,
             validator,
+            node_key,
+            compression_level,
             shard_tracker,
             chain,
             epoch_manager,
@@ -601,6 +761,7 @@ impl StateDumper {
                 num_parts,
                 parts_dumped: Arc::new(AtomicI64::new(0)),
                 parts_missing: Arc::new(RwLock::new((0..num_parts).collect())),
+                part_entries: Default::default(),
                 upload_parts: receiver,
             },
             sender,
@@ -775,6 +936,8 @@ impl StateDumper {
                         num_parts: shard_dump.num_parts,
                         parts_dumped: shard_dump.parts_dumped.clone(),
                         parts_missing: shard_dump.parts_missing.clone(),
+                        part_entries: shard_dump.part_entries.clone(),
+                        compression_level: self.compression_level,
                         obtain_parts: self.obtain_parts.clone(),
                         canceled: dump.canceled.clone(),
                     }))
@@ -900,6 +1063,22 @@ impl StateDumper {
 
         tracing::info!(target: "state_sync_dump", epoch_id = ?&dump.epoch_id, %shard_id, "Shard dump finished");
 
+        let shard_dump = dump.dump_state.get(&shard_id).unwrap();
+        let uploader = ManifestUploader {
+            clock: self.clock.clone(),
+            external: self.external.clone(),
+            chain_id: self.chain_id.clone(),
+            epoch_id: dump.epoch_id,
+            epoch_height: dump.epoch_height,
+            shard_id,
+            state_root: shard_dump.state_root,
+            num_parts: shard_dump.num_parts,
+            part_entries: shard_dump.part_entries.clone(),
+            node_key: self.node_key.clone(),
+            canceled: dump.canceled.clone(),
+        };
+        self.future_spawner.spawn_boxed("upload_manifest", uploader.upload_manifest().boxed());
+
         self.chain
             .chain_store()
             .set_state_sync_dump_progress(
@@ -980,6 +1159,8 @@ async fn state_sync_dump(
     external: ExternalConnection,
     iteration_delay: Duration,
     validator: MutableValidatorSigner,
+    node_key: SecretKey,
+    compression_level: Option<i32>,
     keep_running: Arc<AtomicBool>,
     future_spawner: Arc<dyn FutureSpawner>,
 ) -> anyhow::Result<()> {
@@ -989,6 +1170,8 @@ async fn state_sync_dump(
         clock.clone(),
         chain_id,
         validator,
+        node_key,
+        compression_level,
         shard_tracker,
         chain,
         epoch_manager,
@@ -1036,6 +1219,8 @@ async fn do_state_sync_dump(
     external: ExternalConnection,
     iteration_delay: Duration,
     validator: MutableValidatorSigner,
+    node_key: SecretKey,
+    compression_level: Option<i32>,
     keep_running: Arc<AtomicBool>,
     future_spawner: Arc<dyn FutureSpawner>,
 ) {
@@ -1049,6 +1234,8 @@ async fn do_state_sync_dump(
         external,
         iteration_delay,
         validator,
+        node_key,
+        compression_level,
         keep_running,
         future_spawner,
     )
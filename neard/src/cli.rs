@@ -5,6 +5,7 @@ use near_chain_configs::GenesisValidationMode;
 use near_client::ConfigUpdater;
 use near_cold_store_tool::ColdStoreCommand;
 use near_config_utils::DownloadConfigType;
+use near_crypto::{KeyFile, PassphraseSource};
 use near_database_tool::commands::DatabaseCommand;
 use near_dyn_configs::{UpdateableConfigLoader, UpdateableConfigLoaderError, UpdateableConfigs};
 use near_flat_storage::commands::FlatStorageCommand;
@@ -20,6 +21,7 @@ use near_o11y::{
 use near_ping::PingCommand;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::compute_root_from_path;
+use near_primitives::signing_journal::SigningJournal;
 use near_primitives::types::{Gas, NumSeats, NumShards, ProtocolVersion, ShardId};
 use near_replay_archive_tool::ReplayArchiveCommand;
 use near_state_parts::cli::StatePartsCommand;
@@ -127,6 +129,12 @@ impl NeardCmd {
             NeardSubCommand::ValidateConfig(cmd) => {
                 cmd.run(&home_dir, genesis_validation)?;
             }
+            NeardSubCommand::KeyFile(cmd) => {
+                cmd.run(&home_dir)?;
+            }
+            NeardSubCommand::SigningJournal(cmd) => {
+                cmd.run(&home_dir)?;
+            }
             NeardSubCommand::UndoBlock(cmd) => {
                 cmd.run(&home_dir, genesis_validation)?;
             }
@@ -239,6 +247,14 @@ pub(super) enum NeardSubCommand {
     /// validate config files including genesis.json and config.json
     ValidateConfig(ValidateConfigCommand),
 
+    /// Encrypt, decrypt or change the passphrase of a key file, such as
+    /// validator_key.json or node_key.json
+    KeyFile(KeyFileCommand),
+
+    /// Export or import the signing journal, which protects a validator from
+    /// double signing, when failing over to another node
+    SigningJournal(SigningJournalCommand),
+
     /// reset the head of the chain locally to the prev block of current head
     UndoBlock(UndoBlockCommand),
 
@@ -530,6 +546,11 @@ impl RunCmd {
             broadcast::channel::<Result<UpdateableConfigs, Arc<UpdateableConfigLoaderError>>>(16);
         let sys = actix::System::new();
 
+        // The log file destination can't be changed at runtime, so it's only read at startup.
+        let log_file = nearcore::dyn_config::read_log_config(home_dir)
+            .unwrap_or_else(|e| panic!("Error reading log config: {:#}", e))
+            .and_then(|log_config| log_config.log_file);
+
         sys.block_on(async move {
             // Initialize the subscriber that takes care of both logging and tracing.
             let _subscriber_guard = default_subscriber_with_opentelemetry(
@@ -538,6 +559,7 @@ impl RunCmd {
                 near_config.client_config.chain_id.clone(),
                 near_config.network_config.node_key.public_key().clone(),
                 near_config.network_config.validator.account_id(),
+                log_file.as_ref(),
             )
             .await
             .global();
@@ -809,6 +831,147 @@ impl ValidateConfigCommand {
     }
 }
 
+/// Environment variable with the new passphrase for `key-file rotate`.
+const KEY_FILE_NEW_PASSPHRASE_ENV: &str = "NEAR_KEY_FILE_NEW_PASSPHRASE";
+/// Environment variable with a file descriptor to read the new passphrase for
+/// `key-file rotate` from.
+const KEY_FILE_NEW_PASSPHRASE_FD_ENV: &str = "NEAR_KEY_FILE_NEW_PASSPHRASE_FD";
+
+/// The passphrase is read from NEAR_KEY_FILE_PASSPHRASE, or from the file
+/// descriptor in NEAR_KEY_FILE_PASSPHRASE_FD, and asked for on the terminal
+/// otherwise. `rotate` reads the new passphrase the same way from
+/// NEAR_KEY_FILE_NEW_PASSPHRASE or NEAR_KEY_FILE_NEW_PASSPHRASE_FD.
+#[derive(clap::Parser)]
+pub(super) struct KeyFileCommand {
+    #[clap(subcommand)]
+    subcmd: KeyFileSubCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum KeyFileSubCommand {
+    /// Encrypt a plaintext key file with a passphrase
+    Encrypt(KeyFilePath),
+    /// Replace an encrypted key file with the plaintext key file
+    Decrypt(KeyFilePath),
+    /// Encrypt an encrypted key file with a new passphrase
+    Rotate(KeyFilePath),
+}
+
+#[derive(clap::Args)]
+struct KeyFilePath {
+    /// Path of the key file, relative to the home directory
+    path: PathBuf,
+}
+
+impl KeyFileCommand {
+    pub(super) fn run(self, home_dir: &Path) -> anyhow::Result<()> {
+        let (path, encrypted) = match &self.subcmd {
+            KeyFileSubCommand::Encrypt(args) => (home_dir.join(&args.path), false),
+            KeyFileSubCommand::Decrypt(args) | KeyFileSubCommand::Rotate(args) => {
+                (home_dir.join(&args.path), true)
+            }
+        };
+        if KeyFile::is_encrypted(&path)? != encrypted {
+            let state = if encrypted { "not encrypted" } else { "already encrypted" };
+            anyhow::bail!("{} is {}", path.display(), state);
+        }
+        // Node keys are read with the node key loader, which accepts the
+        // legacy format with an empty account id. It reads other key files too.
+        let key_file = nearcore::config::load_node_key(&path)?;
+        match self.subcmd {
+            KeyFileSubCommand::Encrypt(_) => {
+                let passphrase = read_new_passphrase(&PassphraseSource::from_env())?;
+                replace_key_file(&path, |tmp| key_file.write_encrypted_to_file(tmp, &passphrase))?;
+            }
+            KeyFileSubCommand::Decrypt(_) => {
+                replace_key_file(&path, |tmp| key_file.write_to_file(tmp))?;
+            }
+            KeyFileSubCommand::Rotate(_) => {
+                let passphrase = read_new_passphrase(&PassphraseSource::from_env_vars(
+                    KEY_FILE_NEW_PASSPHRASE_ENV,
+                    KEY_FILE_NEW_PASSPHRASE_FD_ENV,
+                ))?;
+                replace_key_file(&path, |tmp| key_file.write_encrypted_to_file(tmp, &passphrase))?;
+            }
+        }
+        info!(target: "neard", "Updated key file {} for {}", path.display(), key_file.account_id);
+        Ok(())
+    }
+}
+
+fn read_new_passphrase(source: &PassphraseSource) -> anyhow::Result<String> {
+    let passphrase = source.read("New passphrase: ")?;
+    if let PassphraseSource::Prompt = source {
+        if passphrase != source.read("Repeat the new passphrase: ")? {
+            anyhow::bail!("passphrases don't match");
+        }
+    }
+    if passphrase.is_empty() {
+        anyhow::bail!("passphrase must not be empty");
+    }
+    Ok(passphrase)
+}
+
+/// Writes the new key file next to the old one and renames it over the old
+/// one, so that the key isn't lost if writing fails half way.
+fn replace_key_file(
+    path: &Path,
+    write: impl FnOnce(&Path) -> std::io::Result<()>,
+) -> anyhow::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    write(&tmp_path)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[derive(clap::Parser)]
+pub(super) struct SigningJournalCommand {
+    #[clap(subcommand)]
+    subcmd: SigningJournalSubCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum SigningJournalSubCommand {
+    /// Write the entries of the journal of this node to a file. Stop the node
+    /// first, entries added after the export are not included.
+    Export {
+        #[clap(long)]
+        output: PathBuf,
+    },
+    /// Add the entries exported from another node to the journal of this node.
+    /// Run it before the validator key is loaded, the node must not be
+    /// running with the validator key.
+    Import {
+        #[clap(long)]
+        input: PathBuf,
+    },
+}
+
+impl SigningJournalCommand {
+    pub(super) fn run(self, home_dir: &Path) -> anyhow::Result<()> {
+        let config = nearcore::config::Config::from_file_skip_validation(
+            &home_dir.join(nearcore::config::CONFIG_FILENAME),
+        )?;
+        let Some(journal_file) = &config.signing_journal_file else {
+            anyhow::bail!("signing_journal_file is not set in the config");
+        };
+        let journal_path = home_dir.join(journal_file);
+        match self.subcmd {
+            SigningJournalSubCommand::Export { output } => {
+                let exported = SigningJournal::export(&journal_path, &output)?;
+                info!(target: "neard", "Exported {} entries to {}", exported, output.display());
+            }
+            SigningJournalSubCommand::Import { input } => {
+                let imported = SigningJournal::open(&journal_path)?.import(&input)?;
+                info!(target: "neard", "Imported {} entries into {}", imported, journal_path.display());
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(target_os = "linux")]
 fn normalize_whitespace(s: &str) -> String {
     s.split_whitespace().collect::<Vec<_>>().join(" ")
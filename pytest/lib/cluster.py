@@ -1015,7 +1015,7 @@ def apply_config_changes(node_dir: str,
         'save_trie_changes', 'split_storage', 'state_sync',
         'state_sync_enabled', 'store.state_snapshot_enabled',
         'store.state_snapshot_config.state_snapshot_type',
-        'tracked_shard_schedule', 'cold_store',
+        'tracked_shard_schedule', 'tracked_shard_lineage', 'cold_store',
         'store.load_mem_tries_for_tracked_shards')
 
     for k, v in client_config_change.items():
@@ -3,9 +3,11 @@
 pub mod fuzzing;
 pub mod run_test;
 pub mod scenario_builder;
+pub mod scenario_file;
 
 pub use crate::run_test::{BlockConfig, NetworkConfig, RuntimeConfig, Scenario, TransactionConfig};
 pub use crate::scenario_builder::ScenarioBuilder;
+pub use crate::scenario_file::ScenarioFile;
 
 #[test]
 // Use this test as a base for creating reproducers.
@@ -131,7 +131,7 @@ pub struct NetworkConfig {
     pub seeds: Vec<String>,
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct RuntimeConfig {
     pub max_total_prepaid_gas: Gas,
     pub gas_limit: Gas,
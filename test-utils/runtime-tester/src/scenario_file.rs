@@ -0,0 +1,529 @@
+//! Scenarios described in JSON or YAML files.
+//!
+//! A scenario file lists the accounts of the network, the blocks to produce
+//! with their transactions, the expected outcome of the transactions and
+//! assertions on the state after the last block:
+//!
+//! ```yaml
+//! accounts: [token.near, alice.near]
+//! blocks:
+//!   - transactions:
+//!       - signer: token.near
+//!         receiver: token.near
+//!         actions:
+//!           - deploy_contract: { code: { builtin: ft_contract } }
+//!           - function_call:
+//!               method_name: new_default_meta
+//!               args: { json: { owner_id: token.near, total_supply: "1000" } }
+//!         expect: success
+//!   - transactions:
+//!       - signer: alice.near
+//!         receiver: token.near
+//!         actions:
+//!           - function_call: { method_name: ft_balance_of, args: { json: { account_id: token.near } } }
+//!         expect: { success_value: { json: "1000" } }
+//! assertions:
+//!   - account: alice.near
+//!     min_balance: "999000000000000000000000000000000"
+//! ```
+//!
+//! Every signer uses the key of [`InMemorySigner::test_signer`], which all
+//! genesis accounts have. Accounts created in the scenario can sign too if
+//! they are given the key by an `add_key` action without a `public_key`.
+//!
+//! Byte strings, i.e. function call arguments, return values and storage keys
+//! and values, are given as plain text, as `{ json: <value> }` for the JSON
+//! encoding of the value or as `{ base64: "<base64>" }` for binary data.
+//! Balances are in yoctoNEAR, values that don't fit into 64 bits must be given
+//! as strings.
+//!
+//! Put scenario files into the `scenarios` directory of this crate to run
+//! them with `cargo test -p runtime-tester scenario_files`.
+use crate::run_test::{
+    BlockConfig, NetworkConfig, RuntimeConfig, RuntimeStats, Scenario, TransactionConfig,
+};
+use anyhow::Context;
+use near_chain::near_chain_primitives::error::QueryError;
+use near_crypto::{InMemorySigner, PublicKey};
+use near_primitives::account::{AccessKey, AccessKeyPermission};
+use near_primitives::hash::CryptoHash;
+use near_primitives::serialize::dec_format;
+use near_primitives::transaction::{
+    Action, AddKeyAction, CreateAccountAction, DeleteAccountAction, DeleteKeyAction,
+    DeployContractAction, FunctionCallAction, TransferAction,
+};
+use near_primitives::types::{AccountId, Balance, BlockHeightDelta, Gas};
+use near_primitives::views::{FinalExecutionStatus, QueryRequest, QueryResponseKind};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Gas attached to function calls that don't specify it.
+const DEFAULT_FUNCTION_CALL_GAS: Gas = 100 * 10u64.pow(12);
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioFile {
+    /// Accounts in genesis, the first one is the only validator.
+    #[serde(default = "default_accounts")]
+    pub accounts: Vec<AccountId>,
+    #[serde(default = "default_runtime_config")]
+    pub runtime_config: RuntimeConfig,
+    pub blocks: Vec<BlockSpec>,
+    /// Empty blocks produced after the last block, so that all receipts are
+    /// executed before the outcomes and the state are checked.
+    #[serde(default = "default_settle_blocks")]
+    pub settle_blocks: BlockHeightDelta,
+    /// Checked against the state after all blocks were produced.
+    #[serde(default)]
+    pub assertions: Vec<StateAssertion>,
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BlockSpec {
+    #[serde(default)]
+    pub transactions: Vec<TransactionSpec>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TransactionSpec {
+    pub signer: AccountId,
+    pub receiver: AccountId,
+    pub actions: Vec<ActionSpec>,
+    /// Final status of the transaction, including all its receipts. Not
+    /// checked if missing.
+    #[serde(default)]
+    pub expect: Option<ExpectedOutcome>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum ActionSpec {
+    CreateAccount,
+    DeployContract {
+        code: ContractCode,
+    },
+    FunctionCall {
+        method_name: String,
+        #[serde(default)]
+        args: Option<Bytes>,
+        #[serde(default = "default_function_call_gas")]
+        gas: Gas,
+        #[serde(default, with = "dec_format")]
+        deposit: Balance,
+    },
+    Transfer {
+        #[serde(with = "dec_format")]
+        deposit: Balance,
+    },
+    /// Adds a full access key, the key of the test signer of the receiver if
+    /// `public_key` is missing.
+    AddKey {
+        #[serde(default)]
+        public_key: Option<PublicKey>,
+    },
+    DeleteKey {
+        public_key: PublicKey,
+    },
+    DeleteAccount {
+        beneficiary_id: AccountId,
+    },
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum ContractCode {
+    /// One of the contracts of `near-test-contracts`, see [`builtin_contract`].
+    Builtin(String),
+    /// Wasm file, relative to the scenario file.
+    File(PathBuf),
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Bytes {
+    Text(String),
+    Json { json: serde_json::Value },
+    Base64 { base64: String },
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum ExpectedOutcome {
+    /// Succeeds with any return value.
+    Success,
+    SuccessValue(Bytes),
+    /// Fails with an error whose message contains the given string.
+    Failure(String),
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct StateAssertion {
+    pub account: AccountId,
+    #[serde(default = "default_exists")]
+    pub exists: bool,
+    #[serde(default, with = "dec_format")]
+    pub balance: Option<Balance>,
+    /// Balances change by the gas fees, so it's often easier to check a range.
+    #[serde(default, with = "dec_format")]
+    pub min_balance: Option<Balance>,
+    #[serde(default, with = "dec_format")]
+    pub max_balance: Option<Balance>,
+    #[serde(default)]
+    pub code_hash: Option<CryptoHash>,
+    /// Expected contract storage values by key, `null` for keys that must not
+    /// be present. Keys not listed are not checked.
+    #[serde(default)]
+    pub storage: Vec<StorageAssertion>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct StorageAssertion {
+    pub key: Bytes,
+    pub value: Option<Bytes>,
+}
+
+fn default_accounts() -> Vec<AccountId> {
+    (0..4).map(|i| format!("test{}", i).parse().unwrap()).collect()
+}
+
+fn default_runtime_config() -> RuntimeConfig {
+    RuntimeConfig {
+        max_total_prepaid_gas: 300 * 10u64.pow(12),
+        gas_limit: 1_000_000_000_000_000,
+        epoch_length: 500,
+    }
+}
+
+fn default_settle_blocks() -> BlockHeightDelta {
+    5
+}
+
+fn default_function_call_gas() -> Gas {
+    DEFAULT_FUNCTION_CALL_GAS
+}
+
+fn default_exists() -> bool {
+    true
+}
+
+/// Contracts that can be referenced by name in scenario files.
+pub fn builtin_contract(name: &str) -> Option<&'static [u8]> {
+    Some(match name {
+        "rs_contract" => near_test_contracts::rs_contract(),
+        "backwards_compatible_rs_contract" => {
+            near_test_contracts::backwards_compatible_rs_contract()
+        }
+        "ts_contract" => near_test_contracts::ts_contract(),
+        "ft_contract" => near_test_contracts::ft_contract(),
+        "trivial_contract" => near_test_contracts::trivial_contract(),
+        "smallest_rs_contract" => near_test_contracts::smallest_rs_contract(),
+        _ => return None,
+    })
+}
+
+impl Bytes {
+    fn to_vec(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(match self {
+            Self::Text(text) => text.as_bytes().to_vec(),
+            Self::Json { json } => serde_json::to_vec(json)?,
+            Self::Base64 { base64 } => near_primitives::serialize::from_base64(base64)
+                .with_context(|| format!("invalid base64 {base64:?}"))?,
+        })
+    }
+}
+
+/// Formats bytes for error messages, as text if possible.
+fn display_bytes(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => format!("{text:?}"),
+        Err(_) => format!("base64 {}", near_primitives::serialize::to_base64(bytes)),
+    }
+}
+
+impl ScenarioFile {
+    /// Loads a YAML file if the extension is `yaml` or `yml`, and a JSON file
+    /// otherwise.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let is_yaml = matches!(
+            path.extension().and_then(|extension| extension.to_str()),
+            Some("yaml" | "yml")
+        );
+        let mut scenario: Self = if is_yaml {
+            serde_yaml::from_str(&contents)?
+        } else {
+            serde_json::from_str(&contents)?
+        };
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        for block in &mut scenario.blocks {
+            for tx in &mut block.transactions {
+                for action in &mut tx.actions {
+                    if let ActionSpec::DeployContract { code: ContractCode::File(file) } = action {
+                        *file = base_dir.join(file.as_path());
+                    }
+                }
+            }
+        }
+        Ok(scenario)
+    }
+
+    /// Converts the file into a scenario. Blocks are produced at consecutive
+    /// heights starting from 1.
+    pub fn to_scenario(&self) -> anyhow::Result<Scenario> {
+        let mut blocks = vec![];
+        let mut index = 0;
+        for (height, block) in (1..).zip(&self.blocks) {
+            let mut block_config = BlockConfig::at_height(height);
+            for tx in &block.transactions {
+                index += 1;
+                // Keys added by the scenario start with the nonce of the block
+                // they were added in, so the nonce must grow with the height.
+                let nonce = (height - 1) * AccessKey::ACCESS_KEY_NONCE_RANGE_MULTIPLIER + index;
+                let actions = tx
+                    .actions
+                    .iter()
+                    .map(|action| action.to_action(&tx.receiver))
+                    .collect::<anyhow::Result<_>>()
+                    .with_context(|| format!("invalid transaction {index} at height {height}"))?;
+                block_config.transactions.push(TransactionConfig {
+                    nonce,
+                    signer_id: tx.signer.clone(),
+                    receiver_id: tx.receiver.clone(),
+                    signer: InMemorySigner::test_signer(&tx.signer),
+                    actions,
+                });
+            }
+            blocks.push(block_config);
+        }
+        let last_height = self.blocks.len() as u64;
+        for height in last_height + 1..=last_height + self.settle_blocks {
+            blocks.push(BlockConfig::at_height(height));
+        }
+        Ok(Scenario {
+            network_config: NetworkConfig {
+                seeds: self.accounts.iter().map(|account| account.to_string()).collect(),
+            },
+            runtime_config: RuntimeConfig {
+                max_total_prepaid_gas: self.runtime_config.max_total_prepaid_gas,
+                gas_limit: self.runtime_config.gas_limit,
+                epoch_length: self.runtime_config.epoch_length,
+            },
+            blocks,
+            use_in_memory_store: true,
+            is_fuzzing: false,
+        })
+    }
+
+    /// Runs the scenario and checks the expected outcomes and the assertions.
+    /// All mismatches are reported in the error.
+    pub fn run(&self) -> anyhow::Result<RuntimeStats> {
+        let mut result = self.to_scenario()?.run();
+        let stats = result.result.map_err(|err| anyhow::anyhow!("scenario failed: {err}"))?;
+        let env = &mut result.env;
+
+        let mut failures = vec![];
+        for (block, block_stats) in self.blocks.iter().zip(&stats.blocks_stats) {
+            for (tx, tx_hash) in block.transactions.iter().zip(&block_stats.tx_hashes) {
+                let Some(expected) = &tx.expect else {
+                    continue;
+                };
+                let status = match env.clients[0].chain.get_final_transaction_result(tx_hash) {
+                    Ok(outcome) => outcome.status,
+                    Err(err) => {
+                        failures.push(format!("transaction {tx_hash} has no outcome: {err}"));
+                        continue;
+                    }
+                };
+                if let Err(err) = expected.check(&status) {
+                    failures.push(format!(
+                        "transaction {tx_hash} at height {} from {} to {}: {err}",
+                        block_stats.height, tx.signer, tx.receiver
+                    ));
+                }
+            }
+        }
+        for assertion in &self.assertions {
+            if let Err(err) = assertion.check(env) {
+                failures.push(format!("account {}: {err:#}", assertion.account));
+            }
+        }
+
+        if !failures.is_empty() {
+            anyhow::bail!("{} checks failed:\n{}", failures.len(), failures.join("\n"));
+        }
+        Ok(stats)
+    }
+}
+
+impl ActionSpec {
+    fn to_action(&self, receiver_id: &AccountId) -> anyhow::Result<Action> {
+        Ok(match self {
+            Self::CreateAccount => Action::CreateAccount(CreateAccountAction {}),
+            Self::DeployContract { code } => {
+                let code = match code {
+                    ContractCode::Builtin(name) => builtin_contract(name)
+                        .with_context(|| format!("unknown builtin contract {name}"))?
+                        .to_vec(),
+                    ContractCode::File(path) => std::fs::read(path)
+                        .with_context(|| format!("failed to read {}", path.display()))?,
+                };
+                Action::DeployContract(DeployContractAction { code })
+            }
+            Self::FunctionCall { method_name, args, gas, deposit } => {
+                Action::FunctionCall(Box::new(FunctionCallAction {
+                    method_name: method_name.clone(),
+                    args: args.as_ref().map(Bytes::to_vec).transpose()?.unwrap_or_default(),
+                    gas: *gas,
+                    deposit: *deposit,
+                }))
+            }
+            Self::Transfer { deposit } => Action::Transfer(TransferAction { deposit: *deposit }),
+            Self::AddKey { public_key } => Action::AddKey(Box::new(AddKeyAction {
+                public_key: public_key
+                    .clone()
+                    .unwrap_or_else(|| InMemorySigner::test_signer(receiver_id).public_key()),
+                access_key: AccessKey { nonce: 0, permission: AccessKeyPermission::FullAccess },
+            })),
+            Self::DeleteKey { public_key } => {
+                Action::DeleteKey(Box::new(DeleteKeyAction { public_key: public_key.clone() }))
+            }
+            Self::DeleteAccount { beneficiary_id } => Action::DeleteAccount(DeleteAccountAction {
+                beneficiary_id: beneficiary_id.clone(),
+            }),
+        })
+    }
+}
+
+impl ExpectedOutcome {
+    fn check(&self, status: &FinalExecutionStatus) -> anyhow::Result<()> {
+        match (self, status) {
+            (Self::Success, FinalExecutionStatus::SuccessValue(_)) => Ok(()),
+            (Self::SuccessValue(expected), FinalExecutionStatus::SuccessValue(value)) => {
+                let expected = expected.to_vec()?;
+                anyhow::ensure!(
+                    *value == expected,
+                    "returned {} instead of {}",
+                    display_bytes(value),
+                    display_bytes(&expected)
+                );
+                Ok(())
+            }
+            (Self::Failure(message), FinalExecutionStatus::Failure(err)) => {
+                anyhow::ensure!(
+                    err.to_string().contains(message) || format!("{err:?}").contains(message),
+                    "failed with {err:?}, which doesn't contain {message:?}"
+                );
+                Ok(())
+            }
+            _ => anyhow::bail!("expected {self:?} but got {status:?}"),
+        }
+    }
+}
+
+impl StateAssertion {
+    fn check(&self, env: &mut near_client::test_utils::TestEnv) -> anyhow::Result<()> {
+        let request = QueryRequest::ViewAccount { account_id: self.account.clone() };
+        let account = match env.query_view(request) {
+            Ok(response) => match response.kind {
+                QueryResponseKind::ViewAccount(account) => account,
+                kind => anyhow::bail!("unexpected query response {kind:?}"),
+            },
+            Err(QueryError::UnknownAccount { .. }) => {
+                anyhow::ensure!(!self.exists, "doesn't exist");
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        anyhow::ensure!(self.exists, "exists");
+
+        let mut errors = vec![];
+        if let Some(balance) = self.balance {
+            if account.amount != balance {
+                errors.push(format!("balance is {} instead of {balance}", account.amount));
+            }
+        }
+        if let Some(min_balance) = self.min_balance {
+            if account.amount < min_balance {
+                errors.push(format!("balance {} is below {min_balance}", account.amount));
+            }
+        }
+        if let Some(max_balance) = self.max_balance {
+            if account.amount > max_balance {
+                errors.push(format!("balance {} is above {max_balance}", account.amount));
+            }
+        }
+        if let Some(code_hash) = self.code_hash {
+            if account.code_hash != code_hash {
+                errors.push(format!("code hash is {} instead of {code_hash}", account.code_hash));
+            }
+        }
+        if !self.storage.is_empty() {
+            let request = QueryRequest::ViewState {
+                account_id: self.account.clone(),
+                prefix: vec![].into(),
+                include_proof: false,
+            };
+            let state: BTreeMap<Vec<u8>, Vec<u8>> = match env.query_view(request)?.kind {
+                QueryResponseKind::ViewState(state) => state
+                    .values
+                    .into_iter()
+                    .map(|item| (item.key.into(), item.value.into()))
+                    .collect(),
+                kind => anyhow::bail!("unexpected query response {kind:?}"),
+            };
+            for assertion in &self.storage {
+                let key = assertion.key.to_vec()?;
+                let expected = assertion.value.as_ref().map(Bytes::to_vec).transpose()?;
+                let value = state.get(&key);
+                if value != expected.as_ref() {
+                    errors.push(format!(
+                        "value of key {} is {} instead of {}",
+                        display_bytes(&key),
+                        value.map_or("missing".to_string(), |value| display_bytes(value)),
+                        expected.map_or("missing".to_string(), |value| display_bytes(&value)),
+                    ));
+                }
+            }
+        }
+        anyhow::ensure!(errors.is_empty(), "{}", errors.join(", "));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScenarioFile;
+    use std::path::Path;
+
+    /// Runs all scenario files in the `scenarios` directory.
+    #[test]
+    fn test_scenario_files() {
+        near_o11y::testonly::init_test_logger();
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+        let mut paths: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                matches!(
+                    path.extension().and_then(|extension| extension.to_str()),
+                    Some("json" | "yaml" | "yml")
+                )
+            })
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty());
+        let mut failures = vec![];
+        for path in paths {
+            let result = ScenarioFile::from_file(&path).and_then(|scenario| scenario.run());
+            if let Err(err) = result {
+                failures.push(format!("{}: {err:#}", path.display()));
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+}
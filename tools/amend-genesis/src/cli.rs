@@ -1,5 +1,5 @@
 use near_primitives::types::NumBlocks;
-use near_primitives::types::{BlockHeightDelta, NumSeats};
+use near_primitives::types::{AccountId, BlockHeightDelta, NumSeats};
 use near_primitives::version::ProtocolVersion;
 use num_rational::Rational32;
 use std::path::PathBuf;
@@ -29,10 +29,22 @@ pub struct AmendGenesisCommand {
     /// }
     #[clap(long)]
     validators: PathBuf,
-    /// path to extra records to add to the output state. Right now only Accounts and AccessKey
-    /// records are supported, and any added accounts must have zero `code_hash`
+    /// path to extra records to add to the output state. Account, AccessKey, Contract and Data
+    /// records are supported. Contract and Data records replace the contract code or all of the
+    /// contract data of the account in the input records. Added accounts must have zero
+    /// `code_hash`, or the hash of a Contract record in this file
     #[clap(long)]
     extra_records: Option<PathBuf>,
+    /// contract to deploy in the output state, given as <ACCOUNT_ID>=<WASM_FILE>. Replaces the
+    /// current contract of the account. Can be given multiple times
+    #[clap(long, value_parser = parse_account_file)]
+    extra_contract: Vec<(AccountId, PathBuf)>,
+    /// contract data to put in the output state, given as <ACCOUNT_ID>=<JSON_FILE>. The file has
+    /// the format of the result of a `view_state` RPC query, i.e. {"values": [{"key": <BASE64>,
+    /// "value": <BASE64>}, ...]}. Replaces all of the current contract data of the account. Can
+    /// be given multiple times
+    #[clap(long, value_parser = parse_account_file)]
+    extra_contract_data: Vec<(AccountId, PathBuf)>,
     /// chain ID to set on the output genesis
     #[clap(long)]
     chain_id: Option<String>,
@@ -108,6 +120,10 @@ impl AmendGenesisCommand {
             &self.records_file_in,
             &self.records_file_out,
             self.extra_records.as_deref(),
+            &crate::ExtraContractFiles {
+                code: self.extra_contract,
+                data: self.extra_contract_data,
+            },
             &self.validators,
             self.shard_layout_file.as_deref(),
             &genesis_changes,
@@ -116,3 +132,10 @@ impl AmendGenesisCommand {
         )
     }
 }
+
+fn parse_account_file(arg: &str) -> Result<(AccountId, PathBuf), String> {
+    let (account_id, path) =
+        arg.split_once('=').ok_or_else(|| format!("expected <ACCOUNT_ID>=<FILE>, got {arg}"))?;
+    let account_id = account_id.parse().map_err(|err| format!("invalid account id: {err}"))?;
+    Ok((account_id, PathBuf::from(path)))
+}
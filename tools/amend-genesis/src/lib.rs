@@ -2,12 +2,13 @@ use anyhow::Context;
 
 use near_chain_configs::{Genesis, GenesisValidationMode, NEAR_BASE};
 use near_crypto::PublicKey;
-use near_primitives::hash::CryptoHash;
+use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::state_record::StateRecord;
-use near_primitives::types::{AccountId, AccountInfo, StorageUsage};
+use near_primitives::types::{AccountId, AccountInfo, StorageUsage, StoreKey, StoreValue};
 use near_primitives::utils;
 use near_primitives::version::ProtocolVersion;
+use near_primitives::views::ViewStateResult;
 use near_primitives_core::account::{AccessKey, Account};
 use near_primitives_core::types::{Balance, BlockHeightDelta, NumBlocks, NumSeats, NumShards};
 use near_primitives_core::version::PROTOCOL_VERSION;
@@ -16,7 +17,7 @@ use serde::ser::{SerializeSeq, Serializer};
 use std::collections::{hash_map, HashMap};
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 mod cli;
 
@@ -37,6 +38,13 @@ struct AccountRecords {
     // modifying/adding keys for, we will remember any code records (there really should only be one),
     // and add them to the output only after we write the account record
     extra_records: Vec<StateRecord>,
+    // contract code and data given in --extra-records or in the files of --extra-contract and
+    // --extra-contract-data. They replace the contract or the data of the account in the input
+    // records, if any
+    contract: Option<Vec<u8>>,
+    data: Option<Vec<(StoreKey, StoreValue)>>,
+    // the storage usage of the input records replaced by `contract` and `data`
+    replaced_storage_usage: StorageUsage,
 }
 
 // set the total balance to what's in src, keeping the locked amount the same
@@ -111,6 +119,18 @@ impl AccountRecords {
         self.extra_records.push(record);
     }
 
+    fn set_contract(&mut self, account_id: &AccountId, code: Vec<u8>) -> anyhow::Result<()> {
+        if self.contract.is_some() {
+            anyhow::bail!("contract code for {} given twice", account_id);
+        }
+        self.contract = Some(code);
+        Ok(())
+    }
+
+    fn push_data(&mut self, key: StoreKey, value: StoreValue) {
+        self.data.get_or_insert_with(Vec::new).push((key, value));
+    }
+
     fn write_out<S: SerializeSeq>(
         self,
         account_id: AccountId,
@@ -136,6 +156,19 @@ impl AccountRecords {
                         access_key,
                     })?;
                 }
+                let mut added_storage_usage = 0;
+                if let Some(code) = &self.contract {
+                    account.set_code_hash(hash(code));
+                    added_storage_usage += code.len() as u64;
+                }
+                for (key, value) in self.data.iter().flatten() {
+                    added_storage_usage +=
+                        key.len() as u64 + value.len() as u64 + num_extra_bytes_record;
+                }
+                let storage_usage = (account.storage_usage() + added_storage_usage)
+                    .checked_sub(self.replaced_storage_usage)
+                    .context("storage usage of the replaced contract records is too large")?;
+                account.set_storage_usage(storage_usage);
                 if self.amount_needed {
                     account.set_amount(10_000 * NEAR_BASE);
                 }
@@ -144,9 +177,22 @@ impl AccountRecords {
                 for record in self.extra_records.iter() {
                     seq.serialize_element(record)?;
                 }
+                if let Some(code) = self.contract {
+                    seq.serialize_element(&StateRecord::Contract {
+                        account_id: account_id.clone(),
+                        code,
+                    })?;
+                }
+                for (data_key, value) in self.data.into_iter().flatten() {
+                    seq.serialize_element(&StateRecord::Data {
+                        account_id: account_id.clone(),
+                        data_key,
+                        value,
+                    })?;
+                }
             }
             None => {
-                tracing::warn!("access keys for {} were included in --extra-records, but no Account record was found. Not adding them to the output", &account_id);
+                tracing::warn!("access keys or contracts for {} were included in --extra-records, --extra-contract or --extra-contract-data, but no Account record was found. Not adding them to the output", &account_id);
             }
         }
         Ok(())
@@ -185,15 +231,14 @@ fn parse_extra_records(
             format!("Failed opening validators file {}", records_file.display())
         })?);
     let mut records = HashMap::new();
+    let mut code_hashes = HashMap::new();
 
     let mut result = Ok(());
     near_chain_configs::stream_records_from_file(reader, |r| {
         match r {
             StateRecord::Account { account_id, account } => {
                 if account.code_hash() != CryptoHash::default() {
-                    result = Err(anyhow::anyhow!(
-                        "FIXME: accounts in --extra-records with code_hash set not supported"
-                    ));
+                    code_hashes.insert(account_id.clone(), account.code_hash());
                 }
                 match records.entry(account_id.clone()) {
                     hash_map::Entry::Vacant(e) => {
@@ -226,21 +271,74 @@ fn parse_extra_records(
             StateRecord::AccessKey { account_id, public_key, access_key } => {
                 records.entry(account_id).or_default().keys.insert(public_key, access_key);
             }
+            StateRecord::Contract { account_id, code } => {
+                let r: &mut AccountRecords = records.entry(account_id.clone()).or_default();
+                if let Err(err) = r.set_contract(&account_id, code) {
+                    result = Err(err);
+                }
+            }
+            StateRecord::Data { account_id, data_key, value } => {
+                records.entry(account_id).or_default().push_data(data_key, value);
+            }
             _ => {
                 result = Err(anyhow::anyhow!(
-                    "FIXME: only Account and AccessKey records are supported in --extra-records"
+                    "Only Account, AccessKey, Contract and Data records are supported in --extra-records"
                 ));
             }
         };
     })
     .context("Failed deserializing records from --extra-records")?;
+    result?;
+
+    for (account_id, code_hash) in code_hashes {
+        let contract = records.get(&account_id).and_then(|r| r.contract.as_ref());
+        if contract.map(|code| hash(code)) != Some(code_hash) {
+            anyhow::bail!(
+                "account {} in --extra-records has code_hash {}, but no Contract record with that code",
+                &account_id,
+                code_hash
+            );
+        }
+    }
 
     Ok(records)
 }
 
+/// Contract code and data to add to the output state, read from files.
+#[derive(Default)]
+pub struct ExtraContractFiles {
+    /// Wasm files to deploy to the accounts, replacing their current contracts.
+    pub code: Vec<(AccountId, PathBuf)>,
+    /// JSON files in the format of the `view_state` RPC query result, with base64 encoded keys
+    /// and values, replacing the current contract data of the accounts.
+    pub data: Vec<(AccountId, PathBuf)>,
+}
+
+impl ExtraContractFiles {
+    fn add_to(&self, records: &mut HashMap<AccountId, AccountRecords>) -> anyhow::Result<()> {
+        for (account_id, path) in &self.code {
+            let code = std::fs::read(path)
+                .with_context(|| format!("failed reading contract code {}", path.display()))?;
+            records.entry(account_id.clone()).or_default().set_contract(account_id, code)?;
+        }
+        for (account_id, path) in &self.data {
+            let data = std::fs::read(path)
+                .with_context(|| format!("failed reading contract data {}", path.display()))?;
+            let data: ViewStateResult = serde_json::from_slice(&data)
+                .with_context(|| format!("failed deserializing {}", path.display()))?;
+            let r = records.entry(account_id.clone()).or_default();
+            for item in data.values {
+                r.push_data(item.key, item.value);
+            }
+        }
+        Ok(())
+    }
+}
+
 fn wanted_records(
     validators: &[AccountInfo],
     extra_records: Option<&Path>,
+    extra_contract_files: &ExtraContractFiles,
     num_bytes_account: u64,
 ) -> anyhow::Result<HashMap<AccountId, AccountRecords>> {
     let mut records = validator_records(validators, num_bytes_account)?;
@@ -258,6 +356,12 @@ fn wanted_records(
                         validator_records.amount_needed = false;
                     }
                     validator_records.keys.extend(account_records.keys);
+                    if let Some(code) = account_records.contract {
+                        validator_records.contract = Some(code);
+                    }
+                    if let Some(data) = account_records.data {
+                        validator_records.data = Some(data);
+                    }
                 }
                 hash_map::Entry::Vacant(e) => {
                     e.insert(account_records);
@@ -265,6 +369,7 @@ fn wanted_records(
             }
         }
     }
+    extra_contract_files.add_to(&mut records)?;
 
     Ok(records)
 }
@@ -293,6 +398,7 @@ pub fn amend_genesis(
     records_file_in: &Path,
     records_file_out: &Path,
     extra_records: Option<&Path>,
+    extra_contract_files: &ExtraContractFiles,
     validators: &Path,
     shard_layout_file: Option<&Path>,
     genesis_changes: &GenesisChanges,
@@ -322,7 +428,8 @@ pub fn amend_genesis(
     let mut records_seq = records_ser.serialize_seq(None).unwrap();
 
     let validators = parse_validators(validators)?;
-    let mut wanted = wanted_records(&validators, extra_records, num_bytes_account)?;
+    let mut wanted =
+        wanted_records(&validators, extra_records, extra_contract_files, num_bytes_account)?;
     let mut total_supply = 0;
 
     near_chain_configs::stream_records_from_file(reader, |mut r| {
@@ -347,9 +454,25 @@ pub fn amend_genesis(
                     records_seq.serialize_element(&r).unwrap();
                 }
             }
-            StateRecord::Contract { account_id, .. } => {
+            StateRecord::Contract { account_id, code } => {
+                if let Some(records) = wanted.get_mut(account_id) {
+                    if records.contract.is_some() {
+                        records.replaced_storage_usage += code.len() as u64;
+                    } else {
+                        records.push_extra_record(r);
+                    }
+                } else {
+                    records_seq.serialize_element(&r).unwrap();
+                }
+            }
+            StateRecord::Data { account_id, data_key, value } => {
                 if let Some(records) = wanted.get_mut(account_id) {
-                    records.push_extra_record(r);
+                    if records.data.is_some() {
+                        records.replaced_storage_usage +=
+                            data_key.len() as u64 + value.len() as u64 + num_extra_bytes_record;
+                    } else {
+                        records.push_extra_record(r);
+                    }
                 } else {
                     records_seq.serialize_element(&r).unwrap();
                 }
@@ -431,7 +554,7 @@ pub fn amend_genesis(
 mod test {
     use anyhow::Context;
     use near_chain_configs::{get_initial_supply, Genesis, GenesisConfig, NEAR_BASE};
-    use near_primitives::hash::CryptoHash;
+    use near_primitives::hash::{hash, CryptoHash};
     use near_primitives::shard_layout::ShardLayout;
     use near_primitives::state_record::StateRecord;
     use near_primitives::types::{AccountId, AccountInfo};
@@ -474,11 +597,25 @@ mod test {
             account_id: &'static str,
             public_key: &'static str,
         },
+        /// Account with the code of `Contract` records deployed.
+        AccountWithContract {
+            account_id: &'static str,
+            amount: Balance,
+            locked: Balance,
+            storage_usage: StorageUsage,
+        },
         Contract {
             account_id: &'static str,
         },
+        Data {
+            account_id: &'static str,
+            key: &'static [u8],
+            value: &'static [u8],
+        },
     }
 
+    const TEST_CODE: &[u8] = &[123];
+
     impl TestStateRecord {
         fn parse(&self) -> StateRecord {
             match &self {
@@ -495,6 +632,17 @@ mod test {
                     );
                     StateRecord::Account { account_id: account_id.parse().unwrap(), account }
                 }
+                Self::AccountWithContract { account_id, amount, locked, storage_usage } => {
+                    let account = Account::new(
+                        *amount,
+                        *locked,
+                        0,
+                        hash(TEST_CODE),
+                        *storage_usage,
+                        PROTOCOL_VERSION,
+                    );
+                    StateRecord::Account { account_id: account_id.parse().unwrap(), account }
+                }
                 Self::AccessKey { account_id, public_key } => StateRecord::AccessKey {
                     account_id: account_id.parse().unwrap(),
                     public_key: public_key.parse().unwrap(),
@@ -502,7 +650,12 @@ mod test {
                 },
                 Self::Contract { account_id } => StateRecord::Contract {
                     account_id: account_id.parse().unwrap(),
-                    code: vec![123],
+                    code: TEST_CODE.to_vec(),
+                },
+                Self::Data { account_id, key, value } => StateRecord::Data {
+                    account_id: account_id.parse().unwrap(),
+                    data_key: key.to_vec().into(),
+                    value: value.to_vec().into(),
                 },
             }
         }
@@ -537,9 +690,11 @@ mod test {
         let mut got_accounts = HashMap::new();
         let mut got_keys = HashSet::new();
         let mut got_contracts = HashMap::<AccountId, usize>::new();
+        let mut got_data = HashSet::new();
         let mut wanted_accounts = HashMap::new();
         let mut wanted_keys = HashSet::new();
         let mut wanted_contracts = HashMap::<AccountId, usize>::new();
+        let mut wanted_data = HashSet::new();
 
         for r in got_records {
             match r {
@@ -577,6 +732,22 @@ mod test {
                     }
                     *got_contracts.entry(account_id).or_default() += 1;
                 }
+                StateRecord::Data { account_id, data_key, value } => {
+                    if !got_accounts.contains_key(&account_id) {
+                        anyhow::bail!(
+                            "account {} has a data state record before the account state record",
+                            &account_id
+                        );
+                    }
+                    let data_key: Vec<u8> = data_key.into();
+                    if !got_data.insert((account_id.clone(), data_key.clone(), value.to_vec())) {
+                        anyhow::bail!(
+                            "two data records in the output for {}, {:?}",
+                            &account_id,
+                            data_key
+                        );
+                    }
+                }
                 _ => anyhow::bail!("got an unexpected record in the output: {}", r),
             };
         }
@@ -599,6 +770,9 @@ mod test {
                 StateRecord::Contract { account_id, .. } => {
                     *wanted_contracts.entry(account_id).or_default() += 1;
                 }
+                StateRecord::Data { account_id, data_key, value } => {
+                    wanted_data.insert((account_id, data_key.to_vec(), value.to_vec()));
+                }
                 _ => anyhow::bail!("got an unexpected record in the output: {}", r),
             };
         }
@@ -606,6 +780,7 @@ mod test {
         assert_eq!(got_accounts, wanted_accounts);
         assert_eq!(got_keys, wanted_keys);
         assert_eq!(got_contracts, wanted_contracts);
+        assert_eq!(got_data, wanted_data);
         Ok(())
     }
 
@@ -710,6 +885,7 @@ mod test {
                 records_file_in.path(),
                 records_file_out.path(),
                 Some(extra_records_file.path()),
+                &crate::ExtraContractFiles::default(),
                 validators_file.path(),
                 None,
                 &crate::GenesisChanges::default(),
@@ -1068,6 +1244,127 @@ mod test {
                 TestStateRecord::Contract { account_id: "foo0" },
             ],
         },
+        // this one tests that account records appear before the data records of an amended account
+        TestCase {
+            initial_validators: &[TestAccountInfo {
+                account_id: "foo0",
+                public_key: "ed25519:He7QeRuwizNEhBioYG3u4DZ8jWXyETiyNzFD3MkTjDMf",
+                amount: 1_000_000,
+            }],
+            validators_in: &[TestAccountInfo {
+                account_id: "foo0",
+                public_key: "ed25519:He7QeRuwizNEhBioYG3u4DZ8jWXyETiyNzFD3MkTjDMf",
+                amount: 1_000_000,
+            }],
+            records_in: &[
+                TestStateRecord::Account {
+                    account_id: "foo0",
+                    amount: 1_000_000,
+                    locked: 1_000_000,
+                    storage_usage: 226,
+                },
+                TestStateRecord::AccessKey {
+                    account_id: "foo0",
+                    public_key: "ed25519:He7QeRuwizNEhBioYG3u4DZ8jWXyETiyNzFD3MkTjDMf",
+                },
+                TestStateRecord::Contract { account_id: "foo0" },
+                TestStateRecord::Data { account_id: "foo0", key: &[1], value: &[2, 3] },
+            ],
+            extra_records: &[TestStateRecord::Account {
+                account_id: "foo0",
+                amount: 100_000_000,
+                locked: 0,
+                storage_usage: 0,
+            }],
+            wanted_records: &[
+                TestStateRecord::Account {
+                    account_id: "foo0",
+                    amount: 99_000_000,
+                    locked: 1_000_000,
+                    storage_usage: 226,
+                },
+                TestStateRecord::AccessKey {
+                    account_id: "foo0",
+                    public_key: "ed25519:He7QeRuwizNEhBioYG3u4DZ8jWXyETiyNzFD3MkTjDMf",
+                },
+                TestStateRecord::Contract { account_id: "foo0" },
+                TestStateRecord::Data { account_id: "foo0", key: &[1], value: &[2, 3] },
+            ],
+        },
+        // this one replaces the data of an existing contract and adds an account with a contract
+        TestCase {
+            initial_validators: &[TestAccountInfo {
+                account_id: "foo0",
+                public_key: "ed25519:He7QeRuwizNEhBioYG3u4DZ8jWXyETiyNzFD3MkTjDMf",
+                amount: 1_000_000,
+            }],
+            validators_in: &[TestAccountInfo {
+                account_id: "foo0",
+                public_key: "ed25519:He7QeRuwizNEhBioYG3u4DZ8jWXyETiyNzFD3MkTjDMf",
+                amount: 1_000_000,
+            }],
+            records_in: &[
+                TestStateRecord::Account {
+                    account_id: "foo0",
+                    amount: 1_000_000,
+                    locked: 1_000_000,
+                    storage_usage: 182,
+                },
+                TestStateRecord::AccessKey {
+                    account_id: "foo0",
+                    public_key: "ed25519:He7QeRuwizNEhBioYG3u4DZ8jWXyETiyNzFD3MkTjDMf",
+                },
+                TestStateRecord::Account {
+                    account_id: "foo1",
+                    amount: 1_000_000,
+                    locked: 0,
+                    storage_usage: 300,
+                },
+                TestStateRecord::Contract { account_id: "foo1" },
+                TestStateRecord::Data { account_id: "foo1", key: &[1], value: &[2, 3] },
+            ],
+            extra_records: &[
+                TestStateRecord::Data { account_id: "foo1", key: &[4], value: &[5] },
+                TestStateRecord::AccountWithContract {
+                    account_id: "contract.near",
+                    amount: 5_000_000,
+                    locked: 0,
+                    storage_usage: 0,
+                },
+                TestStateRecord::Contract { account_id: "contract.near" },
+                TestStateRecord::Data { account_id: "contract.near", key: &[1], value: &[2] },
+            ],
+            wanted_records: &[
+                TestStateRecord::Account {
+                    account_id: "foo0",
+                    amount: 1_000_000,
+                    locked: 1_000_000,
+                    storage_usage: 182,
+                },
+                TestStateRecord::AccessKey {
+                    account_id: "foo0",
+                    public_key: "ed25519:He7QeRuwizNEhBioYG3u4DZ8jWXyETiyNzFD3MkTjDMf",
+                },
+                // the replaced data record used 1 + 2 + 40 bytes, the new one 1 + 1 + 40
+                TestStateRecord::Account {
+                    account_id: "foo1",
+                    amount: 1_000_000,
+                    locked: 0,
+                    storage_usage: 299,
+                },
+                TestStateRecord::Contract { account_id: "foo1" },
+                TestStateRecord::Data { account_id: "foo1", key: &[4], value: &[5] },
+                // 100 bytes for the account, 1 for the code and 1 + 1 + 40 for the data
+                TestStateRecord::AccountWithContract {
+                    account_id: "contract.near",
+                    amount: 5_000_000,
+                    locked: 0,
+                    storage_usage: 143,
+                },
+                TestStateRecord::Contract { account_id: "contract.near" },
+                TestStateRecord::Data { account_id: "contract.near", key: &[1], value: &[2] },
+            ],
+        },
     ];
 
     #[test]
@@ -7,11 +7,12 @@ use congestion_model::strategy::{
 };
 use congestion_model::workload::{
     AllForOneProducer, BalancedProducer, FairnessBenchmarkProducer, LinearImbalanceProducer,
-    Producer,
+    Producer, TraceProducer,
 };
 use congestion_model::{
     summary_table, CongestionStrategy, Model, ShardQueueLengths, StatsWriter, PGAS, TGAS,
 };
+use std::path::Path;
 use std::time::Duration;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::{self, Layer};
@@ -57,6 +58,12 @@ struct Args {
     /// This can be useful to look at transaction delays.
     #[clap(long, default_value_t = usize::MAX)]
     tx_pool_size: usize,
+
+    /// Path to a trace of a real chain written by
+    /// `neard view-state congestion-control trace`. Adds the "Trace" workload
+    /// which replays it.
+    #[clap(long)]
+    trace: Option<String>,
 }
 
 fn main() {
@@ -69,7 +76,7 @@ fn main() {
 
     summary_table::print_summary_header();
 
-    let workload_names = parse_workload_names(args.workload.as_ref());
+    let workload_names = parse_workload_names(args.workload.as_ref(), args.trace.is_some());
     let strategy_names = parse_strategy_names(args.strategy.as_ref());
 
     if args.write_stats_filepath.is_some()
@@ -95,6 +102,7 @@ fn main() {
                 args.warmup,
                 stats_writer,
                 args.tx_pool_size,
+                args.trace.as_deref(),
             );
         }
     }
@@ -124,9 +132,10 @@ fn run_model(
     num_warmup_rounds: usize,
     mut stats_writer: StatsWriter,
     tx_pool_size: usize,
+    trace: Option<&str>,
 ) {
     let strategy = strategy(strategy_name, num_shards);
-    let workload = workload(workload_name);
+    let workload = workload(workload_name, trace);
     let mut model = Model::new(strategy, workload);
     let mut max_queues = ShardQueueLengths::default();
 
@@ -165,7 +174,7 @@ fn normalize_cmdline_arg(value: &str) -> String {
 }
 
 // Add workloads here to simulate them with `cargo run`.
-fn workload(workload_name: &str) -> Box<dyn Producer> {
+fn workload(workload_name: &str, trace: Option<&str>) -> Box<dyn Producer> {
     match workload_name {
         "Balanced" => Box::<BalancedProducer>::default(),
         "Increasing Size" => {
@@ -189,6 +198,13 @@ fn workload(workload_name: &str) -> Box<dyn Producer> {
         "Linear Imbalance" => Box::<LinearImbalanceProducer>::default(),
         "Big Linear Imbalance" => Box::new(LinearImbalanceProducer::big_receipts()),
         "Fairness Test" => Box::<FairnessBenchmarkProducer>::default(),
+        "Trace" => {
+            let path = trace.expect("the trace workload requires --trace");
+            Box::new(
+                TraceProducer::from_file(Path::new(path))
+                    .unwrap_or_else(|err| panic!("failed to read trace {}: {}", path, err)),
+            )
+        }
         _ => panic!("unknown workload: {}", workload_name),
     }
 }
@@ -320,8 +336,8 @@ fn strategy(strategy_name: &str, num_shards: usize) -> Vec<Box<dyn CongestionStr
     result
 }
 
-fn parse_workload_names(workload_name: &str) -> Vec<String> {
-    let available: Vec<String> = vec![
+fn parse_workload_names(workload_name: &str, has_trace: bool) -> Vec<String> {
+    let mut available: Vec<String> = vec![
         "Balanced".to_string(),
         "Increasing Size".to_string(),
         "Extreme Increasing Size".to_string(),
@@ -336,6 +352,9 @@ fn parse_workload_names(workload_name: &str) -> Vec<String> {
         "Big Linear Imbalance".to_string(),
         "Fairness Test".to_string(),
     ];
+    if has_trace {
+        available.push("Trace".to_string());
+    }
 
     if workload_name == "all" {
         return available;
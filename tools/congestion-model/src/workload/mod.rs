@@ -2,6 +2,7 @@ mod all_for_one;
 mod balanced;
 mod fairness_benchmark;
 mod linear_imbalance;
+mod trace;
 mod transaction_builder;
 mod utils;
 
@@ -9,6 +10,7 @@ pub use all_for_one::AllForOneProducer;
 pub use balanced::BalancedProducer;
 pub use fairness_benchmark::FairnessBenchmarkProducer;
 pub use linear_imbalance::LinearImbalanceProducer;
+pub use trace::{Trace, TraceProducer, TraceReceipt, TraceRound, TraceTransaction};
 pub use transaction_builder::{ReceiptDefinition, ReceiptId, TransactionBuilder};
 
 use crate::{Round, ShardId};
@@ -0,0 +1,227 @@
+use std::path::Path;
+
+use crate::{GGas, ReceiptDefinition, Round, ShardId, TransactionBuilder, GGAS};
+
+use super::Producer;
+
+/// Gas units per GGas, the smallest unit of gas in the model.
+const GAS_PER_GGAS: u64 = 1_000_000_000;
+
+/// Receipts and gas usage of a range of blocks on a real chain, as written by
+/// `neard view-state congestion-control trace`.
+///
+/// Gas values are in gas units, as on chain. Shards are identified by their
+/// shard index in the shard layout of the traced blocks.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct Trace {
+    pub num_shards: usize,
+    pub start_height: u64,
+    /// One entry per block height starting at `start_height`. Heights without
+    /// a block have an empty entry.
+    pub rounds: Vec<TraceRound>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+pub struct TraceRound {
+    pub transactions: Vec<TraceTransaction>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct TraceTransaction {
+    pub sender_shard: usize,
+    pub conversion_gas: u64,
+    /// The receipts created by the transaction, transitively. The first one is
+    /// created by converting the transaction, every other receipt points to
+    /// the receipt that created it, which comes earlier in the list.
+    pub receipts: Vec<TraceReceipt>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct TraceReceipt {
+    pub predecessor: Option<usize>,
+    pub receiver_shard: usize,
+    pub size: u64,
+    pub attached_gas: u64,
+    pub execution_gas: u64,
+}
+
+/// Transaction producer that replays the transactions of a [`Trace`], one
+/// block per round.
+///
+/// Shards of the trace are mapped to the shards of the model by their index,
+/// wrapping around if the model has fewer shards. Run the model with as many
+/// shards as the trace to replay the traffic as it was. After the last block
+/// of the trace no more transactions are produced, which shows how long each
+/// strategy needs to drain its queues.
+pub struct TraceProducer {
+    trace: Trace,
+}
+
+impl Producer for TraceProducer {
+    fn init(&mut self, shards: &[ShardId]) {
+        if shards.len() != self.trace.num_shards {
+            tracing::warn!(
+                trace_shards = self.trace.num_shards,
+                model_shards = shards.len(),
+                "number of shards doesn't match the trace"
+            );
+        }
+    }
+
+    fn produce_transactions(
+        &mut self,
+        round: Round,
+        shards: &[ShardId],
+        tx_factory: &mut dyn FnMut(ShardId) -> TransactionBuilder,
+    ) -> Vec<TransactionBuilder> {
+        // Rounds of the model start at 1.
+        let trace_round =
+            round.checked_sub(1).and_then(|index| self.trace.rounds.get(index as usize));
+        let Some(trace_round) = trace_round else {
+            return vec![];
+        };
+        let shard = |index: usize| shards[index % shards.len()];
+
+        let mut out = vec![];
+        for trace_tx in &trace_round.transactions {
+            let Some((first, rest)) = trace_tx.receipts.split_first() else {
+                continue;
+            };
+            let mut tx = tx_factory(shard(trace_tx.sender_shard));
+            let mut receipt_ids = Vec::with_capacity(trace_tx.receipts.len());
+            receipt_ids.push(tx.add_first_receipt(
+                Self::receipt_definition(first, &shard),
+                to_ggas(trace_tx.conversion_gas),
+            ));
+            for receipt in rest {
+                let predecessor = receipt.predecessor.and_then(|index| receipt_ids.get(index));
+                let predecessor = *predecessor.expect("predecessor must come before the receipt");
+                receipt_ids.push(
+                    tx.new_outgoing_receipt(predecessor, Self::receipt_definition(receipt, &shard)),
+                );
+            }
+            out.push(tx);
+        }
+        out
+    }
+}
+
+impl TraceProducer {
+    pub fn new(trace: Trace) -> Self {
+        Self { trace }
+    }
+
+    pub fn from_file(path: &Path) -> std::io::Result<Self> {
+        let trace = serde_json::from_slice(&std::fs::read(path)?)?;
+        Ok(Self::new(trace))
+    }
+
+    fn receipt_definition(
+        receipt: &TraceReceipt,
+        shard: &dyn Fn(usize) -> ShardId,
+    ) -> ReceiptDefinition {
+        let execution_gas = to_ggas(receipt.execution_gas);
+        ReceiptDefinition {
+            receiver: shard(receipt.receiver_shard),
+            size: receipt.size,
+            attached_gas: to_ggas(receipt.attached_gas).max(execution_gas),
+            execution_gas,
+        }
+    }
+}
+
+/// Converts gas units to GGas, rounding up so that no receipt is free.
+fn to_ggas(gas: u64) -> GGas {
+    gas.div_ceil(GAS_PER_GGAS) * GGAS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Trace, TraceProducer, TraceReceipt, TraceRound, TraceTransaction, GAS_PER_GGAS};
+    use crate::strategy::NoQueueShard;
+    use crate::{CongestionStrategy, Model, GGAS};
+
+    fn receipt(predecessor: Option<usize>, receiver_shard: usize, gas: u64) -> TraceReceipt {
+        TraceReceipt { predecessor, receiver_shard, size: 100, attached_gas: 0, execution_gas: gas }
+    }
+
+    fn model(num_shards: usize, trace: Trace) -> Model {
+        let shards = (0..num_shards)
+            .map(|_| Box::new(NoQueueShard {}) as Box<dyn CongestionStrategy>)
+            .collect();
+        Model::new(shards, Box::new(TraceProducer::new(trace)))
+    }
+
+    #[test]
+    fn test_trace_producer() {
+        let tx = TraceTransaction {
+            sender_shard: 1,
+            conversion_gas: 1,
+            receipts: vec![
+                receipt(None, 0, 5 * GAS_PER_GGAS),
+                receipt(Some(0), 1, 1),
+                receipt(Some(0), 0, 0),
+            ],
+        };
+        let no_receipts = TraceTransaction { sender_shard: 0, conversion_gas: 1, receipts: vec![] };
+        let trace = Trace {
+            num_shards: 2,
+            start_height: 100,
+            rounds: vec![
+                TraceRound { transactions: vec![tx] },
+                TraceRound::default(),
+                TraceRound { transactions: vec![no_receipts] },
+            ],
+        };
+        let mut model = model(2, trace);
+        let shard_ids = model.shard_ids().to_vec();
+        for _ in 0..5 {
+            model.step();
+        }
+
+        // The transaction without receipts is skipped.
+        let transactions: Vec<_> = model.transactions.all_transactions().collect();
+        assert_eq!(transactions.len(), 1);
+        let tx = transactions[0];
+        assert_eq!(tx.submitted_at, 1);
+        assert_eq!(tx.sender_shard, shard_ids[1]);
+        assert_eq!(tx.initial_receipt_receiver, shard_ids[0]);
+        // Gas is rounded up to whole GGas and no less than the execution gas
+        // is attached.
+        assert_eq!(tx.tx_conversion_cost, GGAS);
+        assert_eq!(tx.initial_receipt_gas, 5 * GGAS);
+        assert_eq!(tx.outgoing[&tx.initial_receipt].len(), 2);
+        assert_eq!(tx.executed_receipts.len(), 3);
+        let receivers: Vec<_> = tx.outgoing[&tx.initial_receipt]
+            .iter()
+            .map(|id| tx.executed_receipts[id].receiver)
+            .collect();
+        assert_eq!(receivers, [shard_ids[1], shard_ids[0]]);
+        for receipt in tx.executed_receipts.values() {
+            assert_eq!(receipt.size, 100);
+            assert_eq!(receipt.attached_gas, receipt.gas_burnt());
+        }
+    }
+
+    #[test]
+    fn test_trace_producer_wraps_shards() {
+        let tx = TraceTransaction {
+            sender_shard: 3,
+            conversion_gas: 1,
+            receipts: vec![receipt(None, 2, 1)],
+        };
+        let trace = Trace {
+            num_shards: 4,
+            start_height: 0,
+            rounds: vec![TraceRound { transactions: vec![tx] }],
+        };
+        let mut model = model(2, trace);
+        let shard_ids = model.shard_ids().to_vec();
+        model.step();
+
+        let transactions: Vec<_> = model.transactions.all_transactions().collect();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].sender_shard, shard_ids[1]);
+        assert_eq!(transactions[0].initial_receipt_receiver, shard_ids[0]);
+    }
+}
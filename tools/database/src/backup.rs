@@ -0,0 +1,675 @@
+//! Online, incremental backups of the node databases.
+//!
+//! Backups are taken from a read-only RocksDB instance, so they can be created
+//! while the node is running. Each backup starts as a RocksDB checkpoint whose
+//! SST files are then moved into a directory shared by all backups. SST files
+//! are immutable, so a file already stored by an earlier backup is not stored
+//! again, which makes every backup after the first one incremental. Like
+//! RocksDB's `share_files_with_checksum`, shared files are named by their
+//! checksum and size as well, so that files with the same number from
+//! different databases don't collide.
+//!
+//! Layout of the backup directory:
+//!
+//! ```text
+//! <backup dir>/shared/{hot,cold}/<file number>_<crc32>_<size>.sst
+//! <backup dir>/<id>/manifest.json
+//! <backup dir>/<id>/{hot,cold}/<MANIFEST, OPTIONS, CURRENT and WAL files>
+//! ```
+//!
+//! The manifest is written last, a backup directory without one is incomplete
+//! and is ignored by all commands other than `purge`, which removes it.
+
+use anyhow::Context;
+use near_store::db::{Database, RocksDB};
+use near_store::metadata::{DbVersion, DB_VERSION};
+use near_store::{DBCol, Mode, Store, StoreConfig, Temperature};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use strum::IntoEnumIterator;
+
+const MANIFEST_FILENAME: &str = "manifest.json";
+const SHARED_DIR: &str = "shared";
+const SST_EXTENSION: &str = "sst";
+/// The node may delete SST files (e.g. after a compaction) while the checkpoint
+/// is being created, in which case the checkpoint fails and is retried.
+const MAX_CHECKPOINT_ATTEMPTS: usize = 5;
+
+#[derive(clap::Args)]
+pub(crate) struct BackupCommand {
+    #[clap(subcommand)]
+    subcmd: BackupSubCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum BackupSubCommand {
+    /// Create a new backup. Can be used while the node is running.
+    Create(CreateBackupCommand),
+    /// Restore a backup into the database directories configured in the home dir.
+    Restore(RestoreBackupCommand),
+    /// Check that all files of a backup are present and match their checksums.
+    Verify(VerifyBackupCommand),
+    /// List the backups stored in a backup directory.
+    List(ListBackupsCommand),
+    /// Remove old backups and the SST files no longer referenced by any backup.
+    Purge(PurgeBackupsCommand),
+}
+
+#[derive(clap::Args)]
+struct CreateBackupCommand {
+    /// Directory holding the backups.
+    #[clap(long)]
+    backup_dir: PathBuf,
+    /// Also back up the cold database of an archival node with split storage.
+    #[clap(long)]
+    include_cold: bool,
+}
+
+#[derive(clap::Args)]
+struct RestoreBackupCommand {
+    /// Directory holding the backups.
+    #[clap(long)]
+    backup_dir: PathBuf,
+    /// Id of the backup to restore. Defaults to the latest backup.
+    #[clap(long)]
+    id: Option<u64>,
+}
+
+#[derive(clap::Args)]
+struct VerifyBackupCommand {
+    /// Directory holding the backups.
+    #[clap(long)]
+    backup_dir: PathBuf,
+    /// Id of the backup to verify. Defaults to all backups.
+    #[clap(long)]
+    id: Option<u64>,
+}
+
+#[derive(clap::Args)]
+struct ListBackupsCommand {
+    /// Directory holding the backups.
+    #[clap(long)]
+    backup_dir: PathBuf,
+}
+
+#[derive(clap::Args)]
+struct PurgeBackupsCommand {
+    /// Directory holding the backups.
+    #[clap(long)]
+    backup_dir: PathBuf,
+    /// Number of most recent backups to keep.
+    #[clap(long)]
+    keep: usize,
+}
+
+impl BackupCommand {
+    pub(crate) fn run(&self, home: &Path, config: &nearcore::config::Config) -> anyhow::Result<()> {
+        let hot = DbLocation::new(home, &config.store, Temperature::Hot);
+        let cold =
+            config.cold_store.as_ref().map(|cold| DbLocation::new(home, cold, Temperature::Cold));
+        match &self.subcmd {
+            BackupSubCommand::Create(cmd) => {
+                let cold = if cmd.include_cold {
+                    Some(cold.context("--include-cold requires a configured cold store")?)
+                } else {
+                    None
+                };
+                let manifest = create_backup(&cmd.backup_dir, &hot, cold.as_ref())?;
+                println!("Created backup {} in {}", manifest.id, cmd.backup_dir.display());
+            }
+            BackupSubCommand::Restore(cmd) => {
+                let id = match cmd.id {
+                    Some(id) => id,
+                    None => *list_backups(&cmd.backup_dir)?.last().context("no backups found")?,
+                };
+                restore_backup(&cmd.backup_dir, id, &hot, cold.as_ref())?;
+                println!("Restored backup {id} into {}", hot.path.display());
+            }
+            BackupSubCommand::Verify(cmd) => {
+                let ids = match cmd.id {
+                    Some(id) => vec![id],
+                    None => list_backups(&cmd.backup_dir)?,
+                };
+                let mut failed = false;
+                for id in ids {
+                    let problems =
+                        verify_backup(&cmd.backup_dir, &read_manifest(&cmd.backup_dir, id)?);
+                    if problems.is_empty() {
+                        println!("Backup {id}: OK");
+                    } else {
+                        failed = true;
+                        println!("Backup {id}: {} problem(s)", problems.len());
+                        for problem in problems {
+                            println!("  {problem}");
+                        }
+                    }
+                }
+                anyhow::ensure!(!failed, "some backups failed verification");
+            }
+            BackupSubCommand::List(cmd) => {
+                for id in list_backups(&cmd.backup_dir)? {
+                    let manifest = read_manifest(&cmd.backup_dir, id)?;
+                    let size: u64 =
+                        manifest.databases().flat_map(|db| &db.files).map(|f| f.size).sum();
+                    println!(
+                        "{id}\tcreated_at={}\tdb_version={}\tcold={}\tsize={}",
+                        manifest.created_at_unix_secs,
+                        manifest.db_version,
+                        manifest.cold.is_some(),
+                        bytesize::ByteSize::b(size),
+                    );
+                }
+            }
+            BackupSubCommand::Purge(cmd) => purge_backups(&cmd.backup_dir, cmd.keep)?,
+        }
+        Ok(())
+    }
+}
+
+/// Location and configuration of one of the node databases.
+struct DbLocation<'a> {
+    path: PathBuf,
+    config: &'a StoreConfig,
+    temperature: Temperature,
+}
+
+impl<'a> DbLocation<'a> {
+    /// Resolves the database path the same way the store opener does.
+    fn new(home: &Path, config: &'a StoreConfig, temperature: Temperature) -> Self {
+        let default_path = match temperature {
+            Temperature::Hot => "data",
+            Temperature::Cold => "cold-data",
+        };
+        let path = home.join(config.path.as_deref().unwrap_or_else(|| Path::new(default_path)));
+        Self { path, config, temperature }
+    }
+
+    fn open(&self, mode: Mode) -> anyhow::Result<RocksDB> {
+        RocksDB::open(&self.path, self.config, mode, self.temperature)
+            .with_context(|| format!("failed to open database at {}", self.path.display()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BackupManifest {
+    id: u64,
+    created_at_unix_secs: u64,
+    db_version: DbVersion,
+    hot: DatabaseManifest,
+    cold: Option<DatabaseManifest>,
+}
+
+impl BackupManifest {
+    fn databases(&self) -> impl Iterator<Item = &DatabaseManifest> {
+        std::iter::once(&self.hot).chain(self.cold.as_ref())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct DatabaseManifest {
+    /// `DbKind` of the database at the time of the backup.
+    kind: Option<String>,
+    /// Columns the database was configured with.
+    columns: Vec<String>,
+    files: Vec<BackupFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BackupFile {
+    /// Name of the file in the database directory.
+    name: String,
+    size: u64,
+    /// CRC32 of the file contents.
+    checksum: u32,
+    /// Whether the file is stored in the shared directory.
+    shared: bool,
+}
+
+fn temperature_dir(temperature: Temperature) -> &'static str {
+    match temperature {
+        Temperature::Hot => "hot",
+        Temperature::Cold => "cold",
+    }
+}
+
+fn backup_path(backup_dir: &Path, id: u64) -> PathBuf {
+    backup_dir.join(id.to_string())
+}
+
+fn shared_dir(backup_dir: &Path, temperature: Temperature) -> PathBuf {
+    backup_dir.join(SHARED_DIR).join(temperature_dir(temperature))
+}
+
+/// Returns the path a backed-up file is stored at.
+fn stored_file_path(
+    backup_dir: &Path,
+    id: u64,
+    temperature: Temperature,
+    file: &BackupFile,
+) -> PathBuf {
+    if file.shared {
+        shared_dir(backup_dir, temperature).join(shared_file_name(
+            &file.name,
+            file.checksum,
+            file.size,
+        ))
+    } else {
+        backup_path(backup_dir, id).join(temperature_dir(temperature)).join(&file.name)
+    }
+}
+
+/// SST file numbers are only unique within a single database, the checksum
+/// and size tell different files with the same number apart.
+fn shared_file_name(name: &str, checksum: u32, size: u64) -> String {
+    let stem = name.strip_suffix(&format!(".{SST_EXTENSION}")).unwrap_or(name);
+    format!("{stem}_{checksum}_{size}.{SST_EXTENSION}")
+}
+
+fn file_checksum(path: &Path) -> std::io::Result<u32> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0; 1 << 20];
+    loop {
+        let len = file.read(&mut buf)?;
+        if len == 0 {
+            return Ok(hasher.finalize());
+        }
+        hasher.update(&buf[..len]);
+    }
+}
+
+/// Returns ids of all complete backups in ascending order.
+fn list_backups(backup_dir: &Path) -> anyhow::Result<Vec<u64>> {
+    let mut ids = Vec::new();
+    for entry in std::fs::read_dir(backup_dir)
+        .with_context(|| format!("failed to read backup dir {}", backup_dir.display()))?
+    {
+        let entry = entry?;
+        let Some(id) = entry.file_name().to_str().and_then(|name| name.parse::<u64>().ok()) else {
+            continue;
+        };
+        if entry.path().join(MANIFEST_FILENAME).exists() {
+            ids.push(id);
+        }
+    }
+    ids.sort();
+    Ok(ids)
+}
+
+fn read_manifest(backup_dir: &Path, id: u64) -> anyhow::Result<BackupManifest> {
+    let path = backup_path(backup_dir, id).join(MANIFEST_FILENAME);
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read backup manifest {}", path.display()))?;
+    serde_json::from_str(&contents)
+        .with_context(|| format!("failed to parse backup manifest {}", path.display()))
+}
+
+fn write_manifest(backup_dir: &Path, manifest: &BackupManifest) -> anyhow::Result<()> {
+    let dir = backup_path(backup_dir, manifest.id);
+    let tmp_path = dir.join(format!("{MANIFEST_FILENAME}.tmp"));
+    std::fs::write(&tmp_path, serde_json::to_string_pretty(manifest)?)?;
+    std::fs::rename(&tmp_path, dir.join(MANIFEST_FILENAME))?;
+    Ok(())
+}
+
+fn column_names() -> Vec<String> {
+    DBCol::iter().map(|col| <&str>::from(col).to_string()).collect()
+}
+
+fn create_backup(
+    backup_dir: &Path,
+    hot: &DbLocation,
+    cold: Option<&DbLocation>,
+) -> anyhow::Result<BackupManifest> {
+    std::fs::create_dir_all(backup_dir)?;
+    // Incomplete backups don't have a manifest, don't reuse their ids either.
+    let id = std::fs::read_dir(backup_dir)?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u64>().ok())
+        .max()
+        .map_or(1, |id| id + 1);
+    std::fs::create_dir(backup_path(backup_dir, id))?;
+    tracing::info!(target: "backup", id, backup_dir = %backup_dir.display(), "Creating backup");
+
+    let (db_version, hot_manifest) = backup_database(backup_dir, id, hot)?;
+    let cold_manifest = match cold {
+        Some(cold) => {
+            let (cold_version, manifest) = backup_database(backup_dir, id, cold)?;
+            anyhow::ensure!(
+                cold_version == db_version,
+                "hot and cold databases have different versions: {db_version} and {cold_version}"
+            );
+            Some(manifest)
+        }
+        None => None,
+    };
+
+    let manifest = BackupManifest {
+        id,
+        created_at_unix_secs: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs(),
+        db_version,
+        hot: hot_manifest,
+        cold: cold_manifest,
+    };
+    write_manifest(backup_dir, &manifest)?;
+    Ok(manifest)
+}
+
+fn backup_database(
+    backup_dir: &Path,
+    id: u64,
+    location: &DbLocation,
+) -> anyhow::Result<(DbVersion, DatabaseManifest)> {
+    let temperature = location.temperature;
+    let staging =
+        backup_path(backup_dir, id).join(format!(".staging-{}", temperature_dir(temperature)));
+
+    let mut attempt = 0;
+    let (version, kind) = loop {
+        attempt += 1;
+        match checkpoint_database(location, &staging) {
+            Ok(metadata) => break metadata,
+            Err(err) if attempt < MAX_CHECKPOINT_ATTEMPTS => {
+                tracing::warn!(target: "backup", ?err, attempt, "Failed to create checkpoint, retrying");
+                if staging.exists() {
+                    std::fs::remove_dir_all(&staging)?;
+                }
+            }
+            Err(err) => return Err(err),
+        }
+    };
+
+    let shared = shared_dir(backup_dir, temperature);
+    let private = backup_path(backup_dir, id).join(temperature_dir(temperature));
+    std::fs::create_dir_all(&shared)?;
+    std::fs::create_dir_all(&private)?;
+
+    let mut files = Vec::new();
+    let mut new_shared_bytes = 0;
+    for entry in std::fs::read_dir(&staging)? {
+        let entry = entry?;
+        let name = entry.file_name().into_string().map_err(|name| {
+            anyhow::anyhow!("unexpected file name in checkpoint: {}", name.to_string_lossy())
+        })?;
+        let size = entry.metadata()?.len();
+        let checksum = file_checksum(&entry.path())?;
+        let is_sst = entry.path().extension().is_some_and(|ext| ext == SST_EXTENSION);
+        if is_sst {
+            let shared_path = shared.join(shared_file_name(&name, checksum, size));
+            if shared_path.exists() {
+                std::fs::remove_file(entry.path())?;
+            } else {
+                std::fs::rename(entry.path(), &shared_path)?;
+                new_shared_bytes += size;
+            }
+        } else {
+            std::fs::rename(entry.path(), private.join(&name))?;
+        }
+        files.push(BackupFile { name, size, checksum, shared: is_sst });
+    }
+    std::fs::remove_dir(&staging)?;
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+
+    tracing::info!(
+        target: "backup",
+        ?temperature,
+        num_files = files.len(),
+        new_shared_bytes,
+        "Backed up database"
+    );
+    Ok((version, DatabaseManifest { kind, columns: column_names(), files }))
+}
+
+/// Creates a RocksDB checkpoint of a database from a read-only instance, so
+/// that this works alongside a running node. Returns the version and kind of
+/// the database.
+fn checkpoint_database(
+    location: &DbLocation,
+    checkpoint_path: &Path,
+) -> anyhow::Result<(DbVersion, Option<String>)> {
+    let db = Arc::new(location.open(Mode::ReadOnly)?);
+    let store = Store::new(db.clone());
+    let version = store.get_db_version()?.context("database has no version")?;
+    let kind = store.get_db_kind()?.map(|kind| kind.to_string());
+    db.create_checkpoint(checkpoint_path, None)?;
+    Ok((version, kind))
+}
+
+/// Returns a list of problems found in the backup, empty if the backup is intact.
+fn verify_backup(backup_dir: &Path, manifest: &BackupManifest) -> Vec<String> {
+    let mut problems = Vec::new();
+    let databases =
+        [(Temperature::Hot, Some(&manifest.hot)), (Temperature::Cold, manifest.cold.as_ref())];
+    for (temperature, database) in databases {
+        let Some(database) = database else { continue };
+        for file in &database.files {
+            let path = stored_file_path(backup_dir, manifest.id, temperature, file);
+            match std::fs::metadata(&path) {
+                Ok(metadata) if metadata.len() != file.size => {
+                    problems.push(format!(
+                        "{}: expected {} bytes, found {}",
+                        path.display(),
+                        file.size,
+                        metadata.len()
+                    ));
+                    continue;
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    problems.push(format!("{}: {err}", path.display()));
+                    continue;
+                }
+            }
+            match file_checksum(&path) {
+                Ok(checksum) if checksum == file.checksum => {}
+                Ok(checksum) => problems.push(format!(
+                    "{}: expected checksum {}, found {checksum}",
+                    path.display(),
+                    file.checksum
+                )),
+                Err(err) => problems.push(format!("{}: {err}", path.display())),
+            }
+        }
+    }
+    problems
+}
+
+/// Checks that the backup can be opened by this binary.
+fn validate_compatibility(manifest: &BackupManifest) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        manifest.db_version == DB_VERSION,
+        "backup {} has DbVersion {} but this binary expects {DB_VERSION}",
+        manifest.id,
+        manifest.db_version,
+    );
+    let expected: HashSet<String> = column_names().into_iter().collect();
+    for database in manifest.databases() {
+        let actual: HashSet<String> = database.columns.iter().cloned().collect();
+        anyhow::ensure!(
+            actual == expected,
+            "backup {} columns don't match this binary; missing: {:?}, unknown: {:?}",
+            manifest.id,
+            expected.difference(&actual).collect::<Vec<_>>(),
+            actual.difference(&expected).collect::<Vec<_>>(),
+        );
+    }
+    Ok(())
+}
+
+fn restore_backup(
+    backup_dir: &Path,
+    id: u64,
+    hot: &DbLocation,
+    cold: Option<&DbLocation>,
+) -> anyhow::Result<()> {
+    let manifest = read_manifest(backup_dir, id)?;
+    validate_compatibility(&manifest)?;
+    let problems = verify_backup(backup_dir, &manifest);
+    anyhow::ensure!(problems.is_empty(), "backup {id} is damaged: {problems:?}");
+
+    let targets = match (&manifest.cold, cold) {
+        (None, _) => vec![(&manifest.hot, hot)],
+        (Some(cold_manifest), Some(cold)) => vec![(&manifest.hot, hot), (cold_manifest, cold)],
+        (Some(_), None) => {
+            anyhow::bail!("backup {id} contains a cold database but no cold store is configured")
+        }
+    };
+    for (_, location) in &targets {
+        let is_empty = match std::fs::read_dir(&location.path) {
+            Ok(mut entries) => entries.next().is_none(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => true,
+            Err(err) => return Err(err.into()),
+        };
+        anyhow::ensure!(
+            is_empty,
+            "refusing to restore into non-empty directory {}",
+            location.path.display()
+        );
+    }
+
+    for (database, location) in targets {
+        std::fs::create_dir_all(&location.path)?;
+        for file in &database.files {
+            let source = stored_file_path(backup_dir, id, location.temperature, file);
+            let target = location.path.join(&file.name);
+            if file.shared {
+                // SST files are never modified in place, so the restored
+                // database can share them with the backup.
+                if std::fs::hard_link(&source, &target).is_err() {
+                    std::fs::copy(&source, &target)?;
+                }
+            } else {
+                // MANIFEST and WAL files are appended to, they must be copied.
+                std::fs::copy(&source, &target)?;
+            }
+        }
+
+        let store = Store::new(Arc::new(location.open(Mode::ReadOnly)?));
+        let version = store.get_db_version()?;
+        let kind = store.get_db_kind()?.map(|kind| kind.to_string());
+        anyhow::ensure!(
+            version == Some(manifest.db_version) && kind == database.kind,
+            "restored database at {} has version {version:?} and kind {kind:?}, expected {} and {:?}",
+            location.path.display(),
+            manifest.db_version,
+            database.kind,
+        );
+    }
+    Ok(())
+}
+
+fn purge_backups(backup_dir: &Path, keep: usize) -> anyhow::Result<()> {
+    let ids = list_backups(backup_dir)?;
+    let keep_ids: HashSet<u64> = ids.iter().rev().take(keep).copied().collect();
+
+    // Remove old backups as well as incomplete ones.
+    for entry in std::fs::read_dir(backup_dir)? {
+        let entry = entry?;
+        let Some(id) = entry.file_name().to_str().and_then(|name| name.parse::<u64>().ok()) else {
+            continue;
+        };
+        if !keep_ids.contains(&id) {
+            println!("Removing backup {id}");
+            std::fs::remove_dir_all(entry.path())?;
+        }
+    }
+
+    let mut referenced = HashSet::new();
+    for id in &keep_ids {
+        let manifest = read_manifest(backup_dir, *id)?;
+        let databases =
+            [(Temperature::Hot, Some(&manifest.hot)), (Temperature::Cold, manifest.cold.as_ref())];
+        for (temperature, database) in databases {
+            let Some(database) = database else { continue };
+            for file in database.files.iter().filter(|file| file.shared) {
+                referenced.insert(stored_file_path(backup_dir, *id, temperature, file));
+            }
+        }
+    }
+    let mut removed_bytes = 0;
+    for temperature in [Temperature::Hot, Temperature::Cold] {
+        let dir = shared_dir(backup_dir, temperature);
+        if !dir.exists() {
+            continue;
+        }
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            if !referenced.contains(&entry.path()) {
+                removed_bytes += entry.metadata()?.len();
+                std::fs::remove_file(entry.path())?;
+            }
+        }
+    }
+    println!("Removed {} of unreferenced files", bytesize::ByteSize::b(removed_bytes));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        create_backup, list_backups, restore_backup, stored_file_path, verify_backup, DbLocation,
+    };
+    use near_store::{DBCol, Mode, NodeStorage, StoreConfig, Temperature};
+
+    /// Populates a DB, makes two backups with a change in between, and checks
+    /// that the first backup restores the state before the change.
+    #[test]
+    fn test_backup_and_restore() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let backup_dir = home_dir.path().join("backups");
+        let store_config = StoreConfig::test_config();
+        let opener = NodeStorage::opener(home_dir.path(), &store_config, None);
+        let location = DbLocation::new(home_dir.path(), &store_config, Temperature::Hot);
+
+        let keys = vec![vec![0], vec![1], vec![2], vec![3]];
+        {
+            let node_storage = opener.open().unwrap();
+            let mut store_update = node_storage.get_hot_store().store_update();
+            for key in &keys {
+                store_update.insert(DBCol::Block, key.clone(), vec![42]);
+            }
+            store_update.commit().unwrap();
+            // Write an SST file, so that the backups have a shared file.
+            node_storage.get_hot_store().flush().unwrap();
+        }
+        let first = create_backup(&backup_dir, &location, None).unwrap();
+
+        {
+            let node_storage = opener.open().unwrap();
+            let mut store_update = node_storage.get_hot_store().store_update();
+            store_update.delete_all(DBCol::Block);
+            store_update.commit().unwrap();
+        }
+        let second = create_backup(&backup_dir, &location, None).unwrap();
+        assert_eq!(list_backups(&backup_dir).unwrap(), vec![first.id, second.id]);
+        assert!(verify_backup(&backup_dir, &first).is_empty());
+        assert!(verify_backup(&backup_dir, &second).is_empty());
+
+        let restore_home = tempfile::tempdir().unwrap();
+        let restore_location =
+            DbLocation::new(restore_home.path(), &store_config, Temperature::Hot);
+        restore_backup(&backup_dir, first.id, &restore_location, None).unwrap();
+
+        let restored = NodeStorage::opener(restore_home.path(), &store_config, None)
+            .open_in_mode(Mode::ReadOnly)
+            .unwrap();
+        for key in keys {
+            assert!(restored.get_hot_store().exists(DBCol::Block, &key).unwrap());
+        }
+
+        // A shared file with the right size but different contents is detected.
+        let file = second.hot.files.iter().find(|file| file.shared).unwrap();
+        let path = stored_file_path(&backup_dir, second.id, Temperature::Hot, file);
+        let mut contents = std::fs::read(&path).unwrap();
+        contents[0] ^= 1;
+        std::fs::remove_file(&path).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        let problems = verify_backup(&backup_dir, &second);
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(problems[0].contains("checksum"));
+    }
+}
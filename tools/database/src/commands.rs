@@ -4,12 +4,15 @@ use crate::analyse_gas_usage::AnalyseGasUsageCommand;
 use crate::analyse_high_load::HighLoadStatsCommand;
 use crate::analyze_contract_sizes::AnalyzeContractSizesCommand;
 use crate::analyze_delayed_receipt::AnalyzeDelayedReceiptCommand;
+use crate::backup::BackupCommand;
 use crate::compact::RunCompactionCommand;
 use crate::corrupt::CorruptStateSnapshotCommand;
+use crate::epoch_sync_proof::EpochSyncProofCommand;
 use crate::make_snapshot::MakeSnapshotCommand;
 use crate::memtrie::LoadMemTrieCommand;
 use crate::resharding_v2::ReshardingV2Command;
 use crate::run_migrations::RunMigrationsCommand;
+use crate::scrub::ScrubCommand;
 use crate::state_perf::StatePerfCommand;
 use crate::write_to_db::WriteCryptoHashCommand;
 use clap::Parser;
@@ -31,6 +34,10 @@ enum SubCommand {
     /// Analyse gas usage in a chosen sequnce of blocks
     AnalyseGasUsage(AnalyseGasUsageCommand),
 
+    /// Create, verify and restore incremental backups of the database.
+    /// Backups can be created while the node is running.
+    Backup(BackupCommand),
+
     /// Change DbKind of hot or cold db.
     ChangeDbKind(ChangeDbKindCommand),
 
@@ -40,12 +47,19 @@ enum SubCommand {
     /// Corrupt the state snapshot.
     CorruptStateSnapshot(CorruptStateSnapshotCommand),
 
+    /// Export the epoch sync proof to a file, or bootstrap a fresh node from one.
+    EpochSyncProof(EpochSyncProofCommand),
+
     /// Make snapshot of the database
     MakeSnapshot(MakeSnapshotCommand),
 
     /// Run migrations
     RunMigrations(RunMigrationsCommand),
 
+    /// Check the integrity of the database and suggest repairs.
+    /// The database is opened read-only, so this can run next to a running node.
+    Scrub(ScrubCommand),
+
     /// Run performance test for State column reads.
     /// Uses RocksDB data specified via --home argument.
     StatePerf(StatePerfCommand),
@@ -75,14 +89,20 @@ impl DatabaseCommand {
         match &self.subcmd {
             SubCommand::AnalyseDataSizeDistribution(cmd) => cmd.run(home),
             SubCommand::AnalyseGasUsage(cmd) => cmd.run(home, genesis_validation),
+            SubCommand::Backup(cmd) => {
+                let near_config = load_config(home, genesis_validation);
+                cmd.run(home, &near_config.config)
+            }
             SubCommand::ChangeDbKind(cmd) => cmd.run(home, genesis_validation),
             SubCommand::CompactDatabase(cmd) => cmd.run(home),
             SubCommand::CorruptStateSnapshot(cmd) => cmd.run(home),
+            SubCommand::EpochSyncProof(cmd) => cmd.run(home, genesis_validation),
             SubCommand::MakeSnapshot(cmd) => {
                 let near_config = load_config(home, genesis_validation);
                 cmd.run(home, &near_config.config.store, near_config.config.archival_config())
             }
             SubCommand::RunMigrations(cmd) => cmd.run(home, genesis_validation),
+            SubCommand::Scrub(cmd) => cmd.run(home, genesis_validation),
             SubCommand::StatePerf(cmd) => cmd.run(home),
             SubCommand::LoadMemTrie(cmd) => cmd.run(home, genesis_validation),
             SubCommand::WriteCryptoHash(cmd) => cmd.run(home, genesis_validation),
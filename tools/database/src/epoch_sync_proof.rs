@@ -0,0 +1,256 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use borsh::BorshDeserialize;
+use near_async::messaging::{noop, IntoMultiSender};
+use near_async::time::Clock;
+use near_chain::rayon_spawner::RayonAsyncComputationSpawner;
+use near_chain::types::ChainConfig;
+use near_chain::{Chain, ChainGenesis, ChainStoreAccess, DoomslugThresholdMode};
+use near_chain_configs::{GenesisValidationMode, MutableConfigValue};
+use near_client::sync::epoch::EpochSync;
+use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
+use near_epoch_manager::{EpochManager, EpochManagerAdapter};
+use near_primitives::epoch_sync::CompressedEpochSyncProof;
+use near_primitives::types::{BlockHeightDelta, EpochId};
+use near_primitives::utils::compression::CompressedData;
+use near_store::genesis::initialize_sharded_genesis_state;
+use near_store::{Mode, NodeStorage, Store};
+use nearcore::{open_storage, NearConfig, NightshadeRuntime, NightshadeRuntimeExt};
+
+/// Exports the epoch sync proof of a synced node to a file, or bootstraps a
+/// fresh node from such a file instead of requesting the proof from peers.
+#[derive(clap::Args)]
+pub(crate) struct EpochSyncProofCommand {
+    #[clap(subcommand)]
+    subcmd: EpochSyncProofSubCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum EpochSyncProofSubCommand {
+    /// Derive the epoch sync proof for the current epoch and write it to a file.
+    /// The database is opened read-only, so this can run next to a running node.
+    Export(ExportCmd),
+    /// Verify the proof in the file and bootstrap the node from it. The node
+    /// must be at genesis and must not be running.
+    Import(ImportCmd),
+}
+
+#[derive(clap::Args)]
+struct ExportCmd {
+    /// File to write the compressed proof to.
+    #[clap(long)]
+    output: PathBuf,
+}
+
+#[derive(clap::Args)]
+struct ImportCmd {
+    /// File with a proof written by `export`.
+    #[clap(long)]
+    input: PathBuf,
+}
+
+impl EpochSyncProofCommand {
+    pub(crate) fn run(
+        &self,
+        home: &Path,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        let near_config = nearcore::config::load_config(home, genesis_validation)
+            .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
+        match &self.subcmd {
+            EpochSyncProofSubCommand::Export(cmd) => cmd.run(home, &near_config),
+            EpochSyncProofSubCommand::Import(cmd) => cmd.run(home, near_config),
+        }
+    }
+}
+
+impl ExportCmd {
+    fn run(&self, home: &Path, near_config: &NearConfig) -> anyhow::Result<()> {
+        let storage = NodeStorage::opener(
+            home,
+            &near_config.config.store,
+            near_config.config.archival_config(),
+        )
+        .open_in_mode(Mode::ReadOnly)?;
+        let proof = export_proof(
+            storage.get_hot_store(),
+            near_config.genesis.config.transaction_validity_period,
+            &self.output,
+        )?;
+        let (decoded, _) = proof.decode()?;
+        let current_epoch = decoded.into_v1().current_epoch;
+        println!(
+            "Wrote epoch sync proof for epoch {:?} starting at height {} ({} bytes) to {}",
+            current_epoch.first_block_header_in_epoch.epoch_id(),
+            current_epoch.first_block_header_in_epoch.height(),
+            proof.size_bytes(),
+            self.output.display()
+        );
+        Ok(())
+    }
+}
+
+impl ImportCmd {
+    fn run(&self, home: &Path, mut near_config: NearConfig) -> anyhow::Result<()> {
+        let store = open_storage(home, &mut near_config)?.get_hot_store();
+        let epoch_manager =
+            EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config, Some(home));
+        let genesis_epoch_config = epoch_manager.get_epoch_config(&EpochId::default())?;
+        initialize_sharded_genesis_state(
+            store.clone(),
+            &near_config.genesis,
+            &genesis_epoch_config,
+            Some(home),
+        );
+        let shard_tracker = ShardTracker::new(
+            TrackedConfig::from_config(&near_config.client_config),
+            epoch_manager.clone(),
+        );
+        let runtime_adapter =
+            NightshadeRuntime::from_config(home, store, &near_config, epoch_manager.clone())?;
+        let client_config = &near_config.client_config;
+        let chain_config = ChainConfig {
+            save_trie_changes: client_config.save_trie_changes,
+            background_migration_threads: client_config.client_background_migration_threads,
+            resharding_config: client_config.resharding_config.clone(),
+        };
+        let mut chain = Chain::new(
+            Clock::real(),
+            epoch_manager.clone(),
+            shard_tracker,
+            runtime_adapter,
+            &ChainGenesis::new(&near_config.genesis.config),
+            DoomslugThresholdMode::TwoThirds,
+            chain_config,
+            None,
+            Arc::new(RayonAsyncComputationSpawner),
+            MutableConfigValue::new(None, "validator_signer"),
+            // Resharding sender is not used when importing a proof.
+            noop().into_multi_sender(),
+        )?;
+
+        import_proof(&mut chain, epoch_manager.as_ref(), &self.input)?;
+        let header_head = chain.chain_store().header_head()?;
+        println!(
+            "Bootstrapped from epoch sync proof, header head is {} at height {}",
+            header_head.last_block_hash, header_head.height
+        );
+        Ok(())
+    }
+}
+
+/// Derives the epoch sync proof of the current epoch and writes it to `output`.
+fn export_proof(
+    store: Store,
+    transaction_validity_period: BlockHeightDelta,
+    output: &Path,
+) -> anyhow::Result<CompressedEpochSyncProof> {
+    let proof = EpochSync::derive_epoch_sync_proof(
+        store,
+        transaction_validity_period,
+        Arc::new(Mutex::new(None)),
+    )?;
+    std::fs::write(output, borsh::to_vec(&proof)?)
+        .with_context(|| format!("failed to write {}", output.display()))?;
+    Ok(proof)
+}
+
+/// Reads a proof written by `export_proof` from `input`, verifies it and
+/// bootstraps the chain from it.
+fn import_proof(
+    chain: &mut Chain,
+    epoch_manager: &dyn EpochManagerAdapter,
+    input: &Path,
+) -> anyhow::Result<()> {
+    let data =
+        std::fs::read(input).with_context(|| format!("failed to read {}", input.display()))?;
+    let proof = CompressedEpochSyncProof::try_from_slice(&data)
+        .context("failed to parse epoch sync proof")?;
+    let (proof, _) = proof.decode().context("failed to decompress epoch sync proof")?;
+    EpochSync::import_proof(chain, proof, epoch_manager)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use near_chain::ChainStoreAccess;
+    use near_chain_configs::Genesis;
+    use near_client::test_utils::TestEnv;
+    use near_primitives::epoch_sync::{CompressedEpochSyncProof, EpochSyncProof};
+    use near_primitives::utils::compression::CompressedData;
+    use nearcore::test_utils::TestEnvNightshadeSetupExt;
+
+    use super::{export_proof, import_proof};
+
+    /// Returns an environment where the first client produced a few epochs
+    /// and the second one is still at genesis.
+    fn setup() -> (Genesis, TestEnv) {
+        let mut genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+        genesis.config.epoch_length = 5;
+        genesis.config.transaction_validity_period = 5;
+        let mut env = TestEnv::builder(&genesis.config)
+            .clients_count(2)
+            .validator_seats(1)
+            .nightshade_runtimes(&genesis)
+            .build();
+        for height in 1..=40 {
+            env.produce_block(0, height);
+        }
+        (genesis, env)
+    }
+
+    fn export(genesis: &Genesis, env: &TestEnv, output: &std::path::Path) -> EpochSyncProof {
+        let store = env.clients[0].chain.chain_store().store().clone();
+        let proof =
+            export_proof(store, genesis.config.transaction_validity_period, output).unwrap();
+        proof.decode().unwrap().0
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let (genesis, mut env) = setup();
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("proof");
+        let proof = export(&genesis, &env, &path);
+
+        let client = &mut env.clients[1];
+        import_proof(&mut client.chain, client.epoch_manager.as_ref(), &path).unwrap();
+
+        let first_block = &proof.as_v1().current_epoch.first_block_header_in_epoch;
+        let header_head = client.chain.chain_store().header_head().unwrap();
+        assert_eq!(header_head.last_block_hash, *first_block.hash());
+        let block_hash =
+            env.clients[0].chain.get_block_hash_by_height(first_block.height()).unwrap();
+        assert_eq!(block_hash, *first_block.hash());
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_proof() {
+        let (genesis, mut env) = setup();
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("proof");
+        let proof = export(&genesis, &env, &path);
+        let genesis_height = genesis.config.genesis_height;
+
+        // A truncated file can't be parsed.
+        let data = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &data[..data.len() / 2]).unwrap();
+        let client = &mut env.clients[1];
+        let err =
+            import_proof(&mut client.chain, client.epoch_manager.as_ref(), &path).unwrap_err();
+        assert!(err.to_string().contains("failed to parse"), "{err:#}");
+
+        // A proof that doesn't match the chain is rejected.
+        let mut proof = proof.into_v1();
+        proof.all_epochs[0].block_producers.clear();
+        let (proof, _) = CompressedEpochSyncProof::encode(&EpochSyncProof::V1(proof)).unwrap();
+        std::fs::write(&path, borsh::to_vec(&proof).unwrap()).unwrap();
+        let err =
+            import_proof(&mut client.chain, client.epoch_manager.as_ref(), &path).unwrap_err();
+        assert!(err.to_string().contains("block producers"), "{err:#}");
+
+        assert_eq!(client.chain.chain_store().header_head().unwrap().height, genesis_height);
+    }
+}
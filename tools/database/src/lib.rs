@@ -4,14 +4,17 @@ mod analyse_gas_usage;
 mod analyse_high_load;
 mod analyze_contract_sizes;
 mod analyze_delayed_receipt;
+mod backup;
 mod block_iterators;
 pub mod commands;
 mod compact;
 mod corrupt;
+mod epoch_sync_proof;
 mod make_snapshot;
 mod memtrie;
 mod resharding_v2;
 mod run_migrations;
+mod scrub;
 mod state_perf;
 mod utils;
 mod write_to_db;
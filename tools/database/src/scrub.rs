@@ -0,0 +1,433 @@
+use crate::utils::resolve_column;
+use anyhow::Context;
+use near_chain::store_validator::StoreValidator;
+use near_chain::types::RuntimeAdapter;
+use near_chain::{ChainStore, ChainStoreAccess};
+use near_chain_configs::GenesisValidationMode;
+use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
+use near_epoch_manager::EpochManager;
+use near_primitives::hash::{hash, CryptoHash};
+use near_store::adapter::StoreAdapter;
+use near_store::db::refcount;
+use near_store::flat::FlatStorageStatus;
+use near_store::{DBCol, Mode, NodeStorage, ShardUId, Store};
+use nearcore::{NearConfig, NightshadeRuntime, NightshadeRuntimeExt};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use strum::IntoEnumIterator;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Walks the database and checks its integrity. The database is opened in
+/// read-only mode, so the scrub can run next to a running node.
+///
+/// The following checks are performed:
+/// - every column can be read and values of reference counted columns have a
+///   positive reference count,
+/// - nodes in the `State` column are stored under the hash of their content,
+/// - the checks of the store validator, which include block and header linkage,
+/// - flat storage of every shard matches the trie at the flat storage head.
+///
+/// The result is a JSON report with the problems found and the commands which
+/// may repair them. The command fails if any problem is found.
+#[derive(clap::Parser)]
+pub struct ScrubCommand {
+    /// Columns to scrub, all columns are scrubbed by default.
+    #[clap(long, value_delimiter = ',')]
+    columns: Vec<String>,
+
+    /// Limits the read throughput of the column scans, the store validator and
+    /// the flat storage comparison to reduce the impact on a running node.
+    /// Zero disables the limit.
+    #[clap(long, default_value_t = 64)]
+    max_read_mib_per_sec: u64,
+
+    /// Maximum number of flat storage entries compared with the trie for each
+    /// shard. Zero compares all the entries.
+    #[clap(long, default_value_t = 1_000_000)]
+    flat_storage_max_entries: u64,
+
+    /// Skip the store validator checks.
+    #[clap(long)]
+    skip_store_validator: bool,
+
+    /// Write the report to the given file instead of stdout.
+    #[clap(long)]
+    report: Option<PathBuf>,
+}
+
+#[derive(serde::Serialize, Default)]
+struct ScrubReport {
+    duration_secs: f64,
+    columns: Vec<ColumnSummary>,
+    findings: Vec<Finding>,
+    repairs: BTreeSet<Repair>,
+}
+
+#[derive(serde::Serialize)]
+struct ColumnSummary {
+    column: String,
+    keys: u64,
+    bytes: u64,
+}
+
+#[derive(serde::Serialize)]
+struct Finding {
+    check: &'static str,
+    column: String,
+    key: String,
+    message: String,
+}
+
+#[derive(serde::Serialize, PartialEq, Eq, PartialOrd, Ord)]
+struct Repair {
+    reason: String,
+    command: String,
+}
+
+impl ScrubReport {
+    fn add_finding(&mut self, check: &'static str, col: DBCol, key: String, message: String) {
+        if let Some(repair) = suggest_repair(check, col, &key) {
+            self.repairs.insert(repair);
+        }
+        self.findings.push(Finding { check, column: col.to_string(), key, message });
+    }
+}
+
+/// Suggests a command repairing the problem found by `check`, if there is one.
+fn suggest_repair(check: &'static str, col: DBCol, key: &str) -> Option<Repair> {
+    let restore = || Repair {
+        reason: format!("{col} contains corrupted data which can't be recomputed"),
+        command: "neard database backup restore --backup-dir <BACKUP_DIR>".to_string(),
+    };
+    match (check, col) {
+        ("flat_storage", _) => Some(Repair {
+            reason: format!("flat storage of shard {key} doesn't match the trie, it will be recreated from the trie on the next start"),
+            command: format!("neard flat-storage reset {key}"),
+        }),
+        ("store_validator", DBCol::Block | DBCol::BlockHeight | DBCol::BlockMisc) => {
+            Some(Repair {
+                reason: "the head of the chain points to inconsistent block data".to_string(),
+                command: "neard undo-block".to_string(),
+            })
+        }
+        ("state_refcount" | "trie_node_hash" | "refcount" | "unreadable", _) => Some(restore()),
+        _ => None,
+    }
+}
+
+/// Limits the rate at which the scrub reads the data.
+struct Throttle {
+    max_bytes_per_sec: u64,
+    start: Instant,
+    bytes: u64,
+}
+
+impl Throttle {
+    fn new(max_mib_per_sec: u64) -> Self {
+        Self { max_bytes_per_sec: max_mib_per_sec * MIB, start: Instant::now(), bytes: 0 }
+    }
+
+    fn consume(&mut self, bytes: usize) {
+        if self.max_bytes_per_sec == 0 {
+            return;
+        }
+        self.bytes += bytes as u64;
+        let expected = Duration::from_secs_f64(self.bytes as f64 / self.max_bytes_per_sec as f64);
+        let elapsed = self.start.elapsed();
+        if expected > elapsed {
+            std::thread::sleep(expected - elapsed);
+        }
+    }
+}
+
+impl ScrubCommand {
+    pub fn run(
+        &self,
+        home: &Path,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        let near_config = nearcore::config::load_config(home, genesis_validation)
+            .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
+        let columns = if self.columns.is_empty() {
+            DBCol::iter().collect()
+        } else {
+            self.columns.iter().map(|name| resolve_column(name)).collect::<anyhow::Result<_>>()?
+        };
+        let storage = NodeStorage::opener(
+            home,
+            &near_config.config.store,
+            near_config.config.archival_config(),
+        )
+        .open_in_mode(Mode::ReadOnly)?;
+        let store = storage.get_hot_store();
+
+        let start = Instant::now();
+        let mut report = ScrubReport::default();
+        let mut throttle = Throttle::new(self.max_read_mib_per_sec);
+        for col in columns {
+            eprintln!("Scrubbing column {col}");
+            report.columns.push(scrub_column(&store, col, &mut throttle, &mut report));
+        }
+        if !self.skip_store_validator {
+            eprintln!("Running the store validator");
+            run_store_validator(home, &near_config, &store, self.max_read_mib_per_sec, &mut report);
+        }
+        eprintln!("Comparing flat storage with the trie");
+        self.scrub_flat_storage(home, &near_config, &store, &mut throttle, &mut report)?;
+        report.duration_secs = start.elapsed().as_secs_f64();
+
+        let json = serde_json::to_string_pretty(&report)?;
+        match &self.report {
+            Some(path) => std::fs::write(path, json)
+                .with_context(|| format!("writing report to {}", path.display()))?,
+            None => println!("{json}"),
+        }
+        if !report.findings.is_empty() {
+            anyhow::bail!("scrub found {} problems", report.findings.len());
+        }
+        Ok(())
+    }
+
+    fn scrub_flat_storage(
+        &self,
+        home: &Path,
+        near_config: &NearConfig,
+        store: &Store,
+        throttle: &mut Throttle,
+        report: &mut ScrubReport,
+    ) -> anyhow::Result<()> {
+        let epoch_manager =
+            EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config, Some(home));
+        let runtime =
+            NightshadeRuntime::from_config(home, store.clone(), near_config, epoch_manager)?;
+        let chain_store = ChainStore::new(store.clone(), 0, false, 100);
+        let flat_store = store.flat_store();
+        for item in store.iter(DBCol::FlatStorageStatus) {
+            let (key, _) = item?;
+            let shard_uid = ShardUId::try_from(key.as_ref())?;
+            let FlatStorageStatus::Ready(status) = flat_store.get_flat_storage_status(shard_uid)?
+            else {
+                continue;
+            };
+            let head_hash = status.flat_head.hash;
+            let state_root = *chain_store.get_chunk_extra(&head_hash, &shard_uid)?.state_root();
+            let trie =
+                runtime.get_view_trie_for_shard(shard_uid.shard_id(), &head_hash, state_root)?;
+            let mut trie_iter = trie.disk_iter()?;
+            let mut flat_iter = flat_store.iter(shard_uid);
+            let mut compared = 0;
+            let mismatch = loop {
+                if self.flat_storage_max_entries != 0 && compared >= self.flat_storage_max_entries {
+                    break None;
+                }
+                let (trie_item, flat_item) = match (trie_iter.next(), flat_iter.next()) {
+                    (None, None) => break None,
+                    (Some(_), None) => {
+                        break Some("trie has more entries than flat storage".into())
+                    }
+                    (None, Some(_)) => {
+                        break Some("flat storage has more entries than trie".into())
+                    }
+                    (Some(trie_item), Some(flat_item)) => (trie_item?, flat_item?),
+                };
+                compared += 1;
+                let (trie_key, trie_value) = trie_item;
+                let (flat_key, flat_value) = flat_item;
+                let value_ref = flat_value.to_value_ref();
+                if trie_key != flat_key {
+                    break Some(format!(
+                        "different keys {trie_key:?} in trie and {flat_key:?} in flat storage"
+                    ));
+                }
+                if trie_value.len() != value_ref.length as usize
+                    || hash(&trie_value) != value_ref.hash
+                {
+                    break Some(format!("different values for key {trie_key:?}"));
+                }
+                throttle.consume(trie_key.len() + trie_value.len());
+            };
+            if let Some(message) = mismatch {
+                report.add_finding(
+                    "flat_storage",
+                    DBCol::FlatState,
+                    shard_uid.shard_id().to_string(),
+                    format!("{message} (flat head {head_hash}, {compared} entries compared)"),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the whole column and checks reference counts and, for the `State`
+/// column, trie node hashes.
+fn scrub_column(
+    store: &Store,
+    col: DBCol,
+    throttle: &mut Throttle,
+    report: &mut ScrubReport,
+) -> ColumnSummary {
+    let mut summary = ColumnSummary { column: col.to_string(), keys: 0, bytes: 0 };
+    for item in store.iter_raw_bytes(col) {
+        let (key, value) = match item {
+            Ok(item) => item,
+            Err(err) => {
+                report.add_finding("unreadable", col, String::new(), err.to_string());
+                break;
+            }
+        };
+        summary.keys += 1;
+        summary.bytes += (key.len() + value.len()) as u64;
+        throttle.consume(key.len() + value.len());
+        if !col.is_rc() {
+            continue;
+        }
+        let data = match decode_rc_value(&value) {
+            Ok(Some(data)) => data,
+            Ok(None) => continue,
+            Err(rc) => {
+                let check = if col == DBCol::State { "state_refcount" } else { "refcount" };
+                report.add_finding(
+                    check,
+                    col,
+                    near_fmt::StorageKey(&key).to_string(),
+                    format!("negative reference count {rc}"),
+                );
+                continue;
+            }
+        };
+        if col == DBCol::State {
+            // Keys are the shard uid followed by the hash of the node.
+            let expected = key.get(8..).and_then(|hash| CryptoHash::try_from(hash).ok());
+            if expected != Some(hash(data)) {
+                report.add_finding(
+                    "trie_node_hash",
+                    col,
+                    near_fmt::StorageKey(&key).to_string(),
+                    format!("node hash is {}", hash(data)),
+                );
+            }
+        }
+    }
+    summary
+}
+
+/// Returns the data of a reference counted value. Entries whose reference
+/// count dropped to zero are left empty until compaction removes them and are
+/// skipped like in `iter_with_rc_logic`. Negative reference counts are
+/// returned as errors.
+fn decode_rc_value(value: &[u8]) -> Result<Option<&[u8]>, i64> {
+    match refcount::decode_value_with_rc(value) {
+        (_, rc) if rc < 0 => Err(rc),
+        (data, _) => Ok(data),
+    }
+}
+
+fn run_store_validator(
+    home: &Path,
+    near_config: &NearConfig,
+    store: &Store,
+    max_read_mib_per_sec: u64,
+    report: &mut ScrubReport,
+) {
+    let epoch_manager =
+        EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config, Some(home));
+    let shard_tracker = ShardTracker::new(
+        TrackedConfig::from_config(&near_config.client_config),
+        epoch_manager.clone(),
+    );
+    let runtime =
+        NightshadeRuntime::from_config(home, store.clone(), near_config, epoch_manager.clone())
+            .expect("could not create transaction runtime");
+    let mut store_validator = StoreValidator::new(
+        near_config.validator_signer.get().map(|signer| signer.validator_id().clone()),
+        near_config.genesis.config.clone(),
+        epoch_manager,
+        shard_tracker,
+        runtime,
+        store.clone(),
+        near_config.client_config.archive,
+    );
+    if max_read_mib_per_sec != 0 {
+        store_validator.set_max_read_bytes_per_sec(max_read_mib_per_sec * MIB);
+    }
+    store_validator.validate();
+    for error in std::mem::take(&mut store_validator.errors) {
+        let col = resolve_column(&error.col).unwrap_or(DBCol::BlockMisc);
+        report.add_finding("store_validator", col, error.key, error.err.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_store::StoreConfig;
+
+    #[test]
+    fn test_repair_suggestions() {
+        let repair = suggest_repair("flat_storage", DBCol::FlatState, "3").unwrap();
+        assert_eq!(repair.command, "neard flat-storage reset 3");
+        let repair = suggest_repair("store_validator", DBCol::BlockHeight, "100").unwrap();
+        assert_eq!(repair.command, "neard undo-block");
+        assert!(suggest_repair("store_validator", DBCol::ChunkExtra, "").is_none());
+    }
+
+    #[test]
+    fn test_decode_rc_value() {
+        assert_eq!(
+            decode_rc_value(&[b"foo".as_slice(), &2i64.to_le_bytes()].concat()),
+            Ok(Some(b"foo".as_slice()))
+        );
+        // Zero reference counts are merged into empty values.
+        assert_eq!(decode_rc_value(b""), Ok(None));
+        assert_eq!(decode_rc_value(&0i64.to_le_bytes()), Ok(None));
+        assert_eq!(decode_rc_value(&(-1i64).to_le_bytes()), Err(-1));
+    }
+
+    /// Corrupts a trie node and a reference count in a RocksDB and checks that
+    /// the scrub reports both.
+    #[test]
+    fn test_scrub_corrupted_values() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let store = NodeStorage::opener(home_dir.path(), &StoreConfig::test_config(), None)
+            .open()
+            .unwrap()
+            .get_hot_store();
+        let node_key = |data: &[u8]| {
+            [ShardUId::single_shard().to_bytes().as_slice(), hash(data).as_ref()].concat()
+        };
+        let mut store_update = store.store_update();
+        for data in [b"foo".as_slice(), b"bar"] {
+            store_update.increment_refcount(DBCol::State, &node_key(data), data);
+        }
+        store_update.increment_refcount(DBCol::Transactions, &[1; 32], b"tx");
+        store_update.commit().unwrap();
+        store.flush().unwrap();
+
+        let mut store_update = store.store_update();
+        let corrupted_node = [b"baz".as_slice(), &1i64.to_le_bytes()].concat();
+        store_update.set_raw_bytes(DBCol::State, &node_key(b"foo"), &corrupted_node);
+        store_update.set_raw_bytes(DBCol::Transactions, &[1; 32], &(-1i64).to_le_bytes());
+        store_update.commit().unwrap();
+
+        let mut report = ScrubReport::default();
+        let mut throttle = Throttle::new(0);
+        let summary = scrub_column(&store, DBCol::State, &mut throttle, &mut report);
+        assert_eq!(summary.keys, 2);
+        let summary = scrub_column(&store, DBCol::Transactions, &mut throttle, &mut report);
+        assert_eq!(summary.keys, 1);
+
+        let findings: Vec<_> =
+            report.findings.iter().map(|finding| (finding.check, finding.key.clone())).collect();
+        assert_eq!(
+            findings,
+            [
+                ("trie_node_hash", near_fmt::StorageKey(&node_key(b"foo")).to_string()),
+                ("refcount", near_fmt::StorageKey(&[1; 32]).to_string()),
+            ]
+        );
+        assert_eq!(report.repairs.len(), 2);
+    }
+}
@@ -264,6 +264,7 @@ export interface TrackedShardsResponse {
         TrackedShards: {
             shards_tracked_this_epoch: boolean[];
             shards_tracked_next_epoch: boolean[];
+            tracked_config: string;
         };
     };
 }
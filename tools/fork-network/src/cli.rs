@@ -1,4 +1,6 @@
+use crate::reshard::StateResharder;
 use crate::single_shard_storage_mutator::SingleShardStorageMutator;
+use crate::state_patch::StatePatch;
 use crate::storage_mutator::StorageMutator;
 use anyhow::Context;
 use chrono::{DateTime, Utc};
@@ -17,7 +19,7 @@ use near_primitives::borsh;
 use near_primitives::epoch_manager::{EpochConfig, EpochConfigStore};
 use near_primitives::hash::CryptoHash;
 use near_primitives::serialize::dec_format;
-use near_primitives::shard_layout::ShardUId;
+use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::state::FlatStateValue;
 use near_primitives::state_record::StateRecord;
 use near_primitives::trie_key::col;
@@ -26,9 +28,9 @@ use near_primitives::types::{
     AccountId, AccountInfo, Balance, BlockHeight, EpochId, NumBlocks, NumSeats, ShardId, StateRoot,
 };
 use near_primitives::version::{ProtocolVersion, PROTOCOL_VERSION};
-use near_store::adapter::StoreAdapter;
+use near_store::adapter::{StoreAdapter, StoreUpdateAdapter};
 use near_store::db::RocksDB;
-use near_store::flat::{BlockInfo, FlatStorageManager, FlatStorageStatus};
+use near_store::flat::{BlockInfo, FlatStorageManager, FlatStorageReadyStatus, FlatStorageStatus};
 use near_store::{
     checkpoint_hot_storage_and_cleanup_columns, DBCol, Store, TrieDBStorage, TrieStorage,
     FINAL_HEAD_KEY,
@@ -48,6 +50,8 @@ use strum::IntoEnumIterator;
 /// Use the following sub-commands:
 /// * init
 /// * amend-access-keys
+/// * patch-state (optional)
+/// * set-shard-layout (optional)
 /// * set-validators
 /// * finalize
 ///
@@ -68,6 +72,14 @@ enum SubCommand {
     /// Updates the state to ensure every account has a full access key that is known to us.
     AmendAccessKeys(AmendAccessKeysCmd),
 
+    /// Reads state records and deletions from a file
+    /// Applies them to the state, e.g. to change balances or contracts.
+    PatchState(PatchStateCmd),
+
+    /// Moves the state to the shards of a new shard layout.
+    /// Needs to run after amend-access-keys.
+    SetShardLayout(SetShardLayoutCmd),
+
     /// Creates a DB snapshot, then
     /// Reads a list of validator accounts from a file
     /// Adds validator accounts to the state
@@ -94,6 +106,33 @@ struct AmendAccessKeysCmd {
     batch_size: u64,
 }
 
+#[derive(clap::Parser)]
+struct PatchStateCmd {
+    /// Path to the JSON file with the records to set and delete.
+    /// The path can be relative to `home_dir` or an absolute path.
+    /// Example of a valid file that deletes an account and deploys a contract to another one:
+    /// {
+    ///   "delete": [{ "Account": { "account_id": "old.near" } }],
+    ///   "records": [{ "Contract": { "account_id": "alice.near", "code": "<base64>" } }]
+    /// }
+    /// Records use the genesis records format, deletions can be `Account`, `AccessKey`,
+    /// `Contract` and `Data`. Code hashes and storage usage of the accounts are updated.
+    #[arg(short, long)]
+    pub patch: PathBuf,
+}
+
+#[derive(clap::Parser)]
+struct SetShardLayoutCmd {
+    /// Comma-separated boundary accounts of the new shard layout, e.g.
+    /// `--boundary-accounts aurora,game.hot.tg` makes three shards.
+    /// No boundary accounts make a single shard.
+    /// The new shards get ids following the ids of the current shards.
+    #[arg(long, value_delimiter = ',')]
+    pub boundary_accounts: Vec<AccountId>,
+    #[arg(short, long, default_value = "2000000")]
+    pub batch_size: u64,
+}
+
 #[derive(clap::Parser)]
 struct SetValidatorsCmd {
     /// Path to the JSON list of [`Validator`] structs containing account id and public keys.
@@ -128,6 +167,7 @@ struct SetValidatorsCmd {
 }
 
 const FORKED_ROOTS_KEY_PREFIX: &str = "FORK_TOOL_SHARD_ID:";
+const FORKED_SHARD_LAYOUT_KEY: &[u8] = b"FORK_TOOL_SHARD_LAYOUT";
 
 fn parse_state_roots_key(key: &[u8]) -> anyhow::Result<ShardId> {
     let key = std::str::from_utf8(key)?;
@@ -194,6 +234,7 @@ impl ForkNetworkCommand {
             near_config.client_config.chain_id.clone(),
             near_config.network_config.node_key.public_key().clone(),
             None,
+            None,
         )
         .await
         .global();
@@ -207,6 +248,12 @@ impl ForkNetworkCommand {
             SubCommand::AmendAccessKeys(AmendAccessKeysCmd { batch_size }) => {
                 self.amend_access_keys(*batch_size, near_config, home_dir)?;
             }
+            SubCommand::PatchState(PatchStateCmd { patch }) => {
+                self.patch_state(patch, near_config, home_dir)?;
+            }
+            SubCommand::SetShardLayout(SetShardLayoutCmd { boundary_accounts, batch_size }) => {
+                self.set_shard_layout(boundary_accounts, *batch_size, near_config, home_dir)?;
+            }
             SubCommand::SetValidators(SetValidatorsCmd {
                 genesis_time,
                 protocol_version,
@@ -354,6 +401,11 @@ impl ForkNetworkCommand {
             &near_config.genesis.config,
             Some(home_dir),
         );
+        // Memtries of the new shards can't be loaded, they have no chunk extras.
+        anyhow::ensure!(
+            get_forked_shard_layout(&store)?.is_none(),
+            "amend-access-keys must run before set-shard-layout"
+        );
         let (prev_state_roots, prev_hash, epoch_id, block_height) =
             self.get_state_roots_and_hash(epoch_manager.as_ref(), store.clone())?;
         tracing::info!(?prev_state_roots, ?epoch_id, ?prev_hash);
@@ -370,9 +422,10 @@ impl ForkNetworkCommand {
             .load_memtries_for_enabled_shards(&all_shard_uids, &[].into(), true)
             .unwrap();
 
+        let shard_tries = runtime.get_tries();
         let make_storage_mutator: MakeSingleShardStorageMutatorFn =
             Arc::new(move |prev_state_root| {
-                SingleShardStorageMutator::new(&runtime.clone(), prev_state_root)
+                SingleShardStorageMutator::new(shard_tries.clone(), prev_state_root)
             });
 
         let prev_state_roots = prev_state_roots
@@ -394,6 +447,129 @@ impl ForkNetworkCommand {
         Ok(new_state_roots)
     }
 
+    /// Reads the state patch (which is a path relative to the home dir),
+    /// and applies it to the state of all shards.
+    fn patch_state(
+        &self,
+        patch: &Path,
+        near_config: &mut NearConfig,
+        home_dir: &Path,
+    ) -> anyhow::Result<Vec<StateRoot>> {
+        // Open storage with migration
+        let storage = open_storage(&home_dir, near_config).unwrap();
+        let store = storage.get_hot_store();
+
+        let epoch_manager = EpochManager::new_arc_handle(
+            store.clone(),
+            &near_config.genesis.config,
+            Some(home_dir),
+        );
+
+        let (prev_state_roots, _prev_hash, epoch_id, _block_height) =
+            self.get_state_roots_and_hash(epoch_manager.as_ref(), store.clone())?;
+        let shard_layout = get_shard_layout(epoch_manager.as_ref(), &store, &epoch_id)?;
+
+        let runtime =
+            NightshadeRuntime::from_config(home_dir, store.clone(), &near_config, epoch_manager)
+                .context("could not create the transaction runtime")?;
+
+        let runtime_config_store = RuntimeConfigStore::new(None);
+        let runtime_config = runtime_config_store.get_config(PROTOCOL_VERSION);
+
+        let patch_path =
+            if patch.is_absolute() { PathBuf::from(patch) } else { home_dir.join(patch) };
+        let state_patch = StatePatch::from_file(&patch_path)?;
+
+        let mut storage_mutator =
+            StorageMutator::new(runtime.get_tries(), shard_layout, prev_state_roots)?;
+        let stats = state_patch.apply(
+            store,
+            &runtime_config.fees.storage_usage_config,
+            &mut storage_mutator,
+        )?;
+        tracing::info!(?stats, "Applied the state patch");
+        let new_state_roots = storage_mutator.commit()?;
+        tracing::info!(?new_state_roots, "All done");
+        Ok(new_state_roots)
+    }
+
+    /// Moves the state of all shards to the shards of a new shard layout,
+    /// rebuilding their tries and flat storage.
+    /// Deletes the flat storage of the old shards and persists the new shard
+    /// layout, which set-validators then uses for the genesis.
+    fn set_shard_layout(
+        &self,
+        boundary_accounts: &[AccountId],
+        batch_size: u64,
+        near_config: &mut NearConfig,
+        home_dir: &Path,
+    ) -> anyhow::Result<Vec<StateRoot>> {
+        // Open storage with migration
+        let storage = open_storage(&home_dir, near_config).unwrap();
+        let store = storage.get_hot_store();
+
+        let epoch_manager = EpochManager::new_arc_handle(
+            store.clone(),
+            &near_config.genesis.config,
+            Some(home_dir),
+        );
+
+        let (prev_state_roots, _prev_hash, epoch_id, _block_height) =
+            self.get_state_roots_and_hash(epoch_manager.as_ref(), store.clone())?;
+        let old_shard_layout = get_shard_layout(epoch_manager.as_ref(), &store, &epoch_id)?;
+        // `init` moved the flat heads of all old shards to the fork block, the
+        // flat storage of the new shards starts there too.
+        let old_shard_uids: Vec<_> = old_shard_layout.shard_uids().collect();
+        let flat_head = get_fork_heads(&old_shard_uids, store.clone())?[0];
+
+        let mut boundary_accounts = boundary_accounts.to_vec();
+        boundary_accounts.sort();
+        boundary_accounts.dedup();
+        // The new shards must not share `ShardUId`s, and thus flat storage and
+        // trie nodes, with the old shards that are still being read.
+        let first_shard_id: u64 =
+            old_shard_layout.shard_ids().map(Into::<u64>::into).max().unwrap_or_default() + 1;
+        let shard_ids = (0..=boundary_accounts.len() as u64)
+            .map(|index| ShardId::new(first_shard_id + index))
+            .collect();
+        let new_shard_layout = ShardLayout::v2(boundary_accounts, shard_ids, None);
+        tracing::info!(?prev_state_roots, ?old_shard_layout, ?new_shard_layout);
+
+        let runtime =
+            NightshadeRuntime::from_config(home_dir, store.clone(), &near_config, epoch_manager)
+                .context("could not create the transaction runtime")?;
+
+        let mut resharder = StateResharder::new(
+            store.clone(),
+            runtime.get_tries(),
+            new_shard_layout.clone(),
+            batch_size,
+        )?;
+        for shard_uid in old_shard_layout.shard_uids() {
+            resharder.move_shard(shard_uid)?;
+        }
+        let (new_state_roots, stats) = resharder.finish()?;
+        tracing::info!(?stats, ?new_state_roots, "Moved the state to the new shard layout");
+
+        let mut store_update = store.store_update();
+        for shard_id in old_shard_layout.shard_ids() {
+            let shard_uid = ShardUId::from_shard_id_and_layout(shard_id, &old_shard_layout);
+            store_update.flat_store_update().remove_all_values(shard_uid);
+            store_update.flat_store_update().remove_status(shard_uid);
+            store_update.delete(DBCol::Misc, &make_state_roots_key(shard_id));
+        }
+        for shard_uid in new_shard_layout.shard_uids() {
+            store_update.flat_store_update().set_flat_storage_status(
+                shard_uid,
+                FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head }),
+            );
+        }
+        store_update.set_ser(DBCol::Misc, FORKED_SHARD_LAYOUT_KEY, &new_shard_layout)?;
+        store_update.commit()?;
+        tracing::info!("All done");
+        Ok(new_state_roots)
+    }
+
     /// Creates a DB snapshot, then
     /// Reads a list of validator accounts from a file
     /// Adds validator accounts to the state
@@ -422,16 +598,17 @@ impl ForkNetworkCommand {
 
         let (prev_state_roots, _prev_hash, epoch_id, block_height) =
             self.get_state_roots_and_hash(epoch_manager.as_ref(), store.clone())?;
+        let forked_shard_layout = get_forked_shard_layout(&store)?;
+        let shard_layout = get_shard_layout(epoch_manager.as_ref(), &store, &epoch_id)?;
 
-        let runtime =
-            NightshadeRuntime::from_config(home_dir, store, &near_config, epoch_manager.clone())
-                .context("could not create the transaction runtime")?;
+        let runtime = NightshadeRuntime::from_config(home_dir, store, &near_config, epoch_manager)
+            .context("could not create the transaction runtime")?;
 
         let runtime_config_store = RuntimeConfigStore::new(None);
         let runtime_config = runtime_config_store.get_config(PROTOCOL_VERSION);
 
         let storage_mutator =
-            StorageMutator::new(epoch_manager, &runtime, epoch_id, prev_state_roots)?;
+            StorageMutator::new(runtime.get_tries(), shard_layout, prev_state_roots)?;
         let (new_state_roots, new_validator_accounts) =
             self.add_validator_accounts(validators, runtime_config, home_dir, storage_mutator)?;
 
@@ -442,6 +619,7 @@ impl ForkNetworkCommand {
             protocol_version,
             epoch_length,
             num_seats,
+            forked_shard_layout,
             block_height,
             chain_id_suffix,
             chain_id,
@@ -483,9 +661,7 @@ impl ForkNetworkCommand {
         let block_hash = store.get_ser(DBCol::Misc, b"FORK_TOOL_BLOCK_HASH")?.unwrap();
         let block_height = store.get(DBCol::Misc, b"FORK_TOOL_BLOCK_HEIGHT")?.unwrap();
         let block_height = u64::from_le_bytes(block_height.as_slice().try_into().unwrap());
-        let shard_layout = epoch_manager
-            .get_shard_layout(&epoch_id)
-            .with_context(|| format!("Failed getting shard layout for epoch {}", &epoch_id.0))?;
+        let shard_layout = get_shard_layout(epoch_manager, &store, &epoch_id)?;
         let mut state_roots = vec![None; shard_layout.shard_ids().count()];
         for item in store.iter_prefix(DBCol::Misc, FORKED_ROOTS_KEY_PREFIX.as_bytes()) {
             let (key, value) = item?;
@@ -538,10 +714,12 @@ impl ForkNetworkCommand {
 
     /// Creates epoch config overrides since `first_version` and places them
     /// in `home_dir`.
+    /// If the shard layout was changed, all versions use that shard layout.
     fn override_epoch_configs(
         &self,
         first_version: ProtocolVersion,
         num_seats: &Option<NumSeats>,
+        shard_layout: &Option<ShardLayout>,
         home_dir: &Path,
     ) -> anyhow::Result<EpochConfig> {
         let epoch_config_dir = home_dir.join("epoch_configs");
@@ -563,6 +741,13 @@ impl ForkNetworkCommand {
                 config.num_chunk_producer_seats = *num_seats;
                 config.num_chunk_validator_seats = *num_seats;
             }
+            if let Some(shard_layout) = shard_layout {
+                let num_shards = shard_layout.num_shards() as usize;
+                config.num_block_producer_seats_per_shard =
+                    vec![config.num_block_producer_seats; num_shards];
+                config.avg_hidden_validator_seats_per_shard = vec![0; num_shards];
+                config.shard_layout = shard_layout.clone();
+            }
             new_epoch_configs.insert(version, Arc::new(config));
         }
         let first_config = new_epoch_configs.get(&first_version).unwrap().as_ref().clone();
@@ -875,6 +1060,7 @@ impl ForkNetworkCommand {
         protocol_version: Option<ProtocolVersion>,
         epoch_length: u64,
         num_seats: &Option<NumSeats>,
+        shard_layout: Option<ShardLayout>,
         height: BlockHeight,
         chain_id_suffix: &str,
         chain_id: &Option<String>,
@@ -899,8 +1085,12 @@ impl ForkNetworkCommand {
         // This is based on the assumption that epoch length is part of genesis config and not epoch config.
         near_config.genesis.config.epoch_length = epoch_length;
 
-        let epoch_config =
-            self.override_epoch_configs(genesis_protocol_version, num_seats, home_dir)?;
+        let epoch_config = self.override_epoch_configs(
+            genesis_protocol_version,
+            num_seats,
+            &shard_layout,
+            home_dir,
+        )?;
 
         let original_config = near_config.genesis.config.clone();
 
@@ -959,6 +1149,26 @@ impl ForkNetworkCommand {
     }
 }
 
+/// Returns the shard layout set by set-shard-layout, if any.
+fn get_forked_shard_layout(store: &Store) -> anyhow::Result<Option<ShardLayout>> {
+    Ok(store.get_ser(DBCol::Misc, FORKED_SHARD_LAYOUT_KEY)?)
+}
+
+/// Returns the shard layout of the forked state: the one set by
+/// set-shard-layout, or otherwise the one of the source network.
+fn get_shard_layout(
+    epoch_manager: &EpochManagerHandle,
+    store: &Store,
+    epoch_id: &EpochId,
+) -> anyhow::Result<ShardLayout> {
+    if let Some(shard_layout) = get_forked_shard_layout(store)? {
+        return Ok(shard_layout);
+    }
+    epoch_manager
+        .get_shard_layout(epoch_id)
+        .with_context(|| format!("Failed getting shard layout for epoch {}", &epoch_id.0))
+}
+
 fn backup_genesis_file_path(home_dir: &Path, genesis_file: &str) -> PathBuf {
     home_dir.join(format!("{}.backup", &genesis_file))
 }
@@ -1,3 +1,5 @@
 pub mod cli;
+mod reshard;
 mod single_shard_storage_mutator;
+mod state_patch;
 mod storage_mutator;
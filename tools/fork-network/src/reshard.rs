@@ -0,0 +1,335 @@
+use crate::state_patch::StateReader;
+use crate::storage_mutator::StorageMutator;
+use anyhow::Context;
+use near_primitives::borsh::{self, BorshDeserialize};
+use near_primitives::receipt::{
+    BufferedReceiptIndices, DelayedReceiptIndices, PromiseYieldIndices, PromiseYieldTimeout,
+    ReceiptOrStateStoredReceipt,
+};
+use near_primitives::shard_layout::{ShardLayout, ShardUId};
+use near_primitives::trie_key::trie_key_parsers::parse_account_id_from_raw_key;
+use near_primitives::trie_key::TrieKey;
+use near_primitives::types::{BlockHeight, StateRoot};
+use near_store::adapter::StoreAdapter;
+use near_store::{ShardTries, Store, Trie};
+
+/// Moves the state of a forked network into the shards of a new shard layout.
+///
+/// Everything keyed by an account goes to the shard of that account. Delayed
+/// and buffered receipts of the old shards become delayed receipts of the
+/// shard of their receiver, promise yield timeouts go to the shard of their
+/// account. The bandwidth scheduler state and the metadata of the outgoing
+/// buffers are dropped, the new chain starts without them.
+pub(crate) struct StateResharder {
+    store: Store,
+    reader: StateReader,
+    batch_size: u64,
+    storage_mutator: StorageMutator,
+    /// Number of delayed receipts queued in each new shard, by shard index.
+    delayed_receipts: Vec<u64>,
+    /// Promise yield timeouts of each new shard, by shard index. They are
+    /// written at the end, because the queue must be ordered by expiration.
+    promise_yield_timeouts: Vec<Vec<(BlockHeight, Vec<u8>)>>,
+    stats: ReshardStats,
+}
+
+#[derive(Default, Debug)]
+pub(crate) struct ReshardStats {
+    pub records_moved: u64,
+    pub delayed_receipts_moved: u64,
+    pub buffered_receipts_moved: u64,
+    pub promise_yield_timeouts_moved: u64,
+}
+
+impl StateResharder {
+    pub(crate) fn new(
+        store: Store,
+        shard_tries: ShardTries,
+        new_shard_layout: ShardLayout,
+        batch_size: u64,
+    ) -> anyhow::Result<Self> {
+        let num_shards = new_shard_layout.shard_ids().count();
+        let storage_mutator =
+            StorageMutator::new(shard_tries, new_shard_layout, vec![Trie::EMPTY_ROOT; num_shards])?;
+        Ok(Self {
+            reader: StateReader::new(store.clone()),
+            store,
+            batch_size,
+            storage_mutator,
+            delayed_receipts: vec![0; num_shards],
+            promise_yield_timeouts: vec![vec![]; num_shards],
+            stats: ReshardStats::default(),
+        })
+    }
+
+    /// Moves the state of a shard of the old shard layout, read from its flat
+    /// storage.
+    pub(crate) fn move_shard(&mut self, shard_uid: ShardUId) -> anyhow::Result<()> {
+        tracing::info!(?shard_uid, "Moving the state of the shard");
+        for item in self.store.flat_store().iter(shard_uid) {
+            let (key, value) = item?;
+            // The queues are moved separately, in the order of their indices.
+            let Some(account_id) = parse_account_id_from_raw_key(&key)? else {
+                continue;
+            };
+            let value = self.reader.value_bytes(shard_uid, value)?;
+            let shard_index = self.storage_mutator.shard_index(&account_id)?;
+            self.storage_mutator.set_raw(shard_index, key, value)?;
+            self.stats.records_moved += 1;
+            self.maybe_commit()?;
+        }
+
+        if let Some(indices) =
+            self.get::<DelayedReceiptIndices>(shard_uid, TrieKey::DelayedReceiptIndices)?
+        {
+            for index in indices.first_index..indices.next_available_index {
+                self.move_receipt(shard_uid, TrieKey::DelayedReceipt { index })?;
+                self.stats.delayed_receipts_moved += 1;
+            }
+        }
+        if let Some(indices) =
+            self.get::<BufferedReceiptIndices>(shard_uid, TrieKey::BufferedReceiptIndices)?
+        {
+            for (receiving_shard, indices) in indices.shard_buffers {
+                for index in indices.first_index..indices.next_available_index {
+                    self.move_receipt(
+                        shard_uid,
+                        TrieKey::BufferedReceipt { receiving_shard, index },
+                    )?;
+                    self.stats.buffered_receipts_moved += 1;
+                }
+            }
+        }
+        if let Some(indices) =
+            self.get::<PromiseYieldIndices>(shard_uid, TrieKey::PromiseYieldIndices)?
+        {
+            for index in indices.first_index..indices.next_available_index {
+                let value = self.get_bytes(shard_uid, TrieKey::PromiseYieldTimeout { index })?;
+                let timeout: PromiseYieldTimeout = borsh::from_slice(&value)?;
+                let shard_index = self.storage_mutator.shard_index(&timeout.account_id)?;
+                self.promise_yield_timeouts[shard_index].push((timeout.expires_at, value));
+                self.stats.promise_yield_timeouts_moved += 1;
+            }
+        }
+        tracing::info!(?shard_uid, stats = ?self.stats, "Moved the state of the shard");
+        Ok(())
+    }
+
+    /// Writes the queue indices of the new shards and commits the remaining
+    /// changes. Returns the state roots in the shard index order of the new
+    /// shard layout.
+    pub(crate) fn finish(mut self) -> anyhow::Result<(Vec<StateRoot>, ReshardStats)> {
+        for (shard_index, &next_available_index) in self.delayed_receipts.iter().enumerate() {
+            if next_available_index == 0 {
+                continue;
+            }
+            let indices = DelayedReceiptIndices { first_index: 0, next_available_index };
+            self.storage_mutator.set_raw(
+                shard_index,
+                TrieKey::DelayedReceiptIndices.to_vec(),
+                borsh::to_vec(&indices)?,
+            )?;
+        }
+        let promise_yield_timeouts = std::mem::take(&mut self.promise_yield_timeouts);
+        for (shard_index, mut timeouts) in promise_yield_timeouts.into_iter().enumerate() {
+            if timeouts.is_empty() {
+                continue;
+            }
+            timeouts.sort_by_key(|(expires_at, _)| *expires_at);
+            let indices =
+                PromiseYieldIndices { first_index: 0, next_available_index: timeouts.len() as u64 };
+            for (index, (_, value)) in timeouts.into_iter().enumerate() {
+                let key = TrieKey::PromiseYieldTimeout { index: index as u64 };
+                self.storage_mutator.set_raw(shard_index, key.to_vec(), value)?;
+            }
+            self.storage_mutator.set_raw(
+                shard_index,
+                TrieKey::PromiseYieldIndices.to_vec(),
+                borsh::to_vec(&indices)?,
+            )?;
+        }
+        let state_roots = self.storage_mutator.commit()?;
+        Ok((state_roots, self.stats))
+    }
+
+    /// Appends a receipt to the delayed receipts of the shard of its receiver.
+    fn move_receipt(&mut self, shard_uid: ShardUId, key: TrieKey) -> anyhow::Result<()> {
+        let value = self.get_bytes(shard_uid, key)?;
+        let receipt: ReceiptOrStateStoredReceipt = borsh::from_slice(&value)?;
+        let shard_index = self.storage_mutator.shard_index(receipt.get_receipt().receiver_id())?;
+        let index = self.delayed_receipts[shard_index];
+        self.delayed_receipts[shard_index] += 1;
+        self.storage_mutator.set_raw(
+            shard_index,
+            TrieKey::DelayedReceipt { index }.to_vec(),
+            value,
+        )?;
+        self.maybe_commit()
+    }
+
+    fn get<T: BorshDeserialize>(
+        &self,
+        shard_uid: ShardUId,
+        key: TrieKey,
+    ) -> anyhow::Result<Option<T>> {
+        let value = self.reader.get_bytes(shard_uid, &key.to_vec())?;
+        Ok(value.map(|value| borsh::from_slice(&value)).transpose()?)
+    }
+
+    fn get_bytes(&self, shard_uid: ShardUId, key: TrieKey) -> anyhow::Result<Vec<u8>> {
+        self.reader
+            .get_bytes(shard_uid, &key.to_vec())?
+            .with_context(|| format!("{key:?} is missing in shard {shard_uid}"))
+    }
+
+    fn maybe_commit(&mut self) -> anyhow::Result<()> {
+        if self.storage_mutator.should_commit(self.batch_size) {
+            tracing::info!(stats = ?self.stats, "Committing a batch");
+            self.storage_mutator.commit_batch()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::receipt::{Receipt, ReceiptPriority, TrieQueueIndices};
+    use near_primitives::state::FlatStateValue;
+    use near_primitives::types::{AccountId, ShardId};
+    use near_store::test_utils::{create_test_store, TestTriesBuilder};
+    use std::borrow::Cow;
+    use std::collections::BTreeMap;
+
+    fn alice() -> AccountId {
+        "alice.near".parse().unwrap()
+    }
+
+    fn zoe() -> AccountId {
+        "zoe.near".parse().unwrap()
+    }
+
+    /// A receipt to `receiver_id`, told apart from the others by `refund`.
+    fn receipt(receiver_id: &AccountId, refund: u128) -> Vec<u8> {
+        let receipt = Receipt::new_balance_refund(receiver_id, refund, ReceiptPriority::NoPriority);
+        borsh::to_vec(&ReceiptOrStateStoredReceipt::Receipt(Cow::Owned(receipt))).unwrap()
+    }
+
+    fn timeout(account_id: &AccountId, expires_at: BlockHeight) -> Vec<u8> {
+        let timeout = PromiseYieldTimeout {
+            account_id: account_id.clone(),
+            data_id: CryptoHash::default(),
+            expires_at,
+        };
+        borsh::to_vec(&timeout).unwrap()
+    }
+
+    #[test]
+    fn test_reshard() {
+        let store = create_test_store();
+        let old_shard_uid = ShardLayout::single_shard().shard_uids().next().unwrap();
+        let mut values = vec![];
+        for account_id in [alice(), zoe()] {
+            let key = TrieKey::ContractData { account_id: account_id.clone(), key: b"k".to_vec() };
+            values.push((key, account_id.as_bytes().to_vec()));
+        }
+        // The delayed receipts queue starts at index 1, the first receipt was
+        // already processed.
+        let indices = DelayedReceiptIndices { first_index: 1, next_available_index: 4 };
+        values.push((TrieKey::DelayedReceiptIndices, borsh::to_vec(&indices).unwrap()));
+        values.push((TrieKey::DelayedReceipt { index: 1 }, receipt(&zoe(), 1)));
+        values.push((TrieKey::DelayedReceipt { index: 2 }, receipt(&alice(), 2)));
+        values.push((TrieKey::DelayedReceipt { index: 3 }, receipt(&zoe(), 3)));
+        let receiving_shard = ShardId::new(7);
+        let indices = BufferedReceiptIndices {
+            shard_buffers: BTreeMap::from([(
+                receiving_shard,
+                TrieQueueIndices { first_index: 0, next_available_index: 2 },
+            )]),
+        };
+        values.push((TrieKey::BufferedReceiptIndices, borsh::to_vec(&indices).unwrap()));
+        values.push((TrieKey::BufferedReceipt { receiving_shard, index: 0 }, receipt(&alice(), 4)));
+        values.push((TrieKey::BufferedReceipt { receiving_shard, index: 1 }, receipt(&zoe(), 5)));
+        let indices = PromiseYieldIndices { first_index: 0, next_available_index: 3 };
+        values.push((TrieKey::PromiseYieldIndices, borsh::to_vec(&indices).unwrap()));
+        values.push((TrieKey::PromiseYieldTimeout { index: 0 }, timeout(&alice(), 30)));
+        values.push((TrieKey::PromiseYieldTimeout { index: 1 }, timeout(&zoe(), 10)));
+        values.push((TrieKey::PromiseYieldTimeout { index: 2 }, timeout(&alice(), 20)));
+        // The resharder reads the state from the flat storage only.
+        let mut update = store.flat_store().store_update();
+        for (key, value) in values {
+            update.set(old_shard_uid, key.to_vec(), Some(FlatStateValue::inlined(&value)));
+        }
+        update.commit().unwrap();
+
+        let new_shard_layout = ShardLayout::v2(
+            vec!["m".parse().unwrap()],
+            vec![ShardId::new(1), ShardId::new(2)],
+            None,
+        );
+        let shard_tries = TestTriesBuilder::new().with_store(store.clone()).build();
+        // A batch size of 1 commits after every record.
+        let mut resharder =
+            StateResharder::new(store.clone(), shard_tries, new_shard_layout.clone(), 1).unwrap();
+        resharder.move_shard(old_shard_uid).unwrap();
+        let (state_roots, stats) = resharder.finish().unwrap();
+        assert_eq!(state_roots.len(), 2);
+        assert_eq!(stats.records_moved, 2);
+        assert_eq!(stats.delayed_receipts_moved, 3);
+        assert_eq!(stats.buffered_receipts_moved, 2);
+        assert_eq!(stats.promise_yield_timeouts_moved, 3);
+
+        let reader = StateReader::new(store);
+        let [alice_shard, zoe_shard]: [ShardUId; 2] =
+            new_shard_layout.shard_uids().collect::<Vec<_>>().try_into().unwrap();
+        let get = |shard_uid, key: TrieKey| reader.get_bytes(shard_uid, &key.to_vec()).unwrap();
+        for (account_id, shard_uid, other_shard_uid) in
+            [(alice(), alice_shard, zoe_shard), (zoe(), zoe_shard, alice_shard)]
+        {
+            let key = TrieKey::ContractData { account_id: account_id.clone(), key: b"k".to_vec() };
+            assert_eq!(get(shard_uid, key.clone()), Some(account_id.as_bytes().to_vec()));
+            assert_eq!(get(other_shard_uid, key), None);
+        }
+
+        // The delayed receipts come first, then the buffered ones, each in
+        // the order of their queue.
+        let expected_receipts = [
+            (alice_shard, vec![receipt(&alice(), 2), receipt(&alice(), 4)]),
+            (zoe_shard, vec![receipt(&zoe(), 1), receipt(&zoe(), 3), receipt(&zoe(), 5)]),
+        ];
+        for (shard_uid, receipts) in expected_receipts {
+            let indices = DelayedReceiptIndices {
+                first_index: 0,
+                next_available_index: receipts.len() as u64,
+            };
+            assert_eq!(
+                get(shard_uid, TrieKey::DelayedReceiptIndices),
+                Some(borsh::to_vec(&indices).unwrap())
+            );
+            for (index, receipt) in receipts.into_iter().enumerate() {
+                let key = TrieKey::DelayedReceipt { index: index as u64 };
+                assert_eq!(get(shard_uid, key), Some(receipt));
+            }
+            assert_eq!(get(shard_uid, TrieKey::BufferedReceiptIndices), None);
+        }
+
+        // The promise yield timeouts are ordered by expiration.
+        let expected_timeouts = [
+            (alice_shard, vec![timeout(&alice(), 20), timeout(&alice(), 30)]),
+            (zoe_shard, vec![timeout(&zoe(), 10)]),
+        ];
+        for (shard_uid, timeouts) in expected_timeouts {
+            let indices =
+                PromiseYieldIndices { first_index: 0, next_available_index: timeouts.len() as u64 };
+            assert_eq!(
+                get(shard_uid, TrieKey::PromiseYieldIndices),
+                Some(borsh::to_vec(&indices).unwrap())
+            );
+            for (index, timeout) in timeouts.into_iter().enumerate() {
+                let key = TrieKey::PromiseYieldTimeout { index: index as u64 };
+                assert_eq!(get(shard_uid, key), Some(timeout));
+            }
+        }
+    }
+}
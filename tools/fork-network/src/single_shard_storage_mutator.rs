@@ -1,4 +1,3 @@
-use near_chain::types::RuntimeAdapter;
 use near_crypto::PublicKey;
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::borsh;
@@ -10,7 +9,6 @@ use near_primitives::types::{AccountId, StateRoot};
 use near_primitives::types::{StoreKey, StoreValue};
 use near_store::adapter::StoreUpdateAdapter;
 use near_store::{flat::FlatStateChanges, DBCol, ShardTries};
-use nearcore::NightshadeRuntime;
 
 /// Object that updates the existing state. Combines all changes, commits them
 /// and returns new state roots.
@@ -21,8 +19,8 @@ pub(crate) struct SingleShardStorageMutator {
 }
 
 impl SingleShardStorageMutator {
-    pub(crate) fn new(runtime: &NightshadeRuntime, state_root: StateRoot) -> anyhow::Result<Self> {
-        Ok(Self { updates: Vec::new(), state_root, shard_tries: runtime.get_tries() })
+    pub(crate) fn new(shard_tries: ShardTries, state_root: StateRoot) -> anyhow::Result<Self> {
+        Ok(Self { updates: Vec::new(), state_root, shard_tries })
     }
 
     fn set(&mut self, key: TrieKey, value: Vec<u8>) -> anyhow::Result<()> {
@@ -30,6 +28,12 @@ impl SingleShardStorageMutator {
         Ok(())
     }
 
+    /// Sets a value under a raw trie key, e.g. one copied from another shard.
+    pub(crate) fn set_raw(&mut self, key: Vec<u8>, value: Vec<u8>) -> anyhow::Result<()> {
+        self.updates.push((key, Some(value)));
+        Ok(())
+    }
+
     fn remove(&mut self, key: TrieKey) -> anyhow::Result<()> {
         self.updates.push((key.to_vec(), None));
         Ok(())
@@ -70,10 +74,7 @@ impl SingleShardStorageMutator {
         data_key: &StoreKey,
         value: StoreValue,
     ) -> anyhow::Result<()> {
-        self.set(
-            TrieKey::ContractData { account_id, key: data_key.to_vec() },
-            borsh::to_vec(&value)?,
-        )
+        self.set(TrieKey::ContractData { account_id, key: data_key.to_vec() }, value.into())
     }
 
     pub(crate) fn delete_data(
@@ -0,0 +1,674 @@
+use crate::storage_mutator::StorageMutator;
+use anyhow::Context;
+use near_crypto::PublicKey;
+use near_parameters::StorageUsageConfig;
+use near_primitives::account::Account;
+use near_primitives::borsh;
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::state::FlatStateValue;
+use near_primitives::state_record::StateRecord;
+use near_primitives::trie_key::trie_key_parsers::{
+    get_raw_prefix_for_access_keys, get_raw_prefix_for_contract_data,
+    parse_data_key_from_contract_data_key, parse_public_key_from_access_key_key,
+};
+use near_primitives::trie_key::TrieKey;
+use near_primitives::types::{AccountId, StorageUsage, StoreKey};
+use near_store::adapter::StoreAdapter;
+use near_store::{Store, TrieDBStorage, TrieStorage};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Changes to the state of a forked network, read from a JSON file:
+///
+/// ```json
+/// {
+///   "delete": [
+///     { "Account": { "account_id": "old.near" } },
+///     { "AccessKey": { "account_id": "alice.near", "public_key": "ed25519:..." } },
+///     { "Data": { "account_id": "contract.near", "data_key": "U1RBVEU=" } }
+///   ],
+///   "records": [
+///     {
+///       "Account": {
+///         "account_id": "alice.near",
+///         "account": {
+///           "amount": "1000000000000000000000000",
+///           "locked": "0",
+///           "code_hash": "11111111111111111111111111111111",
+///           "storage_usage": 0
+///         }
+///       }
+///     },
+///     { "Contract": { "account_id": "alice.near", "code": "<base64>" } }
+///   ]
+/// }
+/// ```
+///
+/// `records` are genesis [`StateRecord`]s that add or replace accounts, access
+/// keys, contract code and contract data. Deletions are applied first.
+/// Deleting an account deletes its keys, code and data as well.
+///
+/// The code hash and storage usage of the patched accounts are computed by
+/// the tool, the `code_hash` and `storage_usage` of `Account` records are
+/// ignored.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct StatePatch {
+    #[serde(default)]
+    delete: Vec<StateDeletion>,
+    #[serde(default)]
+    records: Vec<StateRecord>,
+}
+
+#[derive(Deserialize, Debug)]
+enum StateDeletion {
+    Account { account_id: AccountId },
+    AccessKey { account_id: AccountId, public_key: PublicKey },
+    Contract { account_id: AccountId },
+    Data { account_id: AccountId, data_key: StoreKey },
+}
+
+#[derive(Default, Debug)]
+pub(crate) struct PatchStats {
+    pub accounts_deleted: u64,
+    pub accounts_updated: u64,
+    pub records_deleted: u64,
+    pub records_set: u64,
+}
+
+/// An account touched by the patch.
+struct PatchedAccount {
+    /// The account after the changes applied so far, `None` if it doesn't
+    /// exist.
+    account: Option<Account>,
+    shard_uid: ShardUId,
+}
+
+/// Reads the state being patched from the flat storage.
+pub(crate) struct StateReader {
+    store: Store,
+}
+
+impl StateReader {
+    pub(crate) fn new(store: Store) -> Self {
+        Self { store }
+    }
+
+    fn get(&self, shard_uid: ShardUId, key: &[u8]) -> anyhow::Result<Option<FlatStateValue>> {
+        Ok(self.store.flat_store().get(shard_uid, key)?)
+    }
+
+    pub(crate) fn get_bytes(
+        &self,
+        shard_uid: ShardUId,
+        key: &[u8],
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        self.get(shard_uid, key)?.map(|value| self.value_bytes(shard_uid, value)).transpose()
+    }
+
+    pub(crate) fn value_bytes(
+        &self,
+        shard_uid: ShardUId,
+        value: FlatStateValue,
+    ) -> anyhow::Result<Vec<u8>> {
+        Ok(match value {
+            FlatStateValue::Inlined(value) => value,
+            FlatStateValue::Ref(value_ref) => {
+                TrieDBStorage::new(self.store.trie_store(), shard_uid)
+                    .retrieve_raw_bytes(&value_ref.hash)?
+                    .to_vec()
+            }
+        })
+    }
+
+    /// Returns the raw trie keys of all entries starting with the prefix.
+    fn keys_with_prefix(&self, shard_uid: ShardUId, prefix: &[u8]) -> anyhow::Result<Vec<Vec<u8>>> {
+        // The prefixes end with a separator, never with 0xff.
+        let mut upper_bound = prefix.to_vec();
+        *upper_bound.last_mut().unwrap() += 1;
+        let mut keys = vec![];
+        for item in self.store.flat_store().iter_range(shard_uid, Some(prefix), Some(&upper_bound))
+        {
+            keys.push(item?.0);
+        }
+        Ok(keys)
+    }
+}
+
+impl StatePatch {
+    pub(crate) fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open the state patch {}", path.display()))?;
+        serde_json::from_reader(std::io::BufReader::new(file))
+            .with_context(|| format!("Failed to parse the state patch {}", path.display()))
+    }
+
+    pub(crate) fn apply(
+        self,
+        store: Store,
+        config: &StorageUsageConfig,
+        storage_mutator: &mut StorageMutator,
+    ) -> anyhow::Result<PatchStats> {
+        let mut patcher = Patcher {
+            reader: StateReader::new(store),
+            config,
+            storage_mutator,
+            accounts: BTreeMap::new(),
+            written: HashMap::new(),
+            stats: PatchStats::default(),
+        };
+        for deletion in self.delete {
+            patcher.delete(&deletion).with_context(|| format!("Failed to delete {deletion:?}"))?;
+        }
+        // Accounts go first, so that the other records can refer to accounts
+        // created by the patch.
+        let (account_records, other_records): (Vec<_>, Vec<_>) = self
+            .records
+            .into_iter()
+            .partition(|record| matches!(record, StateRecord::Account { .. }));
+        for record in account_records.into_iter().chain(other_records) {
+            let description = record.to_string();
+            patcher.set(record).with_context(|| format!("Failed to set {description}"))?;
+        }
+        patcher.finish()
+    }
+}
+
+struct Patcher<'a> {
+    reader: StateReader,
+    config: &'a StorageUsageConfig,
+    storage_mutator: &'a mut StorageMutator,
+    accounts: BTreeMap<AccountId, PatchedAccount>,
+    /// Lengths of the values written by the patch so far, by raw trie key,
+    /// `None` for deleted values. The state reader doesn't see them until the
+    /// changes are committed.
+    written: HashMap<Vec<u8>, Option<usize>>,
+    stats: PatchStats,
+}
+
+impl<'a> Patcher<'a> {
+    /// Loads the account from the state when it is touched for the first time.
+    fn account(&mut self, account_id: &AccountId) -> anyhow::Result<&mut PatchedAccount> {
+        if !self.accounts.contains_key(account_id) {
+            let shard_uid = self.storage_mutator.shard_uid(account_id)?;
+            let key = TrieKey::Account { account_id: account_id.clone() }.to_vec();
+            let account = self
+                .reader
+                .get_bytes(shard_uid, &key)?
+                .map(|value| borsh::from_slice::<Account>(&value))
+                .transpose()?;
+            self.accounts.insert(account_id.clone(), PatchedAccount { account, shard_uid });
+        }
+        Ok(self.accounts.get_mut(account_id).unwrap())
+    }
+
+    /// Returns the account, which must exist.
+    fn existing_account(&mut self, account_id: &AccountId) -> anyhow::Result<&mut Account> {
+        self.account(account_id)?
+            .account
+            .as_mut()
+            .with_context(|| format!("Account {account_id} does not exist"))
+    }
+
+    /// Returns the length of the current value of the key of an existing
+    /// account, including the changes made by the patch.
+    fn value_len(
+        &mut self,
+        account_id: &AccountId,
+        key: &TrieKey,
+    ) -> anyhow::Result<Option<usize>> {
+        self.existing_account(account_id)?;
+        let key = key.to_vec();
+        if let Some(len) = self.written.get(&key) {
+            return Ok(*len);
+        }
+        let shard_uid = self.accounts[account_id].shard_uid;
+        Ok(self.reader.get(shard_uid, &key)?.map(|value| value.value_len()))
+    }
+
+    /// Updates the storage usage of the account for replacing the value of
+    /// `old_usage` with a value of `new_usage`, zero for missing values.
+    fn update_storage_usage(
+        &mut self,
+        account_id: &AccountId,
+        old_usage: StorageUsage,
+        new_usage: StorageUsage,
+    ) -> anyhow::Result<()> {
+        let account = self.existing_account(account_id)?;
+        let storage_usage = account
+            .storage_usage()
+            .checked_sub(old_usage)
+            .context("Storage usage of the account is inconsistent with its state")?;
+        account.set_storage_usage(storage_usage + new_usage);
+        Ok(())
+    }
+
+    fn access_key_storage_usage(&self, public_key: &PublicKey, value_len: usize) -> StorageUsage {
+        self.config.num_extra_bytes_record
+            + borsh::object_length(public_key).unwrap() as u64
+            + value_len as u64
+    }
+
+    fn data_storage_usage(&self, data_key: &[u8], value_len: usize) -> StorageUsage {
+        self.config.num_extra_bytes_record + data_key.len() as u64 + value_len as u64
+    }
+
+    fn delete(&mut self, deletion: &StateDeletion) -> anyhow::Result<()> {
+        match deletion {
+            StateDeletion::Account { account_id } => return self.delete_account(account_id),
+            StateDeletion::AccessKey { account_id, public_key } => {
+                let key = TrieKey::AccessKey {
+                    account_id: account_id.clone(),
+                    public_key: public_key.clone(),
+                };
+                let len = self.value_len(account_id, &key)?.context("Access key not found")?;
+                let old_usage = self.access_key_storage_usage(public_key, len);
+                self.update_storage_usage(account_id, old_usage, 0)?;
+                self.storage_mutator.delete_access_key(account_id, public_key.clone())?;
+                self.written.insert(key.to_vec(), None);
+            }
+            StateDeletion::Contract { account_id } => {
+                let key = TrieKey::ContractCode { account_id: account_id.clone() };
+                let len = self.value_len(account_id, &key)?.context("Contract not found")?;
+                self.update_storage_usage(account_id, len as u64, 0)?;
+                self.existing_account(account_id)?.set_code_hash(CryptoHash::default());
+                self.storage_mutator.delete_code(account_id)?;
+                self.written.insert(key.to_vec(), None);
+            }
+            StateDeletion::Data { account_id, data_key } => {
+                let key = TrieKey::ContractData {
+                    account_id: account_id.clone(),
+                    key: data_key.to_vec(),
+                };
+                let len = self.value_len(account_id, &key)?.context("Data not found")?;
+                let old_usage = self.data_storage_usage(data_key, len);
+                self.update_storage_usage(account_id, old_usage, 0)?;
+                self.storage_mutator.delete_data(account_id, data_key)?;
+                self.written.insert(key.to_vec(), None);
+            }
+        }
+        self.stats.records_deleted += 1;
+        Ok(())
+    }
+
+    fn delete_account(&mut self, account_id: &AccountId) -> anyhow::Result<()> {
+        let has_code = self.existing_account(account_id)?.code_hash() != CryptoHash::default();
+        let shard_uid = self.accounts[account_id].shard_uid;
+        let mut keys = vec![];
+        let prefix = get_raw_prefix_for_access_keys(account_id);
+        for key in self.reader.keys_with_prefix(shard_uid, &prefix)? {
+            let public_key = parse_public_key_from_access_key_key(&key, account_id)?;
+            keys.push(TrieKey::AccessKey { account_id: account_id.clone(), public_key });
+        }
+        let prefix = get_raw_prefix_for_contract_data(account_id, &[]);
+        for key in self.reader.keys_with_prefix(shard_uid, &prefix)? {
+            let data_key = parse_data_key_from_contract_data_key(&key, account_id)?;
+            keys.push(TrieKey::ContractData {
+                account_id: account_id.clone(),
+                key: data_key.to_vec(),
+            });
+        }
+        if has_code {
+            keys.push(TrieKey::ContractCode { account_id: account_id.clone() });
+        }
+        for key in keys {
+            // Keys deleted earlier in the patch are still in the flat storage.
+            if self.written.insert(key.to_vec(), None) == Some(None) {
+                continue;
+            }
+            match key {
+                TrieKey::AccessKey { public_key, .. } => {
+                    self.storage_mutator.delete_access_key(account_id, public_key)?
+                }
+                TrieKey::ContractData { key, .. } => {
+                    self.storage_mutator.delete_data(account_id, &key.into())?
+                }
+                TrieKey::ContractCode { .. } => self.storage_mutator.delete_code(account_id)?,
+                _ => unreachable!(),
+            }
+            self.stats.records_deleted += 1;
+        }
+        self.storage_mutator.delete_account(account_id)?;
+        self.accounts.get_mut(account_id).unwrap().account = None;
+        self.stats.accounts_deleted += 1;
+        Ok(())
+    }
+
+    fn set(&mut self, record: StateRecord) -> anyhow::Result<()> {
+        match record {
+            StateRecord::Account { account_id, account } => {
+                let num_bytes_account = self.config.num_bytes_account;
+                let patched = self.account(&account_id)?;
+                let (code_hash, storage_usage) = match &patched.account {
+                    Some(existing) => (existing.code_hash(), existing.storage_usage()),
+                    None => (CryptoHash::default(), num_bytes_account),
+                };
+                let mut account = account;
+                account.set_code_hash(code_hash);
+                account.set_storage_usage(storage_usage);
+                patched.account = Some(account);
+            }
+            StateRecord::AccessKey { account_id, public_key, access_key } => {
+                let key = TrieKey::AccessKey {
+                    account_id: account_id.clone(),
+                    public_key: public_key.clone(),
+                };
+                let old_usage = self
+                    .value_len(&account_id, &key)?
+                    .map_or(0, |len| self.access_key_storage_usage(&public_key, len));
+                let len = borsh::object_length(&access_key).unwrap();
+                let new_usage = self.access_key_storage_usage(&public_key, len);
+                self.update_storage_usage(&account_id, old_usage, new_usage)?;
+                self.storage_mutator.set_access_key(&account_id, public_key, access_key)?;
+                self.written.insert(key.to_vec(), Some(len));
+            }
+            StateRecord::Contract { account_id, code } => {
+                let key = TrieKey::ContractCode { account_id: account_id.clone() };
+                let old_usage = self.value_len(&account_id, &key)?.unwrap_or(0) as u64;
+                self.update_storage_usage(&account_id, old_usage, code.len() as u64)?;
+                self.existing_account(&account_id)?.set_code_hash(hash(&code));
+                self.written.insert(key.to_vec(), Some(code.len()));
+                self.storage_mutator.set_code(&account_id, code)?;
+            }
+            StateRecord::Data { account_id, data_key, value } => {
+                let key = TrieKey::ContractData {
+                    account_id: account_id.clone(),
+                    key: data_key.to_vec(),
+                };
+                let old_usage = self
+                    .value_len(&account_id, &key)?
+                    .map_or(0, |len| self.data_storage_usage(&data_key, len));
+                let new_usage = self.data_storage_usage(&data_key, value.len());
+                self.update_storage_usage(&account_id, old_usage, new_usage)?;
+                self.written.insert(key.to_vec(), Some(value.len()));
+                self.storage_mutator.set_data(&account_id, &data_key, value)?;
+            }
+            StateRecord::PostponedReceipt(_)
+            | StateRecord::ReceivedData { .. }
+            | StateRecord::DelayedReceipt(_) => {
+                anyhow::bail!("Receipts and received data can't be patched")
+            }
+        }
+        self.stats.records_set += 1;
+        Ok(())
+    }
+
+    /// Writes the patched accounts.
+    fn finish(self) -> anyhow::Result<PatchStats> {
+        let Self { storage_mutator, accounts, mut stats, .. } = self;
+        for (account_id, patched) in accounts {
+            if let Some(account) = patched.account {
+                storage_mutator.set_account(&account_id, account)?;
+                stats.accounts_updated += 1;
+            }
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_crypto::ED25519PublicKey;
+    use near_primitives::account::{AccessKey, AccessKeyPermission, FunctionCallPermission};
+    use near_primitives::shard_layout::ShardLayout;
+    use near_primitives::version::PROTOCOL_VERSION;
+    use near_store::test_utils::{create_test_store, TestTriesBuilder};
+    use near_store::Trie;
+
+    const CONFIG: StorageUsageConfig = StorageUsageConfig {
+        storage_amount_per_byte: 0,
+        num_bytes_account: 100,
+        num_extra_bytes_record: 40,
+    };
+
+    /// 100 for the account, 40 + 33 + 9 for the full access key, 10 for the
+    /// code and 40 + 1 + 5 for the data.
+    const STORAGE_USAGE: StorageUsage = 238;
+
+    fn alice() -> AccountId {
+        "alice.near".parse().unwrap()
+    }
+
+    fn public_key(seed: u8) -> PublicKey {
+        PublicKey::ED25519(ED25519PublicKey([seed; 32]))
+    }
+
+    fn code() -> Vec<u8> {
+        vec![1; 10]
+    }
+
+    /// A single shard state with the account of alice, with a full access
+    /// key, a contract and a data record.
+    struct TestState {
+        store: Store,
+        shard_uid: ShardUId,
+    }
+
+    impl TestState {
+        fn new() -> Self {
+            let store = create_test_store();
+            let shard_uid = ShardLayout::single_shard().shard_uids().next().unwrap();
+            let account = Account::new(1000, 0, 0, hash(&code()), STORAGE_USAGE, PROTOCOL_VERSION);
+            let values = [
+                (TrieKey::Account { account_id: alice() }, borsh::to_vec(&account).unwrap()),
+                (
+                    TrieKey::AccessKey { account_id: alice(), public_key: public_key(1) },
+                    borsh::to_vec(&AccessKey::full_access()).unwrap(),
+                ),
+                (TrieKey::ContractCode { account_id: alice() }, code()),
+                (
+                    TrieKey::ContractData { account_id: alice(), key: b"k".to_vec() },
+                    b"value".to_vec(),
+                ),
+            ];
+            // The patch reads the state from the flat storage only.
+            let mut update = store.flat_store().store_update();
+            for (key, value) in values {
+                update.set(shard_uid, key.to_vec(), Some(FlatStateValue::inlined(&value)));
+            }
+            update.commit().unwrap();
+            Self { store, shard_uid }
+        }
+
+        fn apply(&self, patch: StatePatch) -> anyhow::Result<PatchStats> {
+            let shard_tries = TestTriesBuilder::new().with_store(self.store.clone()).build();
+            let mut storage_mutator = StorageMutator::new(
+                shard_tries,
+                ShardLayout::single_shard(),
+                vec![Trie::EMPTY_ROOT],
+            )?;
+            let stats = patch.apply(self.store.clone(), &CONFIG, &mut storage_mutator)?;
+            storage_mutator.commit()?;
+            Ok(stats)
+        }
+
+        fn get(&self, key: TrieKey) -> Option<Vec<u8>> {
+            StateReader::new(self.store.clone()).get_bytes(self.shard_uid, &key.to_vec()).unwrap()
+        }
+
+        fn account(&self, account_id: &AccountId) -> Option<Account> {
+            self.get(TrieKey::Account { account_id: account_id.clone() })
+                .map(|value| borsh::from_slice(&value).unwrap())
+        }
+    }
+
+    fn data(account_id: &AccountId, key: &[u8], value: &[u8]) -> StateRecord {
+        StateRecord::Data {
+            account_id: account_id.clone(),
+            data_key: key.to_vec().into(),
+            value: value.to_vec().into(),
+        }
+    }
+
+    #[test]
+    fn test_replace_records() {
+        let state = TestState::new();
+        let new_code = vec![2; 25];
+        let access_key = AccessKey {
+            nonce: 0,
+            permission: AccessKeyPermission::FunctionCall(FunctionCallPermission {
+                allowance: None,
+                receiver_id: "alice.near".to_string(),
+                method_names: vec![],
+            }),
+        };
+        let patch = StatePatch {
+            delete: vec![],
+            records: vec![
+                StateRecord::AccessKey {
+                    account_id: alice(),
+                    public_key: public_key(1),
+                    access_key: access_key.clone(),
+                },
+                StateRecord::Contract { account_id: alice(), code: new_code.clone() },
+                data(&alice(), b"k", b"longer value"),
+                data(&alice(), b"k", b"xy"),
+                data(&alice(), b"key2", b"v"),
+                // Set last, but applied first.
+                StateRecord::Account {
+                    account_id: alice(),
+                    account: Account::new(5000, 0, 0, CryptoHash::default(), 0, PROTOCOL_VERSION),
+                },
+            ],
+        };
+        let stats = state.apply(patch).unwrap();
+        assert_eq!(stats.records_set, 6);
+        assert_eq!(stats.accounts_updated, 1);
+
+        let account = state.account(&alice()).unwrap();
+        assert_eq!(account.amount(), 5000);
+        assert_eq!(account.code_hash(), hash(&new_code));
+        // The access key grows from 9 to 9 + 1 + 14 + 4 bytes, the code from
+        // 10 to 25 bytes, the data from 5 to 2 bytes and the new data adds
+        // 40 + 4 + 1 bytes.
+        assert_eq!(account.storage_usage(), STORAGE_USAGE + 19 + 15 - 3 + 45);
+        let key = TrieKey::AccessKey { account_id: alice(), public_key: public_key(1) };
+        assert_eq!(state.get(key), Some(borsh::to_vec(&access_key).unwrap()));
+        assert_eq!(state.get(TrieKey::ContractCode { account_id: alice() }), Some(new_code));
+        let key = TrieKey::ContractData { account_id: alice(), key: b"k".to_vec() };
+        assert_eq!(state.get(key), Some(b"xy".to_vec()));
+    }
+
+    #[test]
+    fn test_delete_records() {
+        let state = TestState::new();
+        let patch = StatePatch {
+            delete: vec![
+                StateDeletion::AccessKey { account_id: alice(), public_key: public_key(1) },
+                StateDeletion::Contract { account_id: alice() },
+                StateDeletion::Data { account_id: alice(), data_key: b"k".to_vec().into() },
+            ],
+            records: vec![],
+        };
+        let stats = state.apply(patch).unwrap();
+        assert_eq!(stats.records_deleted, 3);
+
+        let account = state.account(&alice()).unwrap();
+        assert_eq!(account.amount(), 1000);
+        assert_eq!(account.code_hash(), CryptoHash::default());
+        assert_eq!(account.storage_usage(), CONFIG.num_bytes_account);
+        assert_eq!(state.get(TrieKey::ContractCode { account_id: alice() }), None);
+        let key = TrieKey::ContractData { account_id: alice(), key: b"k".to_vec() };
+        assert_eq!(state.get(key), None);
+
+        // The records don't exist anymore.
+        let patch = StatePatch {
+            delete: vec![StateDeletion::Contract { account_id: alice() }],
+            records: vec![],
+        };
+        assert!(state.apply(patch).is_err());
+    }
+
+    #[test]
+    fn test_delete_and_recreate_account() {
+        let state = TestState::new();
+        let patch = StatePatch {
+            delete: vec![StateDeletion::Account { account_id: alice() }],
+            records: vec![
+                StateRecord::Account {
+                    account_id: alice(),
+                    account: Account::new(7, 0, 0, hash(&code()), 1, PROTOCOL_VERSION),
+                },
+                StateRecord::AccessKey {
+                    account_id: alice(),
+                    public_key: public_key(2),
+                    access_key: AccessKey::full_access(),
+                },
+                data(&alice(), b"k", b"v"),
+            ],
+        };
+        let stats = state.apply(patch).unwrap();
+        assert_eq!(stats.accounts_deleted, 1);
+        assert_eq!(stats.records_deleted, 3);
+        assert_eq!(stats.records_set, 3);
+
+        // The old key, code and data don't count, the new key and data do.
+        let account = state.account(&alice()).unwrap();
+        assert_eq!(account.amount(), 7);
+        assert_eq!(account.code_hash(), CryptoHash::default());
+        assert_eq!(account.storage_usage(), CONFIG.num_bytes_account + 82 + 42);
+        let key = TrieKey::AccessKey { account_id: alice(), public_key: public_key(1) };
+        assert_eq!(state.get(key), None);
+        assert_eq!(state.get(TrieKey::ContractCode { account_id: alice() }), None);
+        let key = TrieKey::ContractData { account_id: alice(), key: b"k".to_vec() };
+        assert_eq!(state.get(key), Some(b"v".to_vec()));
+    }
+
+    #[test]
+    fn test_delete_account_after_its_records() {
+        let state = TestState::new();
+        let patch = StatePatch {
+            delete: vec![
+                StateDeletion::AccessKey { account_id: alice(), public_key: public_key(1) },
+                StateDeletion::Data { account_id: alice(), data_key: b"k".to_vec().into() },
+                StateDeletion::Account { account_id: alice() },
+            ],
+            records: vec![],
+        };
+        let stats = state.apply(patch).unwrap();
+        // The key and data are counted once, the account deletion adds the
+        // code only.
+        assert_eq!(stats.accounts_deleted, 1);
+        assert_eq!(stats.records_deleted, 3);
+        assert_eq!(state.account(&alice()), None);
+        let key = TrieKey::AccessKey { account_id: alice(), public_key: public_key(1) };
+        assert_eq!(state.get(key), None);
+        assert_eq!(state.get(TrieKey::ContractCode { account_id: alice() }), None);
+        let key = TrieKey::ContractData { account_id: alice(), key: b"k".to_vec() };
+        assert_eq!(state.get(key), None);
+    }
+
+    #[test]
+    fn test_new_account() {
+        let state = TestState::new();
+        let bob: AccountId = "bob.near".parse().unwrap();
+        let patch = StatePatch {
+            delete: vec![],
+            records: vec![
+                StateRecord::Contract { account_id: bob.clone(), code: code() },
+                StateRecord::AccessKey {
+                    account_id: bob.clone(),
+                    public_key: public_key(3),
+                    access_key: AccessKey::full_access(),
+                },
+                StateRecord::Account {
+                    account_id: bob.clone(),
+                    account: Account::new(1, 0, 0, CryptoHash::default(), 0, PROTOCOL_VERSION),
+                },
+            ],
+        };
+        state.apply(patch).unwrap();
+        let account = state.account(&bob).unwrap();
+        assert_eq!(account.code_hash(), hash(&code()));
+        assert_eq!(account.storage_usage(), CONFIG.num_bytes_account + 10 + 82);
+        assert_eq!(state.account(&alice()).unwrap().storage_usage(), STORAGE_USAGE);
+
+        // Records of an account that doesn't exist are rejected.
+        let carol: AccountId = "carol.near".parse().unwrap();
+        let patch = StatePatch { delete: vec![], records: vec![data(&carol, b"k", b"v")] };
+        assert!(state.apply(patch).is_err());
+    }
+}
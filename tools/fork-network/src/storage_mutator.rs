@@ -1,43 +1,50 @@
 use crate::single_shard_storage_mutator::SingleShardStorageMutator;
 use near_crypto::PublicKey;
-use near_epoch_manager::EpochManagerAdapter;
 use near_primitives::account::{AccessKey, Account};
-use near_primitives::types::{AccountId, EpochId, StateRoot};
-use nearcore::NightshadeRuntime;
-use std::sync::Arc;
+use near_primitives::shard_layout::{ShardLayout, ShardUId};
+use near_primitives::types::{AccountId, ShardIndex, StateRoot, StoreKey, StoreValue};
+use near_store::ShardTries;
 
 /// Object that updates the existing state. Combines all changes, commits them
 /// and returns new state roots.
 pub(crate) struct StorageMutator {
-    epoch_manager: Arc<dyn EpochManagerAdapter>,
-    epoch_id: EpochId,
+    shard_tries: ShardTries,
+    shard_layout: ShardLayout,
     mutators: Vec<SingleShardStorageMutator>,
 }
 
 impl StorageMutator {
+    /// The state roots are in the shard index order of `shard_layout`.
     pub(crate) fn new(
-        epoch_manager: Arc<dyn EpochManagerAdapter>,
-        runtime: &NightshadeRuntime,
-        epoch_id: EpochId,
+        shard_tries: ShardTries,
+        shard_layout: ShardLayout,
         state_roots: Vec<StateRoot>,
     ) -> anyhow::Result<Self> {
-        let shard_layout = epoch_manager.get_shard_layout(&epoch_id)?;
         assert_eq!(shard_layout.shard_ids().count(), state_roots.len());
 
         let mut mutators = vec![];
         for state_root in state_roots {
-            mutators.push(SingleShardStorageMutator::new(runtime, state_root)?);
+            mutators.push(SingleShardStorageMutator::new(shard_tries.clone(), state_root)?);
         }
-        Ok(Self { epoch_manager, epoch_id, mutators })
+        Ok(Self { shard_tries, shard_layout, mutators })
     }
 
     fn mutator(
         &mut self,
         account_id: &AccountId,
     ) -> anyhow::Result<&mut SingleShardStorageMutator> {
-        let shard_info =
-            self.epoch_manager.account_id_to_shard_info(&account_id, &self.epoch_id)?;
-        Ok(&mut self.mutators[shard_info.shard_index])
+        let shard_index = self.shard_index(account_id)?;
+        Ok(&mut self.mutators[shard_index])
+    }
+
+    pub(crate) fn shard_index(&self, account_id: &AccountId) -> anyhow::Result<ShardIndex> {
+        let shard_id = self.shard_layout.account_id_to_shard_id(account_id);
+        Ok(self.shard_layout.get_shard_index(shard_id)?)
+    }
+
+    pub(crate) fn shard_uid(&self, account_id: &AccountId) -> anyhow::Result<ShardUId> {
+        let shard_id = self.shard_layout.account_id_to_shard_id(account_id);
+        Ok(ShardUId::from_shard_id_and_layout(shard_id, &self.shard_layout))
     }
 
     pub(crate) fn set_account(
@@ -48,6 +55,10 @@ impl StorageMutator {
         self.mutator(account_id)?.set_account(account_id.clone(), value)
     }
 
+    pub(crate) fn delete_account(&mut self, account_id: &AccountId) -> anyhow::Result<()> {
+        self.mutator(account_id)?.delete_account(account_id.clone())
+    }
+
     pub(crate) fn set_access_key(
         &mut self,
         account_id: &AccountId,
@@ -57,11 +68,75 @@ impl StorageMutator {
         self.mutator(account_id)?.set_access_key(account_id.clone(), public_key, access_key)
     }
 
+    pub(crate) fn delete_access_key(
+        &mut self,
+        account_id: &AccountId,
+        public_key: PublicKey,
+    ) -> anyhow::Result<()> {
+        self.mutator(account_id)?.delete_access_key(account_id.clone(), public_key)
+    }
+
+    pub(crate) fn set_code(&mut self, account_id: &AccountId, code: Vec<u8>) -> anyhow::Result<()> {
+        self.mutator(account_id)?.set_code(account_id.clone(), code)
+    }
+
+    pub(crate) fn delete_code(&mut self, account_id: &AccountId) -> anyhow::Result<()> {
+        self.mutator(account_id)?.delete_code(account_id.clone())
+    }
+
+    pub(crate) fn set_data(
+        &mut self,
+        account_id: &AccountId,
+        data_key: &StoreKey,
+        value: StoreValue,
+    ) -> anyhow::Result<()> {
+        self.mutator(account_id)?.set_data(account_id.clone(), data_key, value)
+    }
+
+    pub(crate) fn delete_data(
+        &mut self,
+        account_id: &AccountId,
+        data_key: &StoreKey,
+    ) -> anyhow::Result<()> {
+        self.mutator(account_id)?.delete_data(account_id.clone(), data_key)
+    }
+
+    /// Sets a raw trie key in the shard with the given index.
+    pub(crate) fn set_raw(
+        &mut self,
+        shard_index: ShardIndex,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        self.mutators[shard_index].set_raw(key, value)
+    }
+
+    pub(crate) fn should_commit(&self, batch_size: u64) -> bool {
+        self.mutators.iter().any(|mutator| mutator.should_commit(batch_size))
+    }
+
+    /// Commits the changes collected so far and continues on top of the new
+    /// state roots.
+    pub(crate) fn commit_batch(&mut self) -> anyhow::Result<()> {
+        let mutators = std::mem::take(&mut self.mutators);
+        for state_root in Self::commit_mutators(&self.shard_layout, mutators)? {
+            self.mutators
+                .push(SingleShardStorageMutator::new(self.shard_tries.clone(), state_root)?);
+        }
+        Ok(())
+    }
+
     pub(crate) fn commit(self) -> anyhow::Result<Vec<StateRoot>> {
-        let shard_layout = self.epoch_manager.get_shard_layout(&self.epoch_id)?;
+        Self::commit_mutators(&self.shard_layout, self.mutators)
+    }
+
+    fn commit_mutators(
+        shard_layout: &ShardLayout,
+        mutators: Vec<SingleShardStorageMutator>,
+    ) -> anyhow::Result<Vec<StateRoot>> {
         let all_shard_uids = shard_layout.shard_uids();
         let mut state_roots = vec![];
-        for (mutator, shard_uid) in self.mutators.into_iter().zip(all_shard_uids.into_iter()) {
+        for (mutator, shard_uid) in mutators.into_iter().zip(all_shard_uids.into_iter()) {
             let state_root = mutator.commit(&shard_uid, 0)?;
             state_roots.push(state_root);
         }
@@ -0,0 +1,93 @@
+//! Light client that follows the chain from a trusted checkpoint using the
+//! light client blocks served by any RPC node, and verifies execution outcome
+//! proofs against its head. Nothing returned by the RPC node is trusted,
+//! everything is verified against the hash of the checkpoint block.
+
+mod rpc;
+mod state;
+mod verify;
+
+pub use rpc::RpcClient;
+pub use state::LightClientState;
+pub use verify::{
+    block_producers_match_hash, validate_light_client_block, verify_execution_proof,
+    LightClientError,
+};
+
+use anyhow::Context;
+use near_jsonrpc_primitives::types::light_client::RpcLightClientExecutionProofResponse;
+use near_primitives::block::BlockHeader;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::TransactionOrReceiptId;
+use near_primitives::views::LightClientBlockLiteView;
+
+/// Creates the light client state from the block with the trusted hash.
+pub fn bootstrap(
+    rpc: &RpcClient,
+    trusted_block_hash: &CryptoHash,
+) -> anyhow::Result<LightClientState> {
+    let head = lite_view(rpc, trusted_block_hash)?;
+    // The hash of the last block of the previous epoch is the next epoch id of
+    // the head, and that block commits to the block producers of the epoch of
+    // the head.
+    let epoch_anchor = lite_view(rpc, &head.inner_lite.next_epoch_id)
+        .context("failed to get the last block of the previous epoch")?;
+    let block_producers = rpc.validators_ordered(trusted_block_hash)?;
+    let mut state = LightClientState::from_checkpoint(
+        trusted_block_hash,
+        head,
+        &epoch_anchor,
+        block_producers,
+    )?;
+    // The next epoch may already have started, in which case its block
+    // producers can be fetched and checked against the checkpoint.
+    if let Some(next_block) = rpc.next_light_client_block(trusted_block_hash)? {
+        if next_block.inner_lite.epoch_id == state.head.inner_lite.next_epoch_id {
+            let next_block_hash = verify::to_lite_view(&next_block).hash();
+            state.set_next_block_producers(rpc.validators_ordered(&next_block_hash)?)?;
+        }
+    }
+    Ok(state)
+}
+
+/// Returns the lite view of the block, its hash is checked by the caller.
+fn lite_view(rpc: &RpcClient, block_hash: &CryptoHash) -> anyhow::Result<LightClientBlockLiteView> {
+    let header: BlockHeader = rpc.block(block_hash)?.header.into();
+    Ok(LightClientBlockLiteView::from(header))
+}
+
+/// Advances the head to the next light client block served by the RPC node.
+/// Returns whether the head changed.
+pub fn sync_step(rpc: &RpcClient, state: &mut LightClientState) -> anyhow::Result<bool> {
+    let Some(block) = rpc.next_light_client_block(&state.head_hash())? else {
+        return Ok(false);
+    };
+    let epoch_id = block.inner_lite.epoch_id;
+    if epoch_id == state.head.inner_lite.next_epoch_id
+        && epoch_id != state.head.inner_lite.epoch_id
+        && state.next_block_producers.is_none()
+    {
+        let block_hash = verify::to_lite_view(&block).hash();
+        let next_block_producers = rpc
+            .validators_ordered(&block_hash)
+            .context("failed to get the block producers of the next epoch")?;
+        state.set_next_block_producers(next_block_producers)?;
+    }
+    if block.inner_lite.height <= state.head.inner_lite.height {
+        return Ok(false);
+    }
+    state.apply_block(&block)?;
+    Ok(true)
+}
+
+/// Fetches the execution outcome proof for the transaction or receipt and
+/// verifies it against the head.
+pub fn verify_outcome(
+    rpc: &RpcClient,
+    state: &LightClientState,
+    id: TransactionOrReceiptId,
+) -> anyhow::Result<RpcLightClientExecutionProofResponse> {
+    let proof = rpc.light_client_proof(id, state.head_hash())?;
+    verify_execution_proof(&state.head, &proof)?;
+    Ok(proof)
+}
@@ -0,0 +1,133 @@
+use anyhow::Context;
+use clap::Parser;
+use near_light_client::{bootstrap, sync_step, verify_outcome, LightClientState, RpcClient};
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, TransactionOrReceiptId};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Parser)]
+#[clap(about = "Light client following the chain through the light client blocks of an RPC node")]
+struct Cli {
+    /// File where the light client state is persisted.
+    #[clap(long, default_value = "light_client_state.json")]
+    state_file: PathBuf,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Initializes the state from a trusted block hash.
+    Init {
+        #[clap(long)]
+        rpc_url: String,
+        /// Hash of a block that is trusted, e.g. obtained from several independent sources.
+        #[clap(long)]
+        trusted_block_hash: CryptoHash,
+        /// Overwrite an existing state file.
+        #[clap(long)]
+        force: bool,
+    },
+    /// Advances the head as far as the RPC node allows.
+    Sync {
+        #[clap(long)]
+        rpc_url: String,
+        /// Keep polling for new light client blocks.
+        #[clap(long)]
+        follow: bool,
+        #[clap(long, default_value = "10")]
+        poll_interval_secs: u64,
+    },
+    /// Prints the current head.
+    Head,
+    /// Verifies that the transaction was executed in a block known to the head.
+    VerifyTx {
+        #[clap(long)]
+        rpc_url: String,
+        #[clap(long)]
+        tx_hash: CryptoHash,
+        #[clap(long)]
+        sender_id: AccountId,
+    },
+    /// Verifies that the receipt was executed in a block known to the head.
+    VerifyReceipt {
+        #[clap(long)]
+        rpc_url: String,
+        #[clap(long)]
+        receipt_id: CryptoHash,
+        #[clap(long)]
+        receiver_id: AccountId,
+    },
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let state_file = cli.state_file.as_path();
+    match cli.command {
+        Command::Init { rpc_url, trusted_block_hash, force } => {
+            if state_file.exists() && !force {
+                anyhow::bail!(
+                    "{} already exists, use --force to overwrite it",
+                    state_file.display()
+                );
+            }
+            let state = bootstrap(&RpcClient::new(&rpc_url), &trusted_block_hash)?;
+            state.save(state_file)?;
+            print_head(&state);
+        }
+        Command::Sync { rpc_url, follow, poll_interval_secs } => {
+            let rpc = RpcClient::new(&rpc_url);
+            let mut state = LightClientState::load(state_file)?;
+            loop {
+                sync(&rpc, &mut state, state_file)?;
+                if !follow {
+                    break;
+                }
+                std::thread::sleep(Duration::from_secs(poll_interval_secs));
+            }
+        }
+        Command::Head => print_head(&LightClientState::load(state_file)?),
+        Command::VerifyTx { rpc_url, tx_hash, sender_id } => {
+            let id = TransactionOrReceiptId::Transaction { transaction_hash: tx_hash, sender_id };
+            verify(&rpc_url, state_file, id)?;
+        }
+        Command::VerifyReceipt { rpc_url, receipt_id, receiver_id } => {
+            let id = TransactionOrReceiptId::Receipt { receipt_id, receiver_id };
+            verify(&rpc_url, state_file, id)?;
+        }
+    }
+    Ok(())
+}
+
+/// Applies all available light client blocks, saving the state after each of them.
+fn sync(rpc: &RpcClient, state: &mut LightClientState, state_file: &Path) -> anyhow::Result<()> {
+    while sync_step(rpc, state)? {
+        state.save(state_file)?;
+        print_head(state);
+    }
+    Ok(())
+}
+
+fn verify(rpc_url: &str, state_file: &Path, id: TransactionOrReceiptId) -> anyhow::Result<()> {
+    let state = LightClientState::load(state_file)?;
+    let proof = verify_outcome(&RpcClient::new(rpc_url), &state, id)
+        .context("failed to verify the execution outcome")?;
+    println!(
+        "Outcome {} is included in block {} at height {}",
+        proof.outcome_proof.id,
+        proof.outcome_proof.block_hash,
+        proof.block_header_lite.inner_lite.height
+    );
+    println!("{}", serde_json::to_string_pretty(&proof.outcome_proof.outcome)?);
+    Ok(())
+}
+
+fn print_head(state: &LightClientState) {
+    println!(
+        "Head: {} at height {} in epoch {}",
+        state.head_hash(),
+        state.head.inner_lite.height,
+        state.head.inner_lite.epoch_id
+    );
+}
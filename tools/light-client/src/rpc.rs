@@ -0,0 +1,84 @@
+use anyhow::{anyhow, Context};
+use near_jsonrpc_primitives::types::light_client::{
+    RpcLightClientExecutionProofRequest, RpcLightClientExecutionProofResponse,
+};
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::TransactionOrReceiptId;
+use near_primitives::views::validator_stake_view::ValidatorStakeView;
+use near_primitives::views::{BlockView, LightClientBlockView};
+use serde_json::{json, Value};
+
+/// Minimal JSON RPC client for the methods used by the light client.
+/// The responses are not trusted and are verified by the caller.
+pub struct RpcClient {
+    client: reqwest::blocking::Client,
+    url: String,
+}
+
+impl RpcClient {
+    pub fn new(url: &str) -> Self {
+        Self { client: reqwest::blocking::Client::new(), url: url.to_string() }
+    }
+
+    fn call<R: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> anyhow::Result<R> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": "light-client",
+            "method": method,
+            "params": params,
+        });
+        let response = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(&request)?)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("{} request to {} failed", method, self.url))?;
+        let mut response: Value = serde_json::from_slice(&response.bytes()?)
+            .with_context(|| format!("failed to parse {} response", method))?;
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!("{} returned an error: {}", method, error));
+        }
+        serde_json::from_value(response["result"].take())
+            .with_context(|| format!("failed to parse {} result", method))
+    }
+
+    pub fn block(&self, block_hash: &CryptoHash) -> anyhow::Result<BlockView> {
+        self.call("block", json!({ "block_id": block_hash }))
+    }
+
+    /// Ordered block producers of the epoch of the given block.
+    pub fn validators_ordered(
+        &self,
+        block_hash: &CryptoHash,
+    ) -> anyhow::Result<Vec<ValidatorStakeView>> {
+        self.call("EXPERIMENTAL_validators_ordered", json!({ "block_id": block_hash }))
+    }
+
+    /// Returns `None` if the node doesn't have a light client block after the given one.
+    pub fn next_light_client_block(
+        &self,
+        last_block_hash: &CryptoHash,
+    ) -> anyhow::Result<Option<LightClientBlockView>> {
+        let result: Value =
+            self.call("next_light_client_block", json!({ "last_block_hash": last_block_hash }))?;
+        if result.is_null() || result.as_object().is_some_and(|object| object.is_empty()) {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_value(result).context("failed to parse light client block")?))
+    }
+
+    pub fn light_client_proof(
+        &self,
+        id: TransactionOrReceiptId,
+        light_client_head: CryptoHash,
+    ) -> anyhow::Result<RpcLightClientExecutionProofResponse> {
+        let request = RpcLightClientExecutionProofRequest { id, light_client_head };
+        self.call("light_client_proof", serde_json::to_value(request)?)
+    }
+}
@@ -0,0 +1,227 @@
+use crate::verify::{
+    block_producers_match_hash, to_lite_view, validate_light_client_block, LightClientError,
+};
+use anyhow::Context;
+use near_primitives::hash::CryptoHash;
+use near_primitives::views::validator_stake_view::ValidatorStakeView;
+use near_primitives::views::{LightClientBlockLiteView, LightClientBlockView};
+use std::path::Path;
+
+/// The trusted state of a light client: the head and the block producers
+/// needed to validate the blocks that may follow it.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct LightClientState {
+    pub head: LightClientBlockLiteView,
+    /// Ordered block producers of the epoch of the head.
+    pub block_producers: Vec<ValidatorStakeView>,
+    /// Ordered block producers of the epoch after the epoch of the head.
+    /// Unknown right after bootstrapping from a checkpoint, until they are
+    /// provided with `set_next_block_producers` or taken from a light client
+    /// block.
+    pub next_block_producers: Option<Vec<ValidatorStakeView>>,
+}
+
+impl LightClientState {
+    /// Creates the state from a checkpoint. The hash of `head` must match the
+    /// trusted block hash. `epoch_anchor` is the last block of the epoch before
+    /// the epoch of the head, whose hash is the `next_epoch_id` of the head.
+    /// Its `next_bp_hash` commits to the block producers of the epoch of the
+    /// head, so `block_producers` are checked against it.
+    pub fn from_checkpoint(
+        trusted_block_hash: &CryptoHash,
+        head: LightClientBlockLiteView,
+        epoch_anchor: &LightClientBlockLiteView,
+        block_producers: Vec<ValidatorStakeView>,
+    ) -> Result<Self, LightClientError> {
+        let head_hash = head.hash();
+        if head_hash != *trusted_block_hash {
+            return Err(LightClientError::UntrustedBlock {
+                expected: *trusted_block_hash,
+                actual: head_hash,
+            });
+        }
+        let anchor_hash = epoch_anchor.hash();
+        if anchor_hash != head.inner_lite.next_epoch_id {
+            return Err(LightClientError::UntrustedBlock {
+                expected: head.inner_lite.next_epoch_id,
+                actual: anchor_hash,
+            });
+        }
+        if !block_producers_match_hash(&block_producers, &epoch_anchor.inner_lite.next_bp_hash) {
+            return Err(LightClientError::InvalidBlockProducers(
+                epoch_anchor.inner_lite.next_bp_hash,
+            ));
+        }
+        Ok(Self { head, block_producers, next_block_producers: None })
+    }
+
+    pub fn head_hash(&self) -> CryptoHash {
+        self.head.hash()
+    }
+
+    /// Sets the block producers of the next epoch after checking them
+    /// against the `next_bp_hash` of the head.
+    pub fn set_next_block_producers(
+        &mut self,
+        next_block_producers: Vec<ValidatorStakeView>,
+    ) -> Result<(), LightClientError> {
+        if !block_producers_match_hash(&next_block_producers, &self.head.inner_lite.next_bp_hash) {
+            return Err(LightClientError::InvalidBlockProducers(self.head.inner_lite.next_bp_hash));
+        }
+        self.next_block_producers = Some(next_block_producers);
+        Ok(())
+    }
+
+    /// Validates the block and makes it the new head.
+    pub fn apply_block(&mut self, block: &LightClientBlockView) -> Result<(), LightClientError> {
+        let epoch_id = block.inner_lite.epoch_id;
+        let is_next_epoch = epoch_id != self.head.inner_lite.epoch_id;
+        let epoch_block_producers = if !is_next_epoch {
+            &self.block_producers
+        } else if epoch_id == self.head.inner_lite.next_epoch_id {
+            self.next_block_producers
+                .as_ref()
+                .ok_or(LightClientError::UnknownBlockProducers(epoch_id))?
+        } else {
+            return Err(LightClientError::UnexpectedEpoch(epoch_id));
+        };
+        validate_light_client_block(&self.head, epoch_block_producers, block)?;
+
+        if is_next_epoch {
+            self.block_producers = self.next_block_producers.take().unwrap();
+        }
+        if let Some(next_bps) = &block.next_bps {
+            self.next_block_producers = Some(next_bps.clone());
+        }
+        self.head = to_lite_view(block);
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read(path).with_context(|| {
+            format!("failed to read light client state from {}", path.display())
+        })?;
+        serde_json::from_slice(&data)
+            .with_context(|| format!("failed to parse light client state from {}", path.display()))
+    }
+
+    /// Writes the state to a temporary file first so that the previous state
+    /// stays intact if writing is interrupted.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, path)
+            .with_context(|| format!("failed to write light client state to {}", path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LightClientState;
+    use crate::verify::tests::{
+        block_producers, bp_hash, head, signed_block, views, TestBlockProducer,
+    };
+    use crate::verify::LightClientError;
+    use near_primitives::views::LightClientBlockLiteView;
+
+    /// Returns the last block of the epoch before the epoch of `checkpoint`,
+    /// committing to `bps`, and sets the `next_epoch_id` of the checkpoint to
+    /// its hash.
+    fn epoch_anchor(
+        checkpoint: &mut LightClientBlockLiteView,
+        bps: &[TestBlockProducer],
+    ) -> LightClientBlockLiteView {
+        let mut anchor = head(5, "epoch0", "epoch1");
+        anchor.inner_lite.next_bp_hash = bp_hash(bps);
+        checkpoint.inner_lite.next_epoch_id = anchor.hash();
+        anchor
+    }
+
+    #[test]
+    fn test_follow_epochs() {
+        let bps1 = block_producers("epoch1", &[10, 10, 10]);
+        let bps2 = block_producers("epoch2", &[10, 10]);
+        let bps3 = block_producers("epoch3", &[10]);
+
+        let mut checkpoint = head(10, "epoch1", "epoch2");
+        let anchor = epoch_anchor(&mut checkpoint, &bps1);
+        checkpoint.inner_lite.next_bp_hash = bp_hash(&bps2);
+        let mut state = LightClientState::from_checkpoint(
+            &checkpoint.hash(),
+            checkpoint.clone(),
+            &anchor,
+            views(&bps1),
+        )
+        .unwrap();
+
+        // The block producers of the next epoch are unknown until they are set.
+        let mut lite_view = head(20, "epoch2", "epoch3");
+        lite_view.inner_lite.epoch_id = checkpoint.inner_lite.next_epoch_id;
+        lite_view.inner_lite.next_bp_hash = bp_hash(&bps3);
+        let block = signed_block(lite_view, &bps2, &[0, 1], Some(views(&bps3)));
+        assert_eq!(
+            state.apply_block(&block),
+            Err(LightClientError::UnknownBlockProducers(block.inner_lite.epoch_id))
+        );
+        assert!(matches!(
+            state.set_next_block_producers(views(&bps3)),
+            Err(LightClientError::InvalidBlockProducers(_))
+        ));
+        state.set_next_block_producers(views(&bps2)).unwrap();
+
+        state.apply_block(&block).unwrap();
+        assert_eq!(state.head.inner_lite.height, 20);
+        assert_eq!(state.block_producers, views(&bps2));
+        assert_eq!(state.next_block_producers, Some(views(&bps3)));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        state.save(&path).unwrap();
+        let loaded = LightClientState::load(&path).unwrap();
+        assert_eq!(loaded.head_hash(), state.head_hash());
+        assert_eq!(loaded.next_block_producers, state.next_block_producers);
+    }
+
+    #[test]
+    fn test_untrusted_checkpoint() {
+        let bps = block_producers("epoch1", &[10]);
+        let mut checkpoint = head(10, "epoch1", "epoch2");
+        let anchor = epoch_anchor(&mut checkpoint, &bps);
+        assert!(matches!(
+            LightClientState::from_checkpoint(
+                &near_primitives::hash::hash(b"other"),
+                checkpoint.clone(),
+                &anchor,
+                views(&bps)
+            ),
+            Err(LightClientError::UntrustedBlock { .. })
+        ));
+
+        // The block producers must be committed to by the last block of the previous epoch.
+        let other_bps = block_producers("other", &[10]);
+        assert_eq!(
+            LightClientState::from_checkpoint(
+                &checkpoint.hash(),
+                checkpoint.clone(),
+                &anchor,
+                views(&other_bps)
+            )
+            .unwrap_err(),
+            LightClientError::InvalidBlockProducers(anchor.inner_lite.next_bp_hash)
+        );
+        let other_anchor = head(6, "epoch0", "epoch1");
+        assert!(matches!(
+            LightClientState::from_checkpoint(
+                &checkpoint.hash(),
+                checkpoint.clone(),
+                &other_anchor,
+                views(&bps)
+            ),
+            Err(LightClientError::UntrustedBlock { .. })
+        ));
+        LightClientState::from_checkpoint(&checkpoint.hash(), checkpoint, &anchor, views(&bps))
+            .unwrap();
+    }
+}
@@ -0,0 +1,308 @@
+//! Verification of light client blocks and execution proofs as described in
+//! <https://nomicon.io/ChainSpec/LightClient>.
+
+use near_jsonrpc_primitives::types::light_client::RpcLightClientExecutionProofResponse;
+use near_primitives::block_header::{Approval, ApprovalInner};
+use near_primitives::hash::CryptoHash;
+use near_primitives::merkle::{combine_hash, compute_root_from_path};
+use near_primitives::types::validator_stake::ValidatorStake;
+use near_primitives::types::{AccountId, Balance, BlockHeight};
+use near_primitives::views::validator_stake_view::ValidatorStakeView;
+use near_primitives::views::{LightClientBlockLiteView, LightClientBlockView};
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum LightClientError {
+    #[error("block height {height} is not above the head height {head_height}")]
+    NotAboveHead { height: BlockHeight, head_height: BlockHeight },
+    #[error("block epoch {0} is neither the epoch nor the next epoch of the head")]
+    UnexpectedEpoch(CryptoHash),
+    #[error("block producers of epoch {0} are unknown")]
+    UnknownBlockProducers(CryptoHash),
+    #[error("block from the next epoch doesn't contain the block producers of the epoch after it")]
+    MissingNextBlockProducers,
+    #[error("block producers don't match the next_bp_hash {0}")]
+    InvalidBlockProducers(CryptoHash),
+    #[error("invalid approval signature of {0}")]
+    InvalidApprovalSignature(AccountId),
+    #[error("not enough stake approved the block: {approved} out of {total}")]
+    NotEnoughApprovals { approved: Balance, total: Balance },
+    #[error("block hash {actual} doesn't match the trusted block hash {expected}")]
+    UntrustedBlock { expected: CryptoHash, actual: CryptoHash },
+    #[error("computed outcome root doesn't match the one of the block")]
+    InvalidOutcomeRootProof,
+    #[error("block hash of the header doesn't match the one of the outcome proof")]
+    InvalidBlockHashProof,
+    #[error("block is not included in the block merkle root of the light client head")]
+    InvalidBlockProof,
+}
+
+/// Returns the lite view of the block, which is what the light client keeps as its head.
+pub fn to_lite_view(block: &LightClientBlockView) -> LightClientBlockLiteView {
+    LightClientBlockLiteView {
+        prev_block_hash: block.prev_block_hash,
+        inner_rest_hash: block.inner_rest_hash,
+        inner_lite: block.inner_lite.clone(),
+    }
+}
+
+/// Whether the block producers hash to `next_bp_hash`. Both the versioned
+/// format and the legacy format used before `BlockHeaderV3` are accepted.
+pub fn block_producers_match_hash(
+    block_producers: &[ValidatorStakeView],
+    next_bp_hash: &CryptoHash,
+) -> bool {
+    let validator_stakes: Vec<ValidatorStake> =
+        block_producers.iter().cloned().map(Into::into).collect();
+    CryptoHash::hash_borsh_iter(&validator_stakes) == *next_bp_hash
+        || CryptoHash::hash_borsh_iter(validator_stakes.into_iter().map(|stake| stake.into_v1()))
+            == *next_bp_hash
+}
+
+/// Validates that `block` can become the new head of a light client whose
+/// current head is `head`. `epoch_block_producers` are the ordered block
+/// producers of the epoch of `block`.
+pub fn validate_light_client_block(
+    head: &LightClientBlockLiteView,
+    epoch_block_producers: &[ValidatorStakeView],
+    block: &LightClientBlockView,
+) -> Result<(), LightClientError> {
+    let height = block.inner_lite.height;
+    if height <= head.inner_lite.height {
+        return Err(LightClientError::NotAboveHead { height, head_height: head.inner_lite.height });
+    }
+    let epoch_id = block.inner_lite.epoch_id;
+    if epoch_id != head.inner_lite.epoch_id && epoch_id != head.inner_lite.next_epoch_id {
+        return Err(LightClientError::UnexpectedEpoch(epoch_id));
+    }
+    if epoch_id == head.inner_lite.next_epoch_id && block.next_bps.is_none() {
+        return Err(LightClientError::MissingNextBlockProducers);
+    }
+
+    // The approvals are the ones of the block after the next one, endorsing
+    // the next block.
+    let current_block_hash = to_lite_view(block).hash();
+    let next_block_hash = combine_hash(&block.next_block_inner_hash, &current_block_hash);
+    let approval_message =
+        Approval::get_data_for_sig(&ApprovalInner::Endorsement(next_block_hash), height + 2);
+
+    let mut total_stake: Balance = 0;
+    let mut approved_stake: Balance = 0;
+    for (block_producer, approval) in
+        epoch_block_producers.iter().zip(block.approvals_after_next.iter())
+    {
+        let block_producer: ValidatorStake = block_producer.clone().into();
+        total_stake += block_producer.stake();
+        let Some(signature) = approval else {
+            continue;
+        };
+        approved_stake += block_producer.stake();
+        if !signature.verify(&approval_message, block_producer.public_key()) {
+            return Err(LightClientError::InvalidApprovalSignature(
+                block_producer.account_id().clone(),
+            ));
+        }
+    }
+    if approved_stake * 3 <= total_stake * 2 {
+        return Err(LightClientError::NotEnoughApprovals {
+            approved: approved_stake,
+            total: total_stake,
+        });
+    }
+
+    if let Some(next_bps) = &block.next_bps {
+        if !block_producers_match_hash(next_bps, &block.inner_lite.next_bp_hash) {
+            return Err(LightClientError::InvalidBlockProducers(block.inner_lite.next_bp_hash));
+        }
+    }
+    Ok(())
+}
+
+/// Verifies an execution outcome proof (result of the `light_client_proof`
+/// RPC call) requested with `head` as the light client head.
+pub fn verify_execution_proof(
+    head: &LightClientBlockLiteView,
+    proof: &RpcLightClientExecutionProofResponse,
+) -> Result<(), LightClientError> {
+    let outcome_hash = CryptoHash::hash_borsh(proof.outcome_proof.to_hashes());
+    let outcome_shard_root = compute_root_from_path(&proof.outcome_proof.proof, outcome_hash);
+    let block_outcome_root = compute_root_from_path(
+        &proof.outcome_root_proof,
+        CryptoHash::hash_borsh(outcome_shard_root),
+    );
+    if proof.block_header_lite.inner_lite.outcome_root != block_outcome_root {
+        return Err(LightClientError::InvalidOutcomeRootProof);
+    }
+    let block_hash = proof.outcome_proof.block_hash;
+    if proof.block_header_lite.hash() != block_hash {
+        return Err(LightClientError::InvalidBlockHashProof);
+    }
+    if compute_root_from_path(&proof.block_proof, block_hash) != head.inner_lite.block_merkle_root {
+        return Err(LightClientError::InvalidBlockProof);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{validate_light_client_block, LightClientError};
+    use near_crypto::{KeyType, SecretKey};
+    use near_primitives::block_header::{Approval, ApprovalInner};
+    use near_primitives::hash::{hash, CryptoHash};
+    use near_primitives::merkle::combine_hash;
+    use near_primitives::types::validator_stake::ValidatorStake;
+    use near_primitives::types::BlockHeight;
+    use near_primitives::views::validator_stake_view::ValidatorStakeView;
+    use near_primitives::views::{
+        BlockHeaderInnerLiteView, LightClientBlockLiteView, LightClientBlockView,
+    };
+
+    pub(crate) struct TestBlockProducer {
+        pub secret_key: SecretKey,
+        pub view: ValidatorStakeView,
+    }
+
+    pub(crate) fn block_producers(epoch: &str, stakes: &[u128]) -> Vec<TestBlockProducer> {
+        stakes
+            .iter()
+            .enumerate()
+            .map(|(i, stake)| {
+                let account_id = format!("bp{}.{}", i, epoch).parse().unwrap();
+                let secret_key = SecretKey::from_seed(KeyType::ED25519, &format!("{}{}", epoch, i));
+                let view = ValidatorStake::new(account_id, secret_key.public_key(), *stake).into();
+                TestBlockProducer { secret_key, view }
+            })
+            .collect()
+    }
+
+    pub(crate) fn views(block_producers: &[TestBlockProducer]) -> Vec<ValidatorStakeView> {
+        block_producers.iter().map(|bp| bp.view.clone()).collect()
+    }
+
+    pub(crate) fn bp_hash(block_producers: &[TestBlockProducer]) -> CryptoHash {
+        let stakes: Vec<ValidatorStake> =
+            block_producers.iter().map(|bp| bp.view.clone().into()).collect();
+        CryptoHash::hash_borsh_iter(stakes)
+    }
+
+    pub(crate) fn head(
+        height: BlockHeight,
+        epoch_id: &str,
+        next_epoch_id: &str,
+    ) -> LightClientBlockLiteView {
+        LightClientBlockLiteView {
+            prev_block_hash: hash(b"prev"),
+            inner_rest_hash: hash(b"rest"),
+            inner_lite: BlockHeaderInnerLiteView {
+                height,
+                epoch_id: hash(epoch_id.as_bytes()),
+                next_epoch_id: hash(next_epoch_id.as_bytes()),
+                prev_state_root: CryptoHash::default(),
+                outcome_root: CryptoHash::default(),
+                timestamp: 0,
+                timestamp_nanosec: 0,
+                next_bp_hash: CryptoHash::default(),
+                block_merkle_root: CryptoHash::default(),
+            },
+        }
+    }
+
+    /// Creates a light client block approved by the block producers whose
+    /// indexes are in `approvers`.
+    pub(crate) fn signed_block(
+        lite_view: LightClientBlockLiteView,
+        block_producers: &[TestBlockProducer],
+        approvers: &[usize],
+        next_bps: Option<Vec<ValidatorStakeView>>,
+    ) -> LightClientBlockView {
+        let next_block_inner_hash = hash(b"next");
+        let next_block_hash = combine_hash(&next_block_inner_hash, &lite_view.hash());
+        let message = Approval::get_data_for_sig(
+            &ApprovalInner::Endorsement(next_block_hash),
+            lite_view.inner_lite.height + 2,
+        );
+        let approvals_after_next = block_producers
+            .iter()
+            .enumerate()
+            .map(|(i, bp)| approvers.contains(&i).then(|| Box::new(bp.secret_key.sign(&message))))
+            .collect();
+        LightClientBlockView {
+            prev_block_hash: lite_view.prev_block_hash,
+            next_block_inner_hash,
+            inner_lite: lite_view.inner_lite,
+            inner_rest_hash: lite_view.inner_rest_hash,
+            next_bps,
+            approvals_after_next,
+        }
+    }
+
+    #[test]
+    fn test_validate_block() {
+        let bps = block_producers("epoch1", &[10, 10, 10, 10]);
+        let next_bps = block_producers("epoch2", &[5, 5]);
+        let head = head(10, "epoch1", "epoch2");
+
+        let mut lite_view = head.clone();
+        lite_view.inner_lite.height = 20;
+        lite_view.inner_lite.next_bp_hash = bp_hash(&next_bps);
+
+        // 3 out of 4 equal stakes is more than 2/3.
+        let block = signed_block(lite_view.clone(), &bps, &[0, 1, 3], Some(views(&next_bps)));
+        assert_eq!(validate_light_client_block(&head, &views(&bps), &block), Ok(()));
+        let block = signed_block(lite_view.clone(), &bps, &[0, 1, 3], None);
+        assert_eq!(validate_light_client_block(&head, &views(&bps), &block), Ok(()));
+
+        // 2 out of 4 is not.
+        let block = signed_block(lite_view.clone(), &bps, &[0, 1], None);
+        assert_eq!(
+            validate_light_client_block(&head, &views(&bps), &block),
+            Err(LightClientError::NotEnoughApprovals { approved: 20, total: 40 })
+        );
+
+        // Approvals signed by someone else.
+        let other_bps = block_producers("other", &[10, 10, 10, 10]);
+        let block = signed_block(lite_view.clone(), &other_bps, &[0, 1, 2], None);
+        assert!(matches!(
+            validate_light_client_block(&head, &views(&bps), &block),
+            Err(LightClientError::InvalidApprovalSignature(_))
+        ));
+
+        // Next block producers not matching next_bp_hash.
+        let block = signed_block(lite_view.clone(), &bps, &[0, 1, 2], Some(views(&other_bps)));
+        assert!(matches!(
+            validate_light_client_block(&head, &views(&bps), &block),
+            Err(LightClientError::InvalidBlockProducers(_))
+        ));
+
+        // Not above the head.
+        let mut old_lite_view = lite_view.clone();
+        old_lite_view.inner_lite.height = 10;
+        let block = signed_block(old_lite_view, &bps, &[0, 1, 2], None);
+        assert_eq!(
+            validate_light_client_block(&head, &views(&bps), &block),
+            Err(LightClientError::NotAboveHead { height: 10, head_height: 10 })
+        );
+
+        // The first block of the next epoch must contain the next block producers.
+        let mut next_epoch_lite_view = lite_view;
+        next_epoch_lite_view.inner_lite.epoch_id = head.inner_lite.next_epoch_id;
+        let block = signed_block(next_epoch_lite_view, &bps, &[0, 1, 2], None);
+        assert_eq!(
+            validate_light_client_block(&head, &views(&bps), &block),
+            Err(LightClientError::MissingNextBlockProducers)
+        );
+    }
+
+    #[test]
+    fn test_block_from_unexpected_epoch() {
+        let bps = block_producers("epoch1", &[10]);
+        let head = head(10, "epoch1", "epoch2");
+        let mut lite_view = head.clone();
+        lite_view.inner_lite.height = 11;
+        lite_view.inner_lite.epoch_id = hash(b"epoch3");
+        let block = signed_block(lite_view, &bps, &[0], None);
+        assert_eq!(
+            validate_light_client_block(&head, &views(&bps), &block),
+            Err(LightClientError::UnexpectedEpoch(hash(b"epoch3")))
+        );
+    }
+}
@@ -5,15 +5,17 @@ use anyhow::{anyhow, Context as AnyhowContext};
 use near_chain::{Block, Chain, ChainStoreAccess, Error};
 use near_client::sync::header::MAX_BLOCK_HEADERS;
 use near_crypto::SecretKey;
-use near_network::raw::{DirectMessage, Listener, Message, RoutedMessage};
+use near_network::raw::{Connection, DirectMessage, Listener, Message, RoutedMessage};
+use near_network::recorder::{MessageDirection, RecordedMessage, RecordingReader};
 use near_network::tcp;
 use near_network::types::{PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg};
+use near_primitives::network::PeerId;
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::sharding::ChunkHash;
 use near_primitives::types::{BlockHeight, ShardId};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::future::Future;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::task::Poll;
 use std::time::Duration;
@@ -36,12 +38,27 @@ pub struct MockIncomingRequestsConfig {
     chunk_request: Option<MockIncomingRequestConfig>,
 }
 
+// Replays a recording of the messages a real node received, made with the
+// `experimental.record_messages_path` network config option. In this mode the
+// mock peer doesn't respond to requests or produce blocks, all messages come
+// from the recording.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct MockReplayConfig {
+    pub path: PathBuf,
+    // Only replay the messages received from this peer. By default the
+    // messages of all peers are replayed over the single mock connection.
+    #[serde(default)]
+    pub peer_id: Option<PeerId>,
+}
+
 #[derive(Clone, Debug, serde::Deserialize)]
 pub struct MockNetworkConfig {
     #[serde(default = "default_delay")]
     // How long we'll wait until sending replies to the client
     pub response_delay: Duration,
     pub incoming_requests: Option<MockIncomingRequestsConfig>,
+    #[serde(default)]
+    pub replay: Option<MockReplayConfig>,
 }
 
 impl MockNetworkConfig {
@@ -65,7 +82,7 @@ fn default_delay() -> Duration {
 
 impl Default for MockNetworkConfig {
     fn default() -> Self {
-        Self { response_delay: default_delay(), incoming_requests: None }
+        Self { response_delay: default_delay(), incoming_requests: None, replay: None }
     }
 }
 
@@ -430,6 +447,9 @@ impl MockPeer {
     // Then respond to messages indefinitely until an error occurs
     async fn run(mut self, target_height: BlockHeight) -> anyhow::Result<()> {
         let mut conn = self.listener.accept().await?;
+        if let Some(replay) = &self.network_config.replay {
+            return replay_recording(&mut conn, replay, &near_time::Clock::real()).await;
+        }
         let messages = InFlightMessages::new(self.network_config.response_delay);
         tokio::pin!(messages);
 
@@ -456,6 +476,68 @@ impl MockPeer {
     }
 }
 
+// The connection to the node under test over which a recording is replayed.
+trait ReplayConnection: std::fmt::Debug {
+    async fn send_recorded_message(&mut self, msg: &RecordedMessage) -> std::io::Result<()>;
+    async fn recv(&mut self) -> std::io::Result<Message>;
+}
+
+impl ReplayConnection for Connection {
+    async fn send_recorded_message(&mut self, msg: &RecordedMessage) -> std::io::Result<()> {
+        Connection::send_recorded_message(self, msg).await
+    }
+
+    async fn recv(&mut self) -> std::io::Result<Message> {
+        Connection::recv(self).await.map(|(msg, _timestamp)| msg)
+    }
+}
+
+// Sends the messages the recorded node received, keeping the time between them as
+// it was recorded. Each message is scheduled at its recorded offset from the first
+// one on `clock`, so the delays don't accumulate and the replay is deterministic
+// under a fake clock. Messages sent by the node under test are only logged, it's up
+// to the recording to contain the responses it needs.
+async fn replay_recording(
+    conn: &mut impl ReplayConnection,
+    config: &MockReplayConfig,
+    clock: &near_time::Clock,
+) -> anyhow::Result<()> {
+    let recording = RecordingReader::open(&config.path)
+        .with_context(|| format!("failed opening recording {}", config.path.display()))?;
+    let start = clock.now();
+    let mut first_timestamp_nanos = None;
+    let mut replayed = 0;
+    for msg in recording {
+        let msg = msg.context("failed reading recording")?;
+        if msg.direction != MessageDirection::Inbound || !msg.is_replayable() {
+            continue;
+        }
+        if config.peer_id.is_some() && msg.peer_id != config.peer_id {
+            continue;
+        }
+        let first_timestamp_nanos = *first_timestamp_nanos.get_or_insert(msg.timestamp_nanos);
+        let offset = i64::try_from(msg.timestamp_nanos - first_timestamp_nanos).unwrap_or(0);
+        let deliver_at = start + near_time::Duration::nanoseconds(offset.max(0));
+        loop {
+            tokio::select! {
+                _ = clock.sleep_until(deliver_at) => break,
+                res = conn.recv() => {
+                    let received = res.with_context(|| format!("failed receiving message from {:?}", &conn))?;
+                    tracing::debug!("mock peer ignoring message during replay: {}", &received);
+                }
+            }
+        }
+        tracing::debug!("mock peer replaying message {:?}", msg.variant());
+        conn.send_recorded_message(&msg).await?;
+        replayed += 1;
+    }
+    tracing::info!(replayed, "mock peer replayed all messages of the recording");
+    // Keep the connection open, so that the node can finish processing.
+    loop {
+        conn.recv().await.with_context(|| format!("failed receiving message from {:?}", &conn))?;
+    }
+}
+
 // TODO: this is not currently correct if we're an archival node and we get
 // asked about an old chunk. In that case it needs to be reconstructed like
 // in ShardsManager::prepare_partial_encoded_chunk_response()
@@ -493,3 +575,81 @@ fn retrieve_partial_encoded_chunk(
 
     Ok(PartialEncodedChunkResponseMsg { chunk_hash: request.chunk_hash.clone(), parts, receipts })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{replay_recording, MockReplayConfig, ReplayConnection};
+    use near_network::raw::{DirectMessage, Message};
+    use near_network::recorder::{write_recording, MessageDirection, RecordedMessage};
+    use near_primitives::hash::hash;
+    use near_primitives::network::PeerId;
+    use near_time::{Duration, FakeClock};
+    use std::sync::{Arc, Mutex};
+
+    // Collects the replayed messages. The node under test never sends anything.
+    #[derive(Debug, Default)]
+    struct TestConnection {
+        sent: Arc<Mutex<Vec<RecordedMessage>>>,
+    }
+
+    impl ReplayConnection for TestConnection {
+        async fn send_recorded_message(&mut self, msg: &RecordedMessage) -> std::io::Result<()> {
+            self.sent.lock().unwrap().push(msg.clone());
+            Ok(())
+        }
+
+        async fn recv(&mut self) -> std::io::Result<Message> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_recording() {
+        let clock = FakeClock::default();
+        let peer_id = PeerId::random();
+        let other_peer_id = PeerId::random();
+        let recorded_at = clock.now_utc();
+        let message = |seconds: i64, direction: MessageDirection, peer_id: &PeerId| {
+            RecordedMessage::new_direct(
+                recorded_at + Duration::seconds(seconds),
+                direction,
+                Some(peer_id.clone()),
+                DirectMessage::BlockRequest(hash(&seconds.to_le_bytes())),
+            )
+        };
+        let recording = [
+            message(10, MessageDirection::Inbound, &peer_id),
+            message(11, MessageDirection::Outbound, &peer_id),
+            message(11, MessageDirection::Inbound, &other_peer_id),
+            message(12, MessageDirection::Inbound, &peer_id),
+            message(15, MessageDirection::Inbound, &peer_id),
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recording");
+        write_recording(&path, &recording).unwrap();
+
+        let config = MockReplayConfig { path, peer_id: Some(peer_id) };
+        let mut conn = TestConnection::default();
+        let sent = conn.sent.clone();
+        let sent_timestamps =
+            || sent.lock().unwrap().iter().map(|msg| msg.timestamp_nanos).collect::<Vec<_>>();
+        let timestamps = |indexes: &[usize]| {
+            indexes.iter().map(|i| recording[*i].timestamp_nanos).collect::<Vec<_>>()
+        };
+
+        let replay = replay_recording(&mut conn, &config, &clock.clock());
+        tokio::pin!(replay);
+        // The first message is sent right away, the others at their recorded offsets.
+        assert!(futures::poll!(replay.as_mut()).is_pending());
+        assert_eq!(sent_timestamps(), timestamps(&[0]));
+        clock.advance(Duration::seconds(2) - Duration::nanoseconds(1));
+        assert!(futures::poll!(replay.as_mut()).is_pending());
+        assert_eq!(sent_timestamps(), timestamps(&[0]));
+        clock.advance(Duration::nanoseconds(1));
+        assert!(futures::poll!(replay.as_mut()).is_pending());
+        assert_eq!(sent_timestamps(), timestamps(&[0, 3]));
+        clock.advance(Duration::seconds(3));
+        assert!(futures::poll!(replay.as_mut()).is_pending());
+        assert_eq!(sent_timestamps(), timestamps(&[0, 3, 4]));
+    }
+}
@@ -6,13 +6,14 @@
 use actix::System;
 use anyhow::Context;
 use mock_node::setup::{setup_mock_node, MockNode};
-use mock_node::MockNetworkConfig;
+use mock_node::{MockNetworkConfig, MockReplayConfig};
 use near_actix_test_utils::run_actix;
 use near_chain_configs::{GenesisValidationMode, MutableConfigValue};
-use near_crypto::{InMemorySigner, KeyType};
+use near_crypto::{InMemorySigner, KeyType, PublicKey};
 use near_jsonrpc_client::JsonRpcClient;
 use near_network::tcp;
 use near_o11y::testonly::init_integration_logger;
+use near_primitives::network::PeerId;
 use near_primitives::types::BlockHeight;
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
@@ -83,6 +84,15 @@ struct Cli {
     /// port the mock node should listen on
     #[clap(long)]
     mock_port: Option<u16>,
+    /// Replay the messages in a recording made by a node with
+    /// `network.experimental.record_messages_path` set, instead of serving
+    /// the chain history. The client keeps the node key of the chain history
+    /// home dir, so that routed messages addressed to the recorded node reach it.
+    #[clap(long)]
+    replay: Option<PathBuf>,
+    /// Only replay the messages the recorded node received from this peer.
+    #[clap(long, requires = "replay")]
+    replay_peer_id: Option<PublicKey>,
 }
 
 async fn target_height_reached(client: &JsonRpcClient, target_height: BlockHeight) -> bool {
@@ -109,8 +119,10 @@ fn main() -> anyhow::Result<()> {
         .context("Error loading config")?;
     near_config.validator_signer = MutableConfigValue::new(None, "validator_signer");
     near_config.client_config.min_num_peers = 1;
-    let signer = InMemorySigner::from_random("mock_node".parse().unwrap(), KeyType::ED25519);
-    near_config.network_config.node_key = signer.secret_key;
+    if args.replay.is_none() {
+        let signer = InMemorySigner::from_random("mock_node".parse().unwrap(), KeyType::ED25519);
+        near_config.network_config.node_key = signer.secret_key;
+    }
     near_config.client_config.tracked_shards =
         near_config.genesis.config.shard_layout.shard_ids().collect();
     if near_config.rpc_config.is_none() {
@@ -136,6 +148,10 @@ fn main() -> anyhow::Result<()> {
     if let Some(delay) = args.network_delay {
         network_config.response_delay = Duration::from_millis(delay);
     }
+    if let Some(path) = args.replay {
+        network_config.replay =
+            Some(MockReplayConfig { path, peer_id: args.replay_peer_id.map(PeerId::new) });
+    }
 
     let client_height = args.start_height.unwrap_or(args.client_height);
     let network_height = args.start_height.or(args.network_height);
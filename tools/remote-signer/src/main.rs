@@ -0,0 +1,251 @@
+//! Reference implementation of the signer process of the remote signer
+//! protocol, see `near_crypto::remote_signer`.
+use anyhow::Context;
+use borsh::BorshDeserialize;
+use clap::Parser;
+use near_crypto::remote_signer::{RemoteSignerRequest, RemoteSignerResponse};
+use near_crypto::{InMemorySigner, KeyFile};
+use near_network::types::check_account_key_payload;
+use near_primitives::block_header::{Approval, ApprovalInner, BlockHeader, BlockHeaderInnerLite};
+use near_primitives::challenge::ChallengeBody;
+use near_primitives::hash::CryptoHash;
+use near_primitives::network::{AnnounceAccount, PeerId};
+use near_primitives::sharding::{ShardChunkHeaderInner, ShardChunkHeaderV3};
+use near_primitives::signing_journal::{SignedItem, SigningJournal};
+use near_primitives::stateless_validation::chunk_endorsement::{
+    ChunkEndorsementInner, ChunkEndorsementMetadata,
+};
+use near_primitives::stateless_validation::contract_distribution::{
+    ChunkContractAccessesInner, ContractCodeRequestInner, PartialEncodedContractDeploysInner,
+};
+use near_primitives::stateless_validation::partial_witness::PartialEncodedStateWitnessInner;
+use near_primitives::stateless_validation::SignedInner;
+use near_primitives::telemetry::TelemetryInfo;
+use near_primitives::types::{AccountId, EpochId};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+#[derive(Parser)]
+#[clap(about = "Holds a validator key and signs for neard over a Unix socket")]
+struct Cli {
+    /// Key file with the secret key. Encrypted key files are supported, the
+    /// passphrase is read as by neard.
+    #[clap(long)]
+    key_file: PathBuf,
+    /// Path of the Unix socket to listen on. Point `remote_signer.socket_path`
+    /// in the validator key file of neard to it.
+    #[clap(long)]
+    socket: PathBuf,
+    /// Signing journal to check blocks, chunks, approvals and chunk
+    /// endorsements against before signing them, see
+    /// `near_primitives::signing_journal`. Use a different file than neard.
+    #[clap(long)]
+    signing_journal: Option<PathBuf>,
+}
+
+fn main() -> anyhow::Result<()> {
+    let env_filter = near_o11y::EnvFilterBuilder::from_env().finish().unwrap();
+    let _subscriber = near_o11y::default_subscriber(env_filter, &Default::default()).global();
+    let args = Cli::parse();
+
+    let key_file = KeyFile::from_file(&args.key_file)
+        .with_context(|| format!("failed to read key file {}", args.key_file.display()))?;
+    let journal = match &args.signing_journal {
+        Some(path) => Some(
+            SigningJournal::open(path)
+                .with_context(|| format!("failed to open signing journal {}", path.display()))?,
+        ),
+        None => None,
+    };
+    let signer = Arc::new(Signer { key: InMemorySigner::from(key_file), journal });
+
+    // Remove the socket left behind by a previous run, but nothing else.
+    if let Ok(metadata) = std::fs::symlink_metadata(&args.socket) {
+        anyhow::ensure!(
+            metadata.file_type().is_socket(),
+            "{} exists and is not a socket",
+            args.socket.display()
+        );
+        std::fs::remove_file(&args.socket)?;
+    }
+    let listener = UnixListener::bind(&args.socket)
+        .with_context(|| format!("failed to listen on {}", args.socket.display()))?;
+    std::fs::set_permissions(&args.socket, std::fs::Permissions::from_mode(0o600))?;
+    info!(
+        account_id = %signer.key.account_id,
+        public_key = %signer.key.public_key,
+        socket = %args.socket.display(),
+        "remote signer listening"
+    );
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let signer = signer.clone();
+        std::thread::spawn(move || {
+            if let Err(err) = signer.serve(stream) {
+                warn!(?err, "remote signer connection failed");
+            }
+        });
+    }
+    Ok(())
+}
+
+struct Signer {
+    key: InMemorySigner,
+    journal: Option<Arc<SigningJournal>>,
+}
+
+impl Signer {
+    /// Answers the requests of one client until it disconnects.
+    fn serve(&self, stream: UnixStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+        let mut line = String::new();
+        while reader.read_line(&mut line)? != 0 {
+            let response = match serde_json::from_str::<RemoteSignerRequest>(&line) {
+                Ok(request) => self.handle(&request).unwrap_or_else(|err| {
+                    warn!(?err, "refusing request");
+                    RemoteSignerResponse::Error(format!("{err:#}"))
+                }),
+                Err(err) => RemoteSignerResponse::Error(format!("invalid request: {err}")),
+            };
+            let mut response = serde_json::to_vec(&response)?;
+            response.push(b'\n');
+            writer.write_all(&response)?;
+            line.clear();
+        }
+        Ok(())
+    }
+
+    /// Checks the object of the request and signs it. Objects that are signed
+    /// at most once per height are checked against the journal first.
+    fn handle(&self, request: &RemoteSignerRequest) -> anyhow::Result<RemoteSignerResponse> {
+        let data = match request {
+            RemoteSignerRequest::PublicKey => {
+                return Ok(RemoteSignerResponse::PublicKey {
+                    account_id: self.key.account_id.clone(),
+                    public_key: self.key.public_key.clone(),
+                });
+            }
+            RemoteSignerRequest::ComputeVrfWithProof { prev_random_value } => {
+                let prev_random_value: CryptoHash = decode(prev_random_value)?;
+                let (value, proof) = self.key.compute_vrf_with_proof(prev_random_value.as_ref());
+                return Ok(RemoteSignerResponse::Vrf { value, proof });
+            }
+            RemoteSignerRequest::SignBlockHeader { prev_hash, inner_lite, inner_rest } => {
+                let prev_hash = decode(prev_hash)?;
+                let height = decode::<BlockHeaderInnerLite>(inner_lite)?.height;
+                let hash = BlockHeader::compute_hash(prev_hash, inner_lite, inner_rest);
+                self.record(SignedItem::block(height, hash))?;
+                vec![hash.as_ref().to_vec()]
+            }
+            RemoteSignerRequest::SignApproval { inner, target_height } => {
+                let inner: ApprovalInner = decode(inner)?;
+                let data = Approval::get_data_for_sig(&inner, *target_height);
+                self.record(SignedItem::approval(*target_height, &data))?;
+                vec![data]
+            }
+            RemoteSignerRequest::SignChunkHeader { inner } => {
+                let inner: ShardChunkHeaderInner = decode(inner)?;
+                let hash = ShardChunkHeaderV3::compute_hash(&inner);
+                self.record(SignedItem::chunk(inner.height_created(), inner.shard_id(), hash.0))?;
+                vec![hash.as_ref().to_vec()]
+            }
+            RemoteSignerRequest::SignChunkEndorsement { inner, metadata } => {
+                let decoded_inner: ChunkEndorsementInner = decode_signed_inner(inner)?;
+                let decoded_metadata: ChunkEndorsementMetadata = decode(metadata)?;
+                self.check_account(decoded_metadata.account_id())?;
+                self.record(SignedItem::chunk_endorsement(
+                    decoded_metadata.height_created(),
+                    decoded_metadata.shard_id(),
+                    decoded_inner.chunk_hash().0,
+                ))?;
+                vec![inner.clone(), metadata.clone()]
+            }
+            RemoteSignerRequest::SignPartialStateWitness { inner } => {
+                decode_signed_inner::<PartialEncodedStateWitnessInner>(inner)?;
+                vec![inner.clone()]
+            }
+            RemoteSignerRequest::SignChunkContractAccesses { inner } => {
+                decode_signed_inner::<ChunkContractAccessesInner>(inner)?;
+                vec![inner.clone()]
+            }
+            RemoteSignerRequest::SignContractCodeRequest { inner } => {
+                let decoded: ContractCodeRequestInner = decode_signed_inner(inner)?;
+                self.check_account(decoded.requester())?;
+                vec![inner.clone()]
+            }
+            RemoteSignerRequest::SignPartialEncodedContractDeploys { inner } => {
+                decode_signed_inner::<PartialEncodedContractDeploysInner>(inner)?;
+                vec![inner.clone()]
+            }
+            RemoteSignerRequest::SignAnnounceAccount { peer_id, epoch_id } => {
+                let epoch_id = EpochId(decode(epoch_id)?);
+                let hash = AnnounceAccount::build_header_hash(
+                    &self.key.account_id,
+                    &PeerId::new(peer_id.clone()),
+                    &epoch_id,
+                );
+                vec![hash.as_ref().to_vec()]
+            }
+            RemoteSignerRequest::SignChallenge { body } => {
+                let body: ChallengeBody = decode(body)?;
+                vec![CryptoHash::hash_borsh(&body).as_ref().to_vec()]
+            }
+            RemoteSignerRequest::SignAccountKeyPayload { payload } => {
+                check_account_key_payload(payload, &self.key.public_key)?;
+                vec![payload.clone()]
+            }
+            RemoteSignerRequest::SignTelemetry { content } => {
+                let info: TelemetryInfo =
+                    serde_json::from_str(content).context("invalid telemetry")?;
+                anyhow::ensure!(
+                    info.chain.account_id.as_ref() == Some(&self.key.account_id),
+                    "telemetry is not of {}",
+                    self.key.account_id
+                );
+                vec![content.as_bytes().to_vec()]
+            }
+        };
+        Ok(RemoteSignerResponse::Signatures(data.iter().map(|data| self.key.sign(data)).collect()))
+    }
+
+    fn check_account(&self, account_id: &AccountId) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            account_id == &self.key.account_id,
+            "refusing to sign for {account_id}, the key is of {}",
+            self.key.account_id
+        );
+        Ok(())
+    }
+
+    fn record(&self, item: SignedItem) -> anyhow::Result<()> {
+        if let Some(journal) = &self.journal {
+            journal.record(&item)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes the whole of `data`, trailing bytes are an error.
+fn decode<T: BorshDeserialize>(data: &[u8]) -> anyhow::Result<T> {
+    borsh::from_slice(data).with_context(|| {
+        format!("invalid {}", std::any::type_name::<T>().rsplit("::").next().unwrap())
+    })
+}
+
+/// Decodes the signed part of a message and checks that it is a message of
+/// the expected type.
+fn decode_signed_inner<T: SignedInner>(data: &[u8]) -> anyhow::Result<T> {
+    let inner: T = decode(data)?;
+    anyhow::ensure!(
+        inner.signature_differentiator() == T::SIGNATURE_DIFFERENTIATOR,
+        "invalid signature differentiator {:?}",
+        inner.signature_differentiator()
+    );
+    Ok(inner)
+}
@@ -5,12 +5,15 @@ use near_client::sync::external::{
     create_bucket_readonly, external_storage_location, external_storage_location_directory,
     get_num_parts_from_filename, ExternalConnection, StateFileType,
 };
+use near_crypto::PublicKey;
 use near_jsonrpc::client::{new_client, JsonRpcClient};
 use near_jsonrpc::primitives::types::config::RpcProtocolConfigRequest;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::state_part::PartId;
-use near_primitives::state_sync::ShardStateSyncResponseHeader;
+use near_primitives::state_sync::{
+    decompress_state_part, ShardStateSyncResponseHeader, SignedStatePartsManifest,
+};
 use near_primitives::types::{
     BlockId, BlockReference, EpochId, EpochReference, Finality, ShardId, StateRoot,
 };
@@ -43,6 +46,12 @@ pub struct StatePartsDumpCheckCommand {
     // the gcs bucket to use when retrieving state parts from GCP
     #[clap(long)]
     gcs_bucket: Option<String>,
+    // the base url to use when retrieving state parts from an HTTP(S) server
+    #[clap(long)]
+    http_base_url: Option<String>,
+    // public keys of the dumpers whose manifests are trusted, separated by commas
+    #[clap(long, value_delimiter = ',')]
+    trusted_dumper_keys: Vec<PublicKey>,
     // this can be either loop-check or single-check
     #[clap(subcommand)]
     subcmd: StatePartsDumpCheckSubCommand,
@@ -89,6 +98,8 @@ impl StatePartsDumpCheckCommand {
             self.s3_bucket.clone(),
             self.s3_region.clone(),
             self.gcs_bucket.clone(),
+            self.http_base_url.clone(),
+            self.trusted_dumper_keys.clone(),
         )
     }
 }
@@ -101,14 +112,28 @@ impl StatePartsDumpCheckSubCommand {
         s3_bucket: Option<String>,
         s3_region: Option<String>,
         gcs_bucket: Option<String>,
+        http_base_url: Option<String>,
+        trusted_dumper_keys: Vec<PublicKey>,
     ) -> anyhow::Result<()> {
         match self {
-            StatePartsDumpCheckSubCommand::SingleCheck(cmd) => {
-                cmd.run(chain_id, root_dir, s3_bucket, s3_region, gcs_bucket)
-            }
-            StatePartsDumpCheckSubCommand::LoopCheck(cmd) => {
-                cmd.run(chain_id, root_dir, s3_bucket, s3_region, gcs_bucket)
-            }
+            StatePartsDumpCheckSubCommand::SingleCheck(cmd) => cmd.run(
+                chain_id,
+                root_dir,
+                s3_bucket,
+                s3_region,
+                gcs_bucket,
+                http_base_url,
+                trusted_dumper_keys,
+            ),
+            StatePartsDumpCheckSubCommand::LoopCheck(cmd) => cmd.run(
+                chain_id,
+                root_dir,
+                s3_bucket,
+                s3_region,
+                gcs_bucket,
+                http_base_url,
+                trusted_dumper_keys,
+            ),
         }
     }
 }
@@ -121,6 +146,8 @@ impl SingleCheckCommand {
         s3_bucket: Option<String>,
         s3_region: Option<String>,
         gcs_bucket: Option<String>,
+        http_base_url: Option<String>,
+        trusted_dumper_keys: Vec<PublicKey>,
     ) -> anyhow::Result<()> {
         let sys = actix::System::new();
         sys.block_on(async move {
@@ -135,6 +162,8 @@ impl SingleCheckCommand {
                 s3_bucket,
                 s3_region,
                 gcs_bucket,
+                http_base_url,
+                trusted_dumper_keys,
             )
             .await;
         });
@@ -145,7 +174,7 @@ impl SingleCheckCommand {
 
 impl LoopCheckCommand {
     // Connect to an RPC server to request latest epoch information.
-    // Whenever an epoch is complete, use the location specified by root_dir/s3_bucket&s3_location/gcs_bucket to download parts and validate them.
+    // Whenever an epoch is complete, use the location specified by root_dir/s3_bucket&s3_location/gcs_bucket/http_base_url to download parts and validate them.
     // Metrics will be emitted for epoch_height and dumped/valid/invalid/total state parts of the shard.
     fn run(
         &self,
@@ -154,6 +183,8 @@ impl LoopCheckCommand {
         s3_bucket: Option<String>,
         s3_region: Option<String>,
         gcs_bucket: Option<String>,
+        http_base_url: Option<String>,
+        trusted_dumper_keys: Vec<PublicKey>,
     ) -> anyhow::Result<()> {
         let rpc_server_addr = match &self.rpc_server_addr {
             None => {
@@ -176,6 +207,8 @@ impl LoopCheckCommand {
             s3_bucket,
             s3_region,
             gcs_bucket,
+            http_base_url,
+            trusted_dumper_keys,
             &rpc_client,
             &self.prometheus_addr,
             self.interval,
@@ -218,6 +251,7 @@ fn create_external_connection(
     bucket: Option<String>,
     region: Option<String>,
     gcs_bucket: Option<String>,
+    http_base_url: Option<String>,
 ) -> ExternalConnection {
     if let Some(root_dir) = root_dir {
         ExternalConnection::Filesystem { root_dir }
@@ -231,9 +265,11 @@ fn create_external_connection(
             reqwest_client: Arc::new(reqwest::Client::default()),
             bucket,
         }
+    } else if let Some(base_url) = http_base_url {
+        ExternalConnection::new_http(&base_url)
     } else {
         panic!(
-            "Please provide --root-dir, or both of --s3-bucket and --s3-region, or --gcs-bucket"
+            "Please provide --root-dir, or both of --s3-bucket and --s3-region, or --gcs-bucket, or --http-base-url"
         );
     }
 }
@@ -279,6 +315,8 @@ fn run_loop_all_shards(
     s3_bucket: Option<String>,
     s3_region: Option<String>,
     gcs_bucket: Option<String>,
+    http_base_url: Option<String>,
+    trusted_dumper_keys: Vec<PublicKey>,
     rpc_client: &JsonRpcClient,
     prometheus_addr: &str,
     loop_interval: u64,
@@ -371,6 +409,8 @@ fn run_loop_all_shards(
             let s3_bucket = s3_bucket.clone();
             let s3_region = s3_region.clone();
             let gcs_bucket = gcs_bucket.clone();
+            let http_base_url = http_base_url.clone();
+            let trusted_dumper_keys = trusted_dumper_keys.clone();
             let old_status = status.as_ref().ok().cloned();
             let new_status = sys.block_on(async move {
                 if !is_prometheus_server_up {
@@ -399,6 +439,8 @@ fn run_loop_all_shards(
                     s3_bucket,
                     s3_region,
                     gcs_bucket,
+                    http_base_url,
+                    trusted_dumper_keys,
                 )
                 .await
             });
@@ -435,6 +477,8 @@ async fn run_single_check_with_3_retries(
     s3_bucket: Option<String>,
     s3_region: Option<String>,
     gcs_bucket: Option<String>,
+    http_base_url: Option<String>,
+    trusted_dumper_keys: Vec<PublicKey>,
 ) -> anyhow::Result<StatePartsDumpCheckStatus> {
     let mut retries = 0;
     let mut res;
@@ -444,6 +488,8 @@ async fn run_single_check_with_3_retries(
         let s3_bucket = s3_bucket.clone();
         let s3_region = s3_region.clone();
         let gcs_bucket = gcs_bucket.clone();
+        let http_base_url = http_base_url.clone();
+        let trusted_dumper_keys = trusted_dumper_keys.clone();
         res = run_single_check(
             status.clone(),
             chain_id,
@@ -455,6 +501,8 @@ async fn run_single_check_with_3_retries(
             s3_bucket,
             s3_region,
             gcs_bucket,
+            http_base_url,
+            trusted_dumper_keys,
         )
         .await;
         match res {
@@ -488,6 +536,7 @@ async fn check_parts(
     shard_id: ShardId,
     state_root: StateRoot,
     external: &ExternalConnection,
+    trusted_dumper_keys: &[PublicKey],
 ) -> anyhow::Result<bool> {
     let directory_path = external_storage_location_directory(
         &chain_id,
@@ -546,6 +595,18 @@ async fn check_parts(
         return Ok(true);
     }
 
+    let manifest = check_manifest(
+        chain_id,
+        epoch_id,
+        epoch_height,
+        shard_id,
+        state_root,
+        num_parts,
+        external,
+        trusted_dumper_keys,
+    )
+    .await;
+
     tracing::info!(
         ?shard_id,
         epoch_height,
@@ -559,6 +620,7 @@ async fn check_parts(
         let chain_id = chain_id.clone();
         let external = external.clone();
         let epoch_id = *epoch_id;
+        let manifest = manifest.clone();
         let handle = tokio::spawn(async move {
             process_part_with_3_retries(
                 part_id,
@@ -569,6 +631,7 @@ async fn check_parts(
                 state_root,
                 num_parts,
                 external,
+                manifest,
             )
             .await
         });
@@ -584,6 +647,57 @@ async fn check_parts(
     Ok(true)
 }
 
+// download and verify the manifest of the state parts for a single epoch and shard.
+// Returns None if the manifest is missing or invalid, in which case the parts are only validated against the state root.
+async fn check_manifest(
+    chain_id: &String,
+    epoch_id: &EpochId,
+    epoch_height: u64,
+    shard_id: ShardId,
+    state_root: StateRoot,
+    num_parts: u64,
+    external: &ExternalConnection,
+    trusted_dumper_keys: &[PublicKey],
+) -> Option<Arc<SignedStatePartsManifest>> {
+    let file_type = StateFileType::StateManifest;
+    let location =
+        external_storage_location(&chain_id, &epoch_id, epoch_height, shard_id, &file_type);
+    let metric = crate::metrics::STATE_SYNC_DUMP_CHECK_MANIFEST_STATUS
+        .with_label_values(&[&shard_id.to_string(), &chain_id.to_string()]);
+    let data = match external.get_file(shard_id, &location, &file_type).await {
+        Ok(data) => data,
+        Err(err) => {
+            tracing::info!(?shard_id, epoch_height, ?err, "manifest is missing.");
+            metric.set(0);
+            return None;
+        }
+    };
+    let manifest = match SignedStatePartsManifest::try_from_slice(&data) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            tracing::info!(?shard_id, epoch_height, ?err, "manifest can't be parsed.");
+            metric.set(-1);
+            return None;
+        }
+    };
+    let expected = (*epoch_id, epoch_height, shard_id, state_root, num_parts);
+    let actual = (
+        manifest.manifest.epoch_id,
+        manifest.manifest.epoch_height,
+        manifest.manifest.shard_id,
+        manifest.manifest.state_root,
+        manifest.manifest.parts.len() as u64,
+    );
+    if !manifest.verify_signature(trusted_dumper_keys) || expected != actual {
+        tracing::info!(?shard_id, epoch_height, public_key = %manifest.public_key, "manifest is invalid.");
+        metric.set(-1);
+        return None;
+    }
+    tracing::info!(?shard_id, epoch_height, public_key = %manifest.public_key, "manifest is valid.");
+    metric.set(1);
+    Some(Arc::new(manifest))
+}
+
 // download and validate state headers for a single epoch and shard
 async fn check_headers(
     chain_id: &String,
@@ -636,6 +750,8 @@ async fn run_single_check(
     s3_bucket: Option<String>,
     s3_region: Option<String>,
     gcs_bucket: Option<String>,
+    http_base_url: Option<String>,
+    trusted_dumper_keys: Vec<PublicKey>,
 ) -> anyhow::Result<StatePartsDumpCheckStatus> {
     tracing::info!(
         current_epoch_height,
@@ -655,6 +771,7 @@ async fn run_single_check(
         s3_bucket.clone(),
         s3_region.clone(),
         gcs_bucket.clone(),
+        http_base_url.clone(),
     );
 
     let (mut parts_done, mut headers_done) = match status {
@@ -676,9 +793,17 @@ async fn run_single_check(
     };
 
     parts_done = parts_done
-        || check_parts(&chain_id, &epoch_id, current_epoch_height, shard_id, state_root, &external)
-            .await
-            .unwrap_or(false);
+        || check_parts(
+            &chain_id,
+            &epoch_id,
+            current_epoch_height,
+            shard_id,
+            state_root,
+            &external,
+            &trusted_dumper_keys,
+        )
+        .await
+        .unwrap_or(false);
     headers_done = headers_done
         || check_headers(&chain_id, &epoch_id, current_epoch_height, shard_id, &external)
             .await
@@ -703,12 +828,14 @@ async fn process_part_with_3_retries(
     state_root: StateRoot,
     num_parts: u64,
     external: ExternalConnection,
+    manifest: Option<Arc<SignedStatePartsManifest>>,
 ) -> anyhow::Result<()> {
     let mut retries = 0;
     let mut res;
     loop {
         let chain_id = chain_id.clone();
         let external = external.clone();
+        let manifest = manifest.clone();
         // timeout is needed to deal with edge cases where process_part awaits forever, i.e. the get_file().await somehow waits forever
         // this is set to a long duration because the timer for each task, i.e. process_part, starts when the task is started, i.e. tokio::spawn is called,
         // and counts actual time instead of CPU time.
@@ -725,6 +852,7 @@ async fn process_part_with_3_retries(
                 state_root,
                 num_parts,
                 external,
+                manifest,
             ),
         )
         .await;
@@ -808,13 +936,28 @@ async fn process_part(
     state_root: StateRoot,
     num_parts: u64,
     external: ExternalConnection,
+    manifest: Option<Arc<SignedStatePartsManifest>>,
 ) -> anyhow::Result<()> {
     tracing::info!(part_id, "process_part started.");
     let file_type = StateFileType::StatePart { part_id, num_parts };
     let location =
         external_storage_location(&chain_id, &epoch_id, epoch_height, shard_id, &file_type);
     let part = external.get_file(shard_id, &location, &file_type).await?;
-    let is_part_valid = validate_state_part(&state_root, PartId::new(part_id, num_parts), &part);
+    let matches_manifest = match manifest.map(|manifest| manifest.verify_part(part_id, &part)) {
+        Some(Err(err)) => {
+            tracing::info!(part_id, %err, "part doesn't match the manifest.");
+            false
+        }
+        _ => true,
+    };
+    let is_part_valid = matches_manifest
+        && match decompress_state_part(part) {
+            Ok(part) => validate_state_part(&state_root, PartId::new(part_id, num_parts), &part),
+            Err(err) => {
+                tracing::info!(part_id, ?err, "part can't be decompressed.");
+                false
+            }
+        };
     if is_part_valid {
         crate::metrics::STATE_SYNC_DUMP_CHECK_NUM_PARTS_VALID
             .with_label_values(&[&shard_id.to_string(), &chain_id.to_string()])
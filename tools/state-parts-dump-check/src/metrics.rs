@@ -99,3 +99,14 @@ pub(crate) static STATE_SYNC_DUMP_CHECK_PROCESS_IS_UP: LazyLock<IntGaugeVec> =
         )
         .unwrap()
     });
+
+pub(crate) static STATE_SYNC_DUMP_CHECK_MANIFEST_STATUS: LazyLock<IntGaugeVec> = LazyLock::new(
+    || {
+        try_create_int_gauge_vec(
+            "near_state_sync_dump_check_manifest_status",
+            "status of the state parts manifest for the epoch: 1 if valid, 0 if missing, -1 if invalid",
+            &["shard_id", "chain_id"],
+        )
+        .unwrap()
+    },
+);
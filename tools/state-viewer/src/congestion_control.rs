@@ -1,15 +1,17 @@
 use rand::Rng;
 use std::borrow::Cow;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use near_chain::types::RuntimeAdapter;
 use near_chain::{ChainStore, ChainStoreAccess};
-use near_epoch_manager::EpochManagerAdapter;
+use near_epoch_manager::{EpochManager, EpochManagerAdapter};
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::{
     DataReceipt, Receipt, ReceiptEnum, ReceiptOrStateStoredReceipt, ReceiptV1,
 };
-use near_primitives::types::{ShardId, StateChangeCause, StateRoot};
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::{AccountId, BlockHeight, Gas, ShardId, StateChangeCause, StateRoot};
 use near_store::trie::receipts_column_helper::{DelayedReceiptQueue, TrieQueue};
 use near_store::{ShardTries, ShardUId, Store, TrieUpdate};
 use nearcore::NearConfig;
@@ -23,6 +25,8 @@ use crate::util::load_trie;
 /// 2) Run the prepare-benchmark command and the bootstrap command to see how
 ///    long it takes to bootstrap the congestion info. Please note that this
 ///    will corrupt the database and it should not be used on production nodes.
+/// 3) Run the trace command to export the traffic of a block range and replay
+///    it in the congestion model (tools/congestion-model).
 #[derive(clap::Subcommand)]
 pub enum CongestionControlCmd {
     /// Print the congestion information.
@@ -33,6 +37,9 @@ pub enum CongestionControlCmd {
     /// for benchmarking the bootstrapping logic. Please note that running this
     /// command will corrupt the database.
     PrepareBenchmark(PrepareBenchmarkCmd),
+    /// Write the receipts and gas usage of the transactions in a block range
+    /// to a JSON file that the congestion model can replay with `--trace`.
+    Trace(TraceCmd),
 }
 
 impl CongestionControlCmd {
@@ -41,6 +48,7 @@ impl CongestionControlCmd {
             CongestionControlCmd::Print(cmd) => cmd.run(&near_config, store),
             CongestionControlCmd::Bootstrap(cmd) => cmd.run(home_dir, &near_config, store),
             CongestionControlCmd::PrepareBenchmark(cmd) => cmd.run(home_dir, &near_config, store),
+            CongestionControlCmd::Trace(cmd) => cmd.run(home_dir, &near_config, store),
         }
     }
 }
@@ -238,3 +246,232 @@ impl PrepareBenchmarkCmd {
         Receipt::V1(receipt)
     }
 }
+
+/// The trace format read by the `TraceProducer` of the congestion model. Gas
+/// is in gas units and shards are identified by their shard index.
+#[derive(serde::Serialize)]
+struct Trace {
+    num_shards: usize,
+    start_height: BlockHeight,
+    rounds: Vec<TraceRound>,
+}
+
+#[derive(serde::Serialize, Default)]
+struct TraceRound {
+    transactions: Vec<TraceTransaction>,
+}
+
+#[derive(serde::Serialize)]
+struct TraceTransaction {
+    sender_shard: usize,
+    conversion_gas: Gas,
+    receipts: Vec<TraceReceipt>,
+}
+
+#[derive(serde::Serialize)]
+struct TraceReceipt {
+    predecessor: Option<usize>,
+    receiver_shard: usize,
+    size: u64,
+    attached_gas: Gas,
+    execution_gas: Gas,
+}
+
+#[derive(clap::Parser)]
+pub struct TraceCmd {
+    /// First block height of the trace, inclusive.
+    #[arg(long)]
+    start_height: BlockHeight,
+    /// Last block height of the trace, inclusive.
+    #[arg(long)]
+    end_height: BlockHeight,
+    /// Path of the JSON file to write the trace to.
+    #[arg(long)]
+    output: PathBuf,
+}
+
+impl TraceCmd {
+    pub(crate) fn run(&self, home_dir: &Path, near_config: &NearConfig, store: Store) {
+        let chain_store = ChainStore::new(
+            store.clone(),
+            near_config.genesis.config.genesis_height,
+            near_config.client_config.save_trie_changes,
+            near_config.genesis.config.transaction_validity_period,
+        );
+        let epoch_manager =
+            EpochManager::new_arc_handle(store, &near_config.genesis.config, Some(home_dir));
+
+        let trace =
+            Self::trace(&chain_store, epoch_manager.as_ref(), self.start_height, self.end_height);
+        let file = std::fs::File::create(&self.output).unwrap();
+        serde_json::to_writer(std::io::BufWriter::new(file), &trace).unwrap();
+        println!(
+            "Wrote trace of {} blocks with {} transactions to {}",
+            trace.rounds.len(),
+            trace.rounds.iter().map(|round| round.transactions.len()).sum::<usize>(),
+            self.output.display()
+        );
+    }
+
+    fn trace(
+        chain_store: &ChainStore,
+        epoch_manager: &dyn EpochManagerAdapter,
+        start_height: BlockHeight,
+        end_height: BlockHeight,
+    ) -> Trace {
+        let mut trace = Trace { num_shards: 0, start_height, rounds: vec![] };
+        for height in start_height..=end_height {
+            let mut round = TraceRound::default();
+            if let Ok(block_hash) = chain_store.get_block_hash_by_height(height) {
+                let block = chain_store.get_block(&block_hash).unwrap();
+                let shard_layout =
+                    epoch_manager.get_shard_layout(block.header().epoch_id()).unwrap();
+                trace.num_shards = trace.num_shards.max(shard_layout.num_shards() as usize);
+                for chunk_header in block.chunks().iter_deprecated() {
+                    if !chunk_header.is_new_chunk(height) {
+                        continue;
+                    }
+                    let sender_shard =
+                        shard_layout.get_shard_index(chunk_header.shard_id()).unwrap();
+                    let chunk = chain_store.get_chunk(&chunk_header.chunk_hash()).unwrap();
+                    for tx in chunk.transactions() {
+                        if let Some(trace_tx) =
+                            Self::trace_transaction(chain_store, &shard_layout, sender_shard, tx)
+                        {
+                            round.transactions.push(trace_tx);
+                        }
+                    }
+                }
+            }
+            trace.rounds.push(round);
+        }
+        trace
+    }
+
+    /// Follows the receipts created by the transaction through the receipt
+    /// ids of the execution outcomes. Returns `None` if the transaction has no
+    /// outcome.
+    ///
+    /// The receipt converted from the transaction may be a local receipt which
+    /// is never stored, so it's described by the transaction itself. The other
+    /// receipts are read from `DBCol::Receipts`. If a receipt is missing there,
+    /// it's included with the executor of its outcome as the receiver and no
+    /// size or attached gas, or left out if it wasn't executed either.
+    /// Receipts that weren't executed yet have no execution gas and no further
+    /// receipts.
+    fn trace_transaction(
+        chain_store: &ChainStore,
+        shard_layout: &ShardLayout,
+        sender_shard: usize,
+        tx: &SignedTransaction,
+    ) -> Option<TraceTransaction> {
+        let tx_outcome =
+            chain_store.get_outcomes_by_id(&tx.get_hash()).unwrap().into_iter().next()?;
+        let tx_outcome = tx_outcome.outcome_with_id.outcome;
+        let shard_index = |account_id: &AccountId| {
+            shard_layout.get_shard_index(shard_layout.account_id_to_shard_id(account_id)).unwrap()
+        };
+
+        let mut receipts = vec![];
+        // Breadth-first, so that every predecessor comes before its receipts.
+        let mut pending: std::collections::VecDeque<_> =
+            tx_outcome.receipt_ids.iter().map(|receipt_id| (None, *receipt_id)).collect();
+        while let Some((predecessor, receipt_id)) = pending.pop_front() {
+            let outcome = chain_store
+                .get_outcomes_by_id(&receipt_id)
+                .unwrap()
+                .into_iter()
+                .next()
+                .map(|outcome| outcome.outcome_with_id.outcome);
+            let (receiver_id, size, attached_gas) = if predecessor.is_none() {
+                let attached_gas =
+                    node_runtime::config::total_prepaid_gas(tx.transaction.actions())
+                        .unwrap_or(Gas::MAX);
+                let size = borsh::object_length(tx).unwrap() as u64;
+                (tx.transaction.receiver_id().clone(), size, attached_gas)
+            } else if let Some(receipt) = chain_store.get_receipt(&receipt_id).unwrap() {
+                let attached_gas = match receipt.receipt() {
+                    ReceiptEnum::Action(action_receipt)
+                    | ReceiptEnum::PromiseYield(action_receipt) => {
+                        node_runtime::config::total_prepaid_gas(&action_receipt.actions)
+                            .unwrap_or(Gas::MAX)
+                    }
+                    _ => 0,
+                };
+                let size = borsh::object_length(receipt.as_ref()).unwrap() as u64;
+                (receipt.receiver_id().clone(), size, attached_gas)
+            } else if let Some(outcome) = &outcome {
+                (outcome.executor_id.clone(), 0, 0)
+            } else {
+                continue;
+            };
+            let index = receipts.len();
+            receipts.push(TraceReceipt {
+                predecessor,
+                receiver_shard: shard_index(&receiver_id),
+                size,
+                attached_gas,
+                execution_gas: outcome.as_ref().map_or(0, |outcome| outcome.gas_burnt),
+            });
+            if let Some(outcome) = outcome {
+                pending.extend(outcome.receipt_ids.into_iter().map(|id| (Some(index), id)));
+            }
+        }
+        Some(TraceTransaction { sender_shard, conversion_gas: tx_outcome.gas_burnt, receipts })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_chain_configs::Genesis;
+    use near_client::test_utils::TestEnv;
+    use near_client::ProcessTxResponse;
+    use near_crypto::InMemorySigner;
+    use near_primitives::transaction::SignedTransaction;
+    use nearcore::test_utils::TestEnvNightshadeSetupExt;
+
+    use super::TraceCmd;
+
+    /// Tests that a transaction whose signer is its receiver is traced. The
+    /// receipt it is converted to is a local receipt, which is not stored in
+    /// `DBCol::Receipts`.
+    #[test]
+    fn test_trace_local_receipt() {
+        let genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+        let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+        let genesis_hash = *env.clients[0].chain.genesis().hash();
+        let signer = InMemorySigner::test_signer(&"test0".parse().unwrap());
+        let tx = SignedTransaction::send_money(
+            1,
+            "test0".parse().unwrap(),
+            "test0".parse().unwrap(),
+            &signer,
+            1,
+            genesis_hash,
+        );
+        assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+        for height in 1..=4 {
+            env.produce_block(0, height);
+        }
+
+        let client = &env.clients[0];
+        let trace =
+            TraceCmd::trace(client.chain.chain_store(), client.epoch_manager.as_ref(), 1, 4);
+        assert_eq!(trace.num_shards, 1);
+        assert_eq!(trace.rounds.len(), 4);
+        let transactions: Vec<_> =
+            trace.rounds.iter().flat_map(|round| &round.transactions).collect();
+        assert_eq!(transactions.len(), 1);
+        let trace_tx = transactions[0];
+        assert_eq!(trace_tx.sender_shard, 0);
+        assert!(trace_tx.conversion_gas > 0);
+        let first = &trace_tx.receipts[0];
+        assert_eq!(first.predecessor, None);
+        assert_eq!(first.receiver_shard, 0);
+        assert!(first.size > 0);
+        assert!(first.execution_gas > 0);
+        for receipt in &trace_tx.receipts[1..] {
+            assert!(receipt.predecessor.is_some());
+        }
+    }
+}
@@ -12,6 +12,7 @@ use near_primitives::challenge::PartialState;
 use near_primitives::epoch_info::EpochInfo;
 use near_primitives::state_part::PartId;
 use near_primitives::state_record::StateRecord;
+use near_primitives::state_sync::decompress_state_part;
 use near_primitives::types::{EpochId, StateRoot};
 use near_primitives_core::hash::CryptoHash;
 use near_primitives_core::types::{BlockHeight, EpochHeight, ShardId};
@@ -385,6 +386,7 @@ async fn load_state_parts(
         let location =
             external_storage_location(chain_id, &epoch_id, epoch_height, shard_id, &file_type);
         let part = external.get_file(shard_id, &location, &file_type).await.unwrap();
+        let part = decompress_state_part(part).unwrap();
 
         match action {
             LoadAction::Apply => {
@@ -491,10 +493,14 @@ async fn dump_state_parts(
             .put_file(file_type, &state_sync_header_buf, shard_id, &location)
             .await
             .expect("Failed to put header into external storage.");
+        add_to_listing(external, shard_id, &location, vec![StateFileType::StateHeader.filename()])
+            .await;
         tracing::info!(target: "state-parts", elapsed_sec = timer.elapsed().as_secs_f64(), "Header saved to external storage.");
     }
 
     // dump parts
+    let mut dumped_file_names = vec![];
+    let mut parts_location = None;
     for part_id in part_ids {
         let timer = Instant::now();
         assert!(part_id < num_parts, "part_id: {}, num_parts: {}", part_id, num_parts);
@@ -516,7 +522,9 @@ async fn dump_state_parts(
             shard_id,
             &file_type,
         );
+        dumped_file_names.push(file_type.filename());
         external.put_file(file_type, &state_part, shard_id, &location).await.unwrap();
+        parts_location = Some(location);
         // part_storage.write(&state_part, part_id, num_parts);
         let elapsed_sec = timer.elapsed().as_secs_f64();
         let first_state_record = get_first_state_record(&state_root, &state_part);
@@ -528,9 +536,33 @@ async fn dump_state_parts(
             first_state_record = ?first_state_record.map(|sr| format!("{}", sr)),
             "Wrote a state part");
     }
+    if let Some(location) = parts_location {
+        add_to_listing(external, shard_id, &location, dumped_file_names).await;
+    }
     tracing::info!(target: "state-parts", total_elapsed_sec = timer.elapsed().as_secs_f64(), "Wrote all requested state parts");
 }
 
+/// Adds the given files to the listing of the directory containing `location`.
+/// The listing is updated once after all files are dumped rather than after every file.
+async fn add_to_listing(
+    external: &ExternalConnection,
+    shard_id: ShardId,
+    location: &str,
+    file_names: Vec<String>,
+) {
+    let directory_path = location.rsplit_once('/').map_or("", |(directory_path, _)| directory_path);
+    let mut listing = external.list_objects(shard_id, directory_path).await.unwrap();
+    for file_name in file_names {
+        if !listing.contains(&file_name) {
+            listing.push(file_name);
+        }
+    }
+    external
+        .put_listing(shard_id, directory_path, &listing)
+        .await
+        .expect("Failed to put listing into external storage.");
+}
+
 /// Returns the first `StateRecord` encountered while iterating over a sub-trie in the state part.
 fn get_first_state_record(state_root: &StateRoot, data: &[u8]) -> Option<StateRecord> {
     let trie_nodes = BorshDeserialize::try_from_slice(data).unwrap();